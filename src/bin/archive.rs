@@ -0,0 +1,75 @@
+use std::env;
+use std::fs;
+
+use libimmuxdb::config::DEFAULT_CHAIN_NAME;
+use libimmuxdb::declarations::basics::ChainName;
+use libimmuxdb::declarations::commands::{Command, ImportChainCommand, Outcome};
+use libimmuxdb::executor::execute::execute;
+use libimmuxdb::storage::core::ImmuxDBCore;
+use libimmuxdb::storage::instructions::StoreNamespace;
+use libimmuxdb::storage::kv::KeyValueEngine;
+
+const USAGE: &str = "Usage:
+    immuxdb-archive export <data root> <chain> <archive file>
+    immuxdb-archive import <data root> <archive file> [chain]";
+
+fn export_chain(data_root: &str, chain: &str, path: &str) -> Result<String, String> {
+    let namespace = StoreNamespace::new(chain.as_bytes());
+    let mut core = ImmuxDBCore::new(&KeyValueEngine::Rocks, data_root, &namespace)
+        .map_err(|error| format!("Cannot open chain: {:?}", error))?;
+    match execute(Command::ExportChain, &mut core) {
+        Ok(Outcome::ExportChain(outcome)) => {
+            fs::write(path, &outcome.archive)
+                .map_err(|error| format!("Cannot write archive: {:?}", error))?;
+            return Ok(format!(
+                "Exported chain {} up to height {}, head {}",
+                outcome.chain.to_string(),
+                outcome.height.as_u64(),
+                outcome.head
+            ));
+        }
+        Ok(outcome) => return Err(format!("Unexpected outcome {:?}", outcome)),
+        Err(error) => return Err(format!("Cannot export chain: {:?}", error)),
+    }
+}
+
+fn import_chain(data_root: &str, path: &str, chain: Option<&String>) -> Result<String, String> {
+    let archive = fs::read(path).map_err(|error| format!("Cannot read archive: {:?}", error))?;
+    let namespace = StoreNamespace::new(DEFAULT_CHAIN_NAME.as_bytes());
+    let mut core = ImmuxDBCore::new(&KeyValueEngine::Rocks, data_root, &namespace)
+        .map_err(|error| format!("Cannot open database: {:?}", error))?;
+    let command = Command::ImportChain(ImportChainCommand {
+        archive,
+        chain: chain.map(|name| ChainName::from(name.as_str())),
+    });
+    match execute(command, &mut core) {
+        Ok(Outcome::ImportChain(outcome)) => {
+            return Ok(format!(
+                "Imported chain {} up to height {}, head {}",
+                outcome.chain.to_string(),
+                outcome.height.as_u64(),
+                outcome.head
+            ));
+        }
+        Ok(outcome) => return Err(format!("Unexpected outcome {:?}", outcome)),
+        Err(error) => return Err(format!("Cannot import chain: {:?}", error)),
+    }
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    let result = match args.get(1).map(|action| action.as_str()) {
+        Some("export") if args.len() == 5 => export_chain(&args[2], &args[3], &args[4]),
+        Some("import") if args.len() == 4 || args.len() == 5 => {
+            import_chain(&args[2], &args[3], args.get(4))
+        }
+        _ => Err(String::from(USAGE)),
+    };
+    match result {
+        Err(message) => {
+            eprintln!("{}", message);
+            std::process::exit(1);
+        }
+        Ok(message) => println!("{}", message),
+    }
+}
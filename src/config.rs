@@ -24,11 +24,52 @@ pub const MYSQL_ENDPOINT: &str = "127.0.0.1:3306";
 pub const INSPECT_KEYWORD: &str = "inspect";
 pub const REVERT_QUERY_KEYWORD: &str = "revert";
 pub const REVERTALL_QUERY_KEYWORD: &str = "revert_all";
+pub const REVERT_AT_QUERY_KEYWORD: &str = "revert_at";
+pub const REVERTALL_AT_QUERY_KEYWORD: &str = "revert_all_at";
+pub const REVERT_RECORD_QUERY_KEYWORD: &str = "revert_record";
 pub const CHAIN_KEYWORD: &str = "chain";
 pub const SELECT_CONDITION_KEYWORD: &str = "select";
 pub const CREATE_INDEX_KEYWORD: &str = "index";
+pub const HEIGHT_KEYWORD: &str = "height";
+pub const AT_KEYWORD: &str = "at";
+pub const VERIFY_CHAIN_KEYWORD: &str = "verify";
+pub const PROVE_KEYWORD: &str = "prove";
+pub const FORK_CHAIN_KEYWORD: &str = "fork";
+pub const MERGE_CHAIN_KEYWORD: &str = "merge";
+pub const MERGE_STRATEGY_KEYWORD: &str = "strategy";
+pub const DIFF_KEYWORD: &str = "diff";
+pub const CHANGES_KEYWORD: &str = "changes";
+pub const CHANGES_LIMIT_KEYWORD: &str = "limit";
+pub const CHANGES_WAIT_KEYWORD: &str = "wait";
+pub const TAG_KEYWORD: &str = "tag";
+pub const TAGS_KEYWORD: &str = "tags";
+pub const AUTHOR_KEYWORD: &str = "author";
+pub const MESSAGE_KEYWORD: &str = "message";
+pub const CLIENT_ID_KEYWORD: &str = "client_id";
+pub const RETENTION_KEYWORD: &str = "retention";
+pub const KEEP_HEIGHTS_KEYWORD: &str = "keep_heights";
+pub const KEEP_SINCE_KEYWORD: &str = "keep_since";
+pub const COMPACT_KEYWORD: &str = "compact";
+pub const REPLAY_KEYWORD: &str = "replay";
 pub const INTERNAL_API_TARGET_ID_IDENTIFIER: &str = "internal_api_target_id_identifier";
 pub const NAME_PROPERTY: &str = "name_property";
+pub const PROPERTY_RANGE: &str = "range";
+pub const RANGE_NAME_KEYWORD: &str = "name";
+pub const LESS_THAN_KEYWORD: &str = "lt";
+pub const LESS_THAN_OR_EQUAL_KEYWORD: &str = "lte";
+pub const GREATER_THAN_KEYWORD: &str = "gt";
+pub const GREATER_THAN_OR_EQUAL_KEYWORD: &str = "gte";
+pub const BETWEEN_KEYWORD: &str = "between";
+pub const SELECT_FILTER: &str = "filter";
+pub const WHERE_KEYWORD: &str = "where";
+pub const SKIP_KEYWORD: &str = "skip";
+pub const SELECT_LIMIT_KEYWORD: &str = "limit";
+pub const SORT_KEYWORD: &str = "sort";
+pub const CONTINUATION_KEYWORD: &str = "continuation";
+pub const FIELDS_KEYWORD: &str = "fields";
+pub const FIELDS_SEPARATOR: &str = ",";
+// Response header carrying the token of the next page of a select
+pub const CONTINUATION_HEADER: &str = "X-Continuation";
 
 pub const MULTIFIELD_SEPARATOR: &str = "|";
 
@@ -37,6 +78,20 @@ pub const DEFAULT_PERMANENCE_PATH: &str = "/tmp/";
 
 pub const INITIAL_TRANSACTION_ID_DATA: u64 = 1;
 
+pub const DEFAULT_CHANGES_LIMIT: usize = 100;
+pub const MAX_CHANGES_WAIT_MS: u64 = 20 * 1000;
+
+// Connections served at once per TCP endpoint; further ones are closed right away
+pub const MAX_CONNECTIONS_PER_ENDPOINT: usize = 256;
+// HTTP requests handled at once, change feeds waiting for records included; further ones get 503
+pub const MAX_HTTP_REQUESTS_IN_PROGRESS: usize = 64;
+// Change feeds waiting for records at once; further ones are answered right away
+pub const MAX_WAITING_CHANGE_FEEDS: usize = 16;
+
+// Per unit a JavaScript predicate is evaluated against
+pub const JS_PREDICATE_MAX_STEPS: u64 = 100_000;
+pub const JS_PREDICATE_MAX_MEMORY: usize = 1024 * 1024; // 1MB
+
 const DEFAULT_KV_ENGINE: KeyValueEngine = KeyValueEngine::Rocks;
 
 pub const MAX_KVKEY_LENGTH: usize = 8 * 1024; // 8KB
@@ -56,8 +111,6 @@ const MIN_MONGO_WIRE_VERSION: u32 = 0;
 const MAX_MONGO_WIRE_VERSION: u32 = 7;
 const READ_ONLY: bool = false;
 
-pub const MAX_RECURSION: u16 = 128;
-
 #[derive(Debug)]
 pub enum ConfigError {
     CannotRead,
@@ -96,6 +149,12 @@ pub enum KVKeySigil {
     // By VKV
     UnitJournal = 0x30,
     HeightToInstructionRecord = 0x31,
+    UnitVersion = 0x32,
+    HeightToChainTime = 0x33,
+    UnitJournalPage = 0x34,
+    HeightToAffectedKeys = 0x35,
+    StateTreeNode = 0x36,
+    HeightToStateRoot = 0x37,
 
     // By executor
     ReverseIndexIdList = 0xA0,
@@ -116,6 +175,18 @@ impl TryFrom<u8> for KVKeySigil {
             return Ok(KVKeySigil::UnitJournal);
         } else if u == KVKeySigil::HeightToInstructionRecord as u8 {
             return Ok(KVKeySigil::HeightToInstructionRecord);
+        } else if u == KVKeySigil::UnitVersion as u8 {
+            return Ok(KVKeySigil::UnitVersion);
+        } else if u == KVKeySigil::HeightToChainTime as u8 {
+            return Ok(KVKeySigil::HeightToChainTime);
+        } else if u == KVKeySigil::UnitJournalPage as u8 {
+            return Ok(KVKeySigil::UnitJournalPage);
+        } else if u == KVKeySigil::HeightToAffectedKeys as u8 {
+            return Ok(KVKeySigil::HeightToAffectedKeys);
+        } else if u == KVKeySigil::StateTreeNode as u8 {
+            return Ok(KVKeySigil::StateTreeNode);
+        } else if u == KVKeySigil::HeightToStateRoot as u8 {
+            return Ok(KVKeySigil::HeightToStateRoot);
         } else if u == KVKeySigil::ReverseIndexIdList as u8 {
             return Ok(KVKeySigil::ReverseIndexIdList);
         } else {
@@ -130,7 +201,7 @@ impl From<KVKeySigil> for u8 {
     }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ImmuxDBConfiguration {
     pub immuxdb_version: u32,
     pub engine_choice: KeyValueEngine,
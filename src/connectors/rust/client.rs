@@ -1,11 +1,27 @@
 use std::fmt::Formatter;
 
 use libimmuxdb::declarations::basics::{
-    ChainName, GroupingLabel, PropertyName, Unit, UnitContent, UnitId,
+    ChainName, GroupingLabel, PropertyName, StoreKey, StoreValue, Unit, UnitContent, UnitId,
+    UnitSpecifier,
 };
-use libimmuxdb::storage::vkv::ChainHeight;
+use libimmuxdb::storage::instructions::MergeStrategy;
+use libimmuxdb::storage::vkv::{ChainHeight, MerkleProof, RecordHash};
 use reqwest;
 
+/// Checks offline that `grouping/id` held `content` in the chain state whose Merkle root is
+/// `root`, as kept for some height.
+pub fn verify_unit_proof(
+    grouping: &GroupingLabel,
+    id: &UnitId,
+    content: &UnitContent,
+    proof: &MerkleProof,
+    root: &RecordHash,
+) -> bool {
+    let key = StoreKey::from(UnitSpecifier::new(grouping.to_owned(), id.to_owned()));
+    let value = StoreValue::new(Some(content.marshal()));
+    return proof.verify(&key, &value, root);
+}
+
 pub trait ImmuxDBConnector {
     fn get_by_id(&self, grouping: &GroupingLabel, id: &UnitId) -> ClientResult;
     fn get_by_property_name(
@@ -15,16 +31,59 @@ pub trait ImmuxDBConnector {
         unit_content: &UnitContent,
     ) -> ClientResult;
     fn inspect_by_id(&self, grouping: &GroupingLabel, id: &UnitId) -> ClientResult;
+    fn prove_by_id(
+        &self,
+        grouping: &GroupingLabel,
+        id: &UnitId,
+        height: Option<&ChainHeight>,
+    ) -> ClientResult;
     fn revert_by_id(
         &self,
         grouping: &GroupingLabel,
         id: &UnitId,
         height: &ChainHeight,
     ) -> ClientResult;
+    fn revert_by_id_at(&self, grouping: &GroupingLabel, id: &UnitId, time: &str) -> ClientResult;
+    fn revert_all_at(&self, time: &str) -> ClientResult;
+    fn revert_record(&self, height: &ChainHeight) -> ClientResult;
     fn set_unit(&self, grouping: &GroupingLabel, unit: &Unit) -> ClientResult;
+    fn remove_by_id(&self, grouping: &GroupingLabel, id: &UnitId) -> ClientResult;
     fn set_batch_units(&self, grouping: &GroupingLabel, units: &[Unit]) -> ClientResult;
     fn create_index(&self, grouping: &GroupingLabel, property_name: &PropertyName) -> ClientResult;
     fn switch_chain(&self, chain_name: &ChainName) -> ClientResult;
+    fn fork_chain(
+        &self,
+        source: &ChainName,
+        at_height: &ChainHeight,
+        new_chain_name: &ChainName,
+    ) -> ClientResult;
+    fn merge_chain(
+        &self,
+        target: &ChainName,
+        source: &ChainName,
+        resolutions: &[(GroupingLabel, UnitId, MergeStrategy)],
+        default_strategy: MergeStrategy,
+    ) -> ClientResult;
+    fn verify_chain(&self) -> ClientResult;
+    fn diff(
+        &self,
+        grouping: Option<&GroupingLabel>,
+        from_height: &ChainHeight,
+        to_height: Option<&ChainHeight>,
+    ) -> ClientResult;
+    fn get_changes(&self, after_height: &ChainHeight, wait_ms: Option<u64>) -> ClientResult;
+    fn create_tag(&self, name: &str, height: Option<&ChainHeight>) -> ClientResult;
+    fn delete_tag(&self, name: &str) -> ClientResult;
+    fn list_tags(&self) -> ClientResult;
+    fn set_retention(
+        &self,
+        grouping: Option<&GroupingLabel>,
+        keep_heights: Option<u64>,
+        keep_since: Option<&str>,
+    ) -> ClientResult;
+    fn get_retention(&self) -> ClientResult;
+    fn compact(&self) -> ClientResult;
+    fn replay_chain(&self) -> ClientResult;
 }
 
 #[derive(Debug)]
@@ -53,6 +112,12 @@ impl From<reqwest::Error> for ImmuxDBClientError {
 
 pub type ClientResult = Result<String, ImmuxDBClientError>;
 
+/// Times are RFC 3339 strings such as `2026-01-01T00:00:00Z`; a `+` offset has to be escaped
+/// so that it is not read back as a space
+fn encode_time(time: &str) -> String {
+    return time.replace("+", "%2B");
+}
+
 pub struct ImmuxDBClient {
     host: String,
 }
@@ -104,6 +169,25 @@ impl ImmuxDBConnector for ImmuxDBClient {
         return response.text().map_err(|e| e.into());
     }
 
+    fn prove_by_id(
+        &self,
+        grouping: &GroupingLabel,
+        id: &UnitId,
+        height: Option<&ChainHeight>,
+    ) -> ClientResult {
+        let mut url = format!(
+            "http://{}/{}/{}?prove",
+            &self.host,
+            grouping.to_string(),
+            id.as_int()
+        );
+        if let Some(height) = height {
+            url += &format!("&height={}", height.as_u64());
+        }
+        let mut response = reqwest::get(&url)?;
+        return response.text().map_err(|e| e.into());
+    }
+
     fn revert_by_id(
         &self,
         grouping: &GroupingLabel,
@@ -123,6 +207,44 @@ impl ImmuxDBConnector for ImmuxDBClient {
         return response.text().map_err(|e| e.into());
     }
 
+    fn revert_by_id_at(&self, grouping: &GroupingLabel, id: &UnitId, time: &str) -> ClientResult {
+        let client = reqwest::Client::new();
+        let mut response = client
+            .put(&format!(
+                "http://{}/{}/{}?revert_at={}",
+                &self.host,
+                grouping.to_string(),
+                id.as_int(),
+                encode_time(time)
+            ))
+            .send()?;
+        return response.text().map_err(|e| e.into());
+    }
+
+    fn revert_all_at(&self, time: &str) -> ClientResult {
+        let client = reqwest::Client::new();
+        let mut response = client
+            .put(&format!(
+                "http://{}/?revert_all_at={}",
+                &self.host,
+                encode_time(time)
+            ))
+            .send()?;
+        return response.text().map_err(|e| e.into());
+    }
+
+    fn revert_record(&self, height: &ChainHeight) -> ClientResult {
+        let client = reqwest::Client::new();
+        let mut response = client
+            .put(&format!(
+                "http://{}/?revert_record={}",
+                &self.host,
+                height.as_u64()
+            ))
+            .send()?;
+        return response.text().map_err(|e| e.into());
+    }
+
     fn set_unit(&self, grouping: &GroupingLabel, unit: &Unit) -> ClientResult {
         let client = reqwest::Client::new();
         let id = unit.id;
@@ -140,6 +262,19 @@ impl ImmuxDBConnector for ImmuxDBClient {
         return response.text().map_err(|e| e.into());
     }
 
+    fn remove_by_id(&self, grouping: &GroupingLabel, id: &UnitId) -> ClientResult {
+        let client = reqwest::Client::new();
+        let mut response = client
+            .delete(&format!(
+                "http://{}/{}/{}",
+                &self.host,
+                grouping.to_string(),
+                id.as_int()
+            ))
+            .send()?;
+        return response.text().map_err(|e| e.into());
+    }
+
     fn set_batch_units(&self, grouping: &GroupingLabel, units: &[Unit]) -> ClientResult {
         let client = reqwest::Client::new();
         let string_vec: Vec<String> = units
@@ -181,4 +316,161 @@ impl ImmuxDBConnector for ImmuxDBClient {
             .send()?;
         return response.text().map_err(|e| e.into());
     }
+
+    fn fork_chain(
+        &self,
+        source: &ChainName,
+        at_height: &ChainHeight,
+        new_chain_name: &ChainName,
+    ) -> ClientResult {
+        let client = reqwest::Client::new();
+        let mut response = client
+            .put(&format!(
+                "http://{}/?chain={}&fork={}&height={}",
+                &self.host,
+                source.to_string(),
+                new_chain_name.to_string(),
+                at_height.as_u64()
+            ))
+            .send()?;
+        return response.text().map_err(|e| e.into());
+    }
+
+    fn merge_chain(
+        &self,
+        target: &ChainName,
+        source: &ChainName,
+        resolutions: &[(GroupingLabel, UnitId, MergeStrategy)],
+        default_strategy: MergeStrategy,
+    ) -> ClientResult {
+        let strategy_str = |strategy: &MergeStrategy| match strategy {
+            MergeStrategy::Ours => "ours",
+            MergeStrategy::Theirs => "theirs",
+            MergeStrategy::Fail => "fail",
+        };
+        let body: Vec<String> = resolutions
+            .iter()
+            .map(|(grouping, id, strategy)| {
+                format!(
+                    "{}|{}|{}",
+                    grouping.to_string(),
+                    id.as_int(),
+                    strategy_str(strategy)
+                )
+            })
+            .collect();
+        let client = reqwest::Client::new();
+        let mut response = client
+            .put(&format!(
+                "http://{}/?chain={}&merge={}&strategy={}",
+                &self.host,
+                target.to_string(),
+                source.to_string(),
+                strategy_str(&default_strategy)
+            ))
+            .body(body.join("\r\n"))
+            .send()?;
+        return response.text().map_err(|e| e.into());
+    }
+
+    fn verify_chain(&self) -> ClientResult {
+        let mut response = reqwest::get(&format!("http://{}/?verify", &self.host))?;
+        return response.text().map_err(|e| e.into());
+    }
+
+    fn diff(
+        &self,
+        grouping: Option<&GroupingLabel>,
+        from_height: &ChainHeight,
+        to_height: Option<&ChainHeight>,
+    ) -> ClientResult {
+        let grouping_path = match grouping {
+            None => String::new(),
+            Some(grouping) => grouping.to_string(),
+        };
+        let mut url = format!(
+            "http://{}/{}?diff={}",
+            &self.host,
+            grouping_path,
+            from_height.as_u64()
+        );
+        if let Some(height) = to_height {
+            url += &format!("&height={}", height.as_u64());
+        }
+        let mut response = reqwest::get(&url)?;
+        return response.text().map_err(|e| e.into());
+    }
+
+    fn get_changes(&self, after_height: &ChainHeight, wait_ms: Option<u64>) -> ClientResult {
+        let mut url = format!("http://{}/?changes={}", &self.host, after_height.as_u64());
+        if let Some(wait_ms) = wait_ms {
+            url += &format!("&wait={}", wait_ms);
+        }
+        let mut response = reqwest::get(&url)?;
+        return response.text().map_err(|e| e.into());
+    }
+
+    fn create_tag(&self, name: &str, height: Option<&ChainHeight>) -> ClientResult {
+        let mut url = format!("http://{}/?tag={}", &self.host, name);
+        if let Some(height) = height {
+            url += &format!("&height={}", height.as_u64());
+        }
+        let client = reqwest::Client::new();
+        let mut response = client.put(&url).send()?;
+        return response.text().map_err(|e| e.into());
+    }
+
+    fn delete_tag(&self, name: &str) -> ClientResult {
+        let client = reqwest::Client::new();
+        let mut response = client
+            .delete(&format!("http://{}/?tag={}", &self.host, name))
+            .send()?;
+        return response.text().map_err(|e| e.into());
+    }
+
+    fn list_tags(&self) -> ClientResult {
+        let mut response = reqwest::get(&format!("http://{}/?tags", &self.host))?;
+        return response.text().map_err(|e| e.into());
+    }
+
+    /// Sets the policy of `grouping`, or of the whole chain if None. A count of chain heights to
+    /// keep takes precedence over a time to keep history since; giving neither removes the policy.
+    fn set_retention(
+        &self,
+        grouping: Option<&GroupingLabel>,
+        keep_heights: Option<u64>,
+        keep_since: Option<&str>,
+    ) -> ClientResult {
+        let mut url = match grouping {
+            None => format!("http://{}/?retention", &self.host),
+            Some(grouping) => format!("http://{}/{}?retention", &self.host, grouping.to_string()),
+        };
+        if let Some(keep_heights) = keep_heights {
+            url += &format!("&keep_heights={}", keep_heights);
+        }
+        if let Some(keep_since) = keep_since {
+            url += &format!("&keep_since={}", encode_time(keep_since));
+        }
+        let client = reqwest::Client::new();
+        let mut response = client.put(&url).send()?;
+        return response.text().map_err(|e| e.into());
+    }
+
+    fn get_retention(&self) -> ClientResult {
+        let mut response = reqwest::get(&format!("http://{}/?retention", &self.host))?;
+        return response.text().map_err(|e| e.into());
+    }
+
+    fn compact(&self) -> ClientResult {
+        let client = reqwest::Client::new();
+        let mut response = client
+            .put(&format!("http://{}/?compact", &self.host))
+            .send()?;
+        return response.text().map_err(|e| e.into());
+    }
+
+    fn replay_chain(&self) -> ClientResult {
+        let mut response = reqwest::get(&format!("http://{}/?replay", &self.host))?;
+        return response.text().map_err(|e| e.into());
+    }
 }
@@ -1,11 +1,14 @@
 use std::net::TcpStream;
 
 use crate::config::ImmuxDBConfiguration;
+use crate::cortices::mongo::cursors::MongoCursors;
 use crate::declarations::errors::ImmuxResult;
 use crate::storage::core::ImmuxDBCore;
+use crate::storage::instructions::StoreNamespace;
 
 pub mod mongo;
 pub mod mysql;
+pub mod select_filter;
 pub mod tcp;
 pub mod unicus;
 pub mod utils;
@@ -15,12 +18,29 @@ pub enum CortexResponse {
     SendThenDisconnect(Vec<u8>),
 }
 
+/// What a client connection keeps between its messages
+pub struct ConnectionState {
+    // The chain the connection works on, which other connections picking chains do not move
+    pub chain: StoreNamespace,
+    pub mongo_cursors: MongoCursors,
+}
+
+impl ConnectionState {
+    pub fn new(chain: StoreNamespace) -> Self {
+        ConnectionState {
+            chain,
+            mongo_cursors: MongoCursors::default(),
+        }
+    }
+}
+
 pub struct Cortex {
     process_incoming_message: fn(
         bytes: &[u8],
         core: &mut ImmuxDBCore,
         stream: &TcpStream,
         config: &ImmuxDBConfiguration,
+        connection: &mut ConnectionState,
     ) -> ImmuxResult<CortexResponse>,
     process_first_connection: Option<fn(core: &mut ImmuxDBCore) -> ImmuxResult<CortexResponse>>,
 }
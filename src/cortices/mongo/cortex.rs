@@ -3,6 +3,7 @@ use std::net::TcpStream;
 use bson::{Bson, Document};
 
 use crate::config::{load_config, ImmuxDBConfiguration};
+use crate::cortices::mongo::cursors::MongoCursors;
 use crate::cortices::mongo::ops::msg_header::MsgHeader;
 use crate::cortices::mongo::ops::op::MongoOp;
 use crate::cortices::mongo::ops::op_msg::{serialize_op_msg, Section};
@@ -16,9 +17,10 @@ use crate::cortices::mongo::transformer::{
 };
 use crate::cortices::mongo::utils::{construct_single_doc_op_msg, is_1, make_bson_from_config};
 use crate::cortices::tcp::TcpError;
-use crate::cortices::{Cortex, CortexResponse};
+use crate::cortices::{ConnectionState, Cortex, CortexResponse};
 use crate::declarations::errors::ImmuxResult;
 use crate::executor::execute::execute;
+use crate::executor::shared::get_current_namespace;
 use crate::storage::core::ImmuxDBCore;
 
 use crate::utils::u32_to_u8_array;
@@ -53,6 +55,7 @@ fn handle_exceptional_query(
     core: &mut ImmuxDBCore,
     stream: &TcpStream,
     config: &ImmuxDBConfiguration,
+    cursors: &mut MongoCursors,
 ) -> ExceptionQueryHandlerResult {
     match op {
         MongoOp::Query(op_query) => {
@@ -203,6 +206,33 @@ fn handle_exceptional_query(
                         response_doc.insert("code", 76i32);
                         response_doc.insert("codeName", "NoReplicationEnabled");
                         construct_reply_result(response_doc, &op_msg.message_header)
+                    } else if let Ok(cursor_ids) = request_doc.get_array("cursors") {
+                        if !request_doc.contains_key("killCursors") {
+                            return ExceptionQueryHandlerResult::NotExceptional;
+                        }
+                        let ids: Vec<i64> = cursor_ids
+                            .iter()
+                            .filter_map(|id| match id {
+                                Bson::I64(id) => Some(*id),
+                                _ => None,
+                            })
+                            .collect();
+                        let killed = cursors.close(&ids);
+                        let not_found: Vec<Bson> = ids
+                            .iter()
+                            .filter(|id| !killed.contains(id))
+                            .map(|id| Bson::I64(*id))
+                            .collect();
+                        let mut response_doc = Document::new();
+                        response_doc.insert(
+                            "cursorsKilled",
+                            killed.into_iter().map(Bson::I64).collect::<Vec<Bson>>(),
+                        );
+                        response_doc.insert("cursorsNotFound", not_found);
+                        response_doc.insert("cursorsAlive", vec![]);
+                        response_doc.insert("cursorsUnknown", vec![]);
+                        response_doc.insert("ok", 1.0);
+                        construct_reply_result(response_doc, &op_msg.message_header)
                     } else if request_doc.contains_key("endSessions") {
                         let mut response_doc = Document::new();
                         response_doc.insert("ok", 1.0);
@@ -227,15 +257,19 @@ pub fn mongo_cortex_process_incoming_message(
     core: &mut ImmuxDBCore,
     stream: &TcpStream,
     config: &ImmuxDBConfiguration,
+    connection: &mut ConnectionState,
 ) -> ImmuxResult<CortexResponse> {
     let op = parse_mongo_incoming_bytes(bytes)?;
     println!("Incoming op: {:#?}", op);
-    match handle_exceptional_query(&op, core, &stream, config) {
+    let cursors = &mut connection.mongo_cursors;
+    match handle_exceptional_query(&op, core, stream, config, cursors) {
         ExceptionQueryHandlerResult::Exceptional(result) => return result,
         ExceptionQueryHandlerResult::NotExceptional => {
-            let command = transform_mongo_op_to_command(&op)?;
+            let namespace = get_current_namespace(core)?;
+            let command = transform_mongo_op_to_command(&op, cursors, &namespace)?;
             let outcome = execute(command, core)?;
-            let op_msg = transform_outcome_to_mongo_msg(&outcome, config, &op)?;
+            let op_msg =
+                transform_outcome_to_mongo_msg(&outcome, config, &op, cursors, &namespace)?;
             println!("Response op: {:#?}", op_msg);
             match serialize_op_with_computed_length(&op_msg, &serialize_op_msg) {
                 Err(error) => return Err(error),
@@ -0,0 +1,156 @@
+/*
+ *  Server-side cursors, which let getMore resume a find whose result did not fit in its first batch
+**/
+
+use std::collections::BTreeMap;
+
+use crate::declarations::commands::SelectCommand;
+use crate::storage::instructions::StoreNamespace;
+
+// Cursors are seldom closed explicitly, so the oldest ones give way beyond this count
+const MAX_OPEN_CURSORS: usize = 1024;
+
+pub struct MongoCursor {
+    // The chain the find read, which the cursor's continuation token is only valid on
+    pub namespace: StoreNamespace,
+    // Selects the next batch, continuing where the previous one ended
+    pub command: SelectCommand,
+    // What is left of the find's limit; None if it had none
+    pub remaining: Option<usize>,
+}
+
+/// The open cursors of one client connection, which only that connection can get more from
+#[derive(Default)]
+pub struct MongoCursors {
+    cursors: BTreeMap<i64, MongoCursor>,
+    // Zero stands for an exhausted cursor in replies, so ids start at one
+    last_id: i64,
+}
+
+impl MongoCursors {
+    pub fn open(&mut self, cursor: MongoCursor) -> i64 {
+        self.last_id += 1;
+        let id = self.last_id;
+        self.cursors.insert(id, cursor);
+        while self.cursors.len() > MAX_OPEN_CURSORS {
+            let oldest = *self.cursors.keys().next().unwrap_or(&id);
+            self.cursors.remove(&oldest);
+        }
+        return id;
+    }
+
+    /// The select of the cursor's next batch, which holds at most `batch_size` units if given.
+    ///
+    /// Cursors opened on another chain than `namespace` are not found.
+    pub fn get_command(
+        &self,
+        id: i64,
+        namespace: &StoreNamespace,
+        batch_size: Option<usize>,
+    ) -> Option<SelectCommand> {
+        let cursor = self.cursors.get(&id)?;
+        if &cursor.namespace != namespace {
+            return None;
+        }
+        let mut command = cursor.command.clone();
+        if let Some(batch_size) = batch_size {
+            command.limit = Some(batch_size);
+        }
+        if let Some(remaining) = cursor.remaining {
+            command.limit = Some(
+                command
+                    .limit
+                    .map_or(remaining, |limit| limit.min(remaining)),
+            );
+        }
+        return Some(command);
+    }
+
+    /// Moves the cursor past a batch of `returned` units, returning its id, or 0 once it is
+    /// exhausted
+    pub fn advance(&mut self, id: i64, returned: usize, continuation: &Option<String>) -> i64 {
+        match (self.cursors.remove(&id), continuation) {
+            (Some(mut cursor), Some(token)) => {
+                cursor.remaining = cursor
+                    .remaining
+                    .map(|remaining| remaining.saturating_sub(returned));
+                if cursor.remaining == Some(0) {
+                    return 0;
+                }
+                cursor.command.continuation = Some(token.to_owned());
+                self.cursors.insert(id, cursor);
+                return id;
+            }
+            _ => return 0,
+        }
+    }
+
+    pub fn close(&mut self, ids: &[i64]) -> Vec<i64> {
+        return ids
+            .iter()
+            .filter(|id| self.cursors.remove(id).is_some())
+            .cloned()
+            .collect();
+    }
+}
+
+#[cfg(test)]
+mod mongo_cursors_tests {
+    use crate::cortices::mongo::cursors::{MongoCursor, MongoCursors};
+    use crate::declarations::basics::GroupingLabel;
+    use crate::declarations::commands::{SelectCommand, SelectCondition};
+    use crate::storage::instructions::StoreNamespace;
+
+    fn get_cursor(remaining: Option<usize>) -> MongoCursor {
+        MongoCursor {
+            namespace: StoreNamespace::new(b"chain"),
+            command: SelectCommand {
+                grouping: GroupingLabel::from("grouping"),
+                condition: SelectCondition::UnconditionalMatch,
+                height: None,
+                skip: 0,
+                limit: Some(2),
+                sort: None,
+                continuation: Some(String::from("first")),
+                projection: None,
+            },
+            remaining,
+        }
+    }
+
+    #[test]
+    fn test_cursor_lifecycle() {
+        let mut cursors = MongoCursors::default();
+        let namespace = StoreNamespace::new(b"chain");
+        let id = cursors.open(get_cursor(Some(5)));
+        assert_ne!(id, 0);
+
+        let command = cursors.get_command(id, &namespace, None).unwrap();
+        assert_eq!(command.limit, Some(2));
+        assert_eq!(command.continuation, Some(String::from("first")));
+        let command = cursors.get_command(id, &namespace, Some(10)).unwrap();
+        assert_eq!(command.limit, Some(5));
+
+        assert_eq!(cursors.advance(id, 2, &Some(String::from("second"))), id);
+        let command = cursors.get_command(id, &namespace, Some(10)).unwrap();
+        assert_eq!(command.limit, Some(3));
+        assert_eq!(command.continuation, Some(String::from("second")));
+
+        // The find's limit is used up
+        assert_eq!(cursors.advance(id, 3, &Some(String::from("third"))), 0);
+        assert!(cursors.get_command(id, &namespace, None).is_none());
+    }
+
+    #[test]
+    fn test_exhausted_and_closed_cursors() {
+        let mut cursors = MongoCursors::default();
+        let namespace = StoreNamespace::new(b"chain");
+        let exhausted = cursors.open(get_cursor(None));
+        assert_eq!(cursors.advance(exhausted, 2, &None), 0);
+        assert!(cursors.get_command(exhausted, &namespace, None).is_none());
+
+        let closed = cursors.open(get_cursor(None));
+        assert_eq!(cursors.close(&[closed, exhausted]), vec![closed]);
+        assert!(cursors.get_command(closed, &namespace, None).is_none());
+    }
+}
@@ -1,4 +1,5 @@
 pub mod cortex;
+pub mod cursors;
 pub mod error;
 pub mod ops;
 mod parser;
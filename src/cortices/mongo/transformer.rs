@@ -1,17 +1,24 @@
 use bson::{Bson, Document};
+use serde_json::Value as JsonValue;
 
 use crate::config::ImmuxDBConfiguration;
+use crate::cortices::mongo::cursors::{MongoCursor, MongoCursors};
 use crate::cortices::mongo::ops::msg_header::MsgHeader;
 use crate::cortices::mongo::ops::op::MongoOp;
 use crate::cortices::mongo::ops::op_msg::{OpMsg, Section};
 
 use crate::cortices::mongo::utils::{construct_single_doc_op_msg, is_1, make_bson_from_config};
-use crate::declarations::basics::{GroupingLabel, UnitContent, UnitId};
+use crate::cortices::select_filter::parse_select_filter;
+use crate::declarations::basics::{
+    GroupingLabel, PropertyName, PropertyNameList, UnitContent, UnitId,
+};
 use crate::declarations::commands::{
-    Command, InsertCommand, InsertCommandSpec, Outcome, PickChainCommand, SelectCommand,
-    SelectCondition,
+    Command, HeightSpecifier, InsertCommand, InsertCommandSpec, Outcome, PickChainCommand,
+    RemoveCommand, SelectCommand, SelectCondition, SelectOutcome, SelectSort, SortOrder,
 };
 use crate::declarations::errors::{ImmuxError, ImmuxResult};
+use crate::storage::instructions::StoreNamespace;
+use crate::storage::vkv::ChainHeight;
 
 #[derive(Debug)]
 pub enum MongoTransformerError {
@@ -19,7 +26,12 @@ pub enum MongoTransformerError {
     EncodeDocument,
     UnexpectedInputShape,
     UnimplementedWhereCondition(Bson),
+    UnexpectedCount(Bson),
+    UnexpectedSort(Document),
+    UnexpectedProjection(Document),
+    CursorNotFound(i64),
     UnexpectedFilterDocument(Document),
+    UnexpectedReadConcern(Document),
     UnimplementedCommand,
     UnexpectedLastSection,
     NoSections,
@@ -65,7 +77,135 @@ fn get_doc_unit_id(doc: &Document) -> ImmuxResult<UnitId> {
     }
 }
 
-pub fn transform_mongo_op_to_command(op: &MongoOp) -> ImmuxResult<Command> {
+fn get_count(request_doc: &Document, key: &str) -> ImmuxResult<Option<usize>> {
+    match request_doc.get(key) {
+        None => Ok(None),
+        Some(Bson::I32(count)) if *count >= 0 => Ok(Some(*count as usize)),
+        Some(Bson::I64(count)) if *count >= 0 => Ok(Some(*count as usize)),
+        Some(Bson::FloatingPoint(count)) if *count >= 0.0 && count.fract() == 0.0 => {
+            Ok(Some(*count as usize))
+        }
+        Some(count) => Err(MongoTransformerError::UnexpectedCount(count.to_owned()).into()),
+    }
+}
+
+// Only sorting by one property, like {age: -1}, is supported
+fn get_sort(request_doc: &Document) -> ImmuxResult<Option<SelectSort>> {
+    let sort = match request_doc.get_document("sort") {
+        Err(_error) => return Ok(None),
+        Ok(sort) => sort,
+    };
+    let mut keys = sort.iter();
+    match (keys.next(), keys.next()) {
+        (None, _) => Ok(None),
+        (Some((name, direction)), None) => {
+            let order = match direction {
+                Bson::I32(1) | Bson::I64(1) => SortOrder::Ascending,
+                Bson::FloatingPoint(direction) if *direction == 1.0 => SortOrder::Ascending,
+                Bson::I32(-1) | Bson::I64(-1) => SortOrder::Descending,
+                Bson::FloatingPoint(direction) if *direction == -1.0 => SortOrder::Descending,
+                _ => return Err(MongoTransformerError::UnexpectedSort(sort.to_owned()).into()),
+            };
+            Ok(Some(SelectSort {
+                name: PropertyName::from(name.as_str()),
+                order,
+            }))
+        }
+        (Some(_), Some(_)) => Err(MongoTransformerError::UnexpectedSort(sort.to_owned()).into()),
+    }
+}
+
+// Only inclusive projections like {name: 1, "address.city": 1} are supported, where _id is kept
+// unless excluded with {_id: 0}
+fn get_projection(request_doc: &Document) -> ImmuxResult<Option<PropertyNameList>> {
+    let projection = match request_doc.get_document("projection") {
+        Err(_error) => return Ok(None),
+        Ok(projection) => projection,
+    };
+    if projection.is_empty() {
+        return Ok(None);
+    }
+    let mut names = vec![];
+    let mut keeps_id = true;
+    for (name, included) in projection.iter() {
+        let is_included = match included {
+            Bson::Boolean(included) => *included,
+            Bson::I32(included) => *included != 0,
+            Bson::I64(included) => *included != 0,
+            Bson::FloatingPoint(included) => *included != 0.0,
+            _ => {
+                return Err(
+                    MongoTransformerError::UnexpectedProjection(projection.to_owned()).into(),
+                )
+            }
+        };
+        if is_included {
+            names.push(PropertyName::from(name.as_str()));
+        } else if name == "_id" {
+            keeps_id = false;
+        } else {
+            return Err(MongoTransformerError::UnexpectedProjection(projection.to_owned()).into());
+        }
+    }
+    if names.is_empty() {
+        return Err(MongoTransformerError::UnexpectedProjection(projection.to_owned()).into());
+    }
+    if keeps_id && !names.iter().any(|name| name.to_string() == "_id") {
+        names.push(PropertyName::from("_id"));
+    }
+    return Ok(Some(PropertyNameList::new(names)));
+}
+
+struct FindPaging {
+    skip: usize,
+    // The find's limit; zero in the request stands for none
+    limit: Option<usize>,
+    // Units in the first batch, bounded by both the limit and the batch size
+    batch_limit: Option<usize>,
+    sort: Option<SelectSort>,
+}
+
+fn get_find_paging(request_doc: &Document) -> ImmuxResult<FindPaging> {
+    let limit = get_count(request_doc, "limit")?.filter(|limit| *limit > 0);
+    let batch_size = get_count(request_doc, "batchSize")?.filter(|batch_size| *batch_size > 0);
+    let batch_limit = match (limit, batch_size) {
+        (Some(limit), Some(batch_size)) => Some(limit.min(batch_size)),
+        (limit, None) => limit,
+        (None, batch_size) => batch_size,
+    };
+    Ok(FindPaging {
+        skip: get_count(request_doc, "skip")?.unwrap_or(0),
+        limit,
+        batch_limit,
+        sort: get_sort(request_doc)?,
+    })
+}
+
+// Historical reads are requested with a read concern like {atHeight: 42}
+fn get_read_height(request_doc: &Document) -> ImmuxResult<Option<ChainHeight>> {
+    match request_doc.get_document("readConcern") {
+        Err(_error) => Ok(None),
+        Ok(read_concern) => match read_concern.get("atHeight") {
+            None => Ok(None),
+            Some(Bson::I32(height)) if *height >= 0 => Ok(Some(ChainHeight::new(*height as u64))),
+            Some(Bson::I64(height)) if *height >= 0 => Ok(Some(ChainHeight::new(*height as u64))),
+            Some(Bson::FloatingPoint(height)) if *height >= 0.0 && height.fract() == 0.0 => {
+                Ok(Some(ChainHeight::new(*height as u64)))
+            }
+            Some(_) => {
+                Err(MongoTransformerError::UnexpectedReadConcern(read_concern.to_owned()).into())
+            }
+        },
+    }
+}
+
+/// Transforms an op into a command, continuing one of `cursors` on a getMore of the chain
+/// `namespace`
+pub fn transform_mongo_op_to_command(
+    op: &MongoOp,
+    cursors: &MongoCursors,
+    namespace: &StoreNamespace,
+) -> ImmuxResult<Command> {
     match op {
         MongoOp::Msg(op_msg) => {
             if let Some(last_section) = &op_msg.sections.last() {
@@ -110,39 +250,101 @@ pub fn transform_mongo_op_to_command(op: &MongoOp) -> ImmuxResult<Command> {
                         } else if let Ok(grouping_str) = request_doc.get_str("find") {
                             if let Ok(filter) = request_doc.get_document("filter") {
                                 let grouping = GroupingLabel::from(grouping_str.as_bytes());
-                                if filter.is_empty() {
-                                    let command = SelectCommand {
-                                        grouping,
-                                        condition: SelectCondition::UnconditionalMatch,
-                                    };
-                                    Ok(Command::Select(command))
-                                } else if let Some(where_condition) = filter.get("$where") {
+                                let height =
+                                    get_read_height(request_doc)?.map(HeightSpecifier::from);
+                                let condition = if filter.is_empty() {
+                                    SelectCondition::UnconditionalMatch
+                                } else if let (1, Some(where_condition)) =
+                                    (filter.len(), filter.get("$where"))
+                                {
                                     match where_condition {
-                                        Bson::JavaScriptCode(code) => {
-                                            let command = SelectCommand {
-                                                grouping,
-                                                condition: SelectCondition::JSCode(
-                                                    code.to_string(),
-                                                ),
-                                            };
-                                            Ok(Command::Select(command))
+                                        Bson::JavaScriptCode(code) | Bson::String(code) => {
+                                            SelectCondition::JSCode(code.to_string())
                                         }
                                         _ => {
-                                            Err(MongoTransformerError::UnimplementedWhereCondition(
-                                                where_condition.to_owned(),
-                                            )
-                                            .into())
+                                            return Err(
+                                                MongoTransformerError::UnimplementedWhereCondition(
+                                                    where_condition.to_owned(),
+                                                )
+                                                .into(),
+                                            );
                                         }
                                     }
                                 } else {
-                                    Err(MongoTransformerError::UnexpectedFilterDocument(
-                                        filter.to_owned(),
-                                    )
-                                    .into())
-                                }
+                                    let filter_json =
+                                        JsonValue::from(Bson::Document(filter.to_owned()));
+                                    parse_select_filter(&filter_json)?
+                                };
+                                let paging = get_find_paging(request_doc)?;
+                                let command = SelectCommand {
+                                    grouping,
+                                    condition,
+                                    height,
+                                    skip: paging.skip,
+                                    limit: paging.batch_limit,
+                                    sort: paging.sort,
+                                    continuation: None,
+                                    projection: get_projection(request_doc)?,
+                                };
+                                Ok(Command::Select(command))
                             } else {
                                 Err(MongoTransformerError::UnexpectedInputShape.into())
                             }
+                        } else if let Ok(cursor_id) = request_doc.get_i64("getMore") {
+                            let batch_size = get_count(request_doc, "batchSize")?
+                                .filter(|batch_size| *batch_size > 0);
+                            match cursors.get_command(cursor_id, namespace, batch_size) {
+                                None => {
+                                    Err(MongoTransformerError::CursorNotFound(cursor_id).into())
+                                }
+                                Some(command) => Ok(Command::Select(command)),
+                            }
+                        } else if let Ok(grouping_str) = request_doc.get_str("delete") {
+                            let statements: Vec<Document> = match op_msg.sections.first() {
+                                Some(Section::Sequence(sequence)) => sequence.documents.clone(),
+                                _ => match request_doc.get_array("deletes") {
+                                    Err(_error) => {
+                                        return Err(
+                                            MongoTransformerError::UnexpectedInputShape.into()
+                                        );
+                                    }
+                                    Ok(deletes) => deletes
+                                        .iter()
+                                        .filter_map(|statement| match statement {
+                                            Bson::Document(doc) => Some(doc.to_owned()),
+                                            _ => None,
+                                        })
+                                        .collect(),
+                                },
+                            };
+                            let mut ids: Vec<UnitId> = Vec::new();
+                            for statement in &statements {
+                                match statement.get_document("q") {
+                                    Err(_error) => {
+                                        return Err(
+                                            MongoTransformerError::UnexpectedInputShape.into()
+                                        );
+                                    }
+                                    Ok(filter) => match filter.get("_id") {
+                                        Some(Bson::ObjectId(_)) => {
+                                            ids.push(get_doc_unit_id(filter)?)
+                                        }
+                                        _ => {
+                                            return Err(
+                                                MongoTransformerError::UnexpectedFilterDocument(
+                                                    filter.to_owned(),
+                                                )
+                                                .into(),
+                                            );
+                                        }
+                                    },
+                                }
+                            }
+                            let command = RemoveCommand {
+                                grouping: GroupingLabel::from(grouping_str),
+                                ids,
+                            };
+                            Ok(Command::Remove(command))
                         } else {
                             Err(MongoTransformerError::UnimplementedCommand.into())
                         }
@@ -157,6 +359,49 @@ pub fn transform_mongo_op_to_command(op: &MongoOp) -> ImmuxResult<Command> {
     }
 }
 
+fn get_request_doc(op: &MongoOp) -> Option<&Document> {
+    match op {
+        MongoOp::Msg(op_msg) => match op_msg.sections.last() {
+            Some(Section::Single(request_doc)) => Some(request_doc),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Keeps a cursor open on the rest of a find's result, if it did not fit in the first batch.
+///
+/// Returns the cursor's id, or 0 if there is nothing left to get.
+fn open_find_cursor(
+    incoming_op: &MongoOp,
+    outcome: &SelectOutcome,
+    cursors: &mut MongoCursors,
+    namespace: &StoreNamespace,
+) -> ImmuxResult<i64> {
+    let (token, request_doc) = match (&outcome.continuation, get_request_doc(incoming_op)) {
+        (Some(token), Some(request_doc)) => (token, request_doc),
+        _ => return Ok(0),
+    };
+    let paging = get_find_paging(request_doc)?;
+    let remaining = paging
+        .limit
+        .map(|limit| limit.saturating_sub(outcome.units.len()));
+    if remaining == Some(0) {
+        return Ok(0);
+    }
+    match transform_mongo_op_to_command(incoming_op, cursors, namespace)? {
+        Command::Select(mut command) => {
+            command.continuation = Some(token.to_owned());
+            Ok(cursors.open(MongoCursor {
+                namespace: namespace.to_owned(),
+                command,
+                remaining,
+            }))
+        }
+        _ => Ok(0),
+    }
+}
+
 fn get_header_from_op(op: &MongoOp) -> Result<MsgHeader, MongoTransformerError> {
     match op {
         MongoOp::Msg(msg) => Ok(msg.message_header.clone()),
@@ -170,6 +415,8 @@ pub fn transform_outcome_to_mongo_msg(
     outcome: &Outcome,
     config: &ImmuxDBConfiguration,
     incoming_op: &MongoOp,
+    cursors: &mut MongoCursors,
+    namespace: &StoreNamespace,
 ) -> ImmuxResult<OpMsg> {
     let header = get_header_from_op(&incoming_op)?;
     match outcome {
@@ -200,18 +447,52 @@ pub fn transform_outcome_to_mongo_msg(
                     _ => Bson::Document(Document::new()),
                 })
                 .collect();
-            cursor.insert("firstBatch", documents);
-            cursor.insert("id", 0i64);
+            let get_more_cursor_id = get_request_doc(incoming_op)
+                .and_then(|request_doc| request_doc.get_i64("getMore").ok());
+            let (batch_name, cursor_id) = match get_more_cursor_id {
+                Some(id) => (
+                    "nextBatch",
+                    cursors.advance(id, ok.units.len(), &ok.continuation),
+                ),
+                None => (
+                    "firstBatch",
+                    open_find_cursor(incoming_op, ok, cursors, namespace)?,
+                ),
+            };
+            cursor.insert(batch_name, documents);
+            cursor.insert("id", cursor_id);
             cursor.insert("ns", ""); // Skipped actual implementation. See issue #82.
             doc.insert("cursor", cursor);
             doc.insert("ok", 1.0);
             Ok(construct_single_doc_op_msg(doc, &header))
         }
+        Outcome::Remove(ok) => {
+            let mut doc = Document::new();
+            doc.insert("n", ok.count as i32);
+            doc.insert("ok", 1.0);
+            Ok(construct_single_doc_op_msg(doc, &header))
+        }
         Outcome::NameChain(_ok) => unimplemented!(),
         Outcome::CreateIndex(_ok) => unimplemented!(),
         Outcome::RevertMany(_) => unimplemented!(),
         Outcome::RevertAll(_) => unimplemented!(),
         Outcome::Inspect(_) => unimplemented!(),
+        Outcome::VerifyChain(_) => unimplemented!(),
+        Outcome::Prove(_) => unimplemented!(),
+        Outcome::ForkChain(_) => unimplemented!(),
+        Outcome::MergeChain(_) => unimplemented!(),
+        Outcome::Diff(_) => unimplemented!(),
+        Outcome::ChangeFeed(_) => unimplemented!(),
+        Outcome::RevertRecord(_) => unimplemented!(),
+        Outcome::CreateTag(_) => unimplemented!(),
+        Outcome::DeleteTag(_) => unimplemented!(),
+        Outcome::ListTags(_) => unimplemented!(),
+        Outcome::SetRetention(_) => unimplemented!(),
+        Outcome::GetRetention(_) => unimplemented!(),
+        Outcome::Compact(_) => unimplemented!(),
+        Outcome::ReplayChain(_) => unimplemented!(),
+        Outcome::ExportChain(_) => unimplemented!(),
+        Outcome::ImportChain(_) => unimplemented!(),
     }
 }
 
@@ -222,16 +503,22 @@ mod mongo_command_transformer_tests {
     use bson::Bson;
     use bson::Document;
 
+    use crate::cortices::mongo::cursors::MongoCursors;
     use crate::cortices::mongo::ops::msg_header::MsgHeader;
     use crate::cortices::mongo::ops::op::MongoOp;
     use crate::cortices::mongo::ops::op_msg::Section::{Sequence, Single};
     use crate::cortices::mongo::ops::op_msg::{DocumentSequence, OpMsg, OpMsgFlags};
     use crate::cortices::mongo::ops::opcodes::MongoOpCode;
-    use crate::cortices::mongo::transformer::transform_mongo_op_to_command;
+    use crate::cortices::mongo::transformer::{
+        transform_mongo_op_to_command, MongoTransformerError,
+    };
     use crate::cortices::mongo::utils::construct_single_doc_op_msg;
 
-    use crate::declarations::basics::UnitContent;
-    use crate::declarations::commands::{Command, SelectCondition};
+    use crate::declarations::basics::{PropertyNameList, UnitContent, UnitId};
+    use crate::declarations::commands::{Command, HeightSpecifier, SelectCondition, SortOrder};
+    use crate::declarations::errors::{ImmuxError, ImmuxResult};
+    use crate::storage::instructions::StoreNamespace;
+    use crate::storage::vkv::ChainHeight;
 
     static HEADER: MsgHeader = MsgHeader {
         message_length: 0,
@@ -240,6 +527,12 @@ mod mongo_command_transformer_tests {
         op_code: MongoOpCode::OpMsg,
     };
 
+    // A connection without open cursors
+    fn transform_msg(op: OpMsg) -> ImmuxResult<Command> {
+        let namespace = StoreNamespace::new(b"test");
+        transform_mongo_op_to_command(&MongoOp::Msg(op), &MongoCursors::default(), &namespace)
+    }
+
     // use SOME_DB
     #[test]
     fn test_ismaster() {
@@ -250,7 +543,7 @@ mod mongo_command_transformer_tests {
         doc.insert("$db", target_db_name.clone());
 
         let op = construct_single_doc_op_msg(doc, &HEADER);
-        match transform_mongo_op_to_command(&MongoOp::Msg(op)) {
+        match transform_msg(op) {
             Ok(Command::PickChain(pick_chain)) => {
                 assert_eq!(
                     pick_chain.new_chain_name.as_bytes(),
@@ -314,7 +607,7 @@ mod mongo_command_transformer_tests {
                 }),
             ],
         };
-        match transform_mongo_op_to_command(&MongoOp::Msg(op)) {
+        match transform_msg(op) {
             Ok(Command::Insert(insert)) => {
                 assert_eq!(data.len(), insert.targets.len());
                 assert_eq!(insert.grouping.as_bytes(), collection.as_bytes());
@@ -370,7 +663,7 @@ mod mongo_command_transformer_tests {
         insert_adhoc_lsid(&mut doc);
         doc.insert("$db", "test");
         let op = construct_single_doc_op_msg(doc, &HEADER);
-        match transform_mongo_op_to_command(&MongoOp::Msg(op)) {
+        match transform_msg(op) {
             Ok(Command::Select(select)) => {
                 assert_eq!(select.grouping.as_bytes(), collection.as_bytes());
                 match select.condition {
@@ -383,6 +676,128 @@ mod mongo_command_transformer_tests {
         }
     }
 
+    // db.collection_name.deleteOne({_id: ObjectId("000000000000000000000007")})
+    #[test]
+    fn test_delete_by_id() {
+        let collection = String::from("collection_name");
+        let op = OpMsg {
+            message_header: HEADER.clone(),
+            flags: OpMsgFlags {
+                check_sum_present: false,
+                more_to_come: false,
+                exhaust_allowed: false,
+            },
+            sections: vec![
+                Sequence(DocumentSequence {
+                    section_size: 0,
+                    identifier: String::from("deletes"),
+                    documents: vec![{
+                        let mut filter = Document::new();
+                        filter.insert(
+                            "_id",
+                            Bson::ObjectId(ObjectId::with_bytes([
+                                0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 7,
+                            ])),
+                        );
+                        let mut statement = Document::new();
+                        statement.insert("q", filter);
+                        statement.insert("limit", 1);
+                        statement
+                    }],
+                }),
+                Single({
+                    let mut doc = Document::new();
+                    doc.insert("delete", collection.clone());
+                    doc.insert("ordered", true);
+                    insert_adhoc_lsid(&mut doc);
+                    doc.insert("$db", "test");
+                    doc
+                }),
+            ],
+        };
+        match transform_msg(op) {
+            Ok(Command::Remove(remove)) => {
+                assert_eq!(remove.grouping.as_bytes(), collection.as_bytes());
+                assert_eq!(remove.ids.len(), 1);
+                assert_eq!(
+                    remove.ids[0],
+                    UnitId::from(&[0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 7, 0, 0, 0, 0])
+                );
+            }
+            Ok(_) => panic!("Mongo delete should be translated to remove command"),
+            Err(error) => panic!("Failed to transform command {:#?}", error),
+        }
+    }
+
+    // db.runCommand({find: "Collection name", filter: {}, readConcern: {atHeight: 42}})
+    #[test]
+    fn test_find_at_height() {
+        let collection = String::from("Collection name");
+
+        let mut doc = Document::new();
+        doc.insert("find", collection.clone());
+        doc.insert("filter", Document::new());
+        let mut read_concern = Document::new();
+        read_concern.insert("atHeight", 42i64);
+        doc.insert("readConcern", read_concern);
+        insert_adhoc_lsid(&mut doc);
+        doc.insert("$db", "test");
+        let op = construct_single_doc_op_msg(doc, &HEADER);
+        match transform_msg(op) {
+            Ok(Command::Select(select)) => {
+                assert_eq!(
+                    select.height,
+                    Some(HeightSpecifier::Height(ChainHeight::new(42)))
+                );
+            }
+            Ok(_) => panic!("Mongo find should be translated to select command"),
+            Err(error) => panic!("Failed to transform command {:#?}", error),
+        }
+    }
+
+    // db.collection_name.find({$or: [{name: "tom"}, {age: {$gt: 3}}]})
+    #[test]
+    fn test_find_by_filter() {
+        let collection = String::from("Collection name");
+
+        let mut doc = Document::new();
+        doc.insert("find", collection.clone());
+        let mut by_name = Document::new();
+        by_name.insert("name", "tom");
+        let mut greater_than = Document::new();
+        greater_than.insert("$gt", 3i32);
+        let mut by_age = Document::new();
+        by_age.insert("age", greater_than);
+        let mut filter = Document::new();
+        filter.insert("$or", vec![Bson::Document(by_name), Bson::Document(by_age)]);
+        doc.insert("filter", filter);
+        insert_adhoc_lsid(&mut doc);
+        doc.insert("$db", "test");
+        let op = construct_single_doc_op_msg(doc, &HEADER);
+        match transform_msg(op) {
+            Ok(Command::Select(select)) => match select.condition {
+                SelectCondition::Or(operands) => {
+                    assert_eq!(operands.len(), 2);
+                    match (&operands[0], &operands[1]) {
+                        (
+                            SelectCondition::NameProperty(name, UnitContent::String(property)),
+                            SelectCondition::GreaterThan(age, UnitContent::Float64(bound)),
+                        ) => {
+                            assert_eq!(name.to_string(), "name");
+                            assert_eq!(property, "tom");
+                            assert_eq!(age.to_string(), "age");
+                            assert_eq!(*bound, 3.0);
+                        }
+                        _ => panic!("select.condition is unexpected"),
+                    }
+                }
+                _ => panic!("select.condition is unexpected"),
+            },
+            Ok(_) => panic!("Mongo find should be translated to select command"),
+            Err(error) => panic!("Failed to transform command {:#?}", error),
+        }
+    }
+
     // db.collection_name.find(x => x == 1)
     #[test]
     fn test_find_by_javascript() {
@@ -397,7 +812,7 @@ mod mongo_command_transformer_tests {
         insert_adhoc_lsid(&mut doc);
         doc.insert("$db", "test");
         let op = construct_single_doc_op_msg(doc, &HEADER);
-        match transform_mongo_op_to_command(&MongoOp::Msg(op)) {
+        match transform_msg(op) {
             Ok(Command::Select(select)) => {
                 assert_eq!(select.grouping.as_bytes(), collection.as_bytes());
                 match select.condition {
@@ -411,6 +826,150 @@ mod mongo_command_transformer_tests {
             Err(error) => panic!("Failed to transform command {:#?}", error),
         }
     }
+
+    // db.collection_name.find({age: {$gt: 3}, $where: "this.name.length > 2"})
+    #[test]
+    fn test_find_by_javascript_and_filter() {
+        let collection = String::from("Collection name");
+        let js_code = String::from("this.name.length > 2");
+
+        let mut doc = Document::new();
+        doc.insert("find", collection.clone());
+        let mut greater_than = Document::new();
+        greater_than.insert("$gt", 3i32);
+        let mut filter = Document::new();
+        filter.insert("age", greater_than);
+        filter.insert("$where", js_code.clone());
+        doc.insert("filter", filter);
+        insert_adhoc_lsid(&mut doc);
+        doc.insert("$db", "test");
+        let op = construct_single_doc_op_msg(doc, &HEADER);
+        match transform_msg(op) {
+            Ok(Command::Select(select)) => match select.condition {
+                SelectCondition::And(operands) => match &operands[..] {
+                    [SelectCondition::GreaterThan(age, _), SelectCondition::JSCode(code)] => {
+                        assert_eq!(age.to_string(), "age");
+                        assert_eq!(code, &js_code);
+                    }
+                    _ => panic!("select.condition is unexpected"),
+                },
+                _ => panic!("select.condition is unexpected"),
+            },
+            Ok(_) => panic!("Mongo find should be translated to select command"),
+            Err(error) => panic!("Failed to transform command {:#?}", error),
+        }
+    }
+
+    #[test]
+    fn test_find_with_paging() {
+        let mut doc = Document::new();
+        doc.insert("find", "Collection name");
+        doc.insert("filter", Document::new());
+        doc.insert("skip", 3i32);
+        doc.insert("limit", 10i64);
+        doc.insert("batchSize", 4i32);
+        let mut sort = Document::new();
+        sort.insert("age", -1i32);
+        doc.insert("sort", sort);
+        doc.insert("$db", "test");
+        let op = construct_single_doc_op_msg(doc, &HEADER);
+        match transform_msg(op) {
+            Ok(Command::Select(select)) => {
+                assert_eq!(select.skip, 3);
+                assert_eq!(select.limit, Some(4));
+                assert_eq!(select.continuation, None);
+                match select.sort {
+                    Some(sort) => {
+                        assert_eq!(sort.name.to_string(), "age");
+                        assert!(sort.order == SortOrder::Descending);
+                    }
+                    None => panic!("select.sort is unexpected"),
+                }
+            }
+            Ok(_) => panic!("Mongo find should be translated to select command"),
+            Err(error) => panic!("Failed to transform command {:#?}", error),
+        }
+    }
+
+    #[test]
+    fn test_find_with_projection() {
+        let find_with_projection = |projection: Document| {
+            let mut doc = Document::new();
+            doc.insert("find", "Collection name");
+            doc.insert("filter", Document::new());
+            doc.insert("projection", projection);
+            doc.insert("$db", "test");
+            let op = construct_single_doc_op_msg(doc, &HEADER);
+            transform_msg(op)
+        };
+        let names_of = |list: &PropertyNameList| -> Vec<String> {
+            list.as_slice()
+                .iter()
+                .map(|name| name.to_string())
+                .collect()
+        };
+
+        let mut projection = Document::new();
+        projection.insert("name", 1i32);
+        projection.insert("address.city", true);
+        match find_with_projection(projection.clone()) {
+            Ok(Command::Select(select)) => {
+                assert_eq!(
+                    names_of(&select.projection.unwrap()),
+                    vec!["name", "address.city", "_id"]
+                );
+            }
+            _ => panic!("Mongo find should be translated to select command"),
+        }
+
+        projection.insert("_id", 0i32);
+        match find_with_projection(projection) {
+            Ok(Command::Select(select)) => {
+                assert_eq!(
+                    names_of(&select.projection.unwrap()),
+                    vec!["name", "address.city"]
+                );
+            }
+            _ => panic!("Mongo find should be translated to select command"),
+        }
+
+        let mut exclusion = Document::new();
+        exclusion.insert("name", 0i32);
+        match find_with_projection(exclusion) {
+            Err(ImmuxError::MongoTransformer(MongoTransformerError::UnexpectedProjection(_))) => {}
+            _ => panic!("Exclusive projections should be rejected"),
+        }
+    }
+
+    #[test]
+    fn test_find_with_unsupported_sort() {
+        let mut doc = Document::new();
+        doc.insert("find", "Collection name");
+        doc.insert("filter", Document::new());
+        let mut sort = Document::new();
+        sort.insert("age", 1i32);
+        sort.insert("name", 1i32);
+        doc.insert("sort", sort);
+        doc.insert("$db", "test");
+        let op = construct_single_doc_op_msg(doc, &HEADER);
+        match transform_msg(op) {
+            Err(ImmuxError::MongoTransformer(MongoTransformerError::UnexpectedSort(_))) => {}
+            _ => panic!("Sorting by two properties should be rejected"),
+        }
+    }
+
+    #[test]
+    fn test_get_more_of_unknown_cursor() {
+        let mut doc = Document::new();
+        doc.insert("getMore", -1i64);
+        doc.insert("collection", "Collection name");
+        doc.insert("$db", "test");
+        let op = construct_single_doc_op_msg(doc, &HEADER);
+        match transform_msg(op) {
+            Err(ImmuxError::MongoTransformer(MongoTransformerError::CursorNotFound(-1))) => {}
+            _ => panic!("getMore should fail on unknown cursors"),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -418,14 +977,29 @@ mod mongo_outcome_transformer_tests {
     use bson::Document;
 
     use crate::config::ImmuxDBConfiguration;
+    use crate::cortices::mongo::cursors::MongoCursors;
     use crate::cortices::mongo::ops::msg_header::MsgHeader;
     use crate::cortices::mongo::ops::op::MongoOp;
     use crate::cortices::mongo::ops::op_msg::{OpMsg, OpMsgFlags, Section};
     use crate::cortices::mongo::ops::opcodes::MongoOpCode;
-    use crate::cortices::mongo::transformer::transform_outcome_to_mongo_msg;
+    use crate::cortices::mongo::transformer::{
+        transform_mongo_op_to_command, transform_outcome_to_mongo_msg, MongoTransformerError,
+    };
+    use crate::cortices::mongo::utils::construct_single_doc_op_msg;
 
     use crate::declarations::basics::ChainName;
-    use crate::declarations::commands::{InsertOutcome, Outcome, PickChainOutcome, SelectOutcome};
+    use crate::declarations::commands::{
+        Command, InsertOutcome, Outcome, PickChainOutcome, SelectOutcome,
+    };
+    use crate::declarations::errors::ImmuxError;
+    use crate::storage::instructions::StoreNamespace;
+
+    static HEADER: MsgHeader = MsgHeader {
+        message_length: 0,
+        request_id: 10,
+        response_to: 0,
+        op_code: MongoOpCode::OpMsg,
+    };
 
     #[test]
     fn test_pickchain() {
@@ -456,6 +1030,8 @@ mod mongo_outcome_transformer_tests {
             &Outcome::PickChain(outcome),
             &mock_config,
             &MongoOp::Msg(mock_incoming_op),
+            &mut MongoCursors::default(),
+            &StoreNamespace::new(b"test"),
         ) {
             Err(_error) => panic!("Cannot transform pickchain outcome"),
             Ok(op_msg) => {
@@ -520,6 +1096,8 @@ mod mongo_outcome_transformer_tests {
             &Outcome::Insert(outcome.clone()),
             &mock_config,
             &MongoOp::Msg(mock_incoming_op),
+            &mut MongoCursors::default(),
+            &StoreNamespace::new(b"test"),
         ) {
             Err(_error) => panic!("Cannot transform insert outcome"),
             Ok(op_msg) => {
@@ -553,11 +1131,16 @@ mod mongo_outcome_transformer_tests {
             },
             sections: vec![],
         };
-        let outcome = SelectOutcome { units: vec![] };
+        let outcome = SelectOutcome {
+            units: vec![],
+            continuation: None,
+        };
         match transform_outcome_to_mongo_msg(
             &Outcome::Select(outcome.clone()),
             &mock_config,
             &MongoOp::Msg(mock_incoming_op),
+            &mut MongoCursors::default(),
+            &StoreNamespace::new(b"test"),
         ) {
             Err(_error) => panic!("Cannot transform select outcome"),
             Ok(op_msg) => {
@@ -572,4 +1155,82 @@ mod mongo_outcome_transformer_tests {
             }
         }
     }
+
+    #[test]
+    fn test_select_in_batches() {
+        let mock_config = ImmuxDBConfiguration::default();
+        let mut cursors = MongoCursors::default();
+        let namespace = StoreNamespace::new(b"test");
+        let mut find_doc = Document::new();
+        find_doc.insert("find", "Collection name");
+        find_doc.insert("filter", Document::new());
+        find_doc.insert("limit", 5i32);
+        find_doc.insert("batchSize", 2i32);
+        find_doc.insert("$db", "test");
+        let find_op = MongoOp::Msg(construct_single_doc_op_msg(find_doc, &HEADER));
+        let outcome = SelectOutcome {
+            units: vec![],
+            continuation: Some(String::from("token")),
+        };
+        let get_cursor = |op_msg: OpMsg| match &op_msg.sections[0] {
+            Section::Single(doc) => doc.get_document("cursor").unwrap().to_owned(),
+            _ => panic!("Unexpected section type"),
+        };
+        let cursor = match transform_outcome_to_mongo_msg(
+            &Outcome::Select(outcome),
+            &mock_config,
+            &find_op,
+            &mut cursors,
+            &namespace,
+        ) {
+            Err(_error) => panic!("Cannot transform select outcome"),
+            Ok(op_msg) => get_cursor(op_msg),
+        };
+        assert!(cursor.get_array("firstBatch").is_ok());
+        let cursor_id = cursor.get_i64("id").unwrap();
+        assert_ne!(cursor_id, 0);
+
+        let mut get_more_doc = Document::new();
+        get_more_doc.insert("getMore", cursor_id);
+        get_more_doc.insert("collection", "Collection name");
+        get_more_doc.insert("$db", "test");
+        let get_more_op = MongoOp::Msg(construct_single_doc_op_msg(get_more_doc, &HEADER));
+        match transform_mongo_op_to_command(&get_more_op, &cursors, &namespace) {
+            Ok(Command::Select(select)) => {
+                assert_eq!(select.limit, Some(2));
+                assert_eq!(select.continuation, Some(String::from("token")));
+            }
+            _ => panic!("getMore should be translated to select command"),
+        }
+
+        // The cursor belongs to this connection and chain
+        let other_chain = StoreNamespace::new(b"other");
+        for (other_cursors, namespace) in &[
+            (&MongoCursors::default(), &namespace),
+            (&cursors, &other_chain),
+        ] {
+            match transform_mongo_op_to_command(&get_more_op, other_cursors, namespace) {
+                Err(ImmuxError::MongoTransformer(MongoTransformerError::CursorNotFound(id)))
+                    if id == cursor_id => {}
+                _ => panic!("getMore should only find the cursor on its connection and chain"),
+            }
+        }
+
+        let last_outcome = SelectOutcome {
+            units: vec![],
+            continuation: None,
+        };
+        let cursor = match transform_outcome_to_mongo_msg(
+            &Outcome::Select(last_outcome),
+            &mock_config,
+            &get_more_op,
+            &mut cursors,
+            &namespace,
+        ) {
+            Err(_error) => panic!("Cannot transform select outcome"),
+            Ok(op_msg) => get_cursor(op_msg),
+        };
+        assert!(cursor.get_array("nextBatch").is_ok());
+        assert_eq!(cursor.get_i64("id"), Ok(0));
+    }
 }
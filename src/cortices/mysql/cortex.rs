@@ -17,7 +17,7 @@ use crate::cortices::mysql::server_status_flags::{
     save_server_status_flags, serialize_status_flags, ServerStatusFlags,
 };
 use crate::cortices::mysql::utils::{get_packet_number, ConnectionStatePhase};
-use crate::cortices::{Cortex, CortexResponse};
+use crate::cortices::{ConnectionState, Cortex, CortexResponse};
 use crate::declarations::errors::{ImmuxError, ImmuxResult};
 use crate::storage::core::ImmuxDBCore;
 use crate::utils::{pretty_dump, u16_to_u8_array};
@@ -27,6 +27,7 @@ pub fn mysql_cortex_process_incoming_message(
     core: &mut ImmuxDBCore,
     _stream: &TcpStream,
     _config: &ImmuxDBConfiguration,
+    _connection: &mut ConnectionState,
 ) -> ImmuxResult<CortexResponse> {
     pretty_dump(bytes);
 
@@ -0,0 +1,258 @@
+/*
+ *  Select conditions from JSON filter documents, in the shape of MongoDB query filters
+**/
+
+use serde_json::{Map, Value as JsonValue};
+
+use crate::declarations::basics::{PropertyName, UnitContent};
+use crate::declarations::commands::SelectCondition;
+use crate::declarations::errors::ImmuxError;
+
+#[derive(Debug)]
+pub enum SelectFilterError {
+    ExpectingObject(JsonValue),
+    ExpectingArray(JsonValue),
+    UnimplementedOperator(String),
+    UnexpectedValue(JsonValue),
+}
+
+impl From<SelectFilterError> for ImmuxError {
+    fn from(error: SelectFilterError) -> ImmuxError {
+        ImmuxError::SelectFilter(error)
+    }
+}
+
+fn combine(mut conditions: Vec<SelectCondition>) -> SelectCondition {
+    if conditions.len() == 1 {
+        return conditions.remove(0);
+    } else {
+        return SelectCondition::And(conditions);
+    }
+}
+
+fn get_property(value: &JsonValue) -> Result<UnitContent, SelectFilterError> {
+    match value {
+        JsonValue::String(string) => return Ok(UnitContent::String(string.clone())),
+        JsonValue::Bool(boolean) => return Ok(UnitContent::Bool(*boolean)),
+        JsonValue::Number(number) => match number.as_f64() {
+            Some(number) => return Ok(UnitContent::Float64(number)),
+            None => return Err(SelectFilterError::UnexpectedValue(value.to_owned())),
+        },
+        JsonValue::Null => return Ok(UnitContent::Nil),
+        _ => return Err(SelectFilterError::UnexpectedValue(value.to_owned())),
+    }
+}
+
+fn parse_operand_list(value: &JsonValue) -> Result<Vec<SelectCondition>, SelectFilterError> {
+    match value {
+        JsonValue::Array(filters) => filters.iter().map(parse_select_filter).collect(),
+        _ => return Err(SelectFilterError::ExpectingArray(value.to_owned())),
+    }
+}
+
+/// Operators on one property, such as `{"$gte": 1, "$lt": 10}`
+fn parse_operators(
+    name: &PropertyName,
+    operators: &Map<String, JsonValue>,
+) -> Result<SelectCondition, SelectFilterError> {
+    if operators.len() == 2 {
+        if let (Some(lower), Some(upper)) = (operators.get("$gte"), operators.get("$lte")) {
+            return Ok(SelectCondition::Between(
+                name.to_owned(),
+                get_property(lower)?,
+                get_property(upper)?,
+            ));
+        }
+    }
+    let mut conditions = Vec::new();
+    for (operator, value) in operators {
+        let condition = match operator.as_str() {
+            "$eq" => SelectCondition::NameProperty(name.to_owned(), get_property(value)?),
+            "$ne" => SelectCondition::Not(Box::new(SelectCondition::NameProperty(
+                name.to_owned(),
+                get_property(value)?,
+            ))),
+            "$lt" => SelectCondition::LessThan(name.to_owned(), get_property(value)?),
+            "$lte" => SelectCondition::LessThanOrEqual(name.to_owned(), get_property(value)?),
+            "$gt" => SelectCondition::GreaterThan(name.to_owned(), get_property(value)?),
+            "$gte" => SelectCondition::GreaterThanOrEqual(name.to_owned(), get_property(value)?),
+            "$in" => match value {
+                JsonValue::Array(values) => {
+                    let mut alternatives = Vec::new();
+                    for value in values {
+                        alternatives.push(SelectCondition::NameProperty(
+                            name.to_owned(),
+                            get_property(value)?,
+                        ));
+                    }
+                    SelectCondition::Or(alternatives)
+                }
+                _ => return Err(SelectFilterError::ExpectingArray(value.to_owned())),
+            },
+            "$not" => match value {
+                JsonValue::Object(inner) => {
+                    SelectCondition::Not(Box::new(parse_operators(name, inner)?))
+                }
+                _ => return Err(SelectFilterError::ExpectingObject(value.to_owned())),
+            },
+            _ => {
+                return Err(SelectFilterError::UnimplementedOperator(
+                    operator.to_owned(),
+                ))
+            }
+        };
+        conditions.push(condition);
+    }
+    return Ok(combine(conditions));
+}
+
+/// Top-level fields are combined with And; `{}` matches every unit.
+///
+/// Supports `$and`, `$or`, `$nor` and `$where` at the top level, and `$eq`, `$ne`, `$lt`, `$lte`,
+/// `$gt`, `$gte`, `$in` and `$not` on properties.
+pub fn parse_select_filter(filter: &JsonValue) -> Result<SelectCondition, SelectFilterError> {
+    let fields = match filter {
+        JsonValue::Object(fields) => fields,
+        _ => return Err(SelectFilterError::ExpectingObject(filter.to_owned())),
+    };
+    if fields.is_empty() {
+        return Ok(SelectCondition::UnconditionalMatch);
+    }
+    let mut conditions = Vec::new();
+    for (field, value) in fields {
+        let condition = match field.as_str() {
+            "$and" => SelectCondition::And(parse_operand_list(value)?),
+            "$or" => SelectCondition::Or(parse_operand_list(value)?),
+            "$nor" => {
+                SelectCondition::Not(Box::new(SelectCondition::Or(parse_operand_list(value)?)))
+            }
+            // BSON JavaScript code arrives as {"$code": code}
+            "$where" => match value.get("$code").unwrap_or(value) {
+                JsonValue::String(code) => SelectCondition::JSCode(code.to_owned()),
+                _ => return Err(SelectFilterError::UnexpectedValue(value.to_owned())),
+            },
+            operator if operator.starts_with('$') => {
+                return Err(SelectFilterError::UnimplementedOperator(
+                    operator.to_owned(),
+                ));
+            }
+            name => {
+                let name = PropertyName::from(name);
+                match value {
+                    JsonValue::Object(operators)
+                        if !operators.is_empty()
+                            && operators.keys().all(|key| key.starts_with('$')) =>
+                    {
+                        parse_operators(&name, operators)?
+                    }
+                    _ => SelectCondition::NameProperty(name, get_property(value)?),
+                }
+            }
+        };
+        conditions.push(condition);
+    }
+    return Ok(combine(conditions));
+}
+
+#[cfg(test)]
+mod select_filter_tests {
+    use serde_json::json;
+
+    use crate::cortices::select_filter::{parse_select_filter, SelectFilterError};
+    use crate::declarations::basics::{PropertyName, UnitContent};
+    use crate::declarations::commands::SelectCondition;
+
+    fn describe(condition: &SelectCondition) -> String {
+        match condition {
+            SelectCondition::UnconditionalMatch => String::from("*"),
+            SelectCondition::Id(id) => format!("id={}", id.as_int()),
+            SelectCondition::JSCode(code) => format!("js({})", code),
+            SelectCondition::NameProperty(name, property) => {
+                format!("{}={}", name.to_string(), property.to_string())
+            }
+            SelectCondition::LessThan(name, bound) => {
+                format!("{}<{}", name.to_string(), bound.to_string())
+            }
+            SelectCondition::LessThanOrEqual(name, bound) => {
+                format!("{}<={}", name.to_string(), bound.to_string())
+            }
+            SelectCondition::GreaterThan(name, bound) => {
+                format!("{}>{}", name.to_string(), bound.to_string())
+            }
+            SelectCondition::GreaterThanOrEqual(name, bound) => {
+                format!("{}>={}", name.to_string(), bound.to_string())
+            }
+            SelectCondition::Between(name, lower, upper) => format!(
+                "{}<={}<={}",
+                lower.to_string(),
+                name.to_string(),
+                upper.to_string()
+            ),
+            SelectCondition::And(operands) => format!(
+                "and({})",
+                operands.iter().map(describe).collect::<Vec<_>>().join(",")
+            ),
+            SelectCondition::Or(operands) => format!(
+                "or({})",
+                operands.iter().map(describe).collect::<Vec<_>>().join(",")
+            ),
+            SelectCondition::Not(operand) => format!("not({})", describe(operand)),
+        }
+    }
+
+    #[test]
+    fn test_parse_select_filter() {
+        let cases = vec![
+            (json!({}), "*"),
+            (json!({"name": "tom"}), "name=tom"),
+            (json!({"age": {"$gte": 18, "$lte": 30}}), "18<=age<=30"),
+            (
+                json!({"$or": [{"name": "tom"}, {"age": {"$lt": 3}}]}),
+                "or(name=tom,age<3)",
+            ),
+            (
+                json!({"$nor": [{"name": null}], "age": {"$not": {"$gt": 5}}}),
+                "and(not(or(name=nil)),not(age>5))",
+            ),
+            (
+                json!({"age": {"$ne": 1}, "name": {"$in": ["tom", "mike"]}}),
+                "and(not(age=1),or(name=tom,name=mike))",
+            ),
+            (json!({"$where": {"$code": "x => true"}}), "js(x => true)"),
+        ];
+        for (filter, expected) in cases {
+            let condition = parse_select_filter(&filter).unwrap();
+            assert_eq!(
+                describe(&condition),
+                expected,
+                "Unexpected result of {}",
+                filter
+            );
+        }
+        match parse_select_filter(&json!({"name": "tom"})).unwrap() {
+            SelectCondition::NameProperty(name, UnitContent::String(property)) => {
+                assert_eq!(name, PropertyName::from("name"));
+                assert_eq!(property, "tom");
+            }
+            condition => panic!("Unexpected condition {:?}", condition),
+        }
+    }
+
+    #[test]
+    fn test_parse_malformed_filter() {
+        match parse_select_filter(&json!({"$or": {"name": "tom"}})) {
+            Err(SelectFilterError::ExpectingArray(_)) => {}
+            result => panic!("Unexpected result {:?}", result),
+        }
+        match parse_select_filter(&json!({"name": {"$regex": "^t"}})) {
+            Err(SelectFilterError::UnimplementedOperator(operator)) => {
+                assert_eq!(operator, "$regex")
+            }
+            result => panic!("Unexpected result {:?}", result),
+        }
+        match parse_select_filter(&json!({"name": [1, 2]})) {
+            Err(SelectFilterError::UnexpectedValue(_)) => {}
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+}
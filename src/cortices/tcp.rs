@@ -1,15 +1,26 @@
 use std::io::{Read, Write};
 use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
 
-use tiny_http::Server;
+use tiny_http::{Response, Server};
 
-use crate::config::ImmuxDBConfiguration;
+use crate::config::{
+    ImmuxDBConfiguration, MAX_CONNECTIONS_PER_ENDPOINT, MAX_HTTP_REQUESTS_IN_PROGRESS,
+};
 use crate::cortices::mongo::cortex::MONGO_CORTEX;
 use crate::cortices::mysql::cortex::MYSQL_CORTEX;
 use crate::cortices::unicus::cortex::responder;
-use crate::cortices::{Cortex, CortexResponse};
+use crate::cortices::{ConnectionState, Cortex, CortexResponse};
 use crate::declarations::errors::{ImmuxError, ImmuxResult};
-use crate::storage::core::ImmuxDBCore;
+use crate::executor::errors::ExecutorError;
+use crate::executor::shared::get_current_namespace;
+use crate::storage::core::{lock_core, CoreStore, ImmuxDBCore, LockedCore, SharedCore};
+use crate::storage::instructions::{
+    Answer, DBSystemAnswer, DBSystemInstruction, Instruction, StoreNamespace,
+    SwitchNamespaceInstruction,
+};
 
 #[derive(Debug)]
 pub enum TcpError {
@@ -18,13 +29,102 @@ pub enum TcpError {
     TcpReadError(std::io::Error),
     TcpWriteError(std::io::Error),
     TcpFlushError(std::io::Error),
+    HttpServerError(String),
+    WorkerPanicked,
+    TooManyConnections,
 }
 
+#[derive(Clone, Copy)]
 pub enum BindMode {
     LongLive,          // Generic TCP
     CloseAfterMessage, // HTTP-like, close after each message
 }
 
+/// Counts the connections an endpoint is serving. Each connection gets its own thread, so idle
+/// clients cannot starve the others, and clients over the limit are turned away.
+struct ConnectionSlots {
+    in_use: Arc<AtomicUsize>,
+    limit: usize,
+}
+
+impl ConnectionSlots {
+    fn new(limit: usize) -> Self {
+        ConnectionSlots {
+            in_use: Arc::new(AtomicUsize::new(0)),
+            limit,
+        }
+    }
+
+    fn try_take(&self) -> Option<ConnectionSlot> {
+        let mut in_use = self.in_use.load(Ordering::SeqCst);
+        loop {
+            if in_use >= self.limit {
+                return None;
+            }
+            match self.in_use.compare_exchange(
+                in_use,
+                in_use + 1,
+                Ordering::SeqCst,
+                Ordering::SeqCst,
+            ) {
+                Err(current) => in_use = current,
+                Ok(_) => {
+                    return Some(ConnectionSlot {
+                        in_use: self.in_use.clone(),
+                    })
+                }
+            }
+        }
+    }
+}
+
+/// Freed when the connection's thread ends, even by panicking
+struct ConnectionSlot {
+    in_use: Arc<AtomicUsize>,
+}
+
+impl Drop for ConnectionSlot {
+    fn drop(&mut self) {
+        self.in_use.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+fn switch_namespace(core: &mut impl CoreStore, namespace: &StoreNamespace) -> ImmuxResult<()> {
+    let instruction = Instruction::DBSystem(DBSystemInstruction::SwitchNamespace(
+        SwitchNamespaceInstruction {
+            new_namespace: namespace.to_owned(),
+        },
+    ));
+    match core.execute(&instruction) {
+        Err(error) => return Err(error),
+        Ok(Answer::DBSystem(DBSystemAnswer::SwitchNamespaceOk(_answer))) => return Ok(()),
+        Ok(answer) => return Err(ExecutorError::UnexpectedAnswerType(answer).into()),
+    }
+}
+
+/// Processes a message on the connection's chain. The core is put back on the chain it was on,
+/// so a connection picking a chain only moves itself.
+fn process_on_connection_chain<F>(
+    core: &mut ImmuxDBCore,
+    connection: &mut ConnectionState,
+    process: F,
+) -> ImmuxResult<CortexResponse>
+where
+    F: FnOnce(&mut ImmuxDBCore, &mut ConnectionState) -> ImmuxResult<CortexResponse>,
+{
+    let shared_chain = get_current_namespace(core)?;
+    if connection.chain != shared_chain {
+        switch_namespace(core, &connection.chain)?;
+    }
+    let response = process(core, connection);
+    let connection_chain = get_current_namespace(core)?;
+    if connection_chain != shared_chain {
+        switch_namespace(core, &shared_chain)?;
+    }
+    connection.chain = connection_chain;
+    return response;
+}
+
 fn send_data_to_stream_with_flushing(
     mut stream: &TcpStream,
     data_to_client: Vec<u8>,
@@ -45,13 +145,17 @@ fn send_data_to_stream_with_flushing(
 
 fn handle_tcp_stream(
     mut stream: TcpStream,
-    core: &mut ImmuxDBCore,
+    core: &SharedCore,
     bind_mode: &BindMode,
     config: &ImmuxDBConfiguration,
     cortex: &Cortex,
 ) -> ImmuxResult<()> {
     if let Some(process_first_connection_func) = cortex.process_first_connection {
-        match process_first_connection_func(core) {
+        let first_response = {
+            let mut core = lock_core(core);
+            process_first_connection_func(&mut core)
+        };
+        match first_response {
             Err(error) => return Err(error),
             Ok(success) => match success {
                 CortexResponse::Send(data_to_client) => {
@@ -68,35 +172,50 @@ fn handle_tcp_stream(
         };
     }
 
-    let mut buffer = [0; 1_024_000];
+    let mut connection = {
+        let mut core = lock_core(core);
+        ConnectionState::new(get_current_namespace(&mut *core)?)
+    };
+    let mut buffer = vec![0; 1_024_000];
     loop {
         match stream.read(&mut buffer) {
             Err(error) => return Err(ImmuxError::Tcp(TcpError::TcpReadError(error))),
             Ok(bytes_read) => {
                 println!("{} bytes read", bytes_read);
-                if bytes_read > 0 {
-                    match (cortex.process_incoming_message)(
-                        &buffer[..bytes_read],
-                        core,
-                        &stream,
-                        config,
-                    ) {
-                        Err(error) => return Err(error),
-                        Ok(CortexResponse::Send(data_to_client)) => {
-                            match send_data_to_stream_with_flushing(&stream, data_to_client) {
-                                Err(error) => return Err(error),
-                                Ok(_) => match bind_mode {
-                                    BindMode::CloseAfterMessage => return Ok(()),
-                                    BindMode::LongLive => continue,
-                                },
-                            };
-                        }
-                        Ok(CortexResponse::SendThenDisconnect(data_to_client)) => {
-                            match send_data_to_stream_with_flushing(&stream, data_to_client) {
-                                Err(error) => return Err(error),
-                                Ok(_) => return Ok(()),
-                            };
-                        }
+                if bytes_read == 0 {
+                    // Peer closed the connection
+                    return Ok(());
+                }
+                // The core is only held while a message is processed, so that idle connections
+                // do not block the others
+                let response = {
+                    let mut core = lock_core(core);
+                    process_on_connection_chain(&mut core, &mut connection, |core, connection| {
+                        (cortex.process_incoming_message)(
+                            &buffer[..bytes_read],
+                            core,
+                            &stream,
+                            config,
+                            connection,
+                        )
+                    })
+                };
+                match response {
+                    Err(error) => return Err(error),
+                    Ok(CortexResponse::Send(data_to_client)) => {
+                        match send_data_to_stream_with_flushing(&stream, data_to_client) {
+                            Err(error) => return Err(error),
+                            Ok(_) => match bind_mode {
+                                BindMode::CloseAfterMessage => return Ok(()),
+                                BindMode::LongLive => continue,
+                            },
+                        };
+                    }
+                    Ok(CortexResponse::SendThenDisconnect(data_to_client)) => {
+                        match send_data_to_stream_with_flushing(&stream, data_to_client) {
+                            Err(error) => return Err(error),
+                            Ok(_) => return Ok(()),
+                        };
                     }
                 }
             }
@@ -104,50 +223,292 @@ fn handle_tcp_stream(
     }
 }
 
+fn serve_tcp_listener(
+    listener: TcpListener,
+    core: SharedCore,
+    cortex: &'static Cortex,
+    bind_mode: BindMode,
+    config: Arc<ImmuxDBConfiguration>,
+    connection_limit: usize,
+) -> ImmuxResult<()> {
+    let slots = ConnectionSlots::new(connection_limit);
+    for stream in listener.incoming() {
+        match stream {
+            Err(error) => return Err(ImmuxError::Tcp(TcpError::TcpStreamError(error))),
+            Ok(stream) => match slots.try_take() {
+                // Dropping the stream closes the connection
+                None => println!(
+                    "Connection closed with error: {:?}",
+                    ImmuxError::Tcp(TcpError::TooManyConnections)
+                ),
+                Some(slot) => {
+                    let core = core.clone();
+                    let config = config.clone();
+                    thread::spawn(move || {
+                        let _slot = slot;
+                        match handle_tcp_stream(stream, &core, &bind_mode, &config, cortex) {
+                            Err(error) => println!("Connection closed with error: {:?}", error),
+                            Ok(_) => {}
+                        }
+                    });
+                }
+            },
+        };
+    }
+    return Ok(());
+}
+
 fn bind_tcp_port(
     endpoint: &str,
-    core: &mut ImmuxDBCore,
-    cortex: &Cortex,
+    core: SharedCore,
+    cortex: &'static Cortex,
     bind_mode: BindMode,
-    config: &ImmuxDBConfiguration,
+    config: Arc<ImmuxDBConfiguration>,
 ) -> ImmuxResult<()> {
     match TcpListener::bind(endpoint) {
         Err(error) => Err(ImmuxError::Tcp(TcpError::TcpBindError(error))),
-        Ok(listener) => {
-            for stream in listener.incoming() {
-                match stream {
-                    Err(error) => return Err(ImmuxError::Tcp(TcpError::TcpStreamError(error))),
-                    Ok(stream) => match handle_tcp_stream(stream, core, &bind_mode, config, cortex)
-                    {
-                        Err(error) => return Err(error),
-                        Ok(_) => {}
-                    },
-                };
+        Ok(listener) => serve_tcp_listener(
+            listener,
+            core,
+            cortex,
+            bind_mode,
+            config,
+            MAX_CONNECTIONS_PER_ENDPOINT,
+        ),
+    }
+}
+
+fn serve_http(endpoint: &str, core: SharedCore) -> ImmuxResult<()> {
+    match Server::http(endpoint) {
+        Err(error) => return Err(TcpError::HttpServerError(error.to_string()).into()),
+        Ok(server) => {
+            let slots = ConnectionSlots::new(MAX_HTTP_REQUESTS_IN_PROGRESS);
+            for request in server.incoming_requests() {
+                match slots.try_take() {
+                    None => {
+                        let response = Response::from_string("Too many requests in progress")
+                            .with_status_code(503);
+                        if let Err(error) = request.respond(response) {
+                            println!("Cannot respond to HTTP request: {:?}", error);
+                        }
+                    }
+                    Some(slot) => {
+                        let core = core.clone();
+                        thread::spawn(move || {
+                            let _slot = slot;
+                            // The responder locks the core itself, so long polls can wait without it
+                            match responder(request, &core) {
+                                Err(error) => {
+                                    println!("Cannot respond to HTTP request: {:?}", error)
+                                }
+                                Ok(_) => {}
+                            }
+                        });
+                    }
+                }
             }
             return Ok(());
         }
     }
 }
 
-pub fn setup_cortices(mut core: ImmuxDBCore, config: &ImmuxDBConfiguration) -> ImmuxResult<()> {
-    let server = Server::http(&config.unicus_endpoint).unwrap();
-    for request in server.incoming_requests() {
-        responder(request, &mut core)?
+fn spawn_listener<F>(name: &'static str, listen: F) -> JoinHandle<ImmuxResult<()>>
+where
+    F: FnOnce() -> ImmuxResult<()> + Send + 'static,
+{
+    thread::spawn(move || {
+        let result = listen();
+        if let Err(error) = &result {
+            println!("{} cortex stopped: {:?}", name, error);
+        }
+        return result;
+    })
+}
+
+pub fn setup_cortices(core: ImmuxDBCore, config: &ImmuxDBConfiguration) -> ImmuxResult<()> {
+    let core: SharedCore = Arc::new(LockedCore::new(core));
+    let config = Arc::new(config.clone());
+
+    let handles = vec![
+        {
+            let endpoint = config.unicus_endpoint.clone();
+            let core = core.clone();
+            spawn_listener("HTTP", move || serve_http(&endpoint, core))
+        },
+        {
+            let endpoint = config.mongo_endpoint.clone();
+            let core = core.clone();
+            let config = config.clone();
+            spawn_listener("Mongo", move || {
+                bind_tcp_port(&endpoint, core, &MONGO_CORTEX, BindMode::LongLive, config)
+            })
+        },
+        {
+            let endpoint = config.mysql_endpoint.clone();
+            let core = core.clone();
+            let config = config.clone();
+            spawn_listener("MySQL", move || {
+                bind_tcp_port(&endpoint, core, &MYSQL_CORTEX, BindMode::LongLive, config)
+            })
+        },
+    ];
+
+    // One endpoint failing does not bring down the others; report the first failure once all
+    // listeners have stopped
+    let mut first_error = None;
+    for handle in handles {
+        let result = match handle.join() {
+            Err(_panic) => Err(TcpError::WorkerPanicked.into()),
+            Ok(result) => result,
+        };
+        if let (Err(error), None) = (result, &first_error) {
+            first_error = Some(error);
+        }
+    }
+    match first_error {
+        None => return Ok(()),
+        Some(error) => return Err(error),
     }
+}
 
-    bind_tcp_port(
-        &config.mongo_endpoint,
-        &mut core,
-        &MONGO_CORTEX,
-        BindMode::LongLive,
-        config,
-    )?;
-    bind_tcp_port(
-        &config.mysql_endpoint,
-        &mut core,
-        &MYSQL_CORTEX,
-        BindMode::LongLive,
-        config,
-    )?;
-    return Ok(());
+#[cfg(test)]
+mod tcp_tests {
+    use std::io::{Read, Write};
+    use std::net::{SocketAddr, TcpListener, TcpStream};
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::Duration;
+
+    use immuxdb_dev_utils::reset_db_dir;
+
+    use crate::config::ImmuxDBConfiguration;
+    use crate::cortices::tcp::{serve_tcp_listener, BindMode};
+    use crate::cortices::{ConnectionState, Cortex, CortexResponse};
+    use crate::declarations::basics::ChainName;
+    use crate::declarations::commands::{Command, PickChainCommand};
+    use crate::declarations::errors::ImmuxResult;
+    use crate::executor::execute::execute;
+    use crate::executor::shared::get_current_namespace;
+    use crate::storage::core::{ImmuxDBCore, LockedCore, SharedCore};
+    use crate::storage::instructions::StoreNamespace;
+    use crate::storage::kv::KeyValueEngine;
+
+    const PICK_PREFIX: &[u8] = b"pick ";
+
+    /// Picks the chain named after "pick ", and answers every message with the current chain
+    fn process_chain_message(
+        bytes: &[u8],
+        core: &mut ImmuxDBCore,
+        _stream: &TcpStream,
+        _config: &ImmuxDBConfiguration,
+        _connection: &mut ConnectionState,
+    ) -> ImmuxResult<CortexResponse> {
+        if bytes.starts_with(PICK_PREFIX) {
+            let command = Command::PickChain(PickChainCommand {
+                new_chain_name: ChainName::new(&bytes[PICK_PREFIX.len()..]),
+            });
+            execute(command, core)?;
+        }
+        let namespace = get_current_namespace(core)?;
+        return Ok(CortexResponse::Send(namespace.as_bytes().to_vec()));
+    }
+
+    static CHAIN_CORTEX: Cortex = Cortex {
+        process_incoming_message: process_chain_message,
+        process_first_connection: None,
+    };
+
+    fn start_server(label: &str, connection_limit: usize) -> SocketAddr {
+        let data_root = format!("/tmp/immuxdb_test_{}/", label);
+        reset_db_dir(&data_root).unwrap();
+        let namespace = StoreNamespace::new(b"default");
+        let core = ImmuxDBCore::new(&KeyValueEngine::Rocks, &data_root, &namespace).unwrap();
+        let core: SharedCore = Arc::new(LockedCore::new(core));
+        let config = Arc::new(ImmuxDBConfiguration::default());
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            serve_tcp_listener(
+                listener,
+                core,
+                &CHAIN_CORTEX,
+                BindMode::LongLive,
+                config,
+                connection_limit,
+            )
+        });
+        return address;
+    }
+
+    fn send(stream: &mut TcpStream, message: &[u8]) -> Vec<u8> {
+        stream.write_all(message).unwrap();
+        let mut buffer = vec![0; 1024];
+        let bytes_read = stream.read(&mut buffer).unwrap();
+        buffer.truncate(bytes_read);
+        return buffer;
+    }
+
+    #[test]
+    fn test_chain_per_connection() {
+        let address = start_server("chain_per_connection", 4);
+        let mut client_a = TcpStream::connect(address).unwrap();
+        let mut client_b = TcpStream::connect(address).unwrap();
+
+        assert_eq!(send(&mut client_a, b"pick chain_a"), b"chain_a");
+        assert_eq!(send(&mut client_b, b"pick chain_b"), b"chain_b");
+        assert_eq!(send(&mut client_a, b"chain"), b"chain_a");
+        assert_eq!(send(&mut client_b, b"chain"), b"chain_b");
+
+        // New connections start on the chain the server is on
+        let mut client_c = TcpStream::connect(address).unwrap();
+        assert_eq!(send(&mut client_c, b"chain"), b"default");
+    }
+
+    #[test]
+    fn test_concurrent_clients() {
+        let address = start_server("concurrent_clients", 8);
+        let clients: Vec<_> = (0..8)
+            .map(|i| {
+                thread::spawn(move || {
+                    let chain = format!("chain_{}", i);
+                    let mut stream = TcpStream::connect(address).unwrap();
+                    let pick = format!("pick {}", chain);
+                    assert_eq!(send(&mut stream, pick.as_bytes()), chain.as_bytes());
+                    for _ in 0..20 {
+                        assert_eq!(send(&mut stream, b"chain"), chain.as_bytes());
+                    }
+                })
+            })
+            .collect();
+        for client in clients {
+            client.join().unwrap();
+        }
+    }
+
+    #[test]
+    fn test_connections_over_limit() {
+        let address = start_server("connections_over_limit", 1);
+        let mut idle_client = TcpStream::connect(address).unwrap();
+        assert_eq!(send(&mut idle_client, b"chain"), b"default");
+
+        // Turned away while the idle client holds the only connection
+        let mut rejected_client = TcpStream::connect(address).unwrap();
+        let mut buffer = vec![0; 1024];
+        assert_eq!(rejected_client.read(&mut buffer).unwrap_or(0), 0);
+
+        // The slot is freed once the idle client leaves
+        drop(idle_client);
+        for _ in 0..100 {
+            let mut client = TcpStream::connect(address).unwrap();
+            let _ = client.write_all(b"chain");
+            match client.read(&mut buffer) {
+                Ok(bytes_read) if bytes_read > 0 => {
+                    assert_eq!(&buffer[..bytes_read], b"default");
+                    return;
+                }
+                _ => thread::sleep(Duration::from_millis(10)),
+            }
+        }
+        panic!("No connection was served after the idle client left");
+    }
 }
@@ -1,22 +1,33 @@
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
 
-use tiny_http::{Method, Request, Response};
+use chrono::DateTime;
+use serde_json::Value as JsonValue;
+use tiny_http::{Header, Method, Request, Response};
 use url::Url;
 
 use crate::config;
+use crate::cortices::select_filter::parse_select_filter;
 use crate::declarations::basics::{
-    ChainName, GroupingLabel, PropertyName, UnitContent, UnitId, UnitIdError, UnitSpecifier,
+    ChainName, GroupingLabel, PropertyName, PropertyNameList, UnitContent, UnitId, UnitIdError,
+    UnitSpecifier,
 };
 use crate::declarations::commands::{
-    Command, CreateIndexCommand, InsertCommand, InsertCommandSpec, InspectCommand, Outcome,
-    PickChainCommand, RevertAllCommand, RevertCommandTargetSpec, RevertManyCommand, SelectCommand,
-    SelectCondition,
+    ChangeFeedCommand, Command, CreateIndexCommand, CreateTagCommand, DeleteTagCommand,
+    DiffCommand, ForkChainCommand, HeightSpecifier, InsertCommand, InsertCommandSpec,
+    InspectCommand, MergeChainCommand, MergeCommandResolution, Outcome, PickChainCommand,
+    ProveCommand, RemoveCommand, ReplayChainCommand, RevertAllCommand, RevertCommandTargetSpec,
+    RevertManyCommand, RevertRecordCommand, SelectCommand, SelectCondition, SelectSort,
+    SetRetentionCommand, SortOrder,
 };
 use crate::declarations::errors::ImmuxError::HttpResponse;
 use crate::declarations::errors::ImmuxResult;
-use crate::executor::execute::execute;
-use crate::storage::core::ImmuxDBCore;
-use crate::storage::vkv::ChainHeight;
+use crate::executor::execute::{execute, execute_with_meta};
+use crate::storage::core::{lock_core, SharedCore};
+use crate::storage::instructions::{MergeStrategy, RetentionPolicy};
+use crate::storage::kv::KeyValueEngine;
+use crate::storage::vkv::{ChainHeight, RecordMeta};
 
 #[derive(Debug)]
 pub enum HttpParsingError {
@@ -47,12 +58,184 @@ impl UrlInformation {
             },
         }
     }
+    fn extract_optional_numeric_query(&self, key: &str) -> Result<Option<u64>, HttpParsingError> {
+        match self.queries.get(key) {
+            None => Ok(None),
+            Some(_) => self.extract_numeric_query(key).map(Some),
+        }
+    }
+    /// Reads an RFC 3339 timestamp, such as 2026-01-01T00:00:00Z, as microseconds since the epoch
+    fn extract_time_query(&self, key: &str) -> Result<u128, HttpParsingError> {
+        match self.queries.get(key) {
+            None => Err(HttpParsingError::UrlParsingError),
+            Some(string) => match DateTime::parse_from_rfc3339(string) {
+                Err(_error) => Err(HttpParsingError::UrlParsingError),
+                Ok(time) => {
+                    if time.timestamp() < 0 {
+                        return Err(HttpParsingError::UrlParsingError);
+                    }
+                    let micros = time.timestamp() as u128 * 1_000_000
+                        + time.timestamp_subsec_micros() as u128;
+                    Ok(micros)
+                }
+            },
+        }
+    }
+    fn extract_optional_time_query(&self, key: &str) -> Result<Option<u128>, HttpParsingError> {
+        match self.queries.get(key) {
+            None => Ok(None),
+            Some(_) => self.extract_time_query(key).map(Some),
+        }
+    }
     fn extract_string_query(&self, key: &str) -> Option<String> {
         match self.queries.get(key) {
             None => None,
             Some(string) => Some(string.clone()),
         }
     }
+    /// Reads a height, or the name of a tag if it is not a number
+    fn extract_height_query(&self, key: &str) -> Result<HeightSpecifier, HttpParsingError> {
+        match self.queries.get(key) {
+            None => Err(HttpParsingError::UrlParsingError),
+            Some(string) => match string.parse::<u64>() {
+                Err(_error) => Ok(HeightSpecifier::Tag(string.clone())),
+                Ok(height) => Ok(HeightSpecifier::Height(ChainHeight::new(height))),
+            },
+        }
+    }
+    /// A time given with `at` takes precedence over a height or tag given with `height`
+    fn extract_optional_height_specifier(
+        &self,
+    ) -> Result<Option<HeightSpecifier>, HttpParsingError> {
+        match self.extract_optional_time_query(config::AT_KEYWORD)? {
+            Some(time) => Ok(Some(HeightSpecifier::Time(time))),
+            None => match self.queries.get(config::HEIGHT_KEYWORD) {
+                None => Ok(None),
+                Some(_) => self.extract_height_query(config::HEIGHT_KEYWORD).map(Some),
+            },
+        }
+    }
+    /// `name` and one of `lt`, `lte`, `gt`, `gte` or `between`, whose two bounds are separated by
+    /// `|`; numeric bounds are compared as numbers and others as strings
+    fn extract_range_condition(&self) -> Result<SelectCondition, HttpParsingError> {
+        let name = match self.extract_string_query(config::RANGE_NAME_KEYWORD) {
+            None => return Err(HttpParsingError::UrlParsingError),
+            Some(name) => PropertyName::from(name.as_str()),
+        };
+        let parse_bound = |string: &str| match string.parse::<f64>() {
+            Ok(number) => UnitContent::Float64(number),
+            Err(_) => UnitContent::String(String::from(string)),
+        };
+        if let Some(bound) = self.extract_string_query(config::LESS_THAN_KEYWORD) {
+            return Ok(SelectCondition::LessThan(name, parse_bound(&bound)));
+        } else if let Some(bound) = self.extract_string_query(config::LESS_THAN_OR_EQUAL_KEYWORD) {
+            return Ok(SelectCondition::LessThanOrEqual(name, parse_bound(&bound)));
+        } else if let Some(bound) = self.extract_string_query(config::GREATER_THAN_KEYWORD) {
+            return Ok(SelectCondition::GreaterThan(name, parse_bound(&bound)));
+        } else if let Some(bound) = self.extract_string_query(config::GREATER_THAN_OR_EQUAL_KEYWORD)
+        {
+            return Ok(SelectCondition::GreaterThanOrEqual(
+                name,
+                parse_bound(&bound),
+            ));
+        } else if let Some(bounds) = self.extract_string_query(config::BETWEEN_KEYWORD) {
+            let bounds: Vec<&str> = bounds.split(config::MULTIFIELD_SEPARATOR).collect();
+            if bounds.len() != 2 {
+                return Err(HttpParsingError::UrlParsingError);
+            }
+            return Ok(SelectCondition::Between(
+                name,
+                parse_bound(bounds[0]),
+                parse_bound(bounds[1]),
+            ));
+        } else {
+            return Err(HttpParsingError::UrlParsingError);
+        }
+    }
+
+    /// A select of one page, shaped by `skip`, `limit`, `continuation` and `sort`, which takes a
+    /// property name, prefixed with `-` for descending order; `fields` lists the paths to project
+    /// the documents to, like `a,b.c`
+    fn extract_select_command(
+        &self,
+        grouping: GroupingLabel,
+        condition: SelectCondition,
+        height: Option<HeightSpecifier>,
+    ) -> Result<Command, HttpParsingError> {
+        let skip = self
+            .extract_optional_numeric_query(config::SKIP_KEYWORD)?
+            .unwrap_or(0);
+        let limit = self.extract_optional_numeric_query(config::SELECT_LIMIT_KEYWORD)?;
+        let sort = match self.extract_string_query(config::SORT_KEYWORD) {
+            None => None,
+            Some(sort) => {
+                let (name, order) = if sort.starts_with('-') {
+                    (&sort[1..], SortOrder::Descending)
+                } else {
+                    (sort.as_str(), SortOrder::Ascending)
+                };
+                if name.is_empty() {
+                    return Err(HttpParsingError::UrlParsingError);
+                }
+                Some(SelectSort {
+                    name: PropertyName::from(name),
+                    order,
+                })
+            }
+        };
+        let projection = match self.extract_string_query(config::FIELDS_KEYWORD) {
+            None => None,
+            Some(fields) => {
+                let names: Vec<PropertyName> = fields
+                    .split(config::FIELDS_SEPARATOR)
+                    .map(|field| field.trim())
+                    .filter(|field| !field.is_empty())
+                    .map(PropertyName::from)
+                    .collect();
+                if names.is_empty() {
+                    return Err(HttpParsingError::UrlParsingError);
+                }
+                Some(PropertyNameList::new(names))
+            }
+        };
+        let command = Command::Select(SelectCommand {
+            grouping,
+            condition,
+            height,
+            skip: skip as usize,
+            limit: limit.map(|limit| limit as usize),
+            sort,
+            continuation: self.extract_string_query(config::CONTINUATION_KEYWORD),
+            projection,
+        });
+        return Ok(command);
+    }
+    /// A count given with `keep_heights` takes precedence over a time given with `keep_since`
+    fn extract_retention_policy(&self) -> Result<Option<RetentionPolicy>, HttpParsingError> {
+        match self.extract_optional_numeric_query(config::KEEP_HEIGHTS_KEYWORD)? {
+            Some(count) => Ok(Some(RetentionPolicy::KeepHeights(count))),
+            None => match self.extract_optional_time_query(config::KEEP_SINCE_KEYWORD)? {
+                Some(time) => Ok(Some(RetentionPolicy::KeepSince(time))),
+                None => Ok(None),
+            },
+        }
+    }
+    fn extract_record_meta(&self) -> RecordMeta {
+        RecordMeta {
+            author: self.extract_string_query(config::AUTHOR_KEYWORD),
+            message: self.extract_string_query(config::MESSAGE_KEYWORD),
+            client_id: self.extract_string_query(config::CLIENT_ID_KEYWORD),
+        }
+    }
+}
+
+fn parse_merge_strategy(strategy: &str) -> Result<MergeStrategy, HttpParsingError> {
+    match strategy {
+        "ours" => Ok(MergeStrategy::Ours),
+        "theirs" => Ok(MergeStrategy::Theirs),
+        "fail" => Ok(MergeStrategy::Fail),
+        _ => Err(HttpParsingError::UrlParsingError),
+    }
 }
 
 pub fn parse_path(path: &str) -> Result<UrlInformation, HttpParsingError> {
@@ -85,9 +268,59 @@ fn parse_http_request(request: &Request, body: &str) -> Result<Command, HttpPars
 
     match request.method() {
         Method::Get => {
+            let height_specifier = url_info.extract_optional_height_specifier()?;
             if let Some(_namespace) = url_info.extract_string_query(config::CHAIN_KEYWORD) {
                 let command = Command::NameChain;
                 return Ok(command);
+            } else if let Some(_) = url_info.extract_string_query(config::VERIFY_CHAIN_KEYWORD) {
+                let command = Command::VerifyChain;
+                return Ok(command);
+            } else if let Some(_) = url_info.extract_string_query(config::REPLAY_KEYWORD) {
+                // Replayed in memory, which checks the chain without keeping the rebuilt store
+                let command = Command::ReplayChain(ReplayChainCommand {
+                    engine: KeyValueEngine::HashMap,
+                    data_root: String::new(),
+                    chain: None,
+                });
+                return Ok(command);
+            } else if let Some(_) = url_info.extract_string_query(config::RETENTION_KEYWORD) {
+                return Ok(Command::GetRetention);
+            } else if let Some(_) = url_info.extract_string_query(config::TAGS_KEYWORD) {
+                let command = Command::ListTags;
+                return Ok(command);
+            } else if let Some(_) = url_info.extract_string_query(config::CHANGES_KEYWORD) {
+                let after_height = url_info.extract_numeric_query(config::CHANGES_KEYWORD)?;
+                let limit =
+                    match url_info.extract_optional_numeric_query(config::CHANGES_LIMIT_KEYWORD)? {
+                        None => config::DEFAULT_CHANGES_LIMIT,
+                        Some(limit) => limit as usize,
+                    };
+                let command = Command::ChangeFeed(ChangeFeedCommand {
+                    after_height: ChainHeight::new(after_height),
+                    limit,
+                });
+                return Ok(command);
+            } else if let Some(_) = url_info.extract_string_query(config::DIFF_KEYWORD) {
+                let from_height = url_info.extract_height_query(config::DIFF_KEYWORD)?;
+                let grouping = if target_grouping_str.is_empty() {
+                    None
+                } else {
+                    Some(target_grouping)
+                };
+                let command = Command::Diff(DiffCommand {
+                    grouping,
+                    from_height,
+                    to_height: height_specifier,
+                });
+                return Ok(command);
+            } else if let Some(code) = url_info.extract_string_query(config::WHERE_KEYWORD) {
+                // A JavaScript predicate, evaluated against each unit's document
+                let command = url_info.extract_select_command(
+                    target_grouping,
+                    SelectCondition::JSCode(code),
+                    height_specifier,
+                )?;
+                return Ok(command);
             } else if let Some(condition) =
                 url_info.extract_string_query(config::SELECT_CONDITION_KEYWORD)
             {
@@ -95,19 +328,25 @@ fn parse_http_request(request: &Request, body: &str) -> Result<Command, HttpPars
                     //                    This is an internal API
                     config::NAME_PROPERTY => {
                         for (property_name_str, unit_content_str) in url_info.queries.iter() {
-                            if property_name_str == config::SELECT_CONDITION_KEYWORD {
+                            if property_name_str == config::SELECT_CONDITION_KEYWORD
+                                || property_name_str == config::HEIGHT_KEYWORD
+                                || property_name_str == config::AT_KEYWORD
+                                || property_name_str == config::SKIP_KEYWORD
+                                || property_name_str == config::SELECT_LIMIT_KEYWORD
+                                || property_name_str == config::SORT_KEYWORD
+                                || property_name_str == config::CONTINUATION_KEYWORD
+                                || property_name_str == config::FIELDS_KEYWORD
+                            {
                                 continue;
                             }
                             let property_name = PropertyName::from(property_name_str.as_str());
                             if let Ok(unit_content_f64) = unit_content_str.parse::<f64>() {
                                 let unit_content = UnitContent::Float64(unit_content_f64);
-                                let command = Command::Select(SelectCommand {
-                                    grouping: target_grouping,
-                                    condition: SelectCondition::NameProperty(
-                                        property_name,
-                                        unit_content,
-                                    ),
-                                });
+                                let command = url_info.extract_select_command(
+                                    target_grouping,
+                                    SelectCondition::NameProperty(property_name, unit_content),
+                                    height_specifier,
+                                )?;
                                 return Ok(command);
                             } else {
                                 return Err(HttpParsingError::BodyParsingError);
@@ -115,32 +354,67 @@ fn parse_http_request(request: &Request, body: &str) -> Result<Command, HttpPars
                         }
                         return Err(HttpParsingError::BodyParsingError);
                     }
+                    // The body is a JSON filter, as in MongoDB's find
+                    config::SELECT_FILTER => {
+                        let filter = match serde_json::from_str::<JsonValue>(body) {
+                            Err(_error) => return Err(HttpParsingError::BodyParsingError),
+                            Ok(filter) => filter,
+                        };
+                        let condition = match parse_select_filter(&filter) {
+                            Err(_error) => return Err(HttpParsingError::BodyParsingError),
+                            Ok(condition) => condition,
+                        };
+                        let command = url_info.extract_select_command(
+                            target_grouping,
+                            condition,
+                            height_specifier,
+                        )?;
+                        return Ok(command);
+                    }
+                    config::PROPERTY_RANGE => {
+                        let condition = url_info.extract_range_condition()?;
+                        let command = url_info.extract_select_command(
+                            target_grouping,
+                            condition,
+                            height_specifier,
+                        )?;
+                        return Ok(command);
+                    }
                     _ => {
-                        let command = Command::Select(SelectCommand {
-                            grouping: target_grouping,
-                            condition: SelectCondition::UnconditionalMatch,
-                        });
+                        let command = url_info.extract_select_command(
+                            target_grouping,
+                            SelectCondition::UnconditionalMatch,
+                            height_specifier,
+                        )?;
                         return Ok(command);
                     }
                 }
+            } else if let Some(_) = url_info.extract_string_query(config::PROVE_KEYWORD) {
+                let target_id = UnitId::read_int_in_str(target_id_str)?;
+                let command = Command::Prove(ProveCommand {
+                    specifier: UnitSpecifier::new(target_grouping, target_id),
+                    height: height_specifier,
+                });
+                return Ok(command);
             } else if let Some(_) = url_info.extract_string_query(config::INSPECT_KEYWORD) {
                 let target_id = UnitId::read_int_in_str(target_id_str)?;
                 let command = Command::Inspect(InspectCommand {
                     specifier: UnitSpecifier::new(target_grouping, target_id),
+                    height: height_specifier,
                 });
                 return Ok(command);
             } else {
                 let target_id = UnitId::read_int_in_str(target_id_str)?;
-                let command = Command::Select(SelectCommand {
-                    grouping: target_grouping,
-                    condition: SelectCondition::Id(target_id),
-                });
+                let command = url_info.extract_select_command(
+                    target_grouping,
+                    SelectCondition::Id(target_id),
+                    height_specifier,
+                )?;
                 return Ok(command);
             }
         }
         Method::Put => {
-            if let Ok(height_u64) = url_info.extract_numeric_query(config::REVERT_QUERY_KEYWORD) {
-                let height = ChainHeight::new(height_u64);
+            if let Ok(height) = url_info.extract_height_query(config::REVERT_QUERY_KEYWORD) {
                 let target_id = UnitId::read_int_in_str(target_id_str)?;
                 let specifier = UnitSpecifier::new(target_grouping, target_id);
                 let command = Command::RevertMany(RevertManyCommand {
@@ -150,14 +424,99 @@ fn parse_http_request(request: &Request, body: &str) -> Result<Command, HttpPars
                     }],
                 });
                 return Ok(command);
-            } else if let Ok(height_u64) =
-                url_info.extract_numeric_query(config::REVERTALL_QUERY_KEYWORD)
+            } else if let Ok(time) = url_info.extract_time_query(config::REVERT_AT_QUERY_KEYWORD) {
+                let target_id = UnitId::read_int_in_str(target_id_str)?;
+                let specifier = UnitSpecifier::new(target_grouping, target_id);
+                let command = Command::RevertMany(RevertManyCommand {
+                    specs: vec![RevertCommandTargetSpec {
+                        specifier,
+                        target_height: HeightSpecifier::Time(time),
+                    }],
+                });
+                return Ok(command);
+            } else if let Ok(height) =
+                url_info.extract_height_query(config::REVERTALL_QUERY_KEYWORD)
             {
-                let height = ChainHeight::new(height_u64);
                 let command = Command::RevertAll(RevertAllCommand {
                     target_height: height,
                 });
                 return Ok(command);
+            } else if let Ok(time) = url_info.extract_time_query(config::REVERTALL_AT_QUERY_KEYWORD)
+            {
+                let command = Command::RevertAll(RevertAllCommand {
+                    target_height: HeightSpecifier::Time(time),
+                });
+                return Ok(command);
+            } else if let Ok(height) =
+                url_info.extract_height_query(config::REVERT_RECORD_QUERY_KEYWORD)
+            {
+                let command = Command::RevertRecord(RevertRecordCommand { height });
+                return Ok(command);
+            } else if let Some(name) = url_info.extract_string_query(config::TAG_KEYWORD) {
+                let command = Command::CreateTag(CreateTagCommand {
+                    name,
+                    height: url_info.extract_optional_height_specifier()?,
+                });
+                return Ok(command);
+            } else if let Some(_) = url_info.extract_string_query(config::RETENTION_KEYWORD) {
+                let grouping = if target_grouping_str.is_empty() {
+                    None
+                } else {
+                    Some(target_grouping)
+                };
+                let command = Command::SetRetention(SetRetentionCommand {
+                    grouping,
+                    policy: url_info.extract_retention_policy()?,
+                });
+                return Ok(command);
+            } else if let Some(_) = url_info.extract_string_query(config::COMPACT_KEYWORD) {
+                return Ok(Command::Compact);
+            } else if let Some(new_chain_name) =
+                url_info.extract_string_query(config::FORK_CHAIN_KEYWORD)
+            {
+                let source = match url_info.extract_string_query(config::CHAIN_KEYWORD) {
+                    None => return Err(HttpParsingError::UrlParsingError.into()),
+                    Some(source) => source,
+                };
+                let at_height = url_info.extract_numeric_query(config::HEIGHT_KEYWORD)?;
+                let command = Command::ForkChain(ForkChainCommand {
+                    source: ChainName::from(source.as_str()),
+                    at_height: ChainHeight::new(at_height),
+                    new_chain_name: ChainName::from(new_chain_name.as_str()),
+                });
+                return Ok(command);
+            } else if let Some(source) = url_info.extract_string_query(config::MERGE_CHAIN_KEYWORD)
+            {
+                let target = match url_info.extract_string_query(config::CHAIN_KEYWORD) {
+                    None => return Err(HttpParsingError::UrlParsingError.into()),
+                    Some(target) => target,
+                };
+                let default_strategy =
+                    match url_info.extract_string_query(config::MERGE_STRATEGY_KEYWORD) {
+                        None => MergeStrategy::Fail,
+                        Some(strategy) => parse_merge_strategy(&strategy)?,
+                    };
+                // Each body line resolves one unit, as "grouping|id|strategy"
+                let mut resolutions: Vec<MergeCommandResolution> = vec![];
+                for line in body.split("\r\n").filter(|line| !line.is_empty()) {
+                    let segments: Vec<&str> = line.split("|").collect();
+                    if segments.len() != 3 {
+                        return Err(HttpParsingError::BodyParsingError);
+                    }
+                    let grouping = GroupingLabel::from(segments[0].as_bytes());
+                    let id = UnitId::read_int_in_str(segments[1])?;
+                    resolutions.push(MergeCommandResolution {
+                        specifier: UnitSpecifier::new(grouping, id),
+                        strategy: parse_merge_strategy(segments[2])?,
+                    });
+                }
+                let command = Command::MergeChain(MergeChainCommand {
+                    target: ChainName::from(target.as_str()),
+                    source: ChainName::from(source.as_str()),
+                    resolutions,
+                    default_strategy,
+                });
+                return Ok(command);
             } else if let Some(namespace) = url_info.extract_string_query(config::CHAIN_KEYWORD) {
                 let command = Command::PickChain(PickChainCommand {
                     new_chain_name: ChainName::from(namespace.as_str()),
@@ -204,11 +563,101 @@ fn parse_http_request(request: &Request, body: &str) -> Result<Command, HttpPars
                 return Ok(command);
             }
         }
+        Method::Delete => {
+            if let Some(name) = url_info.extract_string_query(config::TAG_KEYWORD) {
+                let command = Command::DeleteTag(DeleteTagCommand { name });
+                return Ok(command);
+            }
+            let target_id = UnitId::read_int_in_str(target_id_str)?;
+            let command = Command::Remove(RemoveCommand {
+                grouping: target_grouping,
+                ids: vec![target_id],
+            });
+            return Ok(command);
+        }
         _ => Err(HttpParsingError::BodyParsingError.into()),
     }
 }
 
-pub fn responder(request: Request, core: &mut ImmuxDBCore) -> ImmuxResult<()> {
+// Change feeds currently waiting for records
+static WAITING_CHANGE_FEEDS: AtomicUsize = AtomicUsize::new(0);
+
+/// Counts a change feed as waiting until dropped
+struct WaitingChangeFeed;
+
+impl WaitingChangeFeed {
+    fn try_new() -> Option<WaitingChangeFeed> {
+        if WAITING_CHANGE_FEEDS.fetch_add(1, Ordering::SeqCst) >= config::MAX_WAITING_CHANGE_FEEDS {
+            WAITING_CHANGE_FEEDS.fetch_sub(1, Ordering::SeqCst);
+            return None;
+        }
+        return Some(WaitingChangeFeed);
+    }
+}
+
+impl Drop for WaitingChangeFeed {
+    fn drop(&mut self) {
+        WAITING_CHANGE_FEEDS.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Executes a change feed request again after each write until records above its height arrive
+/// or `wait` runs out. The core is released while waiting, so the writes being waited for can
+/// get in. Once too many feeds are waiting, further ones are answered right away.
+fn execute_waiting_for_changes(
+    change_feed: ChangeFeedCommand,
+    wait: Duration,
+    core: &SharedCore,
+) -> ImmuxResult<Outcome> {
+    let deadline = Instant::now() + wait;
+    let waiting = match wait.as_millis() {
+        0 => None,
+        _ => WaitingChangeFeed::try_new(),
+    };
+    let mut core = lock_core(core);
+    loop {
+        let outcome = execute(Command::ChangeFeed(change_feed.clone()), &mut *core)?;
+        let now = Instant::now();
+        match (&outcome, &waiting) {
+            (Outcome::ChangeFeed(feed), Some(_waiting))
+                if feed.height == change_feed.after_height && now < deadline =>
+            {
+                core = core.wait_for_write(deadline - now);
+            }
+            _ => return Ok(outcome),
+        }
+    }
+}
+
+fn get_changes_wait(request: &Request) -> Result<Duration, HttpParsingError> {
+    let url_info = parse_path(&request.url())?;
+    match url_info.extract_optional_numeric_query(config::CHANGES_WAIT_KEYWORD)? {
+        None => return Ok(Duration::from_millis(0)),
+        Some(wait) => {
+            let wait = std::cmp::min(wait, config::MAX_CHANGES_WAIT_MS);
+            return Ok(Duration::from_millis(wait));
+        }
+    }
+}
+
+fn execute_http_command(
+    command: Command,
+    request: &Request,
+    core: &SharedCore,
+) -> ImmuxResult<Outcome> {
+    match command {
+        Command::ChangeFeed(change_feed) => {
+            let wait = get_changes_wait(request)?;
+            return execute_waiting_for_changes(change_feed, wait, core);
+        }
+        command => {
+            let meta = parse_path(&request.url())?.extract_record_meta();
+            return execute_with_meta(command, meta, &mut *lock_core(core));
+        }
+    }
+}
+
+pub fn responder(request: Request, core: &SharedCore) -> ImmuxResult<()> {
     let mut req = request;
     let mut incoming_body = String::new();
     match req.as_reader().read_to_string(&mut incoming_body) {
@@ -216,12 +665,14 @@ pub fn responder(request: Request, core: &mut ImmuxDBCore) -> ImmuxResult<()> {
         Err(_error) => return Err(HttpParsingError::BodyExtractionError.into()),
     }
 
+    let mut continuation: Option<String> = None;
     let (status, body): (u16, String) = match parse_http_request(&req, &incoming_body) {
         Err(error) => (500, format!("request parsing error {:?}", error)),
-        Ok(command) => match execute(command, core) {
+        Ok(command) => match execute_http_command(command, &req, core) {
             Err(error) => (500, format!("executing error {:?}", error)),
             Ok(outcome) => match outcome {
                 Outcome::Select(outcome) => {
+                    continuation = outcome.continuation;
                     let mut body = String::new();
                     let should_break_line = outcome.units.len() >= 2;
                     for unit in outcome.units {
@@ -233,7 +684,88 @@ pub fn responder(request: Request, core: &mut ImmuxDBCore) -> ImmuxResult<()> {
                     (200, body)
                 }
                 Outcome::NameChain(outcome) => (200, outcome.chain_name.to_string()),
+                Outcome::MergeChain(outcome) => match serde_json::to_string(&outcome) {
+                    Err(error) => (500, format!("serialization error {:?}", error)),
+                    Ok(body) => (200, body),
+                },
+                Outcome::ForkChain(outcome) => (
+                    200,
+                    format!(
+                        "Forked chain {} at height {}, head {}",
+                        outcome.new_chain_name.to_string(),
+                        outcome.height.as_u64(),
+                        outcome.head
+                    ),
+                ),
                 Outcome::Insert(outcome) => (200, format!("Inserted {} items", outcome.count)),
+                Outcome::Remove(outcome) => (200, format!("Removed {} items", outcome.count)),
+                Outcome::ChangeFeed(outcome) => match serde_json::to_string(&outcome) {
+                    Err(error) => (500, format!("serialization error {:?}", error)),
+                    Ok(body) => (200, body),
+                },
+                Outcome::Diff(outcome) => match serde_json::to_string(&outcome) {
+                    Err(error) => (500, format!("serialization error {:?}", error)),
+                    Ok(body) => (200, body),
+                },
+                Outcome::CreateTag(outcome) => match serde_json::to_string(&outcome) {
+                    Err(error) => (500, format!("serialization error {:?}", error)),
+                    Ok(body) => (200, body),
+                },
+                Outcome::DeleteTag(outcome) => match serde_json::to_string(&outcome) {
+                    Err(error) => (500, format!("serialization error {:?}", error)),
+                    Ok(body) => (200, body),
+                },
+                Outcome::ListTags(outcome) => match serde_json::to_string(&outcome) {
+                    Err(error) => (500, format!("serialization error {:?}", error)),
+                    Ok(body) => (200, body),
+                },
+                Outcome::GetRetention(outcome) => match serde_json::to_string(&outcome) {
+                    Err(error) => (500, format!("serialization error {:?}", error)),
+                    Ok(body) => (200, body),
+                },
+                Outcome::Compact(outcome) => match serde_json::to_string(&outcome) {
+                    Err(error) => (500, format!("serialization error {:?}", error)),
+                    Ok(body) => (200, body),
+                },
+                Outcome::RevertRecord(outcome) => match serde_json::to_string(&outcome) {
+                    Err(error) => (500, format!("serialization error {:?}", error)),
+                    Ok(body) => (200, body),
+                },
+                Outcome::Prove(outcome) => match serde_json::to_string(&outcome) {
+                    Err(error) => (500, format!("serialization error {:?}", error)),
+                    Ok(body) => (200, body),
+                },
+                Outcome::VerifyChain(outcome) => match outcome.broken_height {
+                    None => (
+                        200,
+                        format!(
+                            "Chain verified up to height {}, head {}",
+                            outcome.height.as_u64(),
+                            outcome.head
+                        ),
+                    ),
+                    Some(broken_height) => (
+                        409,
+                        format!("Chain broken at height {}", broken_height.as_u64()),
+                    ),
+                },
+                Outcome::ReplayChain(outcome) => {
+                    if outcome.mismatches.is_empty() {
+                        (
+                            200,
+                            format!(
+                                "Chain replayed up to height {}, head {}",
+                                outcome.height.as_u64(),
+                                outcome.head
+                            ),
+                        )
+                    } else {
+                        match serde_json::to_string(&outcome) {
+                            Err(error) => (500, format!("serialization error {:?}", error)),
+                            Ok(body) => (409, body),
+                        }
+                    }
+                }
                 Outcome::Inspect(outcome) => {
                     let mut body = String::new();
                     for inspection in outcome.inspections {
@@ -246,7 +778,13 @@ pub fn responder(request: Request, core: &mut ImmuxDBCore) -> ImmuxResult<()> {
             },
         },
     };
-    let response = Response::from_string(body).with_status_code(status);
+    let mut response = Response::from_string(body).with_status_code(status);
+    if let Some(token) = continuation {
+        // Tokens are plain ASCII, so they always make a valid header
+        if let Ok(header) = Header::from_bytes(config::CONTINUATION_HEADER.as_bytes(), token) {
+            response = response.with_header(header);
+        }
+    }
     match req.respond(response) {
         Ok(_) => {
             return Ok(());
@@ -1,9 +1,16 @@
 use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
 
 use crate::declarations::basics::{
-    ChainName, GroupingLabel, PropertyName, Unit, UnitContent, UnitId, UnitSpecifier,
+    ChainName, GroupingLabel, PropertyName, PropertyNameList, StoreKey, Unit, UnitContent, UnitId,
+    UnitSpecifier,
 };
-use crate::storage::vkv::ChainHeight;
+use crate::storage::instructions::{
+    ChainTag, MergeConflict, MergeStrategy, PruneMarker, ReplayMismatch, RetentionPolicy,
+    RetentionRule,
+};
+use crate::storage::kv::KeyValueEngine;
+use crate::storage::vkv::{ChainHeight, MerkleProof, RecordHash, RecordMeta};
 
 /***************************************************
 *
@@ -11,6 +18,21 @@ use crate::storage::vkv::ChainHeight;
 *
 ***************************************************/
 
+/// A point in the chain's history, given by height, by wall-clock time in microseconds since the
+/// Unix epoch, which stands for the last height reached at or before that time, or by tag name
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum HeightSpecifier {
+    Height(ChainHeight),
+    Time(u128),
+    Tag(String),
+}
+
+impl From<ChainHeight> for HeightSpecifier {
+    fn from(height: ChainHeight) -> HeightSpecifier {
+        HeightSpecifier::Height(height)
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct InsertCommandSpec {
     pub id: UnitId,
@@ -34,24 +56,83 @@ pub struct PickChainCommand {
     pub new_chain_name: ChainName,
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ForkChainCommand {
+    pub source: ChainName,
+    pub at_height: ChainHeight,
+    pub new_chain_name: ChainName,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MergeCommandResolution {
+    pub specifier: UnitSpecifier,
+    pub strategy: MergeStrategy,
+}
+
+/// Conflicting units without a resolution of their own use `default_strategy`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MergeChainCommand {
+    pub target: ChainName,
+    pub source: ChainName,
+    pub resolutions: Vec<MergeCommandResolution>,
+    pub default_strategy: MergeStrategy,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub enum SelectCondition {
     UnconditionalMatch,
     Id(UnitId),
     JSCode(String),
     NameProperty(PropertyName, UnitContent),
+    // Range conditions only match properties of the same type as their bounds
+    LessThan(PropertyName, UnitContent),
+    LessThanOrEqual(PropertyName, UnitContent),
+    GreaterThan(PropertyName, UnitContent),
+    GreaterThanOrEqual(PropertyName, UnitContent),
+    // Both bounds are inclusive
+    Between(PropertyName, UnitContent, UnitContent),
+    // An empty And matches every unit, and an empty Or none
+    And(Vec<SelectCondition>),
+    Or(Vec<SelectCondition>),
+    Not(Box<SelectCondition>),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum SortOrder {
+    Ascending,
+    Descending,
+}
+
+/// Orders units by a property of their documents; properties of different types are grouped by
+/// type
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SelectSort {
+    pub name: PropertyName,
+    pub order: SortOrder,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct SelectCommand {
     pub grouping: GroupingLabel,
     pub condition: SelectCondition,
+    // Read the data as it was at this height; None reads the latest state
+    pub height: Option<HeightSpecifier>,
+    // Units left out from the start of the (sorted) result
+    pub skip: usize,
+    // The most units in one page; None returns all remaining units
+    pub limit: Option<usize>,
+    pub sort: Option<SelectSort>,
+    // The token of the previous page's outcome, which takes the place of `height` and `skip`
+    pub continuation: Option<String>,
+    // Fields kept in returned JSON and BSON documents, with dots separating nested names; None
+    // keeps them all
+    pub projection: Option<PropertyNameList>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct RevertCommandTargetSpec {
     pub specifier: UnitSpecifier,
-    pub target_height: ChainHeight,
+    pub target_height: HeightSpecifier,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -61,24 +142,113 @@ pub struct RevertManyCommand {
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct RevertAllCommand {
-    pub target_height: ChainHeight,
+    pub target_height: HeightSpecifier,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct InspectCommand {
     pub specifier: UnitSpecifier,
+    // Only list versions written up to this height; None lists the whole history
+    pub height: Option<HeightSpecifier>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RemoveCommand {
+    pub grouping: GroupingLabel,
+    pub ids: Vec<UnitId>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ProveCommand {
+    pub specifier: UnitSpecifier,
+    // Prove the content as of this height instead of the latest
+    pub height: Option<HeightSpecifier>,
+}
+
+/// Changes made above `from_height` up to and including `to_height`, or the latest height if None
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DiffCommand {
+    // Only diff units of this grouping; None diffs every key on the chain
+    pub grouping: Option<GroupingLabel>,
+    pub from_height: HeightSpecifier,
+    pub to_height: Option<HeightSpecifier>,
+}
+
+/// Unit changes recorded above `after_height`, from at most `limit` records
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ChangeFeedCommand {
+    pub after_height: ChainHeight,
+    pub limit: usize,
+}
+
+/// Undoes the units written by the record at `height`, keeping writes made after it
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RevertRecordCommand {
+    pub height: HeightSpecifier,
+}
+
+/// Tags `height`, or the current height if None
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CreateTagCommand {
+    pub name: String,
+    pub height: Option<HeightSpecifier>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DeleteTagCommand {
+    pub name: String,
+}
+
+/// Sets the retention policy of `grouping`, or of the whole chain if None. No policy removes it.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SetRetentionCommand {
+    pub grouping: Option<GroupingLabel>,
+    pub policy: Option<RetentionPolicy>,
+}
+
+/// Rebuilds the current chain from its records into `chain` on a fresh store, the current
+/// chain's name if None, and checks the rebuilt journals and indexes against the original
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ReplayChainCommand {
+    pub engine: KeyValueEngine,
+    pub data_root: String,
+    pub chain: Option<ChainName>,
+}
+
+/// Rebuilds the chain held in an archive as `chain`, or as the chain it was exported from if None
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ImportChainCommand {
+    pub archive: Vec<u8>,
+    pub chain: Option<ChainName>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub enum Command {
     Insert(InsertCommand),
     PickChain(PickChainCommand),
+    ForkChain(ForkChainCommand),
+    MergeChain(MergeChainCommand),
     NameChain,
     Select(SelectCommand),
     CreateIndex(CreateIndexCommand),
     RevertMany(RevertManyCommand),
     RevertAll(RevertAllCommand),
     Inspect(InspectCommand),
+    Remove(RemoveCommand),
+    VerifyChain,
+    Prove(ProveCommand),
+    Diff(DiffCommand),
+    ChangeFeed(ChangeFeedCommand),
+    RevertRecord(RevertRecordCommand),
+    CreateTag(CreateTagCommand),
+    DeleteTag(DeleteTagCommand),
+    ListTags,
+    SetRetention(SetRetentionCommand),
+    GetRetention,
+    Compact,
+    ReplayChain(ReplayChainCommand),
+    ExportChain,
+    ImportChain(ImportChainCommand),
 }
 
 /***************************************************
@@ -97,6 +267,19 @@ pub struct PickChainOutcome {
     pub new_chain_name: ChainName,
 }
 #[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ForkChainOutcome {
+    pub new_chain_name: ChainName,
+    pub height: ChainHeight,
+    pub head: RecordHash,
+}
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MergeChainOutcome {
+    pub base_height: ChainHeight,
+    pub height: ChainHeight,
+    pub merged_heights: Vec<ChainHeight>,
+    pub conflicts: Vec<MergeConflict>,
+}
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct NameChainOutcome {
     pub chain_name: ChainName,
 }
@@ -104,6 +287,8 @@ pub struct NameChainOutcome {
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct SelectOutcome {
     pub units: Vec<Unit>,
+    // Present when units remain after this page, for the next SelectCommand to continue from
+    pub continuation: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -137,14 +322,161 @@ pub struct InspectOutcome {
     pub inspections: Vec<Inspection>,
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RemoveOutcome {
+    pub count: usize,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct VerifyChainOutcome {
+    pub height: ChainHeight,
+    pub head: RecordHash,
+    // The first height whose record does not match the hash chain, if any
+    pub broken_height: Option<ChainHeight>,
+}
+
+/// `proof` leads from the unit's content to `root`, the Merkle root of its grouping at `height`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ProveOutcome {
+    pub specifier: UnitSpecifier,
+    pub height: ChainHeight,
+    pub content: UnitContent,
+    pub root: RecordHash,
+    pub proof: MerkleProof,
+}
+
+/// A JSON field that differs between two contents, addressed by its dotted path
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FieldDiff {
+    pub path: String,
+    pub before: Option<JsonValue>,
+    pub after: Option<JsonValue>,
+}
+
+/// `fields` is only filled in when both sides are JSON or BSON documents
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct UnitDiff {
+    pub key: StoreKey,
+    // Keys outside of units, such as indices, have no specifier
+    pub specifier: Option<UnitSpecifier>,
+    pub before: Option<UnitContent>,
+    pub after: Option<UnitContent>,
+    pub fields: Vec<FieldDiff>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DiffOutcome {
+    pub from_height: ChainHeight,
+    pub to_height: ChainHeight,
+    pub changes: Vec<UnitDiff>,
+}
+
+/// A unit written at `height`; removed units have no content
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ChangeEvent {
+    pub grouping: GroupingLabel,
+    pub id: UnitId,
+    pub content: Option<UnitContent>,
+    pub height: ChainHeight,
+    pub sys_time: u128,
+    pub meta: RecordMeta,
+}
+
+/// `height` is the last height read; pass it as `after_height` to resume the feed
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ChangeFeedOutcome {
+    pub height: ChainHeight,
+    pub events: Vec<ChangeEvent>,
+}
+
+/// Units written again after the reverted record are conflicts and keep their latest content
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RevertRecordOutcome {
+    pub reverted: Vec<UnitSpecifier>,
+    pub conflicts: Vec<UnitSpecifier>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CreateTagOutcome {
+    pub tag: ChainTag,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DeleteTagOutcome {
+    pub tag: ChainTag,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ListTagsOutcome {
+    pub tags: Vec<ChainTag>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SetRetentionOutcome {}
+
+/// Scopes of rules and markers are key prefixes, None standing for the whole chain
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GetRetentionOutcome {
+    pub rules: Vec<RetentionRule>,
+    pub markers: Vec<PruneMarker>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CompactOutcome {
+    pub markers: Vec<PruneMarker>,
+    pub pruned_records: u64,
+    pub pruned_versions: u64,
+}
+
+/// A replay without mismatches rebuilt the chain exactly
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ReplayChainOutcome {
+    pub height: ChainHeight,
+    pub head: RecordHash,
+    pub mismatches: Vec<ReplayMismatch>,
+}
+
+/// `archive` holds the current chain in the portable archive format
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ExportChainOutcome {
+    pub chain: ChainName,
+    pub height: ChainHeight,
+    pub head: RecordHash,
+    pub archive: Vec<u8>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ImportChainOutcome {
+    pub chain: ChainName,
+    pub height: ChainHeight,
+    pub head: RecordHash,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub enum Outcome {
     Insert(InsertOutcome),
     PickChain(PickChainOutcome),
+    ForkChain(ForkChainOutcome),
+    MergeChain(MergeChainOutcome),
     Select(SelectOutcome),
     NameChain(NameChainOutcome),
     CreateIndex(CreateIndexOutcome),
     RevertMany(RevertOutcome),
     RevertAll(RevertAllOutcome),
     Inspect(InspectOutcome),
+    Remove(RemoveOutcome),
+    VerifyChain(VerifyChainOutcome),
+    Prove(ProveOutcome),
+    Diff(DiffOutcome),
+    ChangeFeed(ChangeFeedOutcome),
+    RevertRecord(RevertRecordOutcome),
+    CreateTag(CreateTagOutcome),
+    DeleteTag(DeleteTagOutcome),
+    ListTags(ListTagsOutcome),
+    SetRetention(SetRetentionOutcome),
+    GetRetention(GetRetentionOutcome),
+    Compact(CompactOutcome),
+    ReplayChain(ReplayChainOutcome),
+    ExportChain(ExportChainOutcome),
+    ImportChain(ImportChainOutcome),
 }
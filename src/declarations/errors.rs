@@ -4,6 +4,7 @@ use crate::config::ConfigError;
 use crate::cortices::mongo::error::{MongoParserError, MongoSerializeError};
 use crate::cortices::mongo::transformer::MongoTransformerError;
 use crate::cortices::mysql::error::{MySQLParserError, MySQLSerializeError};
+use crate::cortices::select_filter::SelectFilterError;
 use crate::cortices::tcp::TcpError;
 use crate::cortices::unicus::cortex::HttpParsingError;
 use crate::cortices::utils::DeserializationError;
@@ -13,7 +14,9 @@ use crate::declarations::basics::store_value::StoreValueError;
 use crate::declarations::basics::unit_id::UnitIdError;
 use crate::declarations::basics::{StoreKeyError, UnitContentError};
 use crate::executor::errors::ExecutorError;
+use crate::executor::js_sandbox::JsError;
 use crate::executor::shared::ReverseIndexError;
+use crate::storage::archive::ArchiveError;
 use crate::storage::kv::KVError;
 use crate::storage::tkv::TransactionError;
 use crate::storage::vkv::{ChainHeightError, VkvError};
@@ -41,8 +44,14 @@ pub enum ImmuxError {
     MySQLParser(MySQLParserError),
     MySQLSerializer(MySQLSerializeError),
 
+    SelectFilter(SelectFilterError),
+
+    JsSandbox(JsError),
+
     Transaction(TransactionError),
 
+    Archive(ArchiveError),
+
     UnitContentProcessing(UnitContentError),
     ReverseIndexProcessing(ReverseIndexError),
     UnitId(UnitIdError),
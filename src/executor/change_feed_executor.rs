@@ -0,0 +1,216 @@
+use std::collections::VecDeque;
+
+use crate::declarations::basics::UnitContent;
+use crate::declarations::commands::{ChangeEvent, ChangeFeedCommand, ChangeFeedOutcome, Outcome};
+use crate::declarations::errors::ImmuxResult;
+use crate::executor::errors::ExecutorError;
+use crate::executor::shared::get_unit_specifier_of_key;
+use crate::storage::core::CoreStore;
+use crate::storage::instructions::{Answer, DataAnswer, DataReadAnswer, GetChangesInstruction};
+use crate::storage::vkv::ChainHeight;
+
+// Records read per request while iterating over a change feed
+const CHANGE_FEED_PAGE_SIZE: usize = 64;
+
+pub fn execute_change_feed(
+    change_feed: ChangeFeedCommand,
+    core: &mut impl CoreStore,
+) -> ImmuxResult<Outcome> {
+    return read_change_feed(change_feed, core).map(Outcome::ChangeFeed);
+}
+
+fn read_change_feed(
+    change_feed: ChangeFeedCommand,
+    core: &mut impl CoreStore,
+) -> ImmuxResult<ChangeFeedOutcome> {
+    let instruction = GetChangesInstruction {
+        after_height: change_feed.after_height,
+        limit: change_feed.limit,
+    };
+    match core.execute(&instruction.into()) {
+        Err(error) => return Err(error),
+        Ok(Answer::DataAccess(DataAnswer::Read(DataReadAnswer::GetChangesOk(answer)))) => {
+            let mut events = Vec::new();
+            for change in answer.changes {
+                for target in change.targets {
+                    // Index keys are written alongside units but are not units themselves
+                    let specifier = match get_unit_specifier_of_key(&target.key) {
+                        None => continue,
+                        Some(specifier) => specifier,
+                    };
+                    let content = match target.value.inner() {
+                        None => None,
+                        Some(data) => Some(UnitContent::parse_data(data)?),
+                    };
+                    let (grouping, id) = specifier.into_components();
+                    events.push(ChangeEvent {
+                        grouping,
+                        id,
+                        content,
+                        height: change.height,
+                        sys_time: change.sys_time,
+                        meta: change.meta.to_owned(),
+                    });
+                }
+            }
+            return Ok(ChangeFeedOutcome {
+                height: answer.height,
+                events,
+            });
+        }
+        Ok(answer) => return Err(ExecutorError::UnexpectedAnswerType(answer).into()),
+    }
+}
+
+/// Iterates over unit changes above a height, oldest first, until it has caught up with the
+/// chain. Events of one height come together, so a feed stopped after handling every event of
+/// some height can be picked up again from that height.
+pub struct ChangeFeed<'a, C: CoreStore> {
+    core: &'a mut C,
+    height: ChainHeight,
+    pending: VecDeque<ChangeEvent>,
+    caught_up: bool,
+}
+
+impl<'a, C: CoreStore> ChangeFeed<'a, C> {
+    pub fn new(core: &'a mut C, after_height: ChainHeight) -> Self {
+        ChangeFeed {
+            core,
+            height: after_height,
+            pending: VecDeque::new(),
+            caught_up: false,
+        }
+    }
+    /// The last height read from the chain, which may be above the height of the next event
+    pub fn get_height(&self) -> ChainHeight {
+        self.height
+    }
+}
+
+impl<'a, C: CoreStore> Iterator for ChangeFeed<'a, C> {
+    type Item = ImmuxResult<ChangeEvent>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                return Some(Ok(event));
+            }
+            if self.caught_up {
+                return None;
+            }
+            let command = ChangeFeedCommand {
+                after_height: self.height,
+                limit: CHANGE_FEED_PAGE_SIZE,
+            };
+            match read_change_feed(command, self.core) {
+                Err(error) => {
+                    self.caught_up = true;
+                    return Some(Err(error));
+                }
+                Ok(outcome) => {
+                    if outcome.height == self.height {
+                        self.caught_up = true;
+                    }
+                    self.height = outcome.height;
+                    self.pending.extend(outcome.events);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod change_feed_executor_tests {
+    use crate::declarations::basics::{GroupingLabel, StoreKey, StoreValue, UnitContent, UnitId};
+    use crate::declarations::commands::{ChangeFeedCommand, Outcome};
+    use crate::executor::change_feed_executor::{execute_change_feed, ChangeFeed};
+    use crate::executor::tests::FixtureCore;
+    use crate::storage::instructions::{
+        DataInstruction, DataReadInstruction, GetChangesOkAnswer, Instruction, RecordChange,
+        SetTargetSpec,
+    };
+    use crate::storage::vkv::{ChainHeight, RecordMeta};
+
+    // Height 1 writes unit 1, height 2 only writes an index key and height 3 removes unit 1
+    fn make_core<'a>() -> FixtureCore<'a> {
+        let grouping = GroupingLabel::from("grouping");
+        let unit_key = StoreKey::build(&grouping, UnitId::new(1));
+        let content = UnitContent::String(String::from("hello"));
+        let records = vec![
+            vec![SetTargetSpec {
+                key: unit_key.clone(),
+                value: StoreValue::new(Some(content.marshal())),
+            }],
+            vec![SetTargetSpec {
+                key: StoreKey::new(&[0xA0, 0x01]),
+                value: StoreValue::new(Some(vec![0x00])),
+            }],
+            vec![SetTargetSpec {
+                key: unit_key,
+                value: StoreValue::new(None),
+            }],
+        ];
+        FixtureCore::new(Box::new(move |instruction| match instruction {
+            Instruction::DataAccess(DataInstruction::Read(DataReadInstruction::GetChanges(
+                get_changes,
+            ))) => {
+                let mut height = get_changes.after_height;
+                let mut changes = Vec::new();
+                while (height.as_u64() as usize) < records.len()
+                    && changes.len() < get_changes.limit
+                {
+                    height.increment();
+                    changes.push(RecordChange {
+                        height,
+                        sys_time: 1000 + height.as_u64() as u128,
+                        meta: RecordMeta {
+                            author: Some(String::from("alice")),
+                            message: None,
+                            client_id: None,
+                        },
+                        targets: records[height.as_u64() as usize - 1].clone(),
+                    });
+                }
+                Ok(GetChangesOkAnswer { height, changes }.into())
+            }
+            _ => panic!("Unimplemented fixture instruction {:?}", instruction),
+        }))
+    }
+
+    #[test]
+    fn test_change_feed() {
+        let mut core = make_core();
+        let command = ChangeFeedCommand {
+            after_height: ChainHeight::new(0),
+            limit: 2,
+        };
+        match execute_change_feed(command, &mut core).unwrap() {
+            Outcome::ChangeFeed(outcome) => {
+                assert_eq!(outcome.height, ChainHeight::new(2));
+                assert_eq!(outcome.events.len(), 1);
+                let event = &outcome.events[0];
+                assert_eq!(event.grouping.to_string(), "grouping");
+                assert_eq!(event.id, UnitId::new(1));
+                assert_eq!(
+                    event.content,
+                    Some(UnitContent::String(String::from("hello")))
+                );
+                assert_eq!(event.height, ChainHeight::new(1));
+                assert_eq!(event.sys_time, 1001);
+                assert_eq!(event.meta.author, Some(String::from("alice")));
+            }
+            outcome => panic!("Unexpected outcome {:?}", outcome),
+        }
+    }
+
+    #[test]
+    fn test_change_feed_iterator() {
+        let mut core = make_core();
+        let mut feed = ChangeFeed::new(&mut core, ChainHeight::new(1));
+        let event = feed.next().unwrap().unwrap();
+        assert_eq!(event.height, ChainHeight::new(3));
+        assert_eq!(event.content, None);
+        assert!(feed.next().is_none());
+        assert_eq!(feed.get_height(), ChainHeight::new(3));
+    }
+}
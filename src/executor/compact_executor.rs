@@ -0,0 +1,56 @@
+use crate::declarations::commands::{CompactOutcome, Outcome};
+use crate::declarations::errors::ImmuxResult;
+use crate::executor::errors::ExecutorError;
+use crate::storage::core::CoreStore;
+use crate::storage::instructions::{Answer, CompactInstruction, DBSystemAnswer};
+
+pub fn execute_compact(core: &mut impl CoreStore) -> ImmuxResult<Outcome> {
+    match core.execute(&CompactInstruction {}.into()) {
+        Err(error) => return Err(error),
+        Ok(Answer::DBSystem(DBSystemAnswer::CompactOk(answer))) => {
+            return Ok(Outcome::Compact(CompactOutcome {
+                markers: answer.markers,
+                pruned_records: answer.pruned_records,
+                pruned_versions: answer.pruned_versions,
+            }));
+        }
+        Ok(answer) => return Err(ExecutorError::UnexpectedAnswerType(answer).into()),
+    }
+}
+
+#[cfg(test)]
+mod compact_executor_tests {
+    use crate::declarations::commands::Outcome;
+    use crate::executor::compact_executor::execute_compact;
+    use crate::executor::tests::FixtureCore;
+    use crate::storage::instructions::{
+        CompactOkAnswer, DBSystemInstruction, Instruction, PruneMarker,
+    };
+    use crate::storage::vkv::{ChainHeight, RecordHash};
+
+    #[test]
+    fn test_compact() {
+        let mut core = FixtureCore::new(Box::new(|instruction| match instruction {
+            Instruction::DBSystem(DBSystemInstruction::Compact(_)) => Ok(CompactOkAnswer {
+                markers: vec![PruneMarker {
+                    scope: None,
+                    height: ChainHeight::new(8),
+                    head: RecordHash::digest(&[b"head"]),
+                }],
+                pruned_records: 7,
+                pruned_versions: 12,
+            }
+            .into()),
+            _ => panic!("Unimplemented fixture instruction {:?}", instruction),
+        }));
+        match execute_compact(&mut core).unwrap() {
+            Outcome::Compact(outcome) => {
+                assert_eq!(outcome.markers.len(), 1);
+                assert_eq!(outcome.markers[0].height, ChainHeight::new(8));
+                assert_eq!(outcome.pruned_records, 7);
+                assert_eq!(outcome.pruned_versions, 12);
+            }
+            outcome => panic!("Unexpected outcome {:?}", outcome),
+        }
+    }
+}
@@ -0,0 +1,74 @@
+use crate::declarations::commands::{CreateTagCommand, CreateTagOutcome, Outcome};
+use crate::declarations::errors::ImmuxResult;
+use crate::executor::errors::ExecutorError;
+use crate::executor::shared::resolve_optional_height;
+use crate::storage::core::CoreStore;
+use crate::storage::instructions::{Answer, DBSystemAnswer, SetTagInstruction};
+
+/// Numeric names would be taken for heights wherever a height can be given by tag
+fn is_valid_tag_name(name: &str) -> bool {
+    return !name.is_empty() && name.parse::<u64>().is_err();
+}
+
+pub fn execute_create_tag(
+    create_tag: CreateTagCommand,
+    core: &mut impl CoreStore,
+) -> ImmuxResult<Outcome> {
+    if !is_valid_tag_name(&create_tag.name) {
+        return Err(ExecutorError::InvalidTagName(create_tag.name).into());
+    }
+    let instruction = SetTagInstruction {
+        name: create_tag.name,
+        height: resolve_optional_height(&create_tag.height, core)?,
+    };
+    match core.execute(&instruction.into()) {
+        Err(error) => return Err(error),
+        Ok(Answer::DBSystem(DBSystemAnswer::SetTagOk(answer))) => {
+            return Ok(Outcome::CreateTag(CreateTagOutcome { tag: answer.tag }));
+        }
+        Ok(answer) => return Err(ExecutorError::UnexpectedAnswerType(answer).into()),
+    }
+}
+
+#[cfg(test)]
+mod create_tag_executor_tests {
+    use crate::declarations::commands::{CreateTagCommand, HeightSpecifier, Outcome};
+    use crate::executor::create_tag_executor::execute_create_tag;
+    use crate::executor::tests::FixtureCore;
+    use crate::storage::instructions::{
+        ChainTag, DBSystemInstruction, Instruction, SetTagOkAnswer,
+    };
+    use crate::storage::vkv::ChainHeight;
+
+    #[test]
+    fn test_create_tag() {
+        let mut core = FixtureCore::new(Box::new(|instruction| match instruction {
+            Instruction::DBSystem(DBSystemInstruction::SetTag(set_tag)) => Ok(SetTagOkAnswer {
+                tag: ChainTag {
+                    name: set_tag.name.to_owned(),
+                    height: set_tag.height.unwrap_or(ChainHeight::new(9)),
+                },
+            }
+            .into()),
+            instruction => panic!("Unexpected instruction: {:?}", instruction),
+        }));
+        let command = CreateTagCommand {
+            name: String::from("before-migration"),
+            height: Some(HeightSpecifier::Height(ChainHeight::new(4))),
+        };
+        match execute_create_tag(command, &mut core).unwrap() {
+            Outcome::CreateTag(outcome) => {
+                assert_eq!(outcome.tag.name, "before-migration");
+                assert_eq!(outcome.tag.height, ChainHeight::new(4));
+            }
+            outcome => panic!("Unexpected outcome {:?}", outcome),
+        }
+        for name in &["", "42"] {
+            let command = CreateTagCommand {
+                name: name.to_string(),
+                height: None,
+            };
+            assert!(execute_create_tag(command, &mut core).is_err());
+        }
+    }
+}
@@ -0,0 +1,21 @@
+use crate::declarations::commands::{DeleteTagCommand, DeleteTagOutcome, Outcome};
+use crate::declarations::errors::ImmuxResult;
+use crate::executor::errors::ExecutorError;
+use crate::storage::core::CoreStore;
+use crate::storage::instructions::{Answer, DBSystemAnswer, RemoveTagInstruction};
+
+pub fn execute_delete_tag(
+    delete_tag: DeleteTagCommand,
+    core: &mut impl CoreStore,
+) -> ImmuxResult<Outcome> {
+    let instruction = RemoveTagInstruction {
+        name: delete_tag.name,
+    };
+    match core.execute(&instruction.into()) {
+        Err(error) => return Err(error),
+        Ok(Answer::DBSystem(DBSystemAnswer::RemoveTagOk(answer))) => {
+            return Ok(Outcome::DeleteTag(DeleteTagOutcome { tag: answer.tag }));
+        }
+        Ok(answer) => return Err(ExecutorError::UnexpectedAnswerType(answer).into()),
+    }
+}
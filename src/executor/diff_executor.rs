@@ -0,0 +1,227 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde_json::Value as JsonValue;
+
+use crate::declarations::basics::{StoreKeyFragment, StoreValue, UnitContent};
+use crate::declarations::commands::{DiffCommand, DiffOutcome, FieldDiff, Outcome, UnitDiff};
+use crate::declarations::errors::ImmuxResult;
+use crate::executor::errors::ExecutorError;
+use crate::executor::shared::{
+    get_json_document, get_unit_specifier_of_key, resolve_height, resolve_optional_height,
+};
+use crate::storage::core::CoreStore;
+use crate::storage::instructions::{
+    Answer, DataAnswer, DataReadAnswer, DiffInstruction, KeyChange,
+};
+
+pub fn execute_diff(diff: DiffCommand, core: &mut impl CoreStore) -> ImmuxResult<Outcome> {
+    let instruction = DiffInstruction {
+        from_height: resolve_height(&diff.from_height, core)?,
+        to_height: resolve_optional_height(&diff.to_height, core)?,
+        scope: diff
+            .grouping
+            .map(|grouping| StoreKeyFragment::from(grouping.marshal())),
+    };
+    match core.execute(&instruction.into()) {
+        Err(error) => return Err(error),
+        Ok(Answer::DataAccess(DataAnswer::Read(DataReadAnswer::DiffOk(answer)))) => {
+            let mut changes = Vec::with_capacity(answer.changes.len());
+            for change in answer.changes {
+                changes.push(get_unit_diff(change)?);
+            }
+            return Ok(Outcome::Diff(DiffOutcome {
+                from_height: answer.from_height,
+                to_height: answer.to_height,
+                changes,
+            }));
+        }
+        Ok(answer) => return Err(ExecutorError::UnexpectedAnswerType(answer).into()),
+    }
+}
+
+fn get_unit_diff(change: KeyChange) -> ImmuxResult<UnitDiff> {
+    let specifier = get_unit_specifier_of_key(&change.key);
+    let is_unit = specifier.is_some();
+    let before = parse_content(&change.before, is_unit)?;
+    let after = parse_content(&change.after, is_unit)?;
+    let fields = match (&before, &after) {
+        (Some(before), Some(after)) => {
+            match (get_json_document(before), get_json_document(after)) {
+                (Some(before), Some(after)) => diff_fields(&before, &after),
+                _ => vec![],
+            }
+        }
+        _ => vec![],
+    };
+    return Ok(UnitDiff {
+        key: change.key,
+        specifier,
+        before,
+        after,
+        fields,
+    });
+}
+
+fn parse_content(value: &StoreValue, is_unit: bool) -> ImmuxResult<Option<UnitContent>> {
+    match value.inner() {
+        None => return Ok(None),
+        Some(data) => {
+            if is_unit {
+                return Ok(Some(UnitContent::parse_data(data)?));
+            } else {
+                return Ok(Some(UnitContent::Bytes(data.to_owned())));
+            }
+        }
+    }
+}
+
+/// Nested objects are walked into; everything else, arrays included, is compared whole
+fn flatten_fields(path: &str, value: &JsonValue, fields: &mut BTreeMap<String, JsonValue>) {
+    match value {
+        JsonValue::Object(map) if !map.is_empty() => {
+            for (name, inner) in map {
+                let inner_path = if path.is_empty() {
+                    name.to_owned()
+                } else {
+                    format!("{}.{}", path, name)
+                };
+                flatten_fields(&inner_path, inner, fields);
+            }
+        }
+        _ => {
+            fields.insert(path.to_owned(), value.to_owned());
+        }
+    }
+}
+
+fn diff_fields(before: &JsonValue, after: &JsonValue) -> Vec<FieldDiff> {
+    let mut before_fields = BTreeMap::new();
+    let mut after_fields = BTreeMap::new();
+    flatten_fields("", before, &mut before_fields);
+    flatten_fields("", after, &mut after_fields);
+    let paths: BTreeSet<&String> = before_fields.keys().chain(after_fields.keys()).collect();
+    let mut result = Vec::new();
+    for path in paths {
+        let before_value = before_fields.get(path);
+        let after_value = after_fields.get(path);
+        if before_value != after_value {
+            result.push(FieldDiff {
+                path: path.to_owned(),
+                before: before_value.cloned(),
+                after: after_value.cloned(),
+            });
+        }
+    }
+    return result;
+}
+
+#[cfg(test)]
+mod diff_executor_tests {
+    use bson::{bson, doc};
+    use serde_json::json;
+
+    use crate::declarations::basics::{
+        GroupingLabel, StoreKey, StoreKeyFragment, StoreValue, UnitContent, UnitId, UnitSpecifier,
+    };
+    use crate::declarations::commands::{DiffCommand, FieldDiff, Outcome};
+    use crate::executor::diff_executor::{diff_fields, execute_diff};
+    use crate::executor::tests::FixtureCore;
+    use crate::storage::instructions::{
+        DataInstruction, DataReadInstruction, DiffOkAnswer, Instruction, KeyChange,
+    };
+    use crate::storage::vkv::ChainHeight;
+
+    #[test]
+    fn test_diff() {
+        let grouping = GroupingLabel::from("grouping");
+        let specifier = UnitSpecifier::new(grouping.clone(), UnitId::new(1));
+        let key = StoreKey::from(specifier.clone());
+        let expected_key = key.clone();
+        let before = UnitContent::JsonString(String::from(r#"{"a": 1, "b": {"c": true}}"#));
+        let mut bson_bytes = Vec::new();
+        bson::encode_document(&mut bson_bytes, &doc! {"a": 2, "b": {"c": true, "d": "x"}}).unwrap();
+        let after = UnitContent::BsonBytes(bson_bytes);
+        let before_value = StoreValue::new(Some(before.marshal()));
+        let after_value = StoreValue::new(Some(after.marshal()));
+        let index_key = StoreKey::new(&[0xA0, 1, 2]);
+        let mut core = FixtureCore::new(Box::new(move |instruction| match instruction {
+            Instruction::DataAccess(DataInstruction::Read(DataReadInstruction::Diff(diff))) => {
+                assert_eq!(diff.from_height, ChainHeight::new(2));
+                assert_eq!(diff.to_height, None);
+                assert_eq!(diff.scope, Some(StoreKeyFragment::from(grouping.marshal())));
+                Ok(DiffOkAnswer {
+                    from_height: diff.from_height,
+                    to_height: ChainHeight::new(5),
+                    changes: vec![
+                        KeyChange {
+                            key: expected_key.clone(),
+                            before: before_value.clone(),
+                            after: after_value.clone(),
+                        },
+                        KeyChange {
+                            key: index_key.clone(),
+                            before: StoreValue::new(None),
+                            after: StoreValue::new(Some(vec![7])),
+                        },
+                    ],
+                }
+                .into())
+            }
+            _ => panic!("Unimplemented fixture instruction {:?}", instruction),
+        }));
+        let command = DiffCommand {
+            grouping: Some(GroupingLabel::from("grouping")),
+            from_height: ChainHeight::new(2).into(),
+            to_height: None,
+        };
+        match execute_diff(command, &mut core).unwrap() {
+            Outcome::Diff(outcome) => {
+                assert_eq!(outcome.to_height, ChainHeight::new(5));
+                assert_eq!(outcome.changes.len(), 2);
+                let unit_diff = &outcome.changes[0];
+                assert_eq!(unit_diff.key, key);
+                assert_eq!(
+                    StoreKey::from(unit_diff.specifier.clone().unwrap()),
+                    StoreKey::from(specifier)
+                );
+                assert_eq!(unit_diff.before, Some(before));
+                assert_eq!(unit_diff.after, Some(after));
+                assert_eq!(
+                    unit_diff.fields,
+                    vec![
+                        FieldDiff {
+                            path: String::from("a"),
+                            before: Some(json!(1)),
+                            after: Some(json!(2)),
+                        },
+                        FieldDiff {
+                            path: String::from("b.d"),
+                            before: None,
+                            after: Some(json!("x")),
+                        },
+                    ]
+                );
+                let index_diff = &outcome.changes[1];
+                assert!(index_diff.specifier.is_none());
+                assert_eq!(index_diff.before, None);
+                assert_eq!(index_diff.after, Some(UnitContent::Bytes(vec![7])));
+                assert!(index_diff.fields.is_empty());
+            }
+            outcome => panic!("Unexpected outcome {:?}", outcome),
+        }
+    }
+
+    #[test]
+    fn test_diff_fields_compares_arrays_whole() {
+        let before = json!({"list": [1, 2], "same": {"x": null}});
+        let after = json!({"list": [1, 2, 3], "same": {"x": null}});
+        assert_eq!(
+            diff_fields(&before, &after),
+            vec![FieldDiff {
+                path: String::from("list"),
+                before: Some(json!([1, 2])),
+                after: Some(json!([1, 2, 3])),
+            }]
+        );
+    }
+}
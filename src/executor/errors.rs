@@ -12,6 +12,9 @@ pub enum ExecutorError {
     CannotParseJson,
     CannotFindId(UnitId),
     NoneReverseIndex,
+    MissingTag(String),
+    InvalidTagName(String),
+    InvalidContinuation(String),
 }
 
 impl From<ExecutorError> for ImmuxError {
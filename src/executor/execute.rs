@@ -1,19 +1,41 @@
 use crate::declarations::commands::{Command, Outcome};
 use crate::declarations::errors::ImmuxResult;
+use crate::executor::errors::ExecutorError;
 
+use crate::executor::change_feed_executor::execute_change_feed;
+use crate::executor::compact_executor::execute_compact;
 use crate::executor::create_index_executor::execute_create_index;
+use crate::executor::create_tag_executor::execute_create_tag;
+use crate::executor::delete_tag_executor::execute_delete_tag;
+use crate::executor::diff_executor::execute_diff;
+use crate::executor::export_chain_executor::execute_export_chain;
+use crate::executor::fork_chain_executor::execute_fork_chain;
+use crate::executor::get_retention_executor::execute_get_retention;
+use crate::executor::import_chain_executor::execute_import_chain;
 use crate::executor::insert_executor::execute_insert;
 use crate::executor::inspect_executor::execute_inspect;
+use crate::executor::list_tags_executor::execute_list_tags;
+use crate::executor::merge_chain_executor::execute_merge_chain;
 use crate::executor::name_chain_executor::execute_name_chain;
 use crate::executor::pick_chain_executor::execute_pick_chain;
+use crate::executor::prove_executor::execute_prove;
+use crate::executor::remove_executor::execute_remove;
+use crate::executor::replay_chain_executor::execute_replay_chain;
 use crate::executor::revert_all_executor::execute_revert_all;
 use crate::executor::revert_many_executor::execute_revert_many;
+use crate::executor::revert_record_executor::execute_revert_record;
 use crate::executor::select_executor::execute_select;
+use crate::executor::set_retention_executor::execute_set_retention;
+use crate::executor::verify_chain_executor::execute_verify_chain;
 use crate::storage::core::CoreStore;
+use crate::storage::instructions::{Answer, DBSystemAnswer, SetRecordMetaInstruction};
+use crate::storage::vkv::RecordMeta;
 
 pub fn execute(command: Command, core: &mut impl CoreStore) -> ImmuxResult<Outcome> {
     match command {
         Command::PickChain(pick_chain) => execute_pick_chain(pick_chain, core),
+        Command::ForkChain(fork_chain) => execute_fork_chain(fork_chain, core),
+        Command::MergeChain(merge_chain) => execute_merge_chain(merge_chain, core),
         Command::Insert(insert) => execute_insert(insert, core),
         Command::Select(select) => execute_select(select, core),
         Command::NameChain => execute_name_chain(core),
@@ -21,5 +43,102 @@ pub fn execute(command: Command, core: &mut impl CoreStore) -> ImmuxResult<Outco
         Command::RevertMany(revert) => execute_revert_many(revert, core),
         Command::RevertAll(revert_all) => execute_revert_all(revert_all, core),
         Command::Inspect(inspect) => execute_inspect(inspect, core),
+        Command::Remove(remove) => execute_remove(remove, core),
+        Command::VerifyChain => execute_verify_chain(core),
+        Command::Prove(prove) => execute_prove(prove, core),
+        Command::Diff(diff) => execute_diff(diff, core),
+        Command::ChangeFeed(change_feed) => execute_change_feed(change_feed, core),
+        Command::RevertRecord(revert_record) => execute_revert_record(revert_record, core),
+        Command::CreateTag(create_tag) => execute_create_tag(create_tag, core),
+        Command::DeleteTag(delete_tag) => execute_delete_tag(delete_tag, core),
+        Command::ListTags => execute_list_tags(core),
+        Command::SetRetention(set_retention) => execute_set_retention(set_retention, core),
+        Command::GetRetention => execute_get_retention(core),
+        Command::Compact => execute_compact(core),
+        Command::ReplayChain(replay_chain) => execute_replay_chain(replay_chain, core),
+        Command::ExportChain => execute_export_chain(core),
+        Command::ImportChain(import_chain) => execute_import_chain(import_chain, core),
+    }
+}
+
+fn set_record_meta(meta: RecordMeta, core: &mut impl CoreStore) -> ImmuxResult<()> {
+    match core.execute(&SetRecordMetaInstruction { meta }.into()) {
+        Err(error) => return Err(error),
+        Ok(Answer::DBSystem(DBSystemAnswer::SetRecordMetaOk(_answer))) => return Ok(()),
+        Ok(answer) => return Err(ExecutorError::UnexpectedAnswerType(answer).into()),
+    }
+}
+
+/// Puts the record meta back to the default when dropped, so that a command panicking midway
+/// does not leave its meta on the records of the commands after it
+struct RecordMetaGuard<'a, C: CoreStore> {
+    core: &'a mut C,
+    is_reset: bool,
+}
+
+impl<'a, C: CoreStore> RecordMetaGuard<'a, C> {
+    fn reset(mut self) -> ImmuxResult<()> {
+        self.is_reset = true;
+        return set_record_meta(RecordMeta::default(), self.core);
+    }
+}
+
+impl<'a, C: CoreStore> Drop for RecordMetaGuard<'a, C> {
+    fn drop(&mut self) {
+        if !self.is_reset {
+            // Only reached while unwinding, where the error has nowhere to go
+            let _ = set_record_meta(RecordMeta::default(), self.core);
+        }
+    }
+}
+
+/// Executes `command` with `meta` attached to every record it writes. The core must not be
+/// shared with other commands meanwhile, which holding its lock throughout ensures.
+pub fn execute_with_meta(
+    command: Command,
+    meta: RecordMeta,
+    core: &mut impl CoreStore,
+) -> ImmuxResult<Outcome> {
+    set_record_meta(meta, core)?;
+    let guard = RecordMetaGuard {
+        core,
+        is_reset: false,
+    };
+    let result = execute(command, guard.core);
+    guard.reset()?;
+    return result;
+}
+
+#[cfg(test)]
+mod execute_tests {
+    use std::cell::RefCell;
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+
+    use crate::declarations::commands::Command;
+    use crate::executor::execute::execute_with_meta;
+    use crate::executor::tests::FixtureCore;
+    use crate::storage::instructions::{DBSystemInstruction, Instruction, SetRecordMetaOkAnswer};
+    use crate::storage::vkv::RecordMeta;
+
+    #[test]
+    fn test_meta_reset_after_panic() {
+        let current_meta = RefCell::new(RecordMeta::default());
+        let mut core = FixtureCore::new(Box::new(|instruction| match instruction {
+            Instruction::DBSystem(DBSystemInstruction::SetRecordMeta(set_record_meta)) => {
+                current_meta.replace(set_record_meta.meta.clone());
+                return Ok(SetRecordMetaOkAnswer {}.into());
+            }
+            _ => panic!("Malformed command"),
+        }));
+        let meta = RecordMeta {
+            author: Some(String::from("author")),
+            message: None,
+            client_id: None,
+        };
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            execute_with_meta(Command::NameChain, meta, &mut core)
+        }));
+        assert!(result.is_err());
+        assert_eq!(*current_meta.borrow(), RecordMeta::default());
     }
 }
@@ -0,0 +1,116 @@
+use crate::declarations::basics::ChainName;
+use crate::declarations::commands::{ExportChainOutcome, Outcome};
+use crate::declarations::errors::ImmuxResult;
+use crate::executor::errors::ExecutorError;
+use crate::executor::shared::get_all_indexed_names_lists;
+use crate::storage::archive::{ChainArchive, IndexDefinition};
+use crate::storage::core::CoreStore;
+use crate::storage::instructions::{
+    Answer, DBSystemAnswer, DBSystemInstruction, ExportRecordsInstruction, Instruction,
+    ReadNamespaceInstruction,
+};
+
+pub fn execute_export_chain(core: &mut impl CoreStore) -> ImmuxResult<Outcome> {
+    let chain: ChainName = match core.execute(&Instruction::DBSystem(
+        DBSystemInstruction::ReadNamespace(ReadNamespaceInstruction {}),
+    )) {
+        Err(error) => return Err(error),
+        Ok(Answer::DBSystem(DBSystemAnswer::ReadNamespaceOk(answer))) => answer.namespace.into(),
+        Ok(answer) => return Err(ExecutorError::UnexpectedAnswerType(answer).into()),
+    };
+    let indexes = get_all_indexed_names_lists(core)?
+        .into_iter()
+        .map(|(grouping, names)| IndexDefinition { grouping, names })
+        .collect();
+    match core.execute(&ExportRecordsInstruction {}.into()) {
+        Err(error) => return Err(error),
+        Ok(Answer::DBSystem(DBSystemAnswer::ExportRecordsOk(answer))) => {
+            let archive = ChainArchive::new(chain.clone(), indexes, answer.records);
+            return Ok(Outcome::ExportChain(ExportChainOutcome {
+                chain,
+                height: answer.height,
+                head: answer.head,
+                archive: archive.marshal()?,
+            }));
+        }
+        Ok(answer) => return Err(ExecutorError::UnexpectedAnswerType(answer).into()),
+    }
+}
+
+#[cfg(test)]
+mod export_chain_executor_tests {
+    use bincode::serialize;
+
+    use crate::declarations::basics::{
+        ChainName, PropertyName, PropertyNameList, StoreKey, StoreValue,
+    };
+    use crate::declarations::commands::Outcome;
+    use crate::executor::export_chain_executor::execute_export_chain;
+    use crate::executor::tests::FixtureCore;
+    use crate::storage::archive::ChainArchive;
+    use crate::storage::instructions::{
+        Answer, DBSystemInstruction, DataAnswer, DataInstruction, DataReadAnswer,
+        DataReadInstruction, ExportRecordsOkAnswer, GetManyOkAnswer, Instruction,
+        ReadNamespaceOkAnswer, StoreNamespace,
+    };
+    use crate::storage::vkv::{ChainHeight, InstructionRecord, RecordHash};
+
+    #[test]
+    fn test_export_chain() {
+        let names = PropertyNameList::new(vec![PropertyName::from("name")]);
+        let names_data = serialize(&names).unwrap();
+        let mut core = FixtureCore::new(Box::new(move |instruction| match instruction {
+            Instruction::DBSystem(DBSystemInstruction::ReadNamespace(_)) => {
+                Ok(ReadNamespaceOkAnswer {
+                    namespace: StoreNamespace::new(b"main"),
+                }
+                .into())
+            }
+            Instruction::DataAccess(DataInstruction::Read(DataReadInstruction::GetMany(_))) => {
+                Ok(Answer::DataAccess(DataAnswer::Read(
+                    DataReadAnswer::GetManyOk(GetManyOkAnswer {
+                        data: vec![
+                            (
+                                StoreKey::new(&[0x21, 0x01, b'g']).into(),
+                                StoreValue::new(Some(names_data.clone())).into(),
+                            ),
+                            // A unit of a grouping whose label is 0x21 bytes long
+                            (
+                                StoreKey::new(&[0x21; 50]).into(),
+                                StoreValue::new(Some(vec![0x00])).into(),
+                            ),
+                        ],
+                    }),
+                )))
+            }
+            Instruction::DBSystem(DBSystemInstruction::ExportRecords(_)) => {
+                let mut record = InstructionRecord::from(instruction.to_owned());
+                record.content_hash = record.compute_content_hash().unwrap();
+                Ok(ExportRecordsOkAnswer {
+                    height: ChainHeight::new(1),
+                    head: record.chained_hash(),
+                    records: vec![record],
+                }
+                .into())
+            }
+            _ => panic!("Unimplemented fixture instruction {:?}", instruction),
+        }));
+        match execute_export_chain(&mut core).unwrap() {
+            Outcome::ExportChain(outcome) => {
+                assert_eq!(outcome.chain, ChainName::from("main"));
+                assert_eq!(outcome.height, ChainHeight::new(1));
+                let archive = ChainArchive::parse(&outcome.archive).unwrap();
+                assert_eq!(archive.chain, ChainName::from("main"));
+                assert_eq!(archive.indexes.len(), 1);
+                assert_eq!(archive.indexes[0].grouping.as_bytes(), b"g");
+                assert_eq!(
+                    archive.indexes[0].names.as_slice(),
+                    &[PropertyName::from("name")]
+                );
+                assert_eq!(archive.verify_chain().unwrap(), outcome.head);
+                assert_ne!(outcome.head, RecordHash::genesis());
+            }
+            outcome => panic!("Unexpected outcome {:?}", outcome),
+        }
+    }
+}
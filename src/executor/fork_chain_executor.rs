@@ -0,0 +1,72 @@
+use crate::declarations::commands::{ForkChainCommand, ForkChainOutcome, Outcome};
+use crate::declarations::errors::{ImmuxError, ImmuxResult};
+use crate::executor::errors::ExecutorError;
+use crate::storage::core::CoreStore;
+use crate::storage::instructions::{Answer, DBSystemAnswer, ForkNamespaceInstruction};
+
+pub fn execute_fork_chain(
+    fork_chain: ForkChainCommand,
+    core: &mut impl CoreStore,
+) -> ImmuxResult<Outcome> {
+    let instruction = ForkNamespaceInstruction {
+        source: fork_chain.source.into(),
+        at_height: fork_chain.at_height,
+        new_namespace: fork_chain.new_chain_name.into(),
+    };
+    match core.execute(&instruction.into()) {
+        Err(error) => return Err(error),
+        Ok(Answer::DBSystem(DBSystemAnswer::ForkNamespaceOk(answer))) => {
+            return Ok(Outcome::ForkChain(ForkChainOutcome {
+                new_chain_name: answer.new_namespace.into(),
+                height: answer.height,
+                head: answer.head,
+            }))
+        }
+        Ok(answer) => {
+            return Err(ImmuxError::Executor(ExecutorError::UnexpectedAnswerType(
+                answer,
+            )))
+        }
+    }
+}
+
+#[cfg(test)]
+mod fork_chain_executor_tests {
+    use crate::declarations::basics::ChainName;
+    use crate::declarations::commands::{ForkChainCommand, Outcome};
+    use crate::executor::fork_chain_executor::execute_fork_chain;
+    use crate::executor::tests::FixtureCore;
+    use crate::storage::instructions::{
+        DBSystemInstruction, ForkNamespaceOkAnswer, Instruction, StoreNamespace,
+    };
+    use crate::storage::vkv::{ChainHeight, RecordHash};
+
+    #[test]
+    fn test_fork_chain() {
+        let mut core = FixtureCore::new(Box::new(|instruction| match instruction {
+            Instruction::DBSystem(DBSystemInstruction::ForkNamespace(fork_namespace)) => {
+                assert_eq!(fork_namespace.source, StoreNamespace::new(b"production"));
+                Ok(ForkNamespaceOkAnswer {
+                    new_namespace: fork_namespace.new_namespace.to_owned(),
+                    height: fork_namespace.at_height,
+                    head: RecordHash::digest(&[b"head"]),
+                }
+                .into())
+            }
+            _ => panic!("Unimplemented fixture instruction {:?}", instruction),
+        }));
+        let command = ForkChainCommand {
+            source: ChainName::from("production"),
+            at_height: ChainHeight::new(42),
+            new_chain_name: ChainName::from("sandbox"),
+        };
+        match execute_fork_chain(command, &mut core).unwrap() {
+            Outcome::ForkChain(outcome) => {
+                assert_eq!(outcome.new_chain_name, ChainName::from("sandbox"));
+                assert_eq!(outcome.height, ChainHeight::new(42));
+                assert_eq!(outcome.head, RecordHash::digest(&[b"head"]));
+            }
+            outcome => panic!("Unexpected outcome {:?}", outcome),
+        }
+    }
+}
@@ -0,0 +1,18 @@
+use crate::declarations::commands::{GetRetentionOutcome, Outcome};
+use crate::declarations::errors::ImmuxResult;
+use crate::executor::errors::ExecutorError;
+use crate::storage::core::CoreStore;
+use crate::storage::instructions::{Answer, DBSystemAnswer, ReadRetentionInstruction};
+
+pub fn execute_get_retention(core: &mut impl CoreStore) -> ImmuxResult<Outcome> {
+    match core.execute(&ReadRetentionInstruction {}.into()) {
+        Err(error) => return Err(error),
+        Ok(Answer::DBSystem(DBSystemAnswer::ReadRetentionOk(answer))) => {
+            return Ok(Outcome::GetRetention(GetRetentionOutcome {
+                rules: answer.rules,
+                markers: answer.markers,
+            }));
+        }
+        Ok(answer) => return Err(ExecutorError::UnexpectedAnswerType(answer).into()),
+    }
+}
@@ -0,0 +1,159 @@
+use crate::declarations::commands::{ImportChainCommand, ImportChainOutcome, Outcome};
+use crate::declarations::errors::ImmuxResult;
+use crate::executor::errors::ExecutorError;
+use crate::executor::shared::get_indexed_names_list_with_empty_fallback;
+use crate::storage::archive::{ArchiveError, ChainArchive, IndexDefinition};
+use crate::storage::core::CoreStore;
+use crate::storage::instructions::{
+    Answer, DBSystemAnswer, DBSystemInstruction, ImportRecordsInstruction, Instruction,
+    ReadNamespaceInstruction, StoreNamespace, SwitchNamespaceInstruction,
+};
+
+pub fn execute_import_chain(
+    import_chain: ImportChainCommand,
+    core: &mut impl CoreStore,
+) -> ImmuxResult<Outcome> {
+    let archive = ChainArchive::parse(&import_chain.archive)?;
+    let head = archive.verify_chain()?;
+    let chain = import_chain.chain.unwrap_or(archive.chain);
+    let instruction = ImportRecordsInstruction {
+        namespace: chain.clone().into(),
+        records: archive.records,
+    };
+    let answer = match core.execute(&instruction.into()) {
+        Err(error) => return Err(error),
+        Ok(Answer::DBSystem(DBSystemAnswer::ImportRecordsOk(answer))) => answer,
+        Ok(answer) => return Err(ExecutorError::UnexpectedAnswerType(answer).into()),
+    };
+    if answer.head != head {
+        return Err(ArchiveError::BrokenChain(answer.height).into());
+    }
+    check_indexes(&archive.indexes, answer.namespace, core)?;
+    return Ok(Outcome::ImportChain(ImportChainOutcome {
+        chain,
+        height: answer.height,
+        head: answer.head,
+    }));
+}
+
+/// Index definitions are written by the records like any other unit, so the imported chain must
+/// have arrived at the ones the archive lists
+fn check_indexes(
+    indexes: &[IndexDefinition],
+    namespace: StoreNamespace,
+    core: &mut impl CoreStore,
+) -> ImmuxResult<()> {
+    if indexes.is_empty() {
+        return Ok(());
+    }
+    let original_namespace = match core.execute(&Instruction::DBSystem(
+        DBSystemInstruction::ReadNamespace(ReadNamespaceInstruction {}),
+    )) {
+        Err(error) => return Err(error),
+        Ok(Answer::DBSystem(DBSystemAnswer::ReadNamespaceOk(answer))) => answer.namespace,
+        Ok(answer) => return Err(ExecutorError::UnexpectedAnswerType(answer).into()),
+    };
+    core.execute(&Instruction::DBSystem(
+        DBSystemInstruction::SwitchNamespace(SwitchNamespaceInstruction {
+            new_namespace: namespace,
+        }),
+    ))?;
+    let mut result = Ok(());
+    for index in indexes {
+        match get_indexed_names_list_with_empty_fallback(&index.grouping, core) {
+            Err(error) => result = Err(error),
+            Ok(names) => {
+                if names.as_slice() != index.names.as_slice() {
+                    result = Err(ArchiveError::IndexMismatch(index.grouping.to_owned()).into());
+                }
+            }
+        }
+        if result.is_err() {
+            break;
+        }
+    }
+    core.execute(&Instruction::DBSystem(
+        DBSystemInstruction::SwitchNamespace(SwitchNamespaceInstruction {
+            new_namespace: original_namespace,
+        }),
+    ))?;
+    return result;
+}
+
+#[cfg(test)]
+mod import_chain_executor_tests {
+    use crate::declarations::basics::{ChainName, StoreKey, StoreValue};
+    use crate::declarations::commands::{ImportChainCommand, Outcome};
+    use crate::declarations::errors::ImmuxError;
+    use crate::executor::import_chain_executor::execute_import_chain;
+    use crate::executor::tests::FixtureCore;
+    use crate::storage::archive::{ArchiveError, ChainArchive};
+    use crate::storage::instructions::{
+        DBSystemInstruction, ImportRecordsOkAnswer, Instruction, SetManyInstruction, SetTargetSpec,
+        StoreNamespace,
+    };
+    use crate::storage::vkv::{ChainHeight, InstructionRecord, RecordHash};
+
+    fn make_archive_data(previous_hash: RecordHash) -> Vec<u8> {
+        let instruction: Instruction = SetManyInstruction {
+            targets: vec![SetTargetSpec {
+                key: StoreKey::from("key"),
+                value: StoreValue::new(Some(vec![1])),
+            }],
+        }
+        .into();
+        let mut record = InstructionRecord::from(instruction);
+        record.previous_hash = previous_hash;
+        record.content_hash = record.compute_content_hash().unwrap();
+        let archive = ChainArchive::new(ChainName::from("main"), vec![], vec![record]);
+        archive.marshal().unwrap()
+    }
+
+    #[test]
+    fn test_import_chain() {
+        let data = make_archive_data(RecordHash::genesis());
+        let head = ChainArchive::parse(&data).unwrap().verify_chain().unwrap();
+        let mut core = FixtureCore::new(Box::new(move |instruction| match instruction {
+            Instruction::DBSystem(DBSystemInstruction::ImportRecords(import_records)) => {
+                assert_eq!(import_records.namespace, StoreNamespace::new(b"restored"));
+                assert_eq!(import_records.records.len(), 1);
+                Ok(ImportRecordsOkAnswer {
+                    namespace: import_records.namespace.to_owned(),
+                    height: ChainHeight::new(1),
+                    head: import_records.records[0].chained_hash(),
+                }
+                .into())
+            }
+            _ => panic!("Unimplemented fixture instruction {:?}", instruction),
+        }));
+        let command = ImportChainCommand {
+            archive: data,
+            chain: Some(ChainName::from("restored")),
+        };
+        match execute_import_chain(command, &mut core).unwrap() {
+            Outcome::ImportChain(outcome) => {
+                assert_eq!(outcome.chain, ChainName::from("restored"));
+                assert_eq!(outcome.height, ChainHeight::new(1));
+                assert_eq!(outcome.head, head);
+            }
+            outcome => panic!("Unexpected outcome {:?}", outcome),
+        }
+    }
+
+    #[test]
+    fn test_import_broken_chain() {
+        let mut core = FixtureCore::new(Box::new(|instruction| {
+            panic!("Unexpected instruction {:?}", instruction)
+        }));
+        let command = ImportChainCommand {
+            archive: make_archive_data(RecordHash::digest(&[b"elsewhere"])),
+            chain: None,
+        };
+        match execute_import_chain(command, &mut core) {
+            Err(ImmuxError::Archive(ArchiveError::BrokenChain(height))) => {
+                assert_eq!(height, ChainHeight::new(1))
+            }
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+}
@@ -6,7 +6,7 @@ use crate::declarations::basics::{
     GroupingLabel, IdList, PropertyNameList, StoreKey, StoreValue, Unit, UnitContent,
 };
 use crate::declarations::commands::{InsertCommand, InsertOutcome, Outcome};
-use crate::declarations::errors::ImmuxResult;
+use crate::declarations::errors::{ImmuxError, ImmuxResult};
 use crate::executor::errors::ExecutorError;
 use crate::executor::shared::{
     get_indexed_names_list_with_empty_fallback, get_store_key_of_indexed_id_list, ReverseIndex,
@@ -16,6 +16,7 @@ use crate::storage::instructions::{
     Answer, DataAnswer, DataInstruction, DataReadAnswer, DataReadInstruction, DataWriteAnswer,
     DataWriteInstruction, GetOneInstruction, Instruction, SetManyInstruction, SetTargetSpec,
 };
+use crate::storage::vkv::VkvError;
 
 fn get_targets_existed_index(
     grouping: &GroupingLabel,
@@ -141,7 +142,11 @@ pub fn get_updates_for_index(
                     }
                 }
                 Ok(answer) => return Err(ExecutorError::UnexpectedAnswerType(answer).into()),
-                _ => continue,
+                Err(ImmuxError::VKV(VkvError::MissingJournal(_))) => {
+                    // The first unit with this property
+                    updates_for_index.set(&name, unit_content, new_ids)?;
+                }
+                Err(error) => return Err(error),
             }
         } else {
             let mut ids = updates_for_index.get(&name, &property);
@@ -2,12 +2,14 @@ use crate::declarations::basics::{StoreKey, UnitContent};
 use crate::declarations::commands::{InspectCommand, InspectOutcome, Inspection, Outcome};
 use crate::declarations::errors::{ImmuxError, ImmuxResult};
 use crate::executor::errors::ExecutorError;
+use crate::executor::shared::resolve_optional_height;
 use crate::storage::core::CoreStore;
 use crate::storage::instructions::{
     Answer, DataAnswer, DataReadAnswer, GetJournalInstruction, GetOneInstruction, Instruction,
 };
 
 pub fn execute_inspect(inspect: InspectCommand, core: &mut impl CoreStore) -> ImmuxResult<Outcome> {
+    let max_height = resolve_optional_height(&inspect.height, core)?;
     let store_key = StoreKey::from(inspect.specifier);
     let get_journal: Instruction = GetJournalInstruction {
         key: store_key.clone(),
@@ -19,6 +21,11 @@ pub fn execute_inspect(inspect: InspectCommand, core: &mut impl CoreStore) -> Im
             Answer::DataAccess(DataAnswer::Read(DataReadAnswer::GetJournalOk(journal_answer))) => {
                 let mut inspections: Vec<Inspection> = Vec::new();
                 for height in journal_answer.journal.update_heights.iter() {
+                    if let Some(max_height) = max_height {
+                        if height > max_height {
+                            break;
+                        }
+                    }
                     let get_value = GetOneInstruction {
                         height: Some(height),
                         key: store_key.clone(),
@@ -82,6 +89,7 @@ mod inspect_executor_tests {
         }));
         let command = InspectCommand {
             specifier: UnitSpecifier::new(GroupingLabel::from("grouping"), UnitId::new(1)),
+            height: None,
         };
         match execute_inspect(command, &mut core) {
             Err(ImmuxError::VKV(VkvError::MissingJournal(key))) => {
@@ -168,6 +176,7 @@ mod inspect_executor_tests {
         }));
         let command = InspectCommand {
             specifier: UnitSpecifier::new(grouping.clone(), UnitId::new(2)),
+            height: None,
         };
         match execute_inspect(command, &mut core) {
             Err(error) => panic!("Inspect failed: {:?}", error),
@@ -186,5 +195,21 @@ mod inspect_executor_tests {
             }
             Ok(outcome) => panic!("Unexpected outcome {:?}", outcome),
         }
+        let command = InspectCommand {
+            specifier: UnitSpecifier::new(grouping.clone(), UnitId::new(3)),
+            height: Some(ChainHeight::new(7).into()),
+        };
+        match execute_inspect(command, &mut core) {
+            Err(error) => panic!("Inspect failed: {:?}", error),
+            Ok(Outcome::Inspect(outcome)) => {
+                let heights: Vec<u64> = outcome
+                    .inspections
+                    .iter()
+                    .map(|inspection| inspection.height.as_u64())
+                    .collect();
+                assert_eq!(heights, vec![5, 7]);
+            }
+            Ok(outcome) => panic!("Unexpected outcome {:?}", outcome),
+        }
     }
 }
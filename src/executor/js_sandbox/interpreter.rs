@@ -0,0 +1,985 @@
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::rc::Rc;
+
+use serde_json::Value as JsonValue;
+
+use crate::executor::js_sandbox::parser::{
+    BinaryOperator, Expression, FunctionBody, FunctionDefinition, Statement, UnaryOperator,
+};
+use crate::executor::js_sandbox::{JsError, JsLimits};
+
+// Bounds the Rust stack used by nested evaluation, including recursive calls
+const MAX_DEPTH: usize = 256;
+
+// Rough sizes charged against the memory limit for everything but string contents
+const VALUE_SIZE: usize = 16;
+const SCOPE_SIZE: usize = 64;
+
+const STRING_METHODS: [&str; 7] = [
+    "endsWith",
+    "includes",
+    "indexOf",
+    "startsWith",
+    "toLowerCase",
+    "toUpperCase",
+    "trim",
+];
+const ARRAY_METHODS: [&str; 2] = ["includes", "indexOf"];
+const MATH_FUNCTIONS: [&str; 8] = ["abs", "ceil", "floor", "max", "min", "pow", "round", "sqrt"];
+const GLOBAL_FUNCTIONS: [&str; 4] = ["Boolean", "Number", "String", "isNaN"];
+
+pub struct Closure {
+    definition: Rc<FunctionDefinition>,
+    scope: Rc<RefCell<Scope>>,
+    // Captured where the function is defined, as arrow functions do
+    this: JsValue,
+}
+
+// Scopes can hold the closures capturing them, so they are left out
+impl fmt::Debug for Closure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Closure({:?})", self.definition.params)
+    }
+}
+
+#[derive(Debug)]
+pub struct NativeFunction {
+    // Undefined for global functions
+    receiver: JsValue,
+    name: &'static str,
+}
+
+#[derive(Debug, Clone)]
+pub enum JsValue {
+    Undefined,
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(Rc<str>),
+    Array(Rc<Vec<JsValue>>),
+    Object(Rc<BTreeMap<String, JsValue>>),
+    Function(Rc<Closure>),
+    Native(Rc<NativeFunction>),
+}
+
+impl From<&JsonValue> for JsValue {
+    fn from(json: &JsonValue) -> JsValue {
+        match json {
+            JsonValue::Null => JsValue::Null,
+            JsonValue::Bool(boolean) => JsValue::Bool(*boolean),
+            JsonValue::Number(number) => JsValue::Number(number.as_f64().unwrap_or(std::f64::NAN)),
+            JsonValue::String(string) => JsValue::String(Rc::from(string.as_str())),
+            JsonValue::Array(array) => {
+                JsValue::Array(Rc::new(array.iter().map(JsValue::from).collect()))
+            }
+            JsonValue::Object(map) => JsValue::Object(Rc::new(
+                map.iter()
+                    .map(|(key, value)| (key.to_owned(), JsValue::from(value)))
+                    .collect(),
+            )),
+        }
+    }
+}
+
+struct Scope {
+    variables: HashMap<String, JsValue>,
+    parent: Option<Rc<RefCell<Scope>>>,
+}
+
+enum Completion {
+    Normal,
+    Return(JsValue),
+    Break,
+    Continue,
+}
+
+fn to_boolean(value: &JsValue) -> bool {
+    match value {
+        JsValue::Undefined | JsValue::Null => false,
+        JsValue::Bool(boolean) => *boolean,
+        JsValue::Number(number) => !(*number == 0.0 || number.is_nan()),
+        JsValue::String(string) => !string.is_empty(),
+        _ => true,
+    }
+}
+
+fn to_number(value: &JsValue) -> f64 {
+    match value {
+        JsValue::Null => 0.0,
+        JsValue::Bool(boolean) => {
+            if *boolean {
+                1.0
+            } else {
+                0.0
+            }
+        }
+        JsValue::Number(number) => *number,
+        JsValue::String(string) => {
+            let trimmed = string.trim();
+            if trimmed.is_empty() {
+                return 0.0;
+            }
+            match trimmed.parse::<f64>() {
+                Ok(number)
+                    if !trimmed
+                        .chars()
+                        .any(|c| c.is_ascii_alphabetic() && c != 'e' && c != 'E') =>
+                {
+                    number
+                }
+                _ => match trimmed {
+                    "Infinity" | "+Infinity" => std::f64::INFINITY,
+                    "-Infinity" => std::f64::NEG_INFINITY,
+                    _ => std::f64::NAN,
+                },
+            }
+        }
+        _ => std::f64::NAN,
+    }
+}
+
+fn number_to_string(number: f64) -> String {
+    if number.is_nan() {
+        return String::from("NaN");
+    } else if number.is_infinite() {
+        return String::from(if number > 0.0 {
+            "Infinity"
+        } else {
+            "-Infinity"
+        });
+    } else if number == 0.0 {
+        return String::from("0");
+    } else {
+        return format!("{}", number);
+    }
+}
+
+fn to_string(value: &JsValue) -> String {
+    match value {
+        JsValue::Undefined => String::from("undefined"),
+        JsValue::Null => String::from("null"),
+        JsValue::Bool(boolean) => boolean.to_string(),
+        JsValue::Number(number) => number_to_string(*number),
+        JsValue::String(string) => string.to_string(),
+        JsValue::Array(array) => array
+            .iter()
+            .map(|element| match element {
+                JsValue::Undefined | JsValue::Null => String::new(),
+                element => to_string(element),
+            })
+            .collect::<Vec<_>>()
+            .join(","),
+        JsValue::Object(_) => String::from("[object Object]"),
+        JsValue::Function(_) | JsValue::Native(_) => String::from("function"),
+    }
+}
+
+fn type_of(value: &JsValue) -> &'static str {
+    match value {
+        JsValue::Undefined => "undefined",
+        JsValue::Null | JsValue::Array(_) | JsValue::Object(_) => "object",
+        JsValue::Bool(_) => "boolean",
+        JsValue::Number(_) => "number",
+        JsValue::String(_) => "string",
+        JsValue::Function(_) | JsValue::Native(_) => "function",
+    }
+}
+
+fn strict_equals(left: &JsValue, right: &JsValue) -> bool {
+    match (left, right) {
+        (JsValue::Undefined, JsValue::Undefined) | (JsValue::Null, JsValue::Null) => true,
+        (JsValue::Bool(left), JsValue::Bool(right)) => left == right,
+        (JsValue::Number(left), JsValue::Number(right)) => left == right,
+        (JsValue::String(left), JsValue::String(right)) => left == right,
+        (JsValue::Array(left), JsValue::Array(right)) => Rc::ptr_eq(left, right),
+        (JsValue::Object(left), JsValue::Object(right)) => Rc::ptr_eq(left, right),
+        (JsValue::Function(left), JsValue::Function(right)) => Rc::ptr_eq(left, right),
+        (JsValue::Native(left), JsValue::Native(right)) => Rc::ptr_eq(left, right),
+        _ => false,
+    }
+}
+
+fn loose_equals(left: &JsValue, right: &JsValue) -> bool {
+    match (left, right) {
+        (JsValue::Undefined, _) | (JsValue::Null, _) => match right {
+            JsValue::Undefined | JsValue::Null => true,
+            _ => false,
+        },
+        (_, JsValue::Undefined) | (_, JsValue::Null) => false,
+        (JsValue::Number(_), JsValue::String(_))
+        | (JsValue::String(_), JsValue::Number(_))
+        | (JsValue::Bool(_), _)
+        | (_, JsValue::Bool(_)) => to_number(left) == to_number(right),
+        _ => strict_equals(left, right),
+    }
+}
+
+fn compare(left: &JsValue, right: &JsValue) -> Option<Ordering> {
+    match (left, right) {
+        (JsValue::String(left), JsValue::String(right)) => Some(left.cmp(right)),
+        _ => to_number(left).partial_cmp(&to_number(right)),
+    }
+}
+
+fn get_index(key: &JsValue) -> Option<usize> {
+    match key {
+        JsValue::Number(number) if *number >= 0.0 && number.fract() == 0.0 => {
+            Some(*number as usize)
+        }
+        JsValue::String(string) => string.parse::<usize>().ok(),
+        _ => None,
+    }
+}
+
+fn find_name(names: &[&'static str], name: &str) -> Option<&'static str> {
+    names.iter().find(|candidate| **candidate == name).cloned()
+}
+
+fn get_globals() -> HashMap<String, JsValue> {
+    let mut globals = HashMap::new();
+    let mut math = BTreeMap::new();
+    for name in MATH_FUNCTIONS.iter() {
+        let function = NativeFunction {
+            receiver: JsValue::Undefined,
+            name,
+        };
+        math.insert(name.to_string(), JsValue::Native(Rc::new(function)));
+    }
+    math.insert(String::from("PI"), JsValue::Number(std::f64::consts::PI));
+    globals.insert(String::from("Math"), JsValue::Object(Rc::new(math)));
+    for name in GLOBAL_FUNCTIONS.iter() {
+        let function = NativeFunction {
+            receiver: JsValue::Undefined,
+            name,
+        };
+        globals.insert(name.to_string(), JsValue::Native(Rc::new(function)));
+    }
+    globals.insert(String::from("undefined"), JsValue::Undefined);
+    globals.insert(String::from("NaN"), JsValue::Number(std::f64::NAN));
+    globals.insert(
+        String::from("Infinity"),
+        JsValue::Number(std::f64::INFINITY),
+    );
+    return globals;
+}
+
+struct Interpreter {
+    limits: JsLimits,
+    steps: u64,
+    // Bytes allocated so far; nothing is given back during one evaluation
+    memory: usize,
+    depth: usize,
+    // Scopes captured by closures, which may in turn be stored in them
+    captured_scopes: Vec<Rc<RefCell<Scope>>>,
+}
+
+// Breaks the reference cycles between scopes and the closures stored in them
+impl Drop for Interpreter {
+    fn drop(&mut self) {
+        for scope in &self.captured_scopes {
+            scope.borrow_mut().variables.clear();
+        }
+    }
+}
+
+impl Interpreter {
+    fn tick(&mut self) -> Result<(), JsError> {
+        self.steps += 1;
+        if self.steps > self.limits.max_steps {
+            return Err(JsError::StepLimitExceeded);
+        }
+        return Ok(());
+    }
+
+    fn allocate(&mut self, size: usize) -> Result<(), JsError> {
+        self.memory += size;
+        if self.memory > self.limits.max_memory {
+            return Err(JsError::MemoryLimitExceeded);
+        }
+        return Ok(());
+    }
+
+    fn enter(&mut self) -> Result<(), JsError> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return Err(JsError::TooDeeplyNested);
+        }
+        return Ok(());
+    }
+
+    fn new_string(&mut self, string: String) -> Result<JsValue, JsError> {
+        self.allocate(string.len())?;
+        return Ok(JsValue::String(Rc::from(string.as_str())));
+    }
+
+    fn lookup(&self, name: &str, scope: &Rc<RefCell<Scope>>) -> Option<JsValue> {
+        let mut current = scope.clone();
+        loop {
+            if let Some(value) = current.borrow().variables.get(name) {
+                return Some(value.clone());
+            }
+            let parent = match &current.borrow().parent {
+                None => return None,
+                Some(parent) => parent.clone(),
+            };
+            current = parent;
+        }
+    }
+
+    fn assign(
+        &mut self,
+        name: &str,
+        value: JsValue,
+        scope: &Rc<RefCell<Scope>>,
+    ) -> Result<(), JsError> {
+        let mut current = scope.clone();
+        loop {
+            if let Some(variable) = current.borrow_mut().variables.get_mut(name) {
+                *variable = value;
+                return Ok(());
+            }
+            let parent = match &current.borrow().parent {
+                None => return Err(JsError::ReferenceError(name.to_owned())),
+                Some(parent) => parent.clone(),
+            };
+            current = parent;
+        }
+    }
+
+    fn declare(
+        &mut self,
+        name: &str,
+        value: JsValue,
+        scope: &Rc<RefCell<Scope>>,
+    ) -> Result<(), JsError> {
+        self.allocate(VALUE_SIZE + name.len())?;
+        scope.borrow_mut().variables.insert(name.to_owned(), value);
+        return Ok(());
+    }
+
+    fn execute_block(
+        &mut self,
+        statements: &[Statement],
+        scope: &Rc<RefCell<Scope>>,
+        this: &JsValue,
+    ) -> Result<Completion, JsError> {
+        for statement in statements {
+            match self.execute(statement, scope, this)? {
+                Completion::Normal => {}
+                completion => return Ok(completion),
+            }
+        }
+        return Ok(Completion::Normal);
+    }
+
+    fn execute(
+        &mut self,
+        statement: &Statement,
+        scope: &Rc<RefCell<Scope>>,
+        this: &JsValue,
+    ) -> Result<Completion, JsError> {
+        self.tick()?;
+        self.enter()?;
+        let completion = self.execute_inner(statement, scope, this);
+        self.depth -= 1;
+        return completion;
+    }
+
+    fn execute_inner(
+        &mut self,
+        statement: &Statement,
+        scope: &Rc<RefCell<Scope>>,
+        this: &JsValue,
+    ) -> Result<Completion, JsError> {
+        match statement {
+            Statement::Expression(expression) => {
+                self.evaluate(expression, scope, this)?;
+                return Ok(Completion::Normal);
+            }
+            Statement::Declare(declarations) => {
+                for (name, initializer) in declarations {
+                    let value = match initializer {
+                        None => JsValue::Undefined,
+                        Some(initializer) => self.evaluate(initializer, scope, this)?,
+                    };
+                    self.declare(name, value, scope)?;
+                }
+                return Ok(Completion::Normal);
+            }
+            Statement::Return(value) => match value {
+                None => return Ok(Completion::Return(JsValue::Undefined)),
+                Some(value) => return Ok(Completion::Return(self.evaluate(value, scope, this)?)),
+            },
+            Statement::If(condition, consequent, alternate) => {
+                if to_boolean(&self.evaluate(condition, scope, this)?) {
+                    return self.execute(consequent, scope, this);
+                }
+                match alternate {
+                    None => return Ok(Completion::Normal),
+                    Some(alternate) => return self.execute(alternate, scope, this),
+                }
+            }
+            Statement::While(condition, body) => {
+                while to_boolean(&self.evaluate(condition, scope, this)?) {
+                    match self.execute(body, scope, this)? {
+                        Completion::Break => break,
+                        Completion::Return(value) => return Ok(Completion::Return(value)),
+                        Completion::Normal | Completion::Continue => {}
+                    }
+                }
+                return Ok(Completion::Normal);
+            }
+            Statement::For(init, condition, update, body) => {
+                if let Some(init) = init {
+                    self.execute(init, scope, this)?;
+                }
+                loop {
+                    if let Some(condition) = condition {
+                        if !to_boolean(&self.evaluate(condition, scope, this)?) {
+                            break;
+                        }
+                    }
+                    match self.execute(body, scope, this)? {
+                        Completion::Break => break,
+                        Completion::Return(value) => return Ok(Completion::Return(value)),
+                        Completion::Normal | Completion::Continue => {}
+                    }
+                    match update {
+                        None => self.tick()?,
+                        Some(update) => {
+                            self.evaluate(update, scope, this)?;
+                        }
+                    }
+                }
+                return Ok(Completion::Normal);
+            }
+            Statement::Block(statements) => return self.execute_block(statements, scope, this),
+            Statement::Break => return Ok(Completion::Break),
+            Statement::Continue => return Ok(Completion::Continue),
+            Statement::Empty => return Ok(Completion::Normal),
+        }
+    }
+
+    fn evaluate(
+        &mut self,
+        expression: &Expression,
+        scope: &Rc<RefCell<Scope>>,
+        this: &JsValue,
+    ) -> Result<JsValue, JsError> {
+        self.tick()?;
+        self.enter()?;
+        let value = self.evaluate_inner(expression, scope, this);
+        self.depth -= 1;
+        return value;
+    }
+
+    fn evaluate_inner(
+        &mut self,
+        expression: &Expression,
+        scope: &Rc<RefCell<Scope>>,
+        this: &JsValue,
+    ) -> Result<JsValue, JsError> {
+        match expression {
+            Expression::Number(number) => return Ok(JsValue::Number(*number)),
+            // Literals are part of the code, so they are not charged
+            Expression::String(string) => return Ok(JsValue::String(Rc::from(string.as_str()))),
+            Expression::Bool(boolean) => return Ok(JsValue::Bool(*boolean)),
+            Expression::Null => return Ok(JsValue::Null),
+            Expression::This => return Ok(this.clone()),
+            Expression::Identifier(name) => match self.lookup(name, scope) {
+                None => return Err(JsError::ReferenceError(name.to_owned())),
+                Some(value) => return Ok(value),
+            },
+            Expression::Array(elements) => {
+                let mut array = Vec::with_capacity(elements.len());
+                for element in elements {
+                    array.push(self.evaluate(element, scope, this)?);
+                }
+                self.allocate(VALUE_SIZE * array.len())?;
+                return Ok(JsValue::Array(Rc::new(array)));
+            }
+            Expression::Object(entries) => {
+                let mut object = BTreeMap::new();
+                for (key, value) in entries {
+                    let value = self.evaluate(value, scope, this)?;
+                    self.allocate(VALUE_SIZE + key.len())?;
+                    object.insert(key.to_owned(), value);
+                }
+                return Ok(JsValue::Object(Rc::new(object)));
+            }
+            Expression::Member(object, key) => {
+                let object = self.evaluate(object, scope, this)?;
+                let key = self.evaluate(key, scope, this)?;
+                return self.get_member(&object, &key);
+            }
+            Expression::Call(callee, arguments) => {
+                let callee = self.evaluate(callee, scope, this)?;
+                let mut values = Vec::with_capacity(arguments.len());
+                for argument in arguments {
+                    values.push(self.evaluate(argument, scope, this)?);
+                }
+                return self.call(&callee, values);
+            }
+            Expression::Unary(operator, operand) => {
+                if let (UnaryOperator::TypeOf, Expression::Identifier(name)) =
+                    (operator, operand.as_ref())
+                {
+                    // Undeclared variables are allowed here
+                    if self.lookup(name, scope).is_none() {
+                        return Ok(JsValue::String(Rc::from("undefined")));
+                    }
+                }
+                let value = self.evaluate(operand, scope, this)?;
+                match operator {
+                    UnaryOperator::Not => return Ok(JsValue::Bool(!to_boolean(&value))),
+                    UnaryOperator::Negate => return Ok(JsValue::Number(-to_number(&value))),
+                    UnaryOperator::Plus => return Ok(JsValue::Number(to_number(&value))),
+                    UnaryOperator::TypeOf => return Ok(JsValue::String(Rc::from(type_of(&value)))),
+                }
+            }
+            Expression::Binary(BinaryOperator::And, left, right) => {
+                let left = self.evaluate(left, scope, this)?;
+                if !to_boolean(&left) {
+                    return Ok(left);
+                }
+                return self.evaluate(right, scope, this);
+            }
+            Expression::Binary(BinaryOperator::Or, left, right) => {
+                let left = self.evaluate(left, scope, this)?;
+                if to_boolean(&left) {
+                    return Ok(left);
+                }
+                return self.evaluate(right, scope, this);
+            }
+            Expression::Binary(operator, left, right) => {
+                let left = self.evaluate(left, scope, this)?;
+                let right = self.evaluate(right, scope, this)?;
+                return self.apply_binary(*operator, &left, &right);
+            }
+            Expression::Conditional(test, consequent, alternate) => {
+                if to_boolean(&self.evaluate(test, scope, this)?) {
+                    return self.evaluate(consequent, scope, this);
+                } else {
+                    return self.evaluate(alternate, scope, this);
+                }
+            }
+            Expression::Assign(name, operator, value) => {
+                let mut value = self.evaluate(value, scope, this)?;
+                if let Some(operator) = operator {
+                    let current = match self.lookup(name, scope) {
+                        None => return Err(JsError::ReferenceError(name.to_owned())),
+                        Some(current) => current,
+                    };
+                    value = self.apply_binary(*operator, &current, &value)?;
+                }
+                self.assign(name, value.clone(), scope)?;
+                return Ok(value);
+            }
+            Expression::Update(name, step, is_prefix) => {
+                let current = match self.lookup(name, scope) {
+                    None => return Err(JsError::ReferenceError(name.to_owned())),
+                    Some(current) => to_number(&current),
+                };
+                self.assign(name, JsValue::Number(current + step), scope)?;
+                if *is_prefix {
+                    return Ok(JsValue::Number(current + step));
+                } else {
+                    return Ok(JsValue::Number(current));
+                }
+            }
+            Expression::Function(definition) => {
+                self.allocate(SCOPE_SIZE)?;
+                self.captured_scopes.push(scope.clone());
+                return Ok(JsValue::Function(Rc::new(Closure {
+                    definition: definition.clone(),
+                    scope: scope.clone(),
+                    this: this.clone(),
+                })));
+            }
+        }
+    }
+
+    fn apply_binary(
+        &mut self,
+        operator: BinaryOperator,
+        left: &JsValue,
+        right: &JsValue,
+    ) -> Result<JsValue, JsError> {
+        let number = |result: f64| Ok(JsValue::Number(result));
+        let boolean = |result: bool| Ok(JsValue::Bool(result));
+        match operator {
+            BinaryOperator::Add => match (left, right) {
+                (JsValue::String(_), _)
+                | (_, JsValue::String(_))
+                | (JsValue::Array(_), _)
+                | (_, JsValue::Array(_))
+                | (JsValue::Object(_), _)
+                | (_, JsValue::Object(_)) => {
+                    let mut concatenated = to_string(left);
+                    concatenated.push_str(&to_string(right));
+                    self.new_string(concatenated)
+                }
+                _ => number(to_number(left) + to_number(right)),
+            },
+            BinaryOperator::Subtract => number(to_number(left) - to_number(right)),
+            BinaryOperator::Multiply => number(to_number(left) * to_number(right)),
+            BinaryOperator::Divide => number(to_number(left) / to_number(right)),
+            BinaryOperator::Remainder => number(to_number(left) % to_number(right)),
+            BinaryOperator::Equal => boolean(loose_equals(left, right)),
+            BinaryOperator::NotEqual => boolean(!loose_equals(left, right)),
+            BinaryOperator::StrictEqual => boolean(strict_equals(left, right)),
+            BinaryOperator::StrictNotEqual => boolean(!strict_equals(left, right)),
+            BinaryOperator::LessThan => boolean(compare(left, right) == Some(Ordering::Less)),
+            BinaryOperator::LessThanOrEqual => match compare(left, right) {
+                Some(Ordering::Less) | Some(Ordering::Equal) => boolean(true),
+                _ => boolean(false),
+            },
+            BinaryOperator::GreaterThan => boolean(compare(left, right) == Some(Ordering::Greater)),
+            BinaryOperator::GreaterThanOrEqual => match compare(left, right) {
+                Some(Ordering::Greater) | Some(Ordering::Equal) => boolean(true),
+                _ => boolean(false),
+            },
+            BinaryOperator::And => {
+                if to_boolean(left) {
+                    Ok(right.clone())
+                } else {
+                    Ok(left.clone())
+                }
+            }
+            BinaryOperator::Or => {
+                if to_boolean(left) {
+                    Ok(left.clone())
+                } else {
+                    Ok(right.clone())
+                }
+            }
+        }
+    }
+
+    fn get_member(&mut self, object: &JsValue, key: &JsValue) -> Result<JsValue, JsError> {
+        let name = to_string(key);
+        let method_of =
+            |receiver: &JsValue, methods: &[&'static str]| match find_name(methods, &name) {
+                None => JsValue::Undefined,
+                Some(method) => JsValue::Native(Rc::new(NativeFunction {
+                    receiver: receiver.clone(),
+                    name: method,
+                })),
+            };
+        match object {
+            JsValue::Undefined | JsValue::Null => {
+                return Err(JsError::TypeError(format!(
+                    "Cannot read property {} of {}",
+                    name,
+                    to_string(object)
+                )));
+            }
+            JsValue::String(string) => {
+                if name == "length" {
+                    return Ok(JsValue::Number(string.chars().count() as f64));
+                }
+                if let Some(index) = get_index(key) {
+                    match string.chars().nth(index) {
+                        None => return Ok(JsValue::Undefined),
+                        Some(c) => return self.new_string(c.to_string()),
+                    }
+                }
+                return Ok(method_of(object, &STRING_METHODS));
+            }
+            JsValue::Array(array) => {
+                if name == "length" {
+                    return Ok(JsValue::Number(array.len() as f64));
+                }
+                if let Some(index) = get_index(key) {
+                    return Ok(array.get(index).cloned().unwrap_or(JsValue::Undefined));
+                }
+                return Ok(method_of(object, &ARRAY_METHODS));
+            }
+            JsValue::Object(map) => {
+                return Ok(map.get(&name).cloned().unwrap_or(JsValue::Undefined));
+            }
+            _ => return Ok(JsValue::Undefined),
+        }
+    }
+
+    fn call(&mut self, callee: &JsValue, arguments: Vec<JsValue>) -> Result<JsValue, JsError> {
+        match callee {
+            JsValue::Function(closure) => {
+                self.allocate(SCOPE_SIZE)?;
+                let scope = Rc::new(RefCell::new(Scope {
+                    variables: HashMap::new(),
+                    parent: Some(closure.scope.clone()),
+                }));
+                let mut arguments = arguments.into_iter();
+                for param in &closure.definition.params {
+                    let argument = arguments.next().unwrap_or(JsValue::Undefined);
+                    self.declare(param, argument, &scope)?;
+                }
+                match &closure.definition.body {
+                    FunctionBody::Expression(expression) => {
+                        return self.evaluate(expression, &scope, &closure.this);
+                    }
+                    FunctionBody::Block(statements) => {
+                        match self.execute_block(statements, &scope, &closure.this)? {
+                            Completion::Return(value) => return Ok(value),
+                            _ => return Ok(JsValue::Undefined),
+                        }
+                    }
+                }
+            }
+            JsValue::Native(function) => return self.call_native(function, &arguments),
+            _ => {
+                return Err(JsError::TypeError(format!(
+                    "{} is not a function",
+                    to_string(callee)
+                )));
+            }
+        }
+    }
+
+    fn call_native(
+        &mut self,
+        function: &NativeFunction,
+        arguments: &[JsValue],
+    ) -> Result<JsValue, JsError> {
+        let argument = |index: usize| arguments.get(index).cloned().unwrap_or(JsValue::Undefined);
+        let numbers: Vec<f64> = arguments.iter().map(to_number).collect();
+        let first_number = numbers.get(0).cloned().unwrap_or(std::f64::NAN);
+        match (&function.receiver, function.name) {
+            (JsValue::String(string), method) => {
+                let needle = to_string(&argument(0));
+                match method {
+                    "includes" => return Ok(JsValue::Bool(string.contains(needle.as_str()))),
+                    "startsWith" => return Ok(JsValue::Bool(string.starts_with(needle.as_str()))),
+                    "endsWith" => return Ok(JsValue::Bool(string.ends_with(needle.as_str()))),
+                    "indexOf" => match string.find(needle.as_str()) {
+                        None => return Ok(JsValue::Number(-1.0)),
+                        Some(offset) => {
+                            return Ok(JsValue::Number(string[..offset].chars().count() as f64))
+                        }
+                    },
+                    "toLowerCase" => return self.new_string(string.to_lowercase()),
+                    "toUpperCase" => return self.new_string(string.to_uppercase()),
+                    _ => return self.new_string(string.trim().to_owned()),
+                }
+            }
+            (JsValue::Array(array), method) => {
+                let target = argument(0);
+                let position = array
+                    .iter()
+                    .position(|element| strict_equals(element, &target));
+                match method {
+                    "includes" => return Ok(JsValue::Bool(position.is_some())),
+                    _ => {
+                        return Ok(JsValue::Number(
+                            position.map(|index| index as f64).unwrap_or(-1.0),
+                        ))
+                    }
+                }
+            }
+            (_, "abs") => return Ok(JsValue::Number(first_number.abs())),
+            (_, "ceil") => return Ok(JsValue::Number(first_number.ceil())),
+            (_, "floor") => return Ok(JsValue::Number(first_number.floor())),
+            (_, "round") => return Ok(JsValue::Number((first_number + 0.5).floor())),
+            (_, "sqrt") => return Ok(JsValue::Number(first_number.sqrt())),
+            (_, "pow") => {
+                let exponent = numbers.get(1).cloned().unwrap_or(std::f64::NAN);
+                return Ok(JsValue::Number(first_number.powf(exponent)));
+            }
+            (_, "max") | (_, "min") => {
+                let is_max = function.name == "max";
+                let mut result = if is_max {
+                    std::f64::NEG_INFINITY
+                } else {
+                    std::f64::INFINITY
+                };
+                for number in numbers {
+                    if number.is_nan() {
+                        return Ok(JsValue::Number(std::f64::NAN));
+                    }
+                    if (is_max && number > result) || (!is_max && number < result) {
+                        result = number;
+                    }
+                }
+                return Ok(JsValue::Number(result));
+            }
+            (_, "isNaN") => return Ok(JsValue::Bool(first_number.is_nan())),
+            (_, "Number") => {
+                if arguments.is_empty() {
+                    return Ok(JsValue::Number(0.0));
+                }
+                return Ok(JsValue::Number(first_number));
+            }
+            (_, "Boolean") => return Ok(JsValue::Bool(to_boolean(&argument(0)))),
+            (_, _) => {
+                if arguments.is_empty() {
+                    return Ok(JsValue::String(Rc::from("")));
+                }
+                return self.new_string(to_string(&argument(0)));
+            }
+        }
+    }
+}
+
+/// Runs a script against a document bound to both `this` and `obj`.
+///
+/// The result is the value of the last expression statement, or of a top-level `return`.
+/// A function result is called with the document, so `function() { ... }` and `x => ...` both
+/// work as predicates.
+pub fn run(
+    statements: &[Statement],
+    document: &JsonValue,
+    limits: JsLimits,
+) -> Result<bool, JsError> {
+    let mut interpreter = Interpreter {
+        limits,
+        steps: 0,
+        memory: 0,
+        depth: 0,
+        captured_scopes: Vec::new(),
+    };
+    let this = JsValue::from(document);
+    let scope = Rc::new(RefCell::new(Scope {
+        variables: get_globals(),
+        parent: None,
+    }));
+    scope
+        .borrow_mut()
+        .variables
+        .insert(String::from("obj"), this.clone());
+
+    let mut result = JsValue::Undefined;
+    for statement in statements {
+        match statement {
+            Statement::Expression(expression) => {
+                result = interpreter.evaluate(expression, &scope, &this)?;
+            }
+            _ => match interpreter.execute(statement, &scope, &this)? {
+                Completion::Return(value) => {
+                    result = value;
+                    break;
+                }
+                _ => {}
+            },
+        }
+    }
+    if let JsValue::Function(_) = result {
+        result = interpreter.call(&result, vec![this.clone()])?;
+    }
+    return Ok(to_boolean(&result));
+}
+
+#[cfg(test)]
+mod js_interpreter_tests {
+    use serde_json::json;
+
+    use crate::executor::js_sandbox::interpreter::run;
+    use crate::executor::js_sandbox::parser::parse;
+    use crate::executor::js_sandbox::{JsError, JsLimits};
+
+    const LIMITS: JsLimits = JsLimits {
+        max_steps: 10_000,
+        max_memory: 64 * 1024,
+    };
+
+    fn evaluate(code: &str) -> Result<bool, JsError> {
+        let document =
+            json!({"name": "Tom", "age": 30, "tags": ["a", "b"], "address": {"city": "Paris"}});
+        return run(&parse(code).unwrap(), &document, LIMITS);
+    }
+
+    #[test]
+    fn test_expressions() {
+        let truthy = [
+            "this.age === 30",
+            "obj.name == 'Tom'",
+            "this.age > 18 && this.age <= 30",
+            "this['na' + 'me'].length === 3",
+            "this.address.city.startsWith('Pa')",
+            "this.tags.includes('b') && this.tags.indexOf('c') === -1",
+            "this.name.toLowerCase() + 1 === 'tom1'",
+            "'30' == this.age && '30' !== this.age",
+            "null == undefined && null !== undefined",
+            "typeof this.missing === 'undefined' && typeof missing === 'undefined'",
+            "typeof this.tags === 'object' && typeof this.age === 'number'",
+            "Math.max(1, this.age, 3) === 30 && Math.floor(-1.5) === -2",
+            "isNaN(Number('x')) && String(1.5) === '1.5' && 7 % 4 === 3",
+            "this.age > 20 ? true : false",
+            "[1, 2][1] === 2 && ({a: {b: 1}}).a.b === 1",
+            "'b' > 'a' && !('a' > 'b')",
+        ];
+        for code in truthy.iter() {
+            assert_eq!(evaluate(code).unwrap(), true, "{} should be true", code);
+        }
+        let falsy = [
+            "this.missing",
+            "this.age < 18",
+            "''",
+            "0",
+            "NaN",
+            "this.name === 'tom'",
+        ];
+        for code in falsy.iter() {
+            assert_eq!(evaluate(code).unwrap(), false, "{} should be false", code);
+        }
+    }
+
+    #[test]
+    fn test_functions_and_statements() {
+        let truthy = [
+            "function() { return this.age > 18 }",
+            "x => x.name === 'Tom'",
+            "(doc) => { var limit = 20; return doc.age > limit; }",
+            "var total = 0; for (var i = 0; i < this.tags.length; i++) { total += i; } total === 1",
+            "var n = 0; while (true) { n++; if (n >= 5) break; } return n === 5",
+            "function fact(n) { return n <= 1 ? 1 : n * fact(n - 1) } fact(5) === 120",
+            "var add = a => b => a + b; add(1)(2) === 3",
+        ];
+        for code in truthy.iter() {
+            assert_eq!(evaluate(code).unwrap(), true, "{} should be true", code);
+        }
+    }
+
+    #[test]
+    fn test_runtime_errors() {
+        match evaluate("this.missing.name") {
+            Err(JsError::TypeError(_)) => {}
+            result => panic!("Unexpected result {:?}", result),
+        }
+        match evaluate("unknown > 1") {
+            Err(JsError::ReferenceError(name)) => assert_eq!(name, "unknown"),
+            result => panic!("Unexpected result {:?}", result),
+        }
+        match evaluate("this.age()") {
+            Err(JsError::TypeError(_)) => {}
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+
+    #[test]
+    fn test_limits() {
+        match evaluate("while (true) {}") {
+            Err(JsError::StepLimitExceeded) => {}
+            result => panic!("Unexpected result {:?}", result),
+        }
+        match evaluate("for (;;);") {
+            Err(JsError::StepLimitExceeded) => {}
+            result => panic!("Unexpected result {:?}", result),
+        }
+        match evaluate("var s = 'x'; while (true) { s += s; }") {
+            Err(JsError::MemoryLimitExceeded) => {}
+            result => panic!("Unexpected result {:?}", result),
+        }
+        match evaluate("var a = []; while (true) { a = [a, a, a, a, a, a, a, a]; }") {
+            Err(JsError::MemoryLimitExceeded) => {}
+            result => panic!("Unexpected result {:?}", result),
+        }
+        match evaluate("function f() { return f(); } f()") {
+            Err(JsError::TooDeeplyNested) => {}
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+}
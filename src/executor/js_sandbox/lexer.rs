@@ -0,0 +1,212 @@
+use crate::executor::js_sandbox::JsError;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Token {
+    Number(f64),
+    String(String),
+    // Keywords included
+    Identifier(String),
+    Punctuator(&'static str),
+}
+
+// Longer punctuators come first, so they win over their prefixes
+const PUNCTUATORS: [&str; 36] = [
+    "===", "!==", "=>", "==", "!=", "<=", ">=", "&&", "||", "++", "--", "+=", "-=", "*=", "/=",
+    "%=", "(", ")", "{", "}", "[", "]", ".", ",", ";", ":", "?", "+", "-", "*", "/", "%", "<", ">",
+    "!", "=",
+];
+
+fn is_identifier_start(c: char) -> bool {
+    c.is_ascii_alphabetic() || c == '_' || c == '$'
+}
+
+fn is_identifier_part(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '$'
+}
+
+fn read_number(chars: &[char], start: usize) -> Result<(f64, usize), JsError> {
+    let mut end = start;
+    if chars[start] == '0' && chars.get(start + 1).map(|c| *c == 'x' || *c == 'X') == Some(true) {
+        end += 2;
+        while end < chars.len() && chars[end].is_ascii_hexdigit() {
+            end += 1;
+        }
+        let digits: String = chars[start + 2..end].iter().collect();
+        return match u64::from_str_radix(&digits, 16) {
+            Err(_error) => Err(JsError::UnexpectedCharacter(start)),
+            Ok(number) => Ok((number as f64, end)),
+        };
+    }
+    while end < chars.len() && (chars[end].is_ascii_digit() || chars[end] == '.') {
+        end += 1;
+    }
+    if end < chars.len() && (chars[end] == 'e' || chars[end] == 'E') {
+        end += 1;
+        if end < chars.len() && (chars[end] == '+' || chars[end] == '-') {
+            end += 1;
+        }
+        while end < chars.len() && chars[end].is_ascii_digit() {
+            end += 1;
+        }
+    }
+    let text: String = chars[start..end].iter().collect();
+    match text.parse::<f64>() {
+        Err(_error) => return Err(JsError::UnexpectedCharacter(start)),
+        Ok(number) => return Ok((number, end)),
+    }
+}
+
+fn read_string(chars: &[char], start: usize) -> Result<(String, usize), JsError> {
+    let quote = chars[start];
+    let mut result = String::new();
+    let mut position = start + 1;
+    while position < chars.len() {
+        let c = chars[position];
+        if c == quote {
+            return Ok((result, position + 1));
+        } else if c == '\n' {
+            break;
+        } else if c == '\\' {
+            position += 1;
+            match chars.get(position) {
+                None => break,
+                Some('n') => result.push('\n'),
+                Some('t') => result.push('\t'),
+                Some('r') => result.push('\r'),
+                Some('0') => result.push('\0'),
+                Some('u') => {
+                    let digits: String = chars.iter().skip(position + 1).take(4).collect();
+                    match u32::from_str_radix(&digits, 16)
+                        .ok()
+                        .and_then(std::char::from_u32)
+                    {
+                        None => return Err(JsError::UnexpectedCharacter(position)),
+                        Some(decoded) => result.push(decoded),
+                    }
+                    position += 4;
+                }
+                Some(escaped) => result.push(*escaped),
+            }
+        } else {
+            result.push(c);
+        }
+        position += 1;
+    }
+    return Err(JsError::UnterminatedString(start));
+}
+
+/// Tokens paired with the character offsets they start at
+pub fn tokenize(code: &str) -> Result<Vec<(Token, usize)>, JsError> {
+    let chars: Vec<char> = code.chars().collect();
+    let mut tokens = Vec::new();
+    let mut position = 0;
+    'scanning: while position < chars.len() {
+        let c = chars[position];
+        if c.is_whitespace() {
+            position += 1;
+        } else if c == '/' && chars.get(position + 1) == Some(&'/') {
+            while position < chars.len() && chars[position] != '\n' {
+                position += 1;
+            }
+        } else if c == '/' && chars.get(position + 1) == Some(&'*') {
+            position += 2;
+            while position + 1 < chars.len()
+                && !(chars[position] == '*' && chars[position + 1] == '/')
+            {
+                position += 1;
+            }
+            if position + 1 >= chars.len() {
+                return Err(JsError::UnexpectedEnd);
+            }
+            position += 2;
+        } else if c.is_ascii_digit()
+            || (c == '.' && chars.get(position + 1).map(|c| c.is_ascii_digit()) == Some(true))
+        {
+            let (number, end) = read_number(&chars, position)?;
+            tokens.push((Token::Number(number), position));
+            position = end;
+        } else if c == '"' || c == '\'' {
+            let (string, end) = read_string(&chars, position)?;
+            tokens.push((Token::String(string), position));
+            position = end;
+        } else if is_identifier_start(c) {
+            let start = position;
+            while position < chars.len() && is_identifier_part(chars[position]) {
+                position += 1;
+            }
+            let name: String = chars[start..position].iter().collect();
+            tokens.push((Token::Identifier(name), start));
+        } else {
+            for punctuator in PUNCTUATORS.iter() {
+                let length = punctuator.len();
+                if position + length <= chars.len()
+                    && chars[position..position + length]
+                        .iter()
+                        .cloned()
+                        .eq(punctuator.chars())
+                {
+                    tokens.push((Token::Punctuator(punctuator), position));
+                    position += length;
+                    continue 'scanning;
+                }
+            }
+            return Err(JsError::UnexpectedCharacter(position));
+        }
+    }
+    return Ok(tokens);
+}
+
+#[cfg(test)]
+mod js_lexer_tests {
+    use crate::executor::js_sandbox::lexer::{tokenize, Token};
+    use crate::executor::js_sandbox::JsError;
+
+    #[test]
+    fn test_tokenize() {
+        let tokens: Vec<Token> = tokenize("x => x.age >= 0x10 && x['na\\'me'] !== \"a\" // end")
+            .unwrap()
+            .into_iter()
+            .map(|(token, _)| token)
+            .collect();
+        let expected = vec![
+            Token::Identifier(String::from("x")),
+            Token::Punctuator("=>"),
+            Token::Identifier(String::from("x")),
+            Token::Punctuator("."),
+            Token::Identifier(String::from("age")),
+            Token::Punctuator(">="),
+            Token::Number(16.0),
+            Token::Punctuator("&&"),
+            Token::Identifier(String::from("x")),
+            Token::Punctuator("["),
+            Token::String(String::from("na'me")),
+            Token::Punctuator("]"),
+            Token::Punctuator("!=="),
+            Token::String(String::from("a")),
+        ];
+        assert_eq!(tokens, expected);
+    }
+
+    #[test]
+    fn test_numbers() {
+        for (code, expected) in vec![("1.5", 1.5), (".5", 0.5), ("2e3", 2000.0), ("1E-2", 0.01)] {
+            assert_eq!(tokenize(code).unwrap()[0].0, Token::Number(expected));
+        }
+    }
+
+    #[test]
+    fn test_tokenize_errors() {
+        match tokenize("'open") {
+            Err(JsError::UnterminatedString(0)) => {}
+            result => panic!("Unexpected result {:?}", result),
+        }
+        match tokenize("a # b") {
+            Err(JsError::UnexpectedCharacter(2)) => {}
+            result => panic!("Unexpected result {:?}", result),
+        }
+        match tokenize("/* open") {
+            Err(JsError::UnexpectedEnd) => {}
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+}
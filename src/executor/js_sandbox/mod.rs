@@ -0,0 +1,118 @@
+/*
+ *  A sandboxed interpreter for a subset of JavaScript, used to evaluate select predicates against
+ *  the documents of units
+**/
+
+mod interpreter;
+mod lexer;
+mod parser;
+
+use serde_json::Value as JsonValue;
+
+use crate::config::{JS_PREDICATE_MAX_MEMORY, JS_PREDICATE_MAX_STEPS};
+use crate::declarations::errors::ImmuxError;
+use crate::executor::js_sandbox::parser::{parse, Statement};
+
+#[derive(Debug)]
+pub enum JsError {
+    UnexpectedCharacter(usize),
+    UnterminatedString(usize),
+    UnexpectedToken(usize),
+    UnexpectedEnd,
+    TooDeeplyNested,
+    StepLimitExceeded,
+    MemoryLimitExceeded,
+    ReferenceError(String),
+    TypeError(String),
+}
+
+impl From<JsError> for ImmuxError {
+    fn from(error: JsError) -> ImmuxError {
+        ImmuxError::JsSandbox(error)
+    }
+}
+
+/// Bounds on a single evaluation, so a predicate cannot hang or exhaust the server
+#[derive(Debug, Clone, Copy)]
+pub struct JsLimits {
+    // Statements and expressions evaluated
+    pub max_steps: u64,
+    // Bytes allocated for strings, arrays, objects, variables and calls
+    pub max_memory: usize,
+}
+
+impl Default for JsLimits {
+    fn default() -> Self {
+        JsLimits {
+            max_steps: JS_PREDICATE_MAX_STEPS,
+            max_memory: JS_PREDICATE_MAX_MEMORY,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct JsPredicate {
+    statements: Vec<Statement>,
+}
+
+impl JsPredicate {
+    pub fn compile(code: &str) -> Result<Self, JsError> {
+        let statements = parse(code)?;
+        return Ok(JsPredicate { statements });
+    }
+
+    /// Whether the predicate holds for the document, which is bound to `this` and `obj`
+    pub fn test(&self, document: &JsonValue, limits: JsLimits) -> Result<bool, JsError> {
+        return interpreter::run(&self.statements, document, limits);
+    }
+}
+
+#[cfg(test)]
+mod js_predicate_tests {
+    use serde_json::json;
+
+    use crate::executor::js_sandbox::{JsError, JsLimits, JsPredicate};
+
+    #[test]
+    fn test_predicate() {
+        let predicate = JsPredicate::compile("function() { return this.age >= 18 }").unwrap();
+        let adult = json!({"name": "Tom", "age": 30});
+        let child = json!({"name": "Tim", "age": 3});
+        assert!(predicate.test(&adult, JsLimits::default()).unwrap());
+        assert!(!predicate.test(&child, JsLimits::default()).unwrap());
+    }
+
+    #[test]
+    fn test_predicate_limits() {
+        let document = json!({});
+        let looping = JsPredicate::compile("while (true) {}").unwrap();
+        match looping.test(&document, JsLimits::default()) {
+            Err(JsError::StepLimitExceeded) => {}
+            result => panic!("Unexpected result {:?}", result),
+        }
+        let growing = JsPredicate::compile("var s = '-'; for (;;) s = s + s").unwrap();
+        match growing.test(&document, JsLimits::default()) {
+            Err(JsError::MemoryLimitExceeded) => {}
+            result => panic!("Unexpected result {:?}", result),
+        }
+        let limits = JsLimits {
+            max_steps: 3,
+            max_memory: 0,
+        };
+        match JsPredicate::compile("1 + 1 + 1")
+            .unwrap()
+            .test(&document, limits)
+        {
+            Err(JsError::StepLimitExceeded) => {}
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+
+    #[test]
+    fn test_compile_error() {
+        match JsPredicate::compile("this.age >") {
+            Err(JsError::UnexpectedEnd) => {}
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+}
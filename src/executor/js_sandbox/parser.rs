@@ -0,0 +1,695 @@
+use std::rc::Rc;
+
+use crate::executor::js_sandbox::lexer::{tokenize, Token};
+use crate::executor::js_sandbox::JsError;
+
+// Bounds the recursion of both the parser and the interpreter walking the tree
+const MAX_NESTING: usize = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UnaryOperator {
+    Not,
+    Negate,
+    Plus,
+    TypeOf,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BinaryOperator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Remainder,
+    Equal,
+    NotEqual,
+    StrictEqual,
+    StrictNotEqual,
+    LessThan,
+    LessThanOrEqual,
+    GreaterThan,
+    GreaterThanOrEqual,
+    And,
+    Or,
+}
+
+#[derive(Debug)]
+pub enum Expression {
+    Number(f64),
+    String(String),
+    Bool(bool),
+    Null,
+    This,
+    Identifier(String),
+    Array(Vec<Expression>),
+    Object(Vec<(String, Expression)>),
+    // `object.key` is read as `object["key"]`
+    Member(Box<Expression>, Box<Expression>),
+    Call(Box<Expression>, Vec<Expression>),
+    Unary(UnaryOperator, Box<Expression>),
+    Binary(BinaryOperator, Box<Expression>, Box<Expression>),
+    Conditional(Box<Expression>, Box<Expression>, Box<Expression>),
+    // Only variables can be assigned to; None is plain `=`
+    Assign(String, Option<BinaryOperator>, Box<Expression>),
+    // `++x` and `x--`: the step, and whether the updated value is the result
+    Update(String, f64, bool),
+    Function(Rc<FunctionDefinition>),
+}
+
+#[derive(Debug)]
+pub enum FunctionBody {
+    Expression(Expression),
+    Block(Vec<Statement>),
+}
+
+#[derive(Debug)]
+pub struct FunctionDefinition {
+    pub params: Vec<String>,
+    pub body: FunctionBody,
+}
+
+#[derive(Debug)]
+pub enum Statement {
+    Expression(Expression),
+    Declare(Vec<(String, Option<Expression>)>),
+    Return(Option<Expression>),
+    If(Expression, Box<Statement>, Option<Box<Statement>>),
+    While(Expression, Box<Statement>),
+    For(
+        Option<Box<Statement>>,
+        Option<Expression>,
+        Option<Expression>,
+        Box<Statement>,
+    ),
+    Block(Vec<Statement>),
+    Break,
+    Continue,
+    Empty,
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    position: usize,
+    nesting: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position).map(|(token, _)| token)
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<&Token> {
+        self.tokens
+            .get(self.position + offset)
+            .map(|(token, _)| token)
+    }
+
+    fn unexpected(&self) -> JsError {
+        match self.tokens.get(self.position) {
+            None => JsError::UnexpectedEnd,
+            Some((_, offset)) => JsError::UnexpectedToken(*offset),
+        }
+    }
+
+    fn is_punctuator(&self, punctuator: &str) -> bool {
+        match self.peek() {
+            Some(Token::Punctuator(candidate)) => *candidate == punctuator,
+            _ => false,
+        }
+    }
+
+    fn is_keyword(&self, keyword: &str) -> bool {
+        match self.peek() {
+            Some(Token::Identifier(name)) => name == keyword,
+            _ => false,
+        }
+    }
+
+    fn eat_punctuator(&mut self, punctuator: &str) -> bool {
+        if self.is_punctuator(punctuator) {
+            self.position += 1;
+            return true;
+        } else {
+            return false;
+        }
+    }
+
+    fn expect_punctuator(&mut self, punctuator: &str) -> Result<(), JsError> {
+        if self.eat_punctuator(punctuator) {
+            return Ok(());
+        } else {
+            return Err(self.unexpected());
+        }
+    }
+
+    fn expect_identifier(&mut self) -> Result<String, JsError> {
+        match self.peek() {
+            Some(Token::Identifier(name)) => {
+                let name = name.to_owned();
+                self.position += 1;
+                return Ok(name);
+            }
+            _ => return Err(self.unexpected()),
+        }
+    }
+
+    fn enter(&mut self) -> Result<(), JsError> {
+        self.nesting += 1;
+        if self.nesting > MAX_NESTING {
+            return Err(JsError::TooDeeplyNested);
+        }
+        return Ok(());
+    }
+
+    fn leave(&mut self) {
+        self.nesting -= 1;
+    }
+
+    fn parse_statement(&mut self) -> Result<Statement, JsError> {
+        self.enter()?;
+        let statement = self.parse_statement_inner();
+        self.leave();
+        return statement;
+    }
+
+    fn parse_statement_inner(&mut self) -> Result<Statement, JsError> {
+        if self.eat_punctuator(";") {
+            return Ok(Statement::Empty);
+        }
+        if self.eat_punctuator("{") {
+            return Ok(Statement::Block(self.parse_block_rest()?));
+        }
+        let keyword = match self.peek() {
+            Some(Token::Identifier(name)) => name.to_owned(),
+            _ => String::new(),
+        };
+        match keyword.as_str() {
+            "var" | "let" | "const" => {
+                self.position += 1;
+                let statement = self.parse_declarations()?;
+                self.eat_punctuator(";");
+                return Ok(statement);
+            }
+            "function" if self.peek_at(1).map(is_identifier) == Some(true) => {
+                self.position += 1;
+                let name = self.expect_identifier()?;
+                let definition = self.parse_function_rest()?;
+                let value = Expression::Function(Rc::new(definition));
+                return Ok(Statement::Declare(vec![(name, Some(value))]));
+            }
+            "return" => {
+                self.position += 1;
+                if self.eat_punctuator(";") || self.is_punctuator("}") || self.peek().is_none() {
+                    return Ok(Statement::Return(None));
+                }
+                let value = self.parse_expression()?;
+                self.eat_punctuator(";");
+                return Ok(Statement::Return(Some(value)));
+            }
+            "if" => {
+                self.position += 1;
+                self.expect_punctuator("(")?;
+                let condition = self.parse_expression()?;
+                self.expect_punctuator(")")?;
+                let consequent = Box::new(self.parse_statement()?);
+                let alternate = if self.is_keyword("else") {
+                    self.position += 1;
+                    Some(Box::new(self.parse_statement()?))
+                } else {
+                    None
+                };
+                return Ok(Statement::If(condition, consequent, alternate));
+            }
+            "while" => {
+                self.position += 1;
+                self.expect_punctuator("(")?;
+                let condition = self.parse_expression()?;
+                self.expect_punctuator(")")?;
+                let body = Box::new(self.parse_statement()?);
+                return Ok(Statement::While(condition, body));
+            }
+            "for" => {
+                self.position += 1;
+                self.expect_punctuator("(")?;
+                let init = if self.eat_punctuator(";") {
+                    None
+                } else {
+                    let init = if self.is_keyword("var")
+                        || self.is_keyword("let")
+                        || self.is_keyword("const")
+                    {
+                        self.position += 1;
+                        self.parse_declarations()?
+                    } else {
+                        Statement::Expression(self.parse_expression()?)
+                    };
+                    self.expect_punctuator(";")?;
+                    Some(Box::new(init))
+                };
+                let condition = if self.is_punctuator(";") {
+                    None
+                } else {
+                    Some(self.parse_expression()?)
+                };
+                self.expect_punctuator(";")?;
+                let update = if self.is_punctuator(")") {
+                    None
+                } else {
+                    Some(self.parse_expression()?)
+                };
+                self.expect_punctuator(")")?;
+                let body = Box::new(self.parse_statement()?);
+                return Ok(Statement::For(init, condition, update, body));
+            }
+            "break" => {
+                self.position += 1;
+                self.eat_punctuator(";");
+                return Ok(Statement::Break);
+            }
+            "continue" => {
+                self.position += 1;
+                self.eat_punctuator(";");
+                return Ok(Statement::Continue);
+            }
+            _ => {
+                let expression = self.parse_expression()?;
+                self.eat_punctuator(";");
+                return Ok(Statement::Expression(expression));
+            }
+        }
+    }
+
+    fn parse_declarations(&mut self) -> Result<Statement, JsError> {
+        let mut declarations = Vec::new();
+        loop {
+            let name = self.expect_identifier()?;
+            let value = if self.eat_punctuator("=") {
+                Some(self.parse_assignment()?)
+            } else {
+                None
+            };
+            declarations.push((name, value));
+            if !self.eat_punctuator(",") {
+                return Ok(Statement::Declare(declarations));
+            }
+        }
+    }
+
+    // After the opening brace
+    fn parse_block_rest(&mut self) -> Result<Vec<Statement>, JsError> {
+        let mut statements = Vec::new();
+        while !self.eat_punctuator("}") {
+            if self.peek().is_none() {
+                return Err(JsError::UnexpectedEnd);
+            }
+            statements.push(self.parse_statement()?);
+        }
+        return Ok(statements);
+    }
+
+    // After `function` and its optional name
+    fn parse_function_rest(&mut self) -> Result<FunctionDefinition, JsError> {
+        self.expect_punctuator("(")?;
+        let params = self.parse_params_rest()?;
+        self.expect_punctuator("{")?;
+        let body = FunctionBody::Block(self.parse_block_rest()?);
+        return Ok(FunctionDefinition { params, body });
+    }
+
+    // After the opening parenthesis
+    fn parse_params_rest(&mut self) -> Result<Vec<String>, JsError> {
+        let mut params = Vec::new();
+        if self.eat_punctuator(")") {
+            return Ok(params);
+        }
+        loop {
+            params.push(self.expect_identifier()?);
+            if self.eat_punctuator(")") {
+                return Ok(params);
+            }
+            self.expect_punctuator(",")?;
+        }
+    }
+
+    fn parse_arrow_body(&mut self, params: Vec<String>) -> Result<Expression, JsError> {
+        let body = if self.eat_punctuator("{") {
+            FunctionBody::Block(self.parse_block_rest()?)
+        } else {
+            FunctionBody::Expression(self.parse_assignment()?)
+        };
+        return Ok(Expression::Function(Rc::new(FunctionDefinition {
+            params,
+            body,
+        })));
+    }
+
+    /// Whether the parenthesis at the current position opens the parameters of an arrow function
+    fn is_arrow_params(&self) -> bool {
+        let mut offset = 1;
+        loop {
+            match self.peek_at(offset) {
+                Some(Token::Punctuator(")")) => {
+                    return self.peek_at(offset + 1) == Some(&Token::Punctuator("=>"));
+                }
+                Some(Token::Identifier(_)) | Some(Token::Punctuator(",")) => offset += 1,
+                _ => return false,
+            }
+        }
+    }
+
+    fn parse_expression(&mut self) -> Result<Expression, JsError> {
+        self.enter()?;
+        let expression = self.parse_assignment();
+        self.leave();
+        return expression;
+    }
+
+    fn parse_assignment(&mut self) -> Result<Expression, JsError> {
+        if let (Some(Token::Identifier(name)), Some(Token::Punctuator("=>"))) =
+            (self.peek(), self.peek_at(1))
+        {
+            let params = vec![name.to_owned()];
+            self.position += 2;
+            return self.parse_arrow_body(params);
+        }
+        if self.is_punctuator("(") && self.is_arrow_params() {
+            self.position += 1;
+            let params = self.parse_params_rest()?;
+            self.expect_punctuator("=>")?;
+            return self.parse_arrow_body(params);
+        }
+        let target = self.parse_conditional()?;
+        let operator = match self.peek() {
+            Some(Token::Punctuator("=")) => None,
+            Some(Token::Punctuator("+=")) => Some(BinaryOperator::Add),
+            Some(Token::Punctuator("-=")) => Some(BinaryOperator::Subtract),
+            Some(Token::Punctuator("*=")) => Some(BinaryOperator::Multiply),
+            Some(Token::Punctuator("/=")) => Some(BinaryOperator::Divide),
+            Some(Token::Punctuator("%=")) => Some(BinaryOperator::Remainder),
+            _ => return Ok(target),
+        };
+        match target {
+            Expression::Identifier(name) => {
+                self.position += 1;
+                let value = self.parse_assignment()?;
+                return Ok(Expression::Assign(name, operator, Box::new(value)));
+            }
+            _ => return Err(self.unexpected()),
+        }
+    }
+
+    fn parse_conditional(&mut self) -> Result<Expression, JsError> {
+        let test = self.parse_binary(0)?;
+        if !self.eat_punctuator("?") {
+            return Ok(test);
+        }
+        let consequent = self.parse_assignment()?;
+        self.expect_punctuator(":")?;
+        let alternate = self.parse_assignment()?;
+        return Ok(Expression::Conditional(
+            Box::new(test),
+            Box::new(consequent),
+            Box::new(alternate),
+        ));
+    }
+
+    fn get_binary_operator(&self, level: usize) -> Option<BinaryOperator> {
+        let punctuator = match self.peek() {
+            Some(Token::Punctuator(punctuator)) => *punctuator,
+            _ => return None,
+        };
+        let operator = match (level, punctuator) {
+            (0, "||") => BinaryOperator::Or,
+            (1, "&&") => BinaryOperator::And,
+            (2, "==") => BinaryOperator::Equal,
+            (2, "!=") => BinaryOperator::NotEqual,
+            (2, "===") => BinaryOperator::StrictEqual,
+            (2, "!==") => BinaryOperator::StrictNotEqual,
+            (3, "<") => BinaryOperator::LessThan,
+            (3, "<=") => BinaryOperator::LessThanOrEqual,
+            (3, ">") => BinaryOperator::GreaterThan,
+            (3, ">=") => BinaryOperator::GreaterThanOrEqual,
+            (4, "+") => BinaryOperator::Add,
+            (4, "-") => BinaryOperator::Subtract,
+            (5, "*") => BinaryOperator::Multiply,
+            (5, "/") => BinaryOperator::Divide,
+            (5, "%") => BinaryOperator::Remainder,
+            _ => return None,
+        };
+        return Some(operator);
+    }
+
+    /// Left-associative binary operators, from `||` at level 0 to multiplicative ones at level 5
+    fn parse_binary(&mut self, level: usize) -> Result<Expression, JsError> {
+        if level > 5 {
+            return self.parse_unary();
+        }
+        let mut left = self.parse_binary(level + 1)?;
+        while let Some(operator) = self.get_binary_operator(level) {
+            self.position += 1;
+            let right = self.parse_binary(level + 1)?;
+            left = Expression::Binary(operator, Box::new(left), Box::new(right));
+        }
+        return Ok(left);
+    }
+
+    fn parse_unary(&mut self) -> Result<Expression, JsError> {
+        let operator = match self.peek() {
+            Some(Token::Punctuator("!")) => UnaryOperator::Not,
+            Some(Token::Punctuator("-")) => UnaryOperator::Negate,
+            Some(Token::Punctuator("+")) => UnaryOperator::Plus,
+            Some(Token::Identifier(name)) if name == "typeof" => UnaryOperator::TypeOf,
+            Some(Token::Punctuator("++")) | Some(Token::Punctuator("--")) => {
+                let step = if self.is_punctuator("++") { 1.0 } else { -1.0 };
+                self.position += 1;
+                let name = self.expect_identifier()?;
+                return Ok(Expression::Update(name, step, true));
+            }
+            _ => return self.parse_postfix(),
+        };
+        self.position += 1;
+        self.enter()?;
+        let operand = self.parse_unary();
+        self.leave();
+        return Ok(Expression::Unary(operator, Box::new(operand?)));
+    }
+
+    fn parse_postfix(&mut self) -> Result<Expression, JsError> {
+        let expression = self.parse_call()?;
+        let step = if self.is_punctuator("++") {
+            1.0
+        } else if self.is_punctuator("--") {
+            -1.0
+        } else {
+            return Ok(expression);
+        };
+        match expression {
+            Expression::Identifier(name) => {
+                self.position += 1;
+                return Ok(Expression::Update(name, step, false));
+            }
+            _ => return Err(self.unexpected()),
+        }
+    }
+
+    fn parse_call(&mut self) -> Result<Expression, JsError> {
+        let mut expression = self.parse_primary()?;
+        loop {
+            if self.eat_punctuator(".") {
+                let key = self.expect_identifier()?;
+                expression =
+                    Expression::Member(Box::new(expression), Box::new(Expression::String(key)));
+            } else if self.eat_punctuator("[") {
+                let key = self.parse_expression()?;
+                self.expect_punctuator("]")?;
+                expression = Expression::Member(Box::new(expression), Box::new(key));
+            } else if self.eat_punctuator("(") {
+                let mut arguments = Vec::new();
+                while !self.eat_punctuator(")") {
+                    arguments.push(self.parse_expression()?);
+                    if !self.is_punctuator(")") {
+                        self.expect_punctuator(",")?;
+                    }
+                }
+                expression = Expression::Call(Box::new(expression), arguments);
+            } else {
+                return Ok(expression);
+            }
+        }
+    }
+
+    fn parse_primary(&mut self) -> Result<Expression, JsError> {
+        let token = match self.peek() {
+            None => return Err(JsError::UnexpectedEnd),
+            Some(token) => token.to_owned(),
+        };
+        self.position += 1;
+        match token {
+            Token::Number(number) => return Ok(Expression::Number(number)),
+            Token::String(string) => return Ok(Expression::String(string)),
+            Token::Identifier(name) => match name.as_str() {
+                "true" => return Ok(Expression::Bool(true)),
+                "false" => return Ok(Expression::Bool(false)),
+                "null" => return Ok(Expression::Null),
+                "this" => return Ok(Expression::This),
+                "function" => {
+                    if self.peek().map(is_identifier) == Some(true) {
+                        self.position += 1;
+                    }
+                    let definition = self.parse_function_rest()?;
+                    return Ok(Expression::Function(Rc::new(definition)));
+                }
+                _ => return Ok(Expression::Identifier(name)),
+            },
+            Token::Punctuator("(") => {
+                let expression = self.parse_expression()?;
+                self.expect_punctuator(")")?;
+                return Ok(expression);
+            }
+            Token::Punctuator("[") => {
+                self.enter()?;
+                let mut elements = Vec::new();
+                while !self.eat_punctuator("]") {
+                    elements.push(self.parse_expression()?);
+                    if !self.is_punctuator("]") {
+                        self.expect_punctuator(",")?;
+                    }
+                }
+                self.leave();
+                return Ok(Expression::Array(elements));
+            }
+            Token::Punctuator("{") => {
+                self.enter()?;
+                let mut entries = Vec::new();
+                while !self.eat_punctuator("}") {
+                    let key = match self.peek() {
+                        Some(Token::Identifier(key)) | Some(Token::String(key)) => key.to_owned(),
+                        Some(Token::Number(number)) => format!("{}", number),
+                        _ => return Err(self.unexpected()),
+                    };
+                    self.position += 1;
+                    self.expect_punctuator(":")?;
+                    entries.push((key, self.parse_expression()?));
+                    if !self.is_punctuator("}") {
+                        self.expect_punctuator(",")?;
+                    }
+                }
+                self.leave();
+                return Ok(Expression::Object(entries));
+            }
+            _ => {
+                self.position -= 1;
+                return Err(self.unexpected());
+            }
+        }
+    }
+}
+
+fn is_identifier(token: &Token) -> bool {
+    match token {
+        Token::Identifier(_) => true,
+        _ => false,
+    }
+}
+
+/// A script is a list of statements, like the body of a function
+pub fn parse(code: &str) -> Result<Vec<Statement>, JsError> {
+    let mut parser = Parser {
+        tokens: tokenize(code)?,
+        position: 0,
+        nesting: 0,
+    };
+    let mut statements = Vec::new();
+    while parser.peek().is_some() {
+        statements.push(parser.parse_statement()?);
+    }
+    return Ok(statements);
+}
+
+#[cfg(test)]
+mod js_parser_tests {
+    use crate::executor::js_sandbox::parser::{
+        parse, BinaryOperator, Expression, FunctionBody, Statement,
+    };
+    use crate::executor::js_sandbox::JsError;
+
+    #[test]
+    fn test_precedence() {
+        let statements = parse("a || b && c == 1 + 2 * 3").unwrap();
+        match &statements[..] {
+            [Statement::Expression(Expression::Binary(BinaryOperator::Or, _, right))] => {
+                match right.as_ref() {
+                    Expression::Binary(BinaryOperator::And, _, right) => match right.as_ref() {
+                        Expression::Binary(BinaryOperator::Equal, _, right) => {
+                            match right.as_ref() {
+                                Expression::Binary(BinaryOperator::Add, _, right) => {
+                                    match right.as_ref() {
+                                        Expression::Binary(BinaryOperator::Multiply, _, _) => {}
+                                        expression => panic!("Unexpected {:?}", expression),
+                                    }
+                                }
+                                expression => panic!("Unexpected {:?}", expression),
+                            }
+                        }
+                        expression => panic!("Unexpected {:?}", expression),
+                    },
+                    expression => panic!("Unexpected {:?}", expression),
+                }
+            }
+            statements => panic!("Unexpected {:?}", statements),
+        }
+    }
+
+    #[test]
+    fn test_functions() {
+        for code in &[
+            "x => x.a",
+            "(x) => { return x.a; }",
+            "() => this.a",
+            "function () { return this.a }",
+            "(function named(x, y) { return x; })",
+        ] {
+            match &parse(code).unwrap()[..] {
+                [Statement::Expression(Expression::Function(definition))] => {
+                    match &definition.body {
+                        FunctionBody::Expression(_) => assert!(code.contains("=>")),
+                        FunctionBody::Block(statements) => assert_eq!(statements.len(), 1),
+                    }
+                }
+                statements => panic!("Unexpected {:?} from {}", statements, code),
+            }
+        }
+    }
+
+    #[test]
+    fn test_statements() {
+        let code = "var total = 0; for (let i = 0; i < 3; i++) { total += i; } \
+                    while (total > 0) total--; if (total) return 1; else return 2;";
+        let statements = parse(code).unwrap();
+        assert_eq!(statements.len(), 4);
+        match &statements[1] {
+            Statement::For(Some(_), Some(_), Some(Expression::Update(_, _, false)), _) => {}
+            statement => panic!("Unexpected {:?}", statement),
+        }
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        match parse("a +") {
+            Err(JsError::UnexpectedEnd) => {}
+            result => panic!("Unexpected result {:?}", result),
+        }
+        match parse("a b") {
+            Ok(statements) => assert_eq!(statements.len(), 2),
+            result => panic!("Unexpected result {:?}", result),
+        }
+        match parse("1 = 2") {
+            Err(JsError::UnexpectedToken(2)) => {}
+            result => panic!("Unexpected result {:?}", result),
+        }
+        match parse(&format!("{}1{}", "(".repeat(100), ")".repeat(100))) {
+            Err(JsError::TooDeeplyNested) => {}
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+}
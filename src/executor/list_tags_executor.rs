@@ -0,0 +1,15 @@
+use crate::declarations::commands::{ListTagsOutcome, Outcome};
+use crate::declarations::errors::ImmuxResult;
+use crate::executor::errors::ExecutorError;
+use crate::storage::core::CoreStore;
+use crate::storage::instructions::{Answer, DBSystemAnswer, ReadTagsInstruction};
+
+pub fn execute_list_tags(core: &mut impl CoreStore) -> ImmuxResult<Outcome> {
+    match core.execute(&ReadTagsInstruction {}.into()) {
+        Err(error) => return Err(error),
+        Ok(Answer::DBSystem(DBSystemAnswer::ReadTagsOk(answer))) => {
+            return Ok(Outcome::ListTags(ListTagsOutcome { tags: answer.tags }));
+        }
+        Ok(answer) => return Err(ExecutorError::UnexpectedAnswerType(answer).into()),
+    }
+}
@@ -0,0 +1,223 @@
+use std::collections::BTreeMap;
+
+use crate::declarations::basics::{GroupingLabel, StoreKey, Unit, UnitContent};
+use crate::declarations::commands::{MergeChainCommand, MergeChainOutcome, Outcome};
+use crate::declarations::errors::{ImmuxError, ImmuxResult};
+use crate::executor::errors::ExecutorError;
+use crate::executor::insert_executor::get_updates_for_index;
+use crate::executor::shared::{get_current_namespace, get_unit_specifier_of_key};
+use crate::storage::core::CoreStore;
+use crate::storage::instructions::{
+    Answer, DBSystemAnswer, DBSystemInstruction, Instruction, MergeNamespaceInstruction,
+    MergeNamespaceOkAnswer, MergeResolution, SetTargetSpec, StoreNamespace,
+    SwitchNamespaceInstruction,
+};
+
+fn merge_namespace(
+    instruction: MergeNamespaceInstruction,
+    core: &mut impl CoreStore,
+) -> ImmuxResult<MergeNamespaceOkAnswer> {
+    match core.execute(&instruction.into()) {
+        Err(error) => return Err(error),
+        Ok(Answer::DBSystem(DBSystemAnswer::MergeNamespaceOk(answer))) => return Ok(answer),
+        Ok(answer) => {
+            return Err(ImmuxError::Executor(ExecutorError::UnexpectedAnswerType(
+                answer,
+            )))
+        }
+    }
+}
+
+/// The index entries the merged units need on the target, which need not be the chain picked
+fn get_target_index_updates(
+    target: &StoreNamespace,
+    merged_units: BTreeMap<Vec<u8>, (GroupingLabel, Vec<Unit>)>,
+    core: &mut impl CoreStore,
+) -> ImmuxResult<Vec<SetTargetSpec>> {
+    let original_namespace = get_current_namespace(core)?;
+    core.execute(&Instruction::DBSystem(
+        DBSystemInstruction::SwitchNamespace(SwitchNamespaceInstruction {
+            new_namespace: target.to_owned(),
+        }),
+    ))?;
+    let mut index_targets: Vec<SetTargetSpec> = Vec::new();
+    let mut result = Ok(());
+    for (_, (grouping, units)) in merged_units {
+        match get_updates_for_index(&grouping, &units, core) {
+            Err(error) => {
+                result = Err(error);
+                break;
+            }
+            Ok(updates) => index_targets.extend(updates),
+        }
+    }
+    core.execute(&Instruction::DBSystem(
+        DBSystemInstruction::SwitchNamespace(SwitchNamespaceInstruction {
+            new_namespace: original_namespace,
+        }),
+    ))?;
+    result?;
+    return Ok(index_targets);
+}
+
+/// Only units are merged. Reverse indexes are derived from unit contents, so the target's are
+/// brought in line with the merged contents rather than merged from the source.
+pub fn execute_merge_chain(
+    merge_chain: MergeChainCommand,
+    core: &mut impl CoreStore,
+) -> ImmuxResult<Outcome> {
+    let resolutions: Vec<MergeResolution> = merge_chain
+        .resolutions
+        .into_iter()
+        .map(|resolution| MergeResolution {
+            key: StoreKey::from(resolution.specifier),
+            strategy: resolution.strategy,
+        })
+        .collect();
+    let dry_run = MergeNamespaceInstruction {
+        target: merge_chain.target.into(),
+        source: merge_chain.source.into(),
+        resolutions,
+        default_strategy: merge_chain.default_strategy,
+        dry_run: true,
+        keys: None,
+        index_targets: vec![],
+    };
+    let preview = merge_namespace(dry_run.clone(), core)?;
+
+    let mut unit_keys: Vec<StoreKey> = Vec::new();
+    let mut merged_units: BTreeMap<Vec<u8>, (GroupingLabel, Vec<Unit>)> = BTreeMap::new();
+    for conflict in &preview.conflicts {
+        if get_unit_specifier_of_key(&conflict.key).is_some() {
+            unit_keys.push(conflict.key.to_owned());
+        }
+    }
+    for target in preview.merged_targets {
+        if let Some(specifier) = get_unit_specifier_of_key(&target.key) {
+            let content = match target.value.inner() {
+                None => UnitContent::Nil,
+                Some(data) => UnitContent::parse_data(data)?,
+            };
+            let (grouping, id) = specifier.into_components();
+            merged_units
+                .entry(grouping.marshal())
+                .or_insert((grouping, vec![]))
+                .1
+                .push(Unit { id, content });
+            if !unit_keys.contains(&target.key) {
+                unit_keys.push(target.key);
+            }
+        }
+    }
+    let index_targets = get_target_index_updates(&dry_run.target, merged_units, core)?;
+
+    let merge = MergeNamespaceInstruction {
+        dry_run: false,
+        keys: Some(unit_keys),
+        index_targets,
+        ..dry_run
+    };
+    let answer = merge_namespace(merge, core)?;
+    return Ok(Outcome::MergeChain(MergeChainOutcome {
+        base_height: answer.base_height,
+        height: answer.height,
+        merged_heights: answer.merged_heights,
+        conflicts: answer.conflicts,
+    }));
+}
+
+#[cfg(test)]
+mod merge_chain_executor_tests {
+    use std::cell::RefCell;
+
+    use crate::declarations::basics::{
+        ChainName, GroupingLabel, StoreKey, StoreValue, UnitId, UnitSpecifier,
+    };
+    use crate::declarations::commands::{MergeChainCommand, MergeCommandResolution, Outcome};
+    use crate::executor::merge_chain_executor::execute_merge_chain;
+    use crate::executor::tests::FixtureCore;
+    use crate::storage::instructions::{
+        DBSystemInstruction, Instruction, MergeConflict, MergeNamespaceOkAnswer, MergeStrategy,
+        ReadNamespaceOkAnswer, SetTargetSpec, StoreNamespace, SwitchNamespaceOkAnswer,
+    };
+    use crate::storage::vkv::ChainHeight;
+
+    #[test]
+    fn test_merge_chain() {
+        let specifier = UnitSpecifier::new(GroupingLabel::from("grouping"), UnitId::new(1));
+        let key = StoreKey::from(specifier.clone());
+        let expected_key = key.clone();
+        let switches = RefCell::new(vec![]);
+        let switches_made = &switches;
+        let mut core = FixtureCore::new(Box::new(move |instruction| match instruction {
+            Instruction::DBSystem(DBSystemInstruction::ReadNamespace(_)) => {
+                Ok(ReadNamespaceOkAnswer {
+                    namespace: StoreNamespace::new(b"picked"),
+                }
+                .into())
+            }
+            Instruction::DBSystem(DBSystemInstruction::SwitchNamespace(switch_namespace)) => {
+                let new_namespace = switch_namespace.new_namespace.to_owned();
+                switches_made.borrow_mut().push(new_namespace.clone());
+                Ok(SwitchNamespaceOkAnswer { new_namespace }.into())
+            }
+            Instruction::DBSystem(DBSystemInstruction::MergeNamespace(merge_namespace)) => {
+                assert_eq!(merge_namespace.target, StoreNamespace::new(b"main"));
+                assert_eq!(merge_namespace.source, StoreNamespace::new(b"feature"));
+                assert_eq!(merge_namespace.resolutions[0].key, expected_key);
+                assert_eq!(merge_namespace.default_strategy, MergeStrategy::Fail);
+                if merge_namespace.dry_run {
+                    assert!(merge_namespace.keys.is_none());
+                } else {
+                    // The index key the source changed is left out
+                    assert_eq!(merge_namespace.keys, Some(vec![expected_key.clone()]));
+                    assert!(merge_namespace.index_targets.is_empty());
+                }
+                Ok(MergeNamespaceOkAnswer {
+                    base_height: ChainHeight::new(3),
+                    height: ChainHeight::new(6),
+                    merged_heights: vec![ChainHeight::new(4), ChainHeight::new(5)],
+                    conflicts: vec![MergeConflict {
+                        key: expected_key.clone(),
+                        strategy: merge_namespace.resolutions[0].strategy,
+                    }],
+                    merged_targets: vec![SetTargetSpec {
+                        key: StoreKey::new(&[0xA0, 1]),
+                        value: StoreValue::new(Some(vec![1])),
+                    }],
+                }
+                .into())
+            }
+            _ => panic!("Unimplemented fixture instruction {:?}", instruction),
+        }));
+        let command = MergeChainCommand {
+            target: ChainName::from("main"),
+            source: ChainName::from("feature"),
+            resolutions: vec![MergeCommandResolution {
+                specifier,
+                strategy: MergeStrategy::Theirs,
+            }],
+            default_strategy: MergeStrategy::Fail,
+        };
+        match execute_merge_chain(command, &mut core).unwrap() {
+            Outcome::MergeChain(outcome) => {
+                assert_eq!(outcome.base_height, ChainHeight::new(3));
+                assert_eq!(outcome.height, ChainHeight::new(6));
+                assert_eq!(outcome.merged_heights.len(), 2);
+                assert_eq!(
+                    outcome.conflicts,
+                    vec![MergeConflict {
+                        key,
+                        strategy: MergeStrategy::Theirs,
+                    }]
+                );
+            }
+            outcome => panic!("Unexpected outcome {:?}", outcome),
+        }
+        // Indexes are read on the target, then the picked chain is picked again
+        assert_eq!(
+            *switches.borrow(),
+            vec![StoreNamespace::new(b"main"), StoreNamespace::new(b"picked")]
+        );
+    }
+}
@@ -1,12 +1,32 @@
+mod change_feed_executor;
+mod compact_executor;
 mod create_index_executor;
+mod create_tag_executor;
+mod delete_tag_executor;
+mod diff_executor;
 pub mod errors;
 pub mod execute;
+mod export_chain_executor;
+mod fork_chain_executor;
+mod get_retention_executor;
+mod import_chain_executor;
 mod insert_executor;
 mod inspect_executor;
+pub mod js_sandbox;
+mod list_tags_executor;
+mod merge_chain_executor;
 mod name_chain_executor;
 mod pick_chain_executor;
+mod prove_executor;
+mod remove_executor;
+mod replay_chain_executor;
 mod revert_all_executor;
 mod revert_many_executor;
+mod revert_record_executor;
 mod select_executor;
+mod set_retention_executor;
 pub mod shared;
 mod tests;
+mod verify_chain_executor;
+
+pub use change_feed_executor::ChangeFeed;
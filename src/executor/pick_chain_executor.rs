@@ -1,6 +1,7 @@
 use crate::declarations::commands::{Outcome, PickChainCommand, PickChainOutcome};
 use crate::declarations::errors::{ImmuxError, ImmuxResult};
 use crate::executor::errors::ExecutorError;
+use crate::executor::shared::migrate_index_layout;
 use crate::storage::core::CoreStore;
 use crate::storage::instructions::{
     Answer, DBSystemAnswer, DBSystemInstruction, Instruction, SwitchNamespaceInstruction,
@@ -18,6 +19,7 @@ pub fn execute_pick_chain(
     match core.execute(&instruction) {
         Err(error) => return Err(error),
         Ok(Answer::DBSystem(DBSystemAnswer::SwitchNamespaceOk(answer))) => {
+            migrate_index_layout(core)?;
             return Ok(Outcome::PickChain(PickChainOutcome {
                 new_chain_name: answer.new_namespace.into(),
             }))
@@ -36,7 +38,11 @@ mod pick_chain_executor_tests {
     use crate::declarations::commands::{Outcome, PickChainCommand};
     use crate::executor::pick_chain_executor::execute_pick_chain;
     use crate::executor::tests::FixtureCore;
-    use crate::storage::instructions::{DBSystemInstruction, Instruction, SwitchNamespaceOkAnswer};
+    use crate::storage::instructions::{
+        Answer, DBSystemInstruction, DataAnswer, DataInstruction, DataReadAnswer,
+        DataReadInstruction, GetManyOkAnswer, Instruction, SwitchNamespaceOkAnswer,
+    };
+    use crate::storage::vkv::VkvError;
 
     #[test]
     fn test_pick_chain() {
@@ -48,6 +54,15 @@ mod pick_chain_executor_tests {
                 }
                 .into())
             }
+            // A new chain has no layout mark and no indexes to migrate
+            Instruction::DataAccess(DataInstruction::Read(DataReadInstruction::GetOne(
+                get_one,
+            ))) => Err(VkvError::MissingJournal(get_one.key.to_owned()).into()),
+            Instruction::DataAccess(DataInstruction::Read(DataReadInstruction::GetMany(_))) => {
+                Ok(Answer::DataAccess(DataAnswer::Read(
+                    DataReadAnswer::GetManyOk(GetManyOkAnswer { data: vec![] }),
+                )))
+            }
             _ => panic!("Unimplemented fixture instruction {:?}", instruction),
         }));
         let command = PickChainCommand {
@@ -0,0 +1,109 @@
+use crate::declarations::basics::{StoreKey, UnitContent};
+use crate::declarations::commands::{Outcome, ProveCommand, ProveOutcome};
+use crate::declarations::errors::{ImmuxError, ImmuxResult};
+use crate::executor::errors::ExecutorError;
+use crate::executor::shared::resolve_optional_height;
+use crate::storage::core::CoreStore;
+use crate::storage::instructions::{Answer, DataAnswer, DataReadAnswer, GetProofInstruction};
+use crate::storage::vkv::VkvError;
+
+pub fn execute_prove(prove: ProveCommand, core: &mut impl CoreStore) -> ImmuxResult<Outcome> {
+    let id = prove.specifier.get_id();
+    let get_proof = GetProofInstruction {
+        height: resolve_optional_height(&prove.height, core)?,
+        key: StoreKey::from(prove.specifier.clone()),
+    };
+    match core.execute(&get_proof.into()) {
+        Err(ImmuxError::VKV(VkvError::ProofTargetMissing(_key))) => {
+            return Err(ExecutorError::CannotFindId(id).into())
+        }
+        Err(error) => return Err(error),
+        Ok(Answer::DataAccess(DataAnswer::Read(DataReadAnswer::GetProofOk(answer)))) => {
+            match answer.value.inner() {
+                None => return Err(ExecutorError::CannotFindId(id).into()),
+                Some(data) => {
+                    return Ok(Outcome::Prove(ProveOutcome {
+                        specifier: prove.specifier,
+                        height: answer.height,
+                        content: UnitContent::parse_data(data)?,
+                        root: answer.root,
+                        proof: answer.proof,
+                    }));
+                }
+            }
+        }
+        Ok(answer) => return Err(ExecutorError::UnexpectedAnswerType(answer).into()),
+    }
+}
+
+#[cfg(test)]
+mod prove_executor_tests {
+    use crate::declarations::basics::{
+        GroupingLabel, StoreKey, StoreValue, UnitContent, UnitId, UnitSpecifier,
+    };
+    use crate::declarations::commands::{Outcome, ProveCommand};
+    use crate::declarations::errors::ImmuxError;
+    use crate::executor::errors::ExecutorError;
+    use crate::executor::prove_executor::execute_prove;
+    use crate::executor::tests::FixtureCore;
+    use crate::storage::instructions::{
+        DataInstruction, DataReadInstruction, GetProofOkAnswer, Instruction,
+    };
+    use crate::storage::vkv::{ChainHeight, MerkleProof, RecordHash, VkvError};
+
+    #[test]
+    fn test_prove() {
+        let grouping = GroupingLabel::from("grouping");
+        let specifier = UnitSpecifier::new(grouping, UnitId::new(7));
+        let content = UnitContent::String(String::from("hello"));
+        let root = RecordHash::digest(&[b"root"]);
+
+        let expected_key = StoreKey::from(specifier.clone());
+        let value = StoreValue::new(Some(content.marshal()));
+        let mut core = FixtureCore::new(Box::new(|instruction| match instruction {
+            Instruction::DataAccess(DataInstruction::Read(DataReadInstruction::GetProof(
+                get_proof,
+            ))) => {
+                assert_eq!(get_proof.key, expected_key);
+                assert_eq!(get_proof.height, Some(ChainHeight::new(3)));
+                Ok(GetProofOkAnswer {
+                    height: ChainHeight::new(3),
+                    value: value.clone(),
+                    root,
+                    proof: MerkleProof { steps: vec![] },
+                }
+                .into())
+            }
+            instruction => panic!("Unexpected instruction: {:?}", instruction),
+        }));
+        let command = ProveCommand {
+            specifier: specifier.clone(),
+            height: Some(ChainHeight::new(3).into()),
+        };
+        match execute_prove(command, &mut core).unwrap() {
+            Outcome::Prove(outcome) => {
+                assert_eq!(outcome.height, ChainHeight::new(3));
+                assert_eq!(outcome.content, content);
+                assert_eq!(outcome.root, root);
+            }
+            outcome => panic!("Unexpected outcome {:?}", outcome),
+        }
+    }
+
+    #[test]
+    fn test_prove_missing_unit() {
+        let mut core = FixtureCore::new(Box::new(|_instruction| {
+            Err(VkvError::ProofTargetMissing(StoreKey::from("key")).into())
+        }));
+        let command = ProveCommand {
+            specifier: UnitSpecifier::new(GroupingLabel::from("grouping"), UnitId::new(7)),
+            height: None,
+        };
+        match execute_prove(command, &mut core) {
+            Err(ImmuxError::Executor(ExecutorError::CannotFindId(id))) => {
+                assert_eq!(id, UnitId::new(7))
+            }
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+}
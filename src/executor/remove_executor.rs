@@ -0,0 +1,175 @@
+use crate::declarations::basics::{GroupingLabel, StoreKey, StoreValue, Unit, UnitContent, UnitId};
+use crate::declarations::commands::{Outcome, RemoveCommand, RemoveOutcome};
+use crate::declarations::errors::{ImmuxError, ImmuxResult};
+use crate::executor::errors::ExecutorError;
+use crate::executor::insert_executor::get_updates_for_index;
+use crate::storage::core::CoreStore;
+use crate::storage::instructions::{
+    Answer, DataAnswer, DataInstruction, DataReadAnswer, DataReadInstruction, DataWriteAnswer,
+    DataWriteInstruction, GetOneInstruction, Instruction, SetManyInstruction, SetTargetSpec,
+};
+use crate::storage::vkv::VkvError;
+
+fn has_content(
+    grouping: &GroupingLabel,
+    id: UnitId,
+    core: &mut impl CoreStore,
+) -> ImmuxResult<bool> {
+    let instruction = Instruction::DataAccess(DataInstruction::Read(DataReadInstruction::GetOne(
+        GetOneInstruction {
+            key: StoreKey::build(grouping, id),
+            height: None,
+        },
+    )));
+    match core.execute(&instruction) {
+        Ok(Answer::DataAccess(DataAnswer::Read(DataReadAnswer::GetOneOk(answer)))) => {
+            return Ok(answer.value.inner().is_some());
+        }
+        Ok(answer) => return Err(ExecutorError::UnexpectedAnswerType(answer).into()),
+        // The unit was never written
+        Err(ImmuxError::VKV(VkvError::MissingJournal(_))) => return Ok(false),
+        Err(error) => return Err(error),
+    }
+}
+
+pub fn execute_remove(remove: RemoveCommand, core: &mut impl CoreStore) -> ImmuxResult<Outcome> {
+    // Ids that are missing or already removed are left alone, and not counted
+    let mut existing_ids: Vec<UnitId> = Vec::new();
+    for id in remove.ids.iter() {
+        if !existing_ids.contains(id) && has_content(&remove.grouping, *id, core)? {
+            existing_ids.push(*id);
+        }
+    }
+    if existing_ids.is_empty() {
+        return Ok(Outcome::Remove(RemoveOutcome { count: 0 }));
+    }
+
+    // A removal is a new version with no value, so history stays inspectable and revertible
+    let tombstones: Vec<SetTargetSpec> = existing_ids
+        .iter()
+        .map(|id| SetTargetSpec {
+            key: StoreKey::build(&remove.grouping, *id),
+            value: StoreValue::new(None),
+        })
+        .collect();
+
+    // Nil content has no indexed properties, so this only takes the ids out of existing id lists
+    let removed_units: Vec<Unit> = existing_ids
+        .iter()
+        .map(|id| Unit {
+            id: *id,
+            content: UnitContent::Nil,
+        })
+        .collect();
+    let updates_for_index = get_updates_for_index(&remove.grouping, &removed_units, core)?;
+
+    let mut set_targets = Vec::new();
+    set_targets.extend(tombstones);
+    set_targets.extend(updates_for_index);
+
+    let batch_update: Instruction = Instruction::DataAccess(DataInstruction::Write(
+        DataWriteInstruction::SetMany(SetManyInstruction {
+            targets: set_targets,
+        }),
+    ));
+
+    match core.execute(&batch_update) {
+        Err(error) => return Err(error),
+        Ok(Answer::DataAccess(DataAnswer::Write(DataWriteAnswer::SetOk(_answer)))) => {
+            return Ok(Outcome::Remove(RemoveOutcome {
+                count: existing_ids.len(),
+            }));
+        }
+        Ok(answer) => {
+            return Err(ExecutorError::UnexpectedAnswerType(answer).into());
+        }
+    }
+}
+
+#[cfg(test)]
+mod remove_executor_tests {
+    use crate::declarations::basics::{GroupingLabel, StoreKey, StoreValue, UnitContent, UnitId};
+    use crate::declarations::commands::{Outcome, RemoveCommand};
+    use crate::declarations::errors::ImmuxError;
+    use crate::executor::remove_executor::execute_remove;
+    use crate::executor::tests::FixtureCore;
+    use crate::storage::instructions::{
+        DataInstruction, DataReadInstruction, DataWriteInstruction, GetOneOkAnswer, Instruction,
+        SetOkAnswer,
+    };
+    use crate::storage::vkv::VkvError;
+
+    fn get_unit_key(id: u8) -> StoreKey {
+        StoreKey::new(&[3, 1, 2, 3, id, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0])
+    }
+
+    #[test]
+    fn test_remove_writes_tombstones() {
+        let command = RemoveCommand {
+            grouping: GroupingLabel::new(&[1, 2, 3]),
+            ids: vec![UnitId::new(10), UnitId::new(11), UnitId::new(12)],
+        };
+        let mut core = FixtureCore::new(Box::new(|instruction| match instruction {
+            Instruction::DataAccess(DataInstruction::Read(DataReadInstruction::GetOne(
+                get_one,
+            ))) => {
+                if get_one.key == get_unit_key(10) || get_one.key == get_unit_key(11) {
+                    let content = UnitContent::String(String::from("content"));
+                    return Ok(GetOneOkAnswer {
+                        value: StoreValue::new(Some(content.marshal())),
+                    }
+                    .into());
+                }
+                // Neither an indexed names list nor unit 12 exist
+                return Err(ImmuxError::VKV(VkvError::MissingJournal(
+                    get_one.key.to_owned(),
+                )));
+            }
+            Instruction::DataAccess(DataInstruction::Write(DataWriteInstruction::SetMany(
+                set_many,
+            ))) => {
+                assert_eq!(set_many.targets.len(), 2);
+                assert_eq!(set_many.targets[0].key, get_unit_key(10));
+                assert_eq!(set_many.targets[1].key, get_unit_key(11));
+                for target in &set_many.targets {
+                    assert_eq!(target.value.inner(), &None);
+                }
+                return Ok(SetOkAnswer { count: 2 }.into());
+            }
+            _ => panic!("Unexpected instruction"),
+        }));
+        match execute_remove(command, &mut core) {
+            Ok(Outcome::Remove(outcome)) => assert_eq!(outcome.count, 2),
+            _ => panic!("Unexpected outcome"),
+        }
+    }
+
+    #[test]
+    fn test_remove_missing_units() {
+        let command = RemoveCommand {
+            grouping: GroupingLabel::new(&[1, 2, 3]),
+            ids: vec![UnitId::new(10), UnitId::new(11)],
+        };
+        let mut core = FixtureCore::new(Box::new(|instruction| match instruction {
+            Instruction::DataAccess(DataInstruction::Read(DataReadInstruction::GetOne(
+                get_one,
+            ))) => {
+                if get_one.key == get_unit_key(10) {
+                    // Removed before
+                    return Ok(GetOneOkAnswer {
+                        value: StoreValue::new(None),
+                    }
+                    .into());
+                }
+                return Err(ImmuxError::VKV(VkvError::MissingJournal(
+                    get_one.key.to_owned(),
+                )));
+            }
+            _ => panic!("Nothing should be written"),
+        }));
+        match execute_remove(command, &mut core) {
+            Ok(Outcome::Remove(outcome)) => assert_eq!(outcome.count, 0),
+            _ => panic!("Unexpected outcome"),
+        }
+    }
+}
@@ -0,0 +1,75 @@
+use crate::declarations::commands::{Outcome, ReplayChainCommand, ReplayChainOutcome};
+use crate::declarations::errors::ImmuxResult;
+use crate::executor::errors::ExecutorError;
+use crate::storage::core::CoreStore;
+use crate::storage::instructions::{Answer, DBSystemAnswer, ReplayInstruction};
+
+pub fn execute_replay_chain(
+    replay_chain: ReplayChainCommand,
+    core: &mut impl CoreStore,
+) -> ImmuxResult<Outcome> {
+    let instruction = ReplayInstruction {
+        engine: replay_chain.engine,
+        data_root: replay_chain.data_root,
+        namespace: replay_chain.chain.map(|chain| chain.into()),
+    };
+    match core.execute(&instruction.into()) {
+        Err(error) => return Err(error),
+        Ok(Answer::DBSystem(DBSystemAnswer::ReplayOk(answer))) => {
+            return Ok(Outcome::ReplayChain(ReplayChainOutcome {
+                height: answer.height,
+                head: answer.head,
+                mismatches: answer.mismatches,
+            }));
+        }
+        Ok(answer) => return Err(ExecutorError::UnexpectedAnswerType(answer).into()),
+    }
+}
+
+#[cfg(test)]
+mod replay_chain_executor_tests {
+    use crate::declarations::basics::ChainName;
+    use crate::declarations::commands::{Outcome, ReplayChainCommand};
+    use crate::executor::replay_chain_executor::execute_replay_chain;
+    use crate::executor::tests::FixtureCore;
+    use crate::storage::instructions::{
+        DBSystemInstruction, Instruction, ReplayMismatch, ReplayOkAnswer, StoreNamespace,
+    };
+    use crate::storage::kv::KeyValueEngine;
+    use crate::storage::vkv::{ChainHeight, RecordHash};
+
+    #[test]
+    fn test_replay_chain() {
+        let mut core = FixtureCore::new(Box::new(|instruction| match instruction {
+            Instruction::DBSystem(DBSystemInstruction::Replay(replay)) => {
+                assert_eq!(replay.data_root, "/tmp/replayed/");
+                assert_eq!(replay.namespace, Some(StoreNamespace::new(b"rebuilt")));
+                Ok(ReplayOkAnswer {
+                    height: ChainHeight::new(4),
+                    head: RecordHash::digest(&[b"head"]),
+                    mismatches: vec![ReplayMismatch {
+                        kvkey: vec![0x30, 0x01],
+                        original: Some(vec![0x02]),
+                        replayed: None,
+                    }],
+                }
+                .into())
+            }
+            _ => panic!("Unimplemented fixture instruction {:?}", instruction),
+        }));
+        let command = ReplayChainCommand {
+            engine: KeyValueEngine::Rocks,
+            data_root: String::from("/tmp/replayed/"),
+            chain: Some(ChainName::from("rebuilt")),
+        };
+        match execute_replay_chain(command, &mut core).unwrap() {
+            Outcome::ReplayChain(outcome) => {
+                assert_eq!(outcome.height, ChainHeight::new(4));
+                assert_eq!(outcome.head, RecordHash::digest(&[b"head"]));
+                assert_eq!(outcome.mismatches.len(), 1);
+                assert_eq!(outcome.mismatches[0].replayed, None);
+            }
+            outcome => panic!("Unexpected outcome {:?}", outcome),
+        }
+    }
+}
@@ -1,6 +1,7 @@
 use crate::declarations::commands::{Outcome, RevertAllCommand, RevertAllOutcome};
 use crate::declarations::errors::{ImmuxError, ImmuxResult};
 use crate::executor::errors::ExecutorError;
+use crate::executor::shared::resolve_height;
 use crate::storage::core::CoreStore;
 use crate::storage::instructions::{
     Answer, DataAnswer, DataInstruction, DataWriteAnswer, DataWriteInstruction, Instruction,
@@ -11,10 +12,9 @@ pub fn execute_revert_all(
     revert_all: RevertAllCommand,
     core: &mut impl CoreStore,
 ) -> ImmuxResult<Outcome> {
+    let target_height = resolve_height(&revert_all.target_height, core)?;
     let instruction = Instruction::DataAccess(DataInstruction::Write(
-        DataWriteInstruction::RevertAll(RevertAllInstruction {
-            target_height: revert_all.target_height,
-        }),
+        DataWriteInstruction::RevertAll(RevertAllInstruction { target_height }),
     ));
     match core.execute(&instruction) {
         Err(error) => return Err(error),
@@ -43,7 +43,7 @@ mod revert_all_executor_tests {
     #[test]
     fn test_revert_all() {
         let command = RevertAllCommand {
-            target_height: ChainHeight::new(10),
+            target_height: ChainHeight::new(10).into(),
         };
         let mut core = FixtureCore::new(Box::new(|instruction| match instruction {
             Instruction::DataAccess(DataInstruction::Write(DataWriteInstruction::RevertAll(
@@ -1,73 +1,69 @@
-use crate::declarations::basics::{StoreKey, Unit, UnitContent};
+use std::collections::BTreeMap;
+
+use crate::declarations::basics::{GroupingLabel, StoreKey, Unit, UnitContent};
 use crate::declarations::commands::{Outcome, RevertManyCommand, RevertOutcome};
 use crate::declarations::errors::{ImmuxError, ImmuxResult};
 use crate::executor::errors::ExecutorError;
 use crate::executor::insert_executor::get_updates_for_index;
+use crate::executor::shared::resolve_height;
 use crate::storage::core::CoreStore;
 use crate::storage::instructions::{
     Answer, DataAnswer, DataInstruction, DataReadAnswer, DataReadInstruction, DataWriteAnswer,
-    DataWriteInstruction, GetOneInstruction, Instruction, RevertManyInstruction, RevertTargetSpec,
-    SetManyInstruction, SetTargetSpec,
+    GetOneInstruction, IndexedRevertManyInstruction, Instruction, RevertTargetSpec, SetTargetSpec,
 };
 
 pub fn execute_revert_many(
     revert: RevertManyCommand,
     core: &mut impl CoreStore,
 ) -> ImmuxResult<Outcome> {
-    let mut update_for_index: Vec<SetTargetSpec> = Vec::new();
-    for revert_spec in &revert.specs {
-        let key = StoreKey::build(
-            revert_spec.specifier.get_grouping(),
-            revert_spec.specifier.get_id(),
-        );
+    let mut targets: Vec<RevertTargetSpec> = Vec::with_capacity(revert.specs.len());
+    for spec in &revert.specs {
+        targets.push(RevertTargetSpec {
+            key: StoreKey::build(spec.specifier.get_grouping(), spec.specifier.get_id()),
+            height: resolve_height(&spec.target_height, core)?,
+        });
+    }
+
+    // Units sharing a grouping go through the index together, so id lists they share are
+    // updated once with all of them
+    let mut reverted_units: BTreeMap<Vec<u8>, (GroupingLabel, Vec<Unit>)> = BTreeMap::new();
+    for (revert_spec, target) in revert.specs.iter().zip(targets.iter()) {
         let instruction = Instruction::DataAccess(DataInstruction::Read(
             DataReadInstruction::GetOne(GetOneInstruction {
-                key,
-                height: Some(revert_spec.target_height),
+                key: target.key.clone(),
+                height: Some(target.height),
             }),
         ));
         match core.execute(&instruction) {
             Ok(Answer::DataAccess(DataAnswer::Read(DataReadAnswer::GetOneOk(answer)))) => {
-                match answer.value.inner() {
-                    None => continue,
-                    Some(data) => {
-                        let content = UnitContent::parse_data(data)?;
-                        let unit = Unit {
-                            id: revert_spec.specifier.get_id(),
-                            content,
-                        };
-                        let mut set_spect = get_updates_for_index(
-                            revert_spec.specifier.get_grouping(),
-                            &[unit],
-                            core,
-                        )?;
-                        update_for_index.append(&mut set_spect);
-                    }
-                }
+                // Reverting to a removal takes the unit out of the id lists
+                let content = match answer.value.inner() {
+                    None => UnitContent::Nil,
+                    Some(data) => UnitContent::parse_data(data)?,
+                };
+                let grouping = revert_spec.specifier.get_grouping();
+                reverted_units
+                    .entry(grouping.marshal())
+                    .or_insert((grouping.to_owned(), vec![]))
+                    .1
+                    .push(Unit {
+                        id: revert_spec.specifier.get_id(),
+                        content,
+                    });
             }
             _ => continue,
         }
     }
-    let batch_update: Instruction = Instruction::DataAccess(DataInstruction::Write(
-        DataWriteInstruction::SetMany(SetManyInstruction {
-            targets: update_for_index,
-        }),
-    ));
-
-    core.execute(&batch_update)?;
+    let mut index_targets: Vec<SetTargetSpec> = Vec::new();
+    for (_, (grouping, units)) in reverted_units {
+        index_targets.extend(get_updates_for_index(&grouping, &units, core)?);
+    }
 
-    let instruction = Instruction::DataAccess(DataInstruction::Write(
-        DataWriteInstruction::RevertMany(RevertManyInstruction {
-            targets: revert
-                .specs
-                .iter()
-                .map(|spec| RevertTargetSpec {
-                    key: StoreKey::build(spec.specifier.get_grouping(), spec.specifier.get_id()),
-                    height: spec.target_height,
-                })
-                .collect(),
-        }),
-    ));
+    let instruction: Instruction = IndexedRevertManyInstruction {
+        targets,
+        index_targets,
+    }
+    .into();
     match core.execute(&instruction) {
         Err(error) => return Err(error),
         Ok(Answer::DataAccess(DataAnswer::Write(DataWriteAnswer::RevertOk(_answer)))) => {
@@ -85,47 +81,49 @@ pub fn execute_revert_many(
 mod revert_many_executor_tests {
     use crate::declarations::basics::{GroupingLabel, StoreKey, StoreValue, UnitId, UnitSpecifier};
     use crate::declarations::commands::{Outcome, RevertCommandTargetSpec, RevertManyCommand};
+    use crate::declarations::errors::ImmuxError;
     use crate::executor::revert_many_executor::execute_revert_many;
     use crate::executor::tests::FixtureCore;
     use crate::storage::instructions::{
         DataInstruction, DataReadInstruction, DataWriteInstruction, GetOneOkAnswer, Instruction,
-        RevertOkAnswer, SetOkAnswer,
+        RevertOkAnswer,
     };
-    use crate::storage::vkv::ChainHeight;
+    use crate::storage::vkv::{ChainHeight, VkvError};
 
     #[test]
     fn test_revert_many() {
         let command = RevertManyCommand {
             specs: vec![RevertCommandTargetSpec {
                 specifier: UnitSpecifier::new(GroupingLabel::new(&[1, 2, 3]), UnitId::new(10)),
-                target_height: ChainHeight::new(100),
+                target_height: ChainHeight::new(100).into(),
             }],
         };
         let store_key =
             StoreKey::new(&[3, 1, 2, 3, 10, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
         let mut core = FixtureCore::new(Box::new(|instruction| match instruction {
-            Instruction::DataAccess(DataInstruction::Write(DataWriteInstruction::RevertMany(
-                revert_many,
-            ))) => {
+            Instruction::DataAccess(DataInstruction::Write(
+                DataWriteInstruction::IndexedRevertMany(revert_many),
+            )) => {
                 assert_eq!(revert_many.targets.len(), 1);
                 assert_eq!(revert_many.targets[0].key, store_key);
                 assert_eq!(revert_many.targets[0].height.as_u64(), 100);
+                assert!(revert_many.index_targets.is_empty());
                 return Ok(RevertOkAnswer {}.into());
             }
             Instruction::DataAccess(DataInstruction::Read(DataReadInstruction::GetOne(
                 get_one,
             ))) => {
-                assert_eq!(get_one.key, store_key);
-                return Ok(GetOneOkAnswer {
-                    value: StoreValue::new(None),
+                if get_one.key == store_key {
+                    // Removed at the target height
+                    return Ok(GetOneOkAnswer {
+                        value: StoreValue::new(None),
+                    }
+                    .into());
                 }
-                .into());
-            }
-            Instruction::DataAccess(DataInstruction::Write(DataWriteInstruction::SetMany(
-                set_many,
-            ))) => {
-                assert!(set_many.targets.is_empty());
-                return Ok(SetOkAnswer { count: 0 }.into());
+                // The grouping has no indexed names list
+                return Err(ImmuxError::VKV(VkvError::MissingJournal(
+                    get_one.key.to_owned(),
+                )));
             }
             _ => panic!("Unexpected instruction"),
         }));
@@ -0,0 +1,242 @@
+use std::collections::BTreeMap;
+
+use crate::declarations::basics::{GroupingLabel, StoreKey, Unit, UnitContent, UnitSpecifier};
+use crate::declarations::commands::{Outcome, RevertRecordCommand, RevertRecordOutcome};
+use crate::declarations::errors::{ImmuxError, ImmuxResult};
+use crate::executor::errors::ExecutorError;
+use crate::executor::insert_executor::get_updates_for_index;
+use crate::executor::shared::{get_unit_specifier_of_key, resolve_height};
+use crate::storage::core::CoreStore;
+use crate::storage::instructions::{
+    Answer, DataAnswer, DataReadAnswer, DataWriteAnswer, GetChangesInstruction,
+    GetJournalInstruction, GetOneInstruction, Instruction, RevertInstruction,
+    RevertKeysInstruction, SetTargetSpec,
+};
+use crate::storage::vkv::{ChainHeight, VkvError};
+
+/// Units the record at `height` wrote, read from the change feed
+fn get_written_units(
+    height: ChainHeight,
+    core: &mut impl CoreStore,
+) -> ImmuxResult<Vec<UnitSpecifier>> {
+    let mut after_height = height;
+    after_height.decrement();
+    let instruction = GetChangesInstruction {
+        after_height,
+        limit: 1,
+    };
+    match core.execute(&instruction.into()) {
+        Err(error) => return Err(error),
+        Ok(Answer::DataAccess(DataAnswer::Read(DataReadAnswer::GetChangesOk(answer)))) => {
+            match answer.changes.into_iter().next() {
+                Some(change) if change.height == height => {
+                    return Ok(change
+                        .targets
+                        .iter()
+                        .filter_map(|target| get_unit_specifier_of_key(&target.key))
+                        .collect());
+                }
+                _ => return Err(VkvError::InvalidRevertHeight(height).into()),
+            }
+        }
+        Ok(answer) => return Err(ExecutorError::UnexpectedAnswerType(answer).into()),
+    }
+}
+
+fn is_written_after(
+    key: &StoreKey,
+    height: ChainHeight,
+    core: &mut impl CoreStore,
+) -> ImmuxResult<bool> {
+    let instruction: Instruction = GetJournalInstruction {
+        key: key.to_owned(),
+    }
+    .into();
+    match core.execute(&instruction) {
+        Err(error) => return Err(error),
+        Ok(Answer::DataAccess(DataAnswer::Read(DataReadAnswer::GetJournalOk(answer)))) => {
+            return Ok(answer
+                .journal
+                .update_heights
+                .iter()
+                .any(|update_height| update_height > height));
+        }
+        Ok(answer) => return Err(ExecutorError::UnexpectedAnswerType(answer).into()),
+    }
+}
+
+/// Content of the unit just below `height`, where units that did not exist yet are Nil
+fn get_content_before(
+    key: &StoreKey,
+    height: ChainHeight,
+    core: &mut impl CoreStore,
+) -> ImmuxResult<UnitContent> {
+    let mut previous_height = height;
+    previous_height.decrement();
+    let instruction: Instruction = GetOneInstruction {
+        key: key.to_owned(),
+        height: Some(previous_height),
+    }
+    .into();
+    match core.execute(&instruction) {
+        Err(ImmuxError::VKV(VkvError::CannotFindSuitableVersion)) => return Ok(UnitContent::Nil),
+        Err(error) => return Err(error),
+        Ok(Answer::DataAccess(DataAnswer::Read(DataReadAnswer::GetOneOk(answer)))) => {
+            match answer.value.inner() {
+                None => return Ok(UnitContent::Nil),
+                Some(data) => return Ok(UnitContent::parse_data(data)?),
+            }
+        }
+        Ok(answer) => return Err(ExecutorError::UnexpectedAnswerType(answer).into()),
+    }
+}
+
+pub fn execute_revert_record(
+    revert_record: RevertRecordCommand,
+    core: &mut impl CoreStore,
+) -> ImmuxResult<Outcome> {
+    let height = resolve_height(&revert_record.height, core)?;
+    if height.is_zero() {
+        return Err(VkvError::InvalidRevertHeight(height).into());
+    }
+
+    let written_units = get_written_units(height, core)?;
+    if written_units.is_empty() {
+        // Nothing indexed depends on the record, so it is reverted key by key as it is
+        let instruction: Instruction = RevertInstruction { height }.into();
+        match core.execute(&instruction) {
+            Err(error) => return Err(error),
+            Ok(Answer::DataAccess(DataAnswer::Write(DataWriteAnswer::RevertRecordOk(_)))) => {
+                return Ok(Outcome::RevertRecord(RevertRecordOutcome {
+                    reverted: vec![],
+                    conflicts: vec![],
+                }));
+            }
+            Ok(answer) => return Err(ExecutorError::UnexpectedAnswerType(answer).into()),
+        }
+    }
+
+    // Reverse indexes are derived from unit contents, so they are brought in line with the
+    // restored contents in the same instruction rather than rewound along with the units
+    let mut keys: Vec<StoreKey> = Vec::new();
+    let mut conflicts: Vec<UnitSpecifier> = Vec::new();
+    let mut restored_units: BTreeMap<Vec<u8>, (GroupingLabel, Vec<Unit>)> = BTreeMap::new();
+    for specifier in written_units {
+        let key = StoreKey::from(specifier.clone());
+        if is_written_after(&key, height, core)? {
+            conflicts.push(specifier);
+            continue;
+        }
+        let content = get_content_before(&key, height, core)?;
+        let (grouping, id) = specifier.into_components();
+        restored_units
+            .entry(grouping.marshal())
+            .or_insert((grouping, vec![]))
+            .1
+            .push(Unit { id, content });
+        keys.push(key);
+    }
+    let mut index_targets: Vec<SetTargetSpec> = Vec::new();
+    for (_, (grouping, units)) in restored_units {
+        index_targets.extend(get_updates_for_index(&grouping, &units, core)?);
+    }
+
+    let instruction: Instruction = RevertKeysInstruction {
+        height,
+        keys,
+        index_targets,
+    }
+    .into();
+    match core.execute(&instruction) {
+        Err(error) => return Err(error),
+        Ok(Answer::DataAccess(DataAnswer::Write(DataWriteAnswer::RevertRecordOk(answer)))) => {
+            let reverted = answer
+                .reverted_keys
+                .iter()
+                .filter_map(get_unit_specifier_of_key)
+                .collect();
+            return Ok(Outcome::RevertRecord(RevertRecordOutcome {
+                reverted,
+                conflicts,
+            }));
+        }
+        Ok(answer) => return Err(ExecutorError::UnexpectedAnswerType(answer).into()),
+    }
+}
+
+#[cfg(test)]
+mod revert_record_executor_tests {
+    use crate::declarations::basics::{GroupingLabel, StoreKey, StoreValue, UnitId, UnitSpecifier};
+    use crate::declarations::commands::{Outcome, RevertRecordCommand};
+    use crate::executor::revert_record_executor::execute_revert_record;
+    use crate::executor::tests::FixtureCore;
+    use crate::storage::instructions::{
+        DataInstruction, DataReadInstruction, DataWriteInstruction, GetChangesOkAnswer,
+        GetJournalOkAnswer, Instruction, RecordChange, RevertRecordOkAnswer, SetTargetSpec,
+    };
+    use crate::storage::vkv::{ChainHeight, HeightList, RecordMeta, UnitJournal};
+
+    #[test]
+    fn test_revert_record_with_conflict() {
+        let specifier = UnitSpecifier::new(GroupingLabel::from("grouping"), UnitId::new(1));
+        let key = StoreKey::from(specifier.clone());
+        let mut core = FixtureCore::new(Box::new(move |instruction| match instruction {
+            Instruction::DataAccess(DataInstruction::Read(DataReadInstruction::GetChanges(
+                get_changes,
+            ))) => {
+                assert_eq!(get_changes.after_height, ChainHeight::new(2));
+                Ok(GetChangesOkAnswer {
+                    height: ChainHeight::new(3),
+                    changes: vec![RecordChange {
+                        height: ChainHeight::new(3),
+                        sys_time: 0,
+                        meta: RecordMeta::default(),
+                        targets: vec![SetTargetSpec {
+                            key: key.clone(),
+                            value: StoreValue::new(Some(vec![1])),
+                        }],
+                    }],
+                }
+                .into())
+            }
+            // The unit was written again at height 5
+            Instruction::DataAccess(DataInstruction::Read(DataReadInstruction::GetJournal(
+                _get_journal,
+            ))) => Ok(GetJournalOkAnswer {
+                journal: UnitJournal {
+                    value: StoreValue::new(Some(vec![2])),
+                    update_heights: HeightList::new(&[ChainHeight::new(3), ChainHeight::new(5)]),
+                },
+            }
+            .into()),
+            // The conflicting unit is left out, along with its index entries
+            Instruction::DataAccess(DataInstruction::Write(DataWriteInstruction::RevertKeys(
+                revert_keys,
+            ))) => {
+                assert_eq!(revert_keys.height, ChainHeight::new(3));
+                assert!(revert_keys.keys.is_empty());
+                assert!(revert_keys.index_targets.is_empty());
+                Ok(RevertRecordOkAnswer {
+                    reverted_keys: vec![],
+                    conflicts: vec![],
+                }
+                .into())
+            }
+            _ => panic!("Unimplemented fixture instruction {:?}", instruction),
+        }));
+        let command = RevertRecordCommand {
+            height: ChainHeight::new(3).into(),
+        };
+        match execute_revert_record(command, &mut core).unwrap() {
+            Outcome::RevertRecord(outcome) => {
+                assert!(outcome.reverted.is_empty());
+                assert_eq!(outcome.conflicts.len(), 1);
+                assert_eq!(
+                    StoreKey::from(outcome.conflicts[0].clone()),
+                    StoreKey::from(specifier)
+                );
+            }
+            outcome => panic!("Unexpected outcome {:?}", outcome),
+        }
+    }
+}
@@ -1,33 +1,44 @@
+use std::cmp::Reverse;
+use std::collections::{BTreeSet, HashMap};
 use std::convert::TryFrom;
+use std::ops::Bound;
 
+use bincode::serialize;
 use serde_json::Value as JsonValue;
 
 use crate::declarations::basics::{
-    GroupingLabel, IdList, StoreKey, StoreKeyFragment, Unit, UnitContent, UnitSpecifier,
+    GroupingLabel, IdList, PropertyName, PropertyNameList, StoreKey, StoreKeyFragment, Unit,
+    UnitContent, UnitId, UnitSpecifier, UNIT_ID_BYTES,
+};
+use crate::declarations::commands::{
+    Outcome, SelectCommand, SelectCondition, SelectOutcome, SelectSort, SortOrder,
 };
-use crate::declarations::commands::{Outcome, SelectCommand, SelectCondition, SelectOutcome};
 use crate::declarations::errors::{ImmuxError, ImmuxResult};
 use crate::executor::errors::ExecutorError;
-use crate::executor::shared::get_store_key_of_indexed_id_list;
+use crate::executor::js_sandbox::{JsLimits, JsPredicate};
+use crate::executor::shared::{
+    get_current_height, get_indexed_names_list_at_height, get_indexed_property, get_json_document,
+    get_sortable_property_bytes, get_store_key_of_indexed_id_list,
+    get_store_key_prefix_of_indexed_id_lists, is_index_layout_current, resolve_optional_height,
+    Projection,
+};
 use crate::storage::core::CoreStore;
 use crate::storage::instructions::{
     Answer, DataAnswer, DataInstruction, DataReadAnswer, DataReadInstruction, GetManyInstruction,
     GetManyTargetSpec, GetOneInstruction, Instruction,
 };
-use crate::storage::vkv::VkvError;
+use crate::storage::vkv::{ChainHeight, RecordHash, VkvError, RECORD_HASH_LENGTH};
+use crate::utils::{u64_to_u8_array, u8_array_to_u64};
 
-fn get_all_in_grouping(
-    grouping: &GroupingLabel,
+fn get_units_of_targets(
+    targets: GetManyTargetSpec,
+    height: Option<ChainHeight>,
     core: &mut impl CoreStore,
 ) -> ImmuxResult<Vec<Unit>> {
-    let prefix: StoreKeyFragment = grouping.marshal().into();
-    let get_all = Instruction::DataAccess(DataInstruction::Read(DataReadInstruction::GetMany(
-        GetManyInstruction {
-            height: None,
-            targets: GetManyTargetSpec::KeyPrefix(prefix),
-        },
+    let get_many = Instruction::DataAccess(DataInstruction::Read(DataReadInstruction::GetMany(
+        GetManyInstruction { height, targets },
     )));
-    match core.execute(&get_all) {
+    match core.execute(&get_many) {
         Err(error) => return Err(error),
         Ok(Answer::DataAccess(DataAnswer::Read(DataReadAnswer::GetManyOk(answer)))) => {
             let mut units = Vec::with_capacity(answer.data.len());
@@ -50,24 +61,626 @@ fn get_all_in_grouping(
     }
 }
 
-pub fn execute_select(select: SelectCommand, core: &mut impl CoreStore) -> ImmuxResult<Outcome> {
-    match &select.condition {
-        SelectCondition::UnconditionalMatch => get_all_in_grouping(&select.grouping, core)
-            .map(|units| Ok(Outcome::Select(SelectOutcome { units })))?,
+fn get_all_in_grouping(
+    grouping: &GroupingLabel,
+    height: Option<ChainHeight>,
+    core: &mut impl CoreStore,
+) -> ImmuxResult<Vec<Unit>> {
+    let prefix: StoreKeyFragment = grouping.marshal().into();
+    let targets = GetManyTargetSpec::KeyPrefix(prefix);
+    return get_units_of_targets(targets, height, core);
+}
+
+/// At most `limit` units of the grouping whose ids sort after `after`, in key order
+fn get_units_after(
+    grouping: &GroupingLabel,
+    after: Option<UnitId>,
+    limit: usize,
+    height: Option<ChainHeight>,
+    core: &mut impl CoreStore,
+) -> ImmuxResult<Vec<Unit>> {
+    let prefix: StoreKeyFragment = grouping.marshal().into();
+    let after = after.map(|id| StoreKey::build(grouping, id));
+    let targets = GetManyTargetSpec::KeyRange(prefix, after, limit);
+    return get_units_of_targets(targets, height, core);
+}
+
+fn get_units_of_ids(
+    grouping: &GroupingLabel,
+    ids: impl IntoIterator<Item = UnitId>,
+    height: Option<ChainHeight>,
+    core: &mut impl CoreStore,
+) -> ImmuxResult<Vec<Unit>> {
+    let mut result: Vec<Unit> = Vec::new();
+    for id in ids {
+        let get_data = Instruction::DataAccess(DataInstruction::Read(DataReadInstruction::GetOne(
+            GetOneInstruction {
+                key: StoreKey::build(grouping, id),
+                height: height,
+            },
+        )));
+
+        match core.execute(&get_data) {
+            Err(error) => return Err(error),
+            Ok(Answer::DataAccess(DataAnswer::Read(DataReadAnswer::GetOneOk(answer)))) => {
+                // Units removed since they were indexed are left out
+                if let Some(data) = answer.value.inner() {
+                    let content = UnitContent::parse_data(data)?;
+                    result.push(Unit { id, content });
+                }
+            }
+            Ok(answer) => return Err(ExecutorError::UnexpectedAnswerType(answer).into()),
+        }
+    }
+    return Ok(result);
+}
+
+/// Bounds on the sortable bytes of indexed properties
+struct PropertyRange {
+    lower: Bound<Vec<u8>>,
+    upper: Bound<Vec<u8>>,
+}
+
+impl PropertyRange {
+    fn new(lower: Bound<&UnitContent>, upper: Bound<&UnitContent>) -> Self {
+        let to_bytes = |bound: Bound<&UnitContent>| match bound {
+            Bound::Included(content) => Bound::Included(get_sortable_property_bytes(content)),
+            Bound::Excluded(content) => Bound::Excluded(get_sortable_property_bytes(content)),
+            Bound::Unbounded => Bound::Unbounded,
+        };
+        return PropertyRange {
+            lower: to_bytes(lower),
+            upper: to_bytes(upper),
+        };
+    }
+
+    /// The content type prefix of the bounds, which matching properties share
+    fn get_type_prefix(&self) -> Option<u8> {
+        for bound in &[&self.lower, &self.upper] {
+            match bound {
+                Bound::Included(bytes) | Bound::Excluded(bytes) => return bytes.first().cloned(),
+                Bound::Unbounded => continue,
+            }
+        }
+        return None;
+    }
+
+    fn contains(&self, property_bytes: &[u8]) -> bool {
+        let is_same_type = |bytes: &Vec<u8>| bytes.first() == property_bytes.first();
+        let is_above_lower = match &self.lower {
+            Bound::Included(bytes) => is_same_type(bytes) && property_bytes >= bytes.as_slice(),
+            Bound::Excluded(bytes) => is_same_type(bytes) && property_bytes > bytes.as_slice(),
+            Bound::Unbounded => true,
+        };
+        let is_below_upper = match &self.upper {
+            Bound::Included(bytes) => is_same_type(bytes) && property_bytes <= bytes.as_slice(),
+            Bound::Excluded(bytes) => is_same_type(bytes) && property_bytes < bytes.as_slice(),
+            Bound::Unbounded => true,
+        };
+        return is_above_lower && is_below_upper;
+    }
+}
+
+/// The property and range of a range condition
+fn get_property_range(condition: &SelectCondition) -> Option<(&PropertyName, PropertyRange)> {
+    match condition {
+        SelectCondition::LessThan(name, bound) => Some((
+            name,
+            PropertyRange::new(Bound::Unbounded, Bound::Excluded(bound)),
+        )),
+        SelectCondition::LessThanOrEqual(name, bound) => Some((
+            name,
+            PropertyRange::new(Bound::Unbounded, Bound::Included(bound)),
+        )),
+        SelectCondition::GreaterThan(name, bound) => Some((
+            name,
+            PropertyRange::new(Bound::Excluded(bound), Bound::Unbounded),
+        )),
+        SelectCondition::GreaterThanOrEqual(name, bound) => Some((
+            name,
+            PropertyRange::new(Bound::Included(bound), Bound::Unbounded),
+        )),
+        SelectCondition::Between(name, lower, upper) => Some((
+            name,
+            PropertyRange::new(Bound::Included(lower), Bound::Included(upper)),
+        )),
+        _ => None,
+    }
+}
+
+fn get_sortable_bytes_of_property(unit: &Unit, name: &PropertyName) -> Option<Vec<u8>> {
+    let document = get_json_document(&unit.content)?;
+    match document.get(name.to_string()).map(get_indexed_property) {
+        Some(Ok(property)) => return Some(get_sortable_property_bytes(&property)),
+        _ => return None,
+    }
+}
+
+fn is_indexed(
+    grouping: &GroupingLabel,
+    name: &PropertyName,
+    height: Option<ChainHeight>,
+    core: &mut impl CoreStore,
+) -> ImmuxResult<bool> {
+    if !is_index_layout_current(height, core)? {
+        return Ok(false);
+    }
+    match get_indexed_names_list_at_height(grouping, height, core)? {
+        None => return Ok(false),
+        Some(names) => return Ok(names.as_slice().contains(name)),
+    }
+}
+
+/// The prefix of the index keys of the properties sharing the type of the range's bounds
+fn get_index_scan_prefix(
+    index_prefix: &StoreKeyFragment,
+    range: &PropertyRange,
+) -> StoreKeyFragment {
+    let mut bytes = index_prefix.as_slice().to_vec();
+    if let Some(type_prefix) = range.get_type_prefix() {
+        bytes.push(type_prefix);
+    }
+    return StoreKeyFragment::from(bytes);
+}
+
+/// The id list stored under an index key, or None if no unit had the property
+fn get_id_list(
+    key: StoreKey,
+    height: Option<ChainHeight>,
+    core: &mut impl CoreStore,
+) -> ImmuxResult<Option<IdList>> {
+    let get_indexed_id_list = Instruction::DataAccess(DataInstruction::Read(
+        DataReadInstruction::GetOne(GetOneInstruction { key, height }),
+    ));
+    match core.execute(&get_indexed_id_list) {
+        Err(ImmuxError::VKV(VkvError::MissingJournal(_)))
+        | Err(ImmuxError::VKV(VkvError::CannotFindSuitableVersion)) => return Ok(None),
+        Err(error) => return Err(error),
+        Ok(Answer::DataAccess(DataAnswer::Read(DataReadAnswer::GetOneOk(answer)))) => {
+            match answer.value.inner() {
+                None => return Ok(None),
+                Some(data) => return Ok(Some(IdList::try_from(data.as_slice())?)),
+            }
+        }
+        Ok(answer) => return Err(ExecutorError::UnexpectedAnswerType(answer).into()),
+    }
+}
+
+/// The id lists of the properties of indexed name `name` within `range`, ordered by the property
+fn get_indexed_id_lists_in_range(
+    grouping: &GroupingLabel,
+    name: &PropertyName,
+    range: &PropertyRange,
+    height: Option<ChainHeight>,
+    core: &mut impl CoreStore,
+) -> ImmuxResult<Vec<IdList>> {
+    let index_prefix = get_store_key_prefix_of_indexed_id_lists(grouping, name);
+    let scan_prefix = get_index_scan_prefix(&index_prefix, range);
+    let get_id_lists = Instruction::DataAccess(DataInstruction::Read(
+        DataReadInstruction::GetMany(GetManyInstruction {
+            height,
+            targets: GetManyTargetSpec::KeyPrefix(scan_prefix),
+        }),
+    ));
+    let mut id_lists: Vec<(Vec<u8>, IdList)> = match core.execute(&get_id_lists) {
+        Err(error) => return Err(error),
+        Ok(Answer::DataAccess(DataAnswer::Read(DataReadAnswer::GetManyOk(answer)))) => {
+            let mut result = Vec::new();
+            for (key, value) in answer.data {
+                let property_bytes = &key.as_slice()[index_prefix.as_slice().len()..];
+                if !range.contains(property_bytes) {
+                    continue;
+                }
+                if let Some(data) = value.inner() {
+                    let id_list = IdList::try_from(&data[..])?;
+                    result.push((property_bytes.to_vec(), id_list));
+                }
+            }
+            result
+        }
+        Ok(answer) => return Err(ExecutorError::UnexpectedAnswerType(answer).into()),
+    };
+    id_lists.sort_by(|a, b| a.0.cmp(&b.0));
+    return Ok(id_lists.into_iter().map(|(_, id_list)| id_list).collect());
+}
+
+/// Units whose property `name` lies in `range`, ordered by the property.
+///
+/// Indexed names are answered from the id lists under the index key prefix of the bounds' type;
+/// the others fall back to filtering the whole grouping.
+fn select_in_range(
+    grouping: &GroupingLabel,
+    name: &PropertyName,
+    range: &PropertyRange,
+    height: Option<ChainHeight>,
+    core: &mut impl CoreStore,
+) -> ImmuxResult<Vec<Unit>> {
+    if range.get_type_prefix().is_none() {
+        return get_all_in_grouping(grouping, height, core);
+    }
+
+    if !is_indexed(grouping, name, height, core)? {
+        let mut matches: Vec<(Vec<u8>, Unit)> = Vec::new();
+        for unit in get_all_in_grouping(grouping, height, core)? {
+            match get_sortable_bytes_of_property(&unit, name) {
+                Some(property_bytes) if range.contains(&property_bytes) => {
+                    matches.push((property_bytes, unit))
+                }
+                _ => continue,
+            }
+        }
+        matches.sort_by(|a, b| a.0.cmp(&b.0));
+        return Ok(matches.into_iter().map(|(_, unit)| unit).collect());
+    }
+
+    let id_lists = get_indexed_id_lists_in_range(grouping, name, range, height, core)?;
+    let ids = id_lists.into_iter().flat_map(|id_list| id_list);
+    return get_units_of_ids(grouping, ids, height, core);
+}
+
+/// How many id lists an index scan reads at a time
+const ID_LIST_SCAN_BATCH: usize = 64;
+
+/// Units whose indexed property `name` lies in `range`, ordered by the property, read from the id
+/// lists until at least `limit` are found.
+///
+/// With `after` (the sortable bytes of the last unit returned, and its id), the scan resumes
+/// within that unit's id list.
+fn stream_indexed_range(
+    grouping: &GroupingLabel,
+    name: &PropertyName,
+    range: &PropertyRange,
+    after: Option<(Vec<u8>, UnitId)>,
+    limit: usize,
+    height: Option<ChainHeight>,
+    core: &mut impl CoreStore,
+) -> ImmuxResult<Vec<Unit>> {
+    let index_prefix = get_store_key_prefix_of_indexed_id_lists(grouping, name);
+    let scan_prefix = get_index_scan_prefix(&index_prefix, range);
+    let get_list_key = |property_bytes: &[u8]| {
+        let mut bytes = index_prefix.as_slice().to_vec();
+        bytes.extend_from_slice(property_bytes);
+        StoreKey::new(&bytes)
+    };
+
+    // The id list the scan starts within, if any, and the id it resumes after
+    let (first_list, mut scan_after): (Option<(Vec<u8>, Option<UnitId>)>, Option<StoreKey>) =
+        match (after, &range.lower) {
+            (Some((property_bytes, id)), _) => {
+                let key = get_list_key(&property_bytes);
+                (Some((property_bytes, Some(id))), Some(key))
+            }
+            (None, Bound::Included(bytes)) => {
+                (Some((bytes.clone(), None)), Some(get_list_key(bytes)))
+            }
+            (None, Bound::Excluded(bytes)) => (None, Some(get_list_key(bytes))),
+            (None, Bound::Unbounded) => (None, None),
+        };
+
+    let mut units = Vec::new();
+    if let Some((property_bytes, last_id)) = first_list {
+        if range.contains(&property_bytes) {
+            if let Some(id_list) = get_id_list(get_list_key(&property_bytes), height, core)? {
+                let ids: Vec<UnitId> = match last_id {
+                    None => id_list.into_iter().collect(),
+                    Some(last_id) => id_list
+                        .into_iter()
+                        .skip_while(|id| *id != last_id)
+                        .skip(1)
+                        .collect(),
+                };
+                units.extend(get_units_of_ids(grouping, ids, height, core)?);
+            }
+        }
+    }
+
+    while units.len() < limit {
+        let get_id_lists = Instruction::DataAccess(DataInstruction::Read(
+            DataReadInstruction::GetMany(GetManyInstruction {
+                height,
+                targets: GetManyTargetSpec::KeyRange(
+                    scan_prefix.clone(),
+                    scan_after.clone(),
+                    ID_LIST_SCAN_BATCH,
+                ),
+            }),
+        ));
+        let pairs = match core.execute(&get_id_lists) {
+            Err(error) => return Err(error),
+            Ok(Answer::DataAccess(DataAnswer::Read(DataReadAnswer::GetManyOk(answer)))) => {
+                answer.data
+            }
+            Ok(answer) => return Err(ExecutorError::UnexpectedAnswerType(answer).into()),
+        };
+        if pairs.is_empty() {
+            break;
+        }
+        for (key, value) in pairs {
+            if units.len() >= limit {
+                break;
+            }
+            let property_bytes = &key.as_slice()[index_prefix.as_slice().len()..];
+            // The scan starts at the lower bound, so the first list out of range is past the upper
+            if !range.contains(property_bytes) {
+                return Ok(units);
+            }
+            if let Some(data) = value.inner() {
+                let id_list = IdList::try_from(&data[..])?;
+                units.extend(get_units_of_ids(grouping, id_list, height, core)?);
+            }
+            scan_after = Some(StoreKey::new(key.as_slice()));
+        }
+    }
+    return Ok(units);
+}
+
+/// Whether the unit satisfies the condition, judged from its content alone.
+///
+/// JavaScript predicates are compiled once into `predicates`, keyed by their code.
+fn is_match(
+    condition: &SelectCondition,
+    unit: &Unit,
+    predicates: &mut HashMap<String, JsPredicate>,
+) -> ImmuxResult<bool> {
+    match condition {
+        SelectCondition::UnconditionalMatch => return Ok(true),
+        SelectCondition::Id(id) => return Ok(unit.id == *id),
+        SelectCondition::NameProperty(name, property) => match get_json_document(&unit.content) {
+            None => return Ok(false),
+            Some(document) => match document.get(name.to_string()) {
+                None => return Ok(false),
+                Some(value) => return Ok(property == value),
+            },
+        },
+        SelectCondition::And(operands) => {
+            for operand in operands {
+                if !is_match(operand, unit, predicates)? {
+                    return Ok(false);
+                }
+            }
+            return Ok(true);
+        }
+        SelectCondition::Or(operands) => {
+            for operand in operands {
+                if is_match(operand, unit, predicates)? {
+                    return Ok(true);
+                }
+            }
+            return Ok(false);
+        }
+        SelectCondition::Not(operand) => return Ok(!is_match(operand, unit, predicates)?),
+        SelectCondition::JSCode(code) => match get_json_document(&unit.content) {
+            None => return Ok(false),
+            Some(document) => {
+                if !predicates.contains_key(code) {
+                    predicates.insert(code.to_owned(), JsPredicate::compile(code)?);
+                }
+                return Ok(predicates[code].test(&document, JsLimits::default())?);
+            }
+        },
+        _ => match get_property_range(condition) {
+            None => {
+                return Err(
+                    ExecutorError::UnimplementedSelectCondition(condition.to_owned()).into(),
+                );
+            }
+            Some((name, range)) => match get_sortable_bytes_of_property(unit, name) {
+                None => return Ok(false),
+                Some(property_bytes) => return Ok(range.contains(&property_bytes)),
+            },
+        },
+    }
+}
+
+/// The ids matching the condition according to the indexes alone, or None if it has a leaf they
+/// cannot answer
+fn get_indexed_ids(
+    grouping: &GroupingLabel,
+    condition: &SelectCondition,
+    indexed_names: &PropertyNameList,
+    height: Option<ChainHeight>,
+    core: &mut impl CoreStore,
+) -> ImmuxResult<Option<BTreeSet<UnitId>>> {
+    match condition {
+        SelectCondition::Id(id) => {
+            let mut ids = BTreeSet::new();
+            ids.insert(*id);
+            return Ok(Some(ids));
+        }
+        SelectCondition::NameProperty(name, property) => {
+            if !indexed_names.as_slice().contains(name) {
+                return Ok(None);
+            }
+            let key = get_store_key_of_indexed_id_list(grouping, name, property);
+            match get_id_list(key, height, core)? {
+                // No unit had the property
+                None => return Ok(Some(BTreeSet::new())),
+                Some(id_list) => return Ok(Some(id_list.into_iter().collect())),
+            }
+        }
+        SelectCondition::And(operands) => {
+            let mut result: Option<BTreeSet<UnitId>> = None;
+            for operand in operands {
+                match get_indexed_ids(grouping, operand, indexed_names, height, core)? {
+                    None => return Ok(None),
+                    Some(ids) => {
+                        result = match result {
+                            None => Some(ids),
+                            Some(existing) => Some(existing.intersection(&ids).cloned().collect()),
+                        }
+                    }
+                }
+            }
+            return Ok(result);
+        }
+        SelectCondition::Or(operands) => {
+            let mut result = BTreeSet::new();
+            for operand in operands {
+                match get_indexed_ids(grouping, operand, indexed_names, height, core)? {
+                    None => return Ok(None),
+                    Some(ids) => result.extend(ids),
+                }
+            }
+            return Ok(Some(result));
+        }
+        _ => match get_property_range(condition) {
+            Some((name, range)) => {
+                if !indexed_names.as_slice().contains(name) {
+                    return Ok(None);
+                }
+                let id_lists = get_indexed_id_lists_in_range(grouping, name, &range, height, core)?;
+                return Ok(Some(
+                    id_lists.into_iter().flat_map(|id_list| id_list).collect(),
+                ));
+            }
+            None => return Ok(None),
+        },
+    }
+}
+
+/// Compiles the JavaScript predicates in the condition, so malformed code is reported even when
+/// no unit gets checked against it
+fn compile_predicates(
+    condition: &SelectCondition,
+    predicates: &mut HashMap<String, JsPredicate>,
+) -> ImmuxResult<()> {
+    match condition {
+        SelectCondition::JSCode(code) => {
+            if !predicates.contains_key(code) {
+                predicates.insert(code.to_owned(), JsPredicate::compile(code)?);
+            }
+        }
+        SelectCondition::And(operands) | SelectCondition::Or(operands) => {
+            for operand in operands {
+                compile_predicates(operand, predicates)?;
+            }
+        }
+        SelectCondition::Not(operand) => compile_predicates(operand, predicates)?,
+        _ => {}
+    }
+    return Ok(());
+}
+
+/// Units matching a compound condition or a JavaScript predicate, ordered by id when the indexes
+/// answer it.
+///
+/// The indexed operands of a top-level And narrow the candidates down, so only the other operands
+/// are checked against the units' content.
+fn select_compound(
+    grouping: &GroupingLabel,
+    condition: &SelectCondition,
+    height: Option<ChainHeight>,
+    core: &mut impl CoreStore,
+) -> ImmuxResult<Vec<Unit>> {
+    let mut predicates = HashMap::new();
+    compile_predicates(condition, &mut predicates)?;
+    let indexed_names = if is_index_layout_current(height, core)? {
+        get_indexed_names_list_at_height(grouping, height, core)?
+            .unwrap_or(PropertyNameList::new(vec![]))
+    } else {
+        PropertyNameList::new(vec![])
+    };
+
+    let (candidates, unindexed_operands): (Vec<Unit>, Vec<&SelectCondition>) = match condition {
+        SelectCondition::And(operands) => {
+            let mut candidate_ids: Option<BTreeSet<UnitId>> = None;
+            let mut unindexed_operands = Vec::new();
+            for operand in operands {
+                match get_indexed_ids(grouping, operand, &indexed_names, height, core)? {
+                    None => unindexed_operands.push(operand),
+                    Some(ids) => {
+                        candidate_ids = match candidate_ids {
+                            None => Some(ids),
+                            Some(existing) => Some(existing.intersection(&ids).cloned().collect()),
+                        }
+                    }
+                }
+            }
+            let candidates = match candidate_ids {
+                None => get_all_in_grouping(grouping, height, core)?,
+                Some(ids) => get_units_of_ids(grouping, ids, height, core)?,
+            };
+            (candidates, unindexed_operands)
+        }
+        _ => match get_indexed_ids(grouping, condition, &indexed_names, height, core)? {
+            Some(ids) => (get_units_of_ids(grouping, ids, height, core)?, vec![]),
+            None => (
+                get_all_in_grouping(grouping, height, core)?,
+                vec![condition],
+            ),
+        },
+    };
+
+    let mut result = Vec::with_capacity(candidates.len());
+    for unit in candidates {
+        let mut is_matching = true;
+        for operand in &unindexed_operands {
+            if !is_match(operand, &unit, &mut predicates)? {
+                is_matching = false;
+                break;
+            }
+        }
+        if is_matching {
+            result.push(unit);
+        }
+    }
+    return Ok(result);
+}
+
+/// Units whose property `name` equals `property`, found by filtering the whole grouping
+fn scan_by_name_property(
+    grouping: &GroupingLabel,
+    name: &PropertyName,
+    property: &UnitContent,
+    height: Option<ChainHeight>,
+    core: &mut impl CoreStore,
+) -> ImmuxResult<Vec<Unit>> {
+    let all_units = get_all_in_grouping(grouping, height, core)?;
+    let proper_units = all_units.into_iter().filter(|unit| match &unit.content {
+        UnitContent::JsonString(s) => match serde_json::from_str::<JsonValue>(s) {
+            Err(_) => return false,
+            Ok(json) => {
+                let key = name.to_string();
+                match json.get(key) {
+                    None => return false,
+                    Some(value) => return property == value,
+                }
+            }
+        },
+        _ => false,
+    });
+    return Ok(proper_units.collect());
+}
+
+/// Every unit matching the condition, in the order the condition yields them
+fn select_units(
+    grouping: &GroupingLabel,
+    condition: &SelectCondition,
+    height: Option<ChainHeight>,
+    core: &mut impl CoreStore,
+) -> ImmuxResult<Vec<Unit>> {
+    match condition {
+        SelectCondition::UnconditionalMatch => get_all_in_grouping(grouping, height, core),
         SelectCondition::Id(id) => {
-            let key = StoreKey::build(&select.grouping, id.to_owned());
+            let key = StoreKey::build(grouping, id.to_owned());
             let instruction = Instruction::DataAccess(DataInstruction::Read(
-                DataReadInstruction::GetOne(GetOneInstruction { key, height: None }),
+                DataReadInstruction::GetOne(GetOneInstruction {
+                    key,
+                    height: height,
+                }),
             ));
             match core.execute(&instruction) {
+                Err(ImmuxError::VKV(VkvError::CannotFindSuitableVersion)) => {
+                    // The unit did not exist yet at the requested height
+                    Err(ExecutorError::CannotFindId(*id).into())
+                }
                 Err(error) => return Err(error),
                 Ok(Answer::DataAccess(DataAnswer::Read(DataReadAnswer::GetOneOk(answer)))) => {
                     match answer.value.inner() {
                         None => Err(ExecutorError::CannotFindId(*id).into()),
                         Some(data) => {
                             let content = UnitContent::parse_data(data)?;
-                            let units = vec![Unit { id: *id, content }];
-                            Ok(Outcome::Select(SelectOutcome { units }))
+                            Ok(vec![Unit { id: *id, content }])
                         }
                     }
                 }
@@ -75,38 +688,23 @@ pub fn execute_select(select: SelectCommand, core: &mut impl CoreStore) -> Immux
             }
         }
         SelectCondition::NameProperty(name, property) => {
-            let grouping = &select.grouping;
-
+            if !is_index_layout_current(height, core)? {
+                return scan_by_name_property(grouping, name, property, height, core);
+            }
             let units: Vec<Unit> = {
-                let mut result: Vec<Unit> = Vec::new();
+                let result: Vec<Unit>;
                 let get_indexed_id_list = Instruction::DataAccess(DataInstruction::Read(
                     DataReadInstruction::GetOne(GetOneInstruction {
                         key: get_store_key_of_indexed_id_list(grouping, name, property),
-                        height: None,
+                        height: height,
                     }),
                 ));
 
                 match core.execute(&get_indexed_id_list) {
-                    Err(ImmuxError::VKV(VkvError::MissingJournal(_error))) => {
-                        // No index for the name-property
-                        let all_units = get_all_in_grouping(&select.grouping, core)?;
-                        let proper_units =
-                            all_units.into_iter().filter(|unit| match &unit.content {
-                                UnitContent::JsonString(s) => {
-                                    match serde_json::from_str::<JsonValue>(&s) {
-                                        Err(_) => return false,
-                                        Ok(json) => {
-                                            let key = name.to_string();
-                                            match json.get(key) {
-                                                None => return false,
-                                                Some(value) => return property == value,
-                                            }
-                                        }
-                                    }
-                                }
-                                _ => false,
-                            });
-                        result = proper_units.collect();
+                    Err(ImmuxError::VKV(VkvError::MissingJournal(_)))
+                    | Err(ImmuxError::VKV(VkvError::CannotFindSuitableVersion)) => {
+                        // No index for the name-property (at the requested height)
+                        result = scan_by_name_property(grouping, name, property, height, core)?;
                     }
                     Err(error) => {
                         return Err(error.into());
@@ -116,36 +714,7 @@ pub fn execute_select(select: SelectCommand, core: &mut impl CoreStore) -> Immux
                             None => return Err(ExecutorError::NoneReverseIndex.into()),
                             Some(data) => {
                                 let id_list = IdList::try_from(data.as_slice())?;
-                                for id in id_list {
-                                    let get_data = Instruction::DataAccess(DataInstruction::Read(
-                                        DataReadInstruction::GetOne(GetOneInstruction {
-                                            key: StoreKey::build(grouping, id),
-                                            height: None,
-                                        }),
-                                    ));
-
-                                    match core.execute(&get_data) {
-                                        Err(error) => return Err(error),
-                                        Ok(Answer::DataAccess(DataAnswer::Read(
-                                            DataReadAnswer::GetOneOk(answer),
-                                        ))) => {
-                                            let _content = match answer.value.inner() {
-                                                None => (),
-                                                Some(data) => {
-                                                    let content = UnitContent::parse_data(data)?;
-                                                    let unit = Unit { id, content };
-                                                    result.push(unit);
-                                                }
-                                            };
-                                        }
-                                        Ok(answer) => {
-                                            return Err(ExecutorError::UnexpectedAnswerType(
-                                                answer,
-                                            )
-                                            .into());
-                                        }
-                                    };
-                                }
+                                result = get_units_of_ids(grouping, id_list, height, core)?;
                             }
                         }
                     }
@@ -154,15 +723,230 @@ pub fn execute_select(select: SelectCommand, core: &mut impl CoreStore) -> Immux
                 result
             };
 
-            Ok(Outcome::Select(SelectOutcome { units }))
+            Ok(units)
+        }
+        SelectCondition::LessThan(..)
+        | SelectCondition::LessThanOrEqual(..)
+        | SelectCondition::GreaterThan(..)
+        | SelectCondition::GreaterThanOrEqual(..)
+        | SelectCondition::Between(..) => match get_property_range(condition) {
+            None => Err(ExecutorError::UnimplementedSelectCondition(condition.to_owned()).into()),
+            Some((name, range)) => select_in_range(grouping, name, &range, height, core),
+        },
+        SelectCondition::And(_)
+        | SelectCondition::Or(_)
+        | SelectCondition::Not(_)
+        | SelectCondition::JSCode(_) => select_compound(grouping, condition, height, core),
+    }
+}
+
+/// Units without the property come first in ascending order. The sort is stable, so units with
+/// equal properties keep the order the condition yields them in.
+fn sort_units(units: &mut Vec<Unit>, sort: &SelectSort) {
+    match sort.order {
+        SortOrder::Ascending => {
+            units.sort_by_cached_key(|unit| get_sortable_bytes_of_property(unit, &sort.name))
+        }
+        SortOrder::Descending => units
+            .sort_by_cached_key(|unit| Reverse(get_sortable_bytes_of_property(unit, &sort.name))),
+    }
+}
+
+/// Where a page ended, so the next one resumes after its last unit
+struct Continuation {
+    height: ChainHeight,
+    query_hash: RecordHash,
+    last_id: UnitId,
+}
+
+const CONTINUATION_BYTES: usize = 8 + RECORD_HASH_LENGTH + UNIT_ID_BYTES;
+
+impl Continuation {
+    fn encode(&self) -> String {
+        let mut bytes = Vec::with_capacity(CONTINUATION_BYTES);
+        bytes.extend_from_slice(&u64_to_u8_array(self.height.as_u64()));
+        bytes.extend_from_slice(self.query_hash.as_bytes());
+        bytes.extend(self.last_id.marshal());
+        return bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+    }
+
+    fn decode(token: &str) -> ImmuxResult<Self> {
+        let invalid = || ExecutorError::InvalidContinuation(token.to_owned());
+        if token.len() != CONTINUATION_BYTES * 2 || !token.is_ascii() {
+            return Err(invalid().into());
+        }
+        let mut bytes = [0u8; CONTINUATION_BYTES];
+        for (index, byte) in bytes.iter_mut().enumerate() {
+            match u8::from_str_radix(&token[index * 2..index * 2 + 2], 16) {
+                Err(_error) => return Err(invalid().into()),
+                Ok(parsed) => *byte = parsed,
+            }
+        }
+        let mut height = [0u8; 8];
+        height.copy_from_slice(&bytes[..8]);
+        let hash_end = 8 + RECORD_HASH_LENGTH;
+        let query_hash = RecordHash::parse(&bytes[8..hash_end]).map_err(|_error| invalid())?;
+        let last_id = UnitId::parse(&bytes[hash_end..]).map_err(|_error| invalid())?;
+        return Ok(Continuation {
+            height: ChainHeight::new(u8_array_to_u64(&height)),
+            query_hash,
+            last_id,
+        });
+    }
+}
+
+/// Identifies the grouping, condition and sort of a select, so a continuation token is only taken
+/// by the query that issued it
+fn hash_select_query(select: &SelectCommand) -> ImmuxResult<RecordHash> {
+    let query = (&select.grouping, &select.condition, &select.sort);
+    let bytes = serialize(&query).map_err(|_error| ExecutorError::CannotSerialize)?;
+    return Ok(RecordHash::digest(&[&bytes]));
+}
+
+/// The order a select reads its units in, when it can read them straight from a key range
+enum StreamedOrder<'a> {
+    // By id, from the grouping's unit keys
+    Grouping,
+    // By property, from the id lists of an indexed name
+    IndexedRange(&'a PropertyName, PropertyRange),
+}
+
+fn get_streamed_order<'a>(
+    select: &'a SelectCommand,
+    height: Option<ChainHeight>,
+    core: &mut impl CoreStore,
+) -> ImmuxResult<Option<StreamedOrder<'a>>> {
+    if let (SelectCondition::UnconditionalMatch, None) = (&select.condition, &select.sort) {
+        return Ok(Some(StreamedOrder::Grouping));
+    }
+    match get_property_range(&select.condition) {
+        Some((name, range)) if range.get_type_prefix().is_some() => {
+            let is_in_index_order = match &select.sort {
+                None => true,
+                Some(sort) => match sort.order {
+                    SortOrder::Ascending => sort.name == *name,
+                    SortOrder::Descending => false,
+                },
+            };
+            if is_in_index_order && is_indexed(&select.grouping, name, height, core)? {
+                return Ok(Some(StreamedOrder::IndexedRange(name, range)));
+            }
+            return Ok(None);
+        }
+        _ => return Ok(None),
+    }
+}
+
+/// Matching units after the unit `after`, in the order of the select, of which at least `limit`
+/// are returned unless fewer remain
+fn select_units_after(
+    select: &SelectCommand,
+    after: Option<UnitId>,
+    limit: usize,
+    height: Option<ChainHeight>,
+    core: &mut impl CoreStore,
+) -> ImmuxResult<Vec<Unit>> {
+    let grouping = &select.grouping;
+    // Only a continuation token gives a unit to resume after
+    let invalid =
+        || ExecutorError::InvalidContinuation(select.continuation.clone().unwrap_or_default());
+    match get_streamed_order(select, height, core)? {
+        Some(StreamedOrder::Grouping) => {
+            return get_units_after(grouping, after, limit, height, core);
+        }
+        Some(StreamedOrder::IndexedRange(name, range)) => {
+            let after = match after {
+                None => None,
+                Some(last_id) => {
+                    let last_unit = get_units_of_ids(grouping, vec![last_id], height, core)?
+                        .pop()
+                        .ok_or_else(invalid)?;
+                    let property_bytes =
+                        get_sortable_bytes_of_property(&last_unit, name).ok_or_else(invalid)?;
+                    Some((property_bytes, last_id))
+                }
+            };
+            return stream_indexed_range(grouping, name, &range, after, limit, height, core);
+        }
+        None => {
+            let mut units = select_units(grouping, &select.condition, height, core)?;
+            if let Some(sort) = &select.sort {
+                sort_units(&mut units, sort);
+            }
+            match after {
+                None => return Ok(units),
+                Some(last_id) => match units.iter().position(|unit| unit.id == last_id) {
+                    None => return Err(invalid().into()),
+                    Some(position) => return Ok(units.split_off(position + 1)),
+                },
+            }
+        }
+    }
+}
+
+/// Selects one page of the matching units, sorted if requested.
+///
+/// When units remain after the page, the outcome carries a continuation token. It pins the height
+/// this page was read at and the query it answers, and the next page resumes after the last unit
+/// of this one. Unsorted queries on the whole grouping, and range conditions on an indexed name
+/// sorted by nothing else, read only as far as the page reaches.
+pub fn execute_select(select: SelectCommand, core: &mut impl CoreStore) -> ImmuxResult<Outcome> {
+    let query_hash = hash_select_query(&select)?;
+    let (height, after, skip) = match &select.continuation {
+        None => (
+            resolve_optional_height(&select.height, core)?,
+            None,
+            select.skip,
+        ),
+        Some(token) => {
+            let continuation = Continuation::decode(token)?;
+            if continuation.query_hash != query_hash {
+                return Err(ExecutorError::InvalidContinuation(token.to_owned()).into());
+            }
+            (Some(continuation.height), Some(continuation.last_id), 0)
+        }
+    };
+
+    // One unit past the page tells whether another page follows
+    let wanted = match select.limit {
+        None => usize::MAX,
+        Some(limit) => skip.saturating_add(limit).saturating_add(1),
+    };
+    let units = select_units_after(&select, after, wanted, height, core)?;
+    let mut page: Vec<Unit> = match select.limit {
+        None => units.into_iter().skip(skip).collect(),
+        Some(limit) => units.into_iter().skip(skip).take(limit + 1).collect(),
+    };
+    let has_more = match select.limit {
+        None => false,
+        Some(limit) => page.len() > limit,
+    };
+    if has_more {
+        page.pop();
+    }
+    let continuation = match page.last() {
+        Some(last_unit) if has_more => {
+            let height = match height {
+                None => get_current_height(core)?,
+                Some(height) => height,
+            };
+            let continuation = Continuation {
+                height,
+                query_hash,
+                last_id: last_unit.id,
+            };
+            Some(continuation.encode())
         }
-        SelectCondition::JSCode(js_code) => {
-            return Err(
-                ExecutorError::UnimplementedSelectCondition(SelectCondition::JSCode(
-                    js_code.to_owned(),
-                ))
-                .into(),
-            );
+        _ => None,
+    };
+    if let Some(projection) = &select.projection {
+        let projection = Projection::new(projection);
+        for unit in page.iter_mut() {
+            unit.content = projection.apply(&unit.content);
         }
     }
+    return Ok(Outcome::Select(SelectOutcome {
+        units: page,
+        continuation,
+    }));
 }
@@ -0,0 +1,58 @@
+use crate::declarations::basics::StoreKeyFragment;
+use crate::declarations::commands::{Outcome, SetRetentionCommand, SetRetentionOutcome};
+use crate::declarations::errors::ImmuxResult;
+use crate::executor::errors::ExecutorError;
+use crate::storage::core::CoreStore;
+use crate::storage::instructions::{Answer, DBSystemAnswer, SetRetentionInstruction};
+
+pub fn execute_set_retention(
+    set_retention: SetRetentionCommand,
+    core: &mut impl CoreStore,
+) -> ImmuxResult<Outcome> {
+    let instruction = SetRetentionInstruction {
+        scope: set_retention
+            .grouping
+            .map(|grouping| StoreKeyFragment::from(grouping.marshal())),
+        policy: set_retention.policy,
+    };
+    match core.execute(&instruction.into()) {
+        Err(error) => return Err(error),
+        Ok(Answer::DBSystem(DBSystemAnswer::SetRetentionOk(_answer))) => {
+            return Ok(Outcome::SetRetention(SetRetentionOutcome {}));
+        }
+        Ok(answer) => return Err(ExecutorError::UnexpectedAnswerType(answer).into()),
+    }
+}
+
+#[cfg(test)]
+mod set_retention_executor_tests {
+    use crate::declarations::basics::{GroupingLabel, StoreKeyFragment};
+    use crate::declarations::commands::{Outcome, SetRetentionCommand};
+    use crate::executor::set_retention_executor::execute_set_retention;
+    use crate::executor::tests::FixtureCore;
+    use crate::storage::instructions::{
+        DBSystemInstruction, Instruction, RetentionPolicy, SetRetentionOkAnswer,
+    };
+
+    #[test]
+    fn test_set_retention() {
+        let grouping = GroupingLabel::from("grouping");
+        let expected_scope = StoreKeyFragment::from(grouping.marshal());
+        let mut core = FixtureCore::new(Box::new(move |instruction| match instruction {
+            Instruction::DBSystem(DBSystemInstruction::SetRetention(set_retention)) => {
+                assert_eq!(set_retention.scope, Some(expected_scope.clone()));
+                assert_eq!(set_retention.policy, Some(RetentionPolicy::KeepHeights(3)));
+                Ok(SetRetentionOkAnswer {}.into())
+            }
+            _ => panic!("Unimplemented fixture instruction {:?}", instruction),
+        }));
+        let command = SetRetentionCommand {
+            grouping: Some(grouping),
+            policy: Some(RetentionPolicy::KeepHeights(3)),
+        };
+        match execute_set_retention(command, &mut core).unwrap() {
+            Outcome::SetRetention(_outcome) => {}
+            outcome => panic!("Unexpected outcome {:?}", outcome),
+        }
+    }
+}
@@ -0,0 +1,20 @@
+use crate::declarations::errors::ImmuxResult;
+use crate::executor::errors::ExecutorError;
+use crate::storage::core::CoreStore;
+use crate::storage::instructions::{
+    Answer, DBSystemAnswer, DBSystemInstruction, Instruction, ReadNamespaceInstruction,
+    StoreNamespace,
+};
+
+pub fn get_current_namespace(core: &mut impl CoreStore) -> ImmuxResult<StoreNamespace> {
+    let read_namespace = Instruction::DBSystem(DBSystemInstruction::ReadNamespace(
+        ReadNamespaceInstruction {},
+    ));
+    match core.execute(&read_namespace) {
+        Err(error) => return Err(error),
+        Ok(Answer::DBSystem(DBSystemAnswer::ReadNamespaceOk(answer))) => {
+            return Ok(answer.namespace)
+        }
+        Ok(answer) => return Err(ExecutorError::UnexpectedAnswerType(answer).into()),
+    }
+}
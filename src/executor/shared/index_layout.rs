@@ -0,0 +1,192 @@
+use std::collections::BTreeMap;
+
+use serde_json::Value as JsonValue;
+
+use crate::config::KVKeySigil;
+use crate::declarations::basics::unit_content::ContentTypePrefix;
+use crate::declarations::basics::{
+    GroupingLabel, IdList, PropertyNameList, StoreKey, StoreKeyFragment, StoreValue, UnitContent,
+};
+use crate::declarations::errors::{ImmuxError, ImmuxResult};
+use crate::executor::errors::ExecutorError;
+use crate::executor::shared::{
+    get_all_indexed_names_lists, get_store_key_of_indexed_id_list,
+    get_store_key_prefix_of_indexed_id_lists, get_unit_specifier_of_key, ReverseIndex,
+};
+use crate::storage::core::CoreStore;
+use crate::storage::instructions::{
+    Answer, DataAnswer, DataInstruction, DataReadAnswer, DataReadInstruction, DataWriteInstruction,
+    GetManyInstruction, GetManyTargetSpec, GetOneInstruction, Instruction, SetManyInstruction,
+    SetTargetSpec,
+};
+use crate::storage::vkv::{ChainHeight, VkvError};
+use crate::utils::{u64_to_u8_array, u8_array_to_u64};
+
+// Index keys held the marshalled properties, whose numbers do not sort in numeric order
+const MARSHALLED_INDEX_LAYOUT: u64 = 0;
+// Index keys hold the sortable bytes of the properties
+const SORTABLE_INDEX_LAYOUT: u64 = 1;
+const CURRENT_INDEX_LAYOUT: u64 = SORTABLE_INDEX_LAYOUT;
+
+const INDEX_LAYOUT_FIELD: &[u8] = b"index_layout";
+
+fn get_index_layout_store_key() -> StoreKey {
+    let mut key_bytes = Vec::new();
+    key_bytes.push(KVKeySigil::ChainInfo as u8);
+    key_bytes.extend_from_slice(INDEX_LAYOUT_FIELD);
+    StoreKey::from(key_bytes)
+}
+
+/// Marks the chain's index keys as being in the current layout. It is written along with every
+/// indexed names list, so a chain with indexes but without the mark predates the layout.
+pub fn get_index_layout_target() -> SetTargetSpec {
+    SetTargetSpec {
+        key: get_index_layout_store_key(),
+        value: StoreValue::new(Some(u64_to_u8_array(CURRENT_INDEX_LAYOUT).to_vec())),
+    }
+}
+
+fn get_index_layout(height: Option<ChainHeight>, core: &mut impl CoreStore) -> ImmuxResult<u64> {
+    let instruction = Instruction::DataAccess(DataInstruction::Read(DataReadInstruction::GetOne(
+        GetOneInstruction {
+            key: get_index_layout_store_key(),
+            height,
+        },
+    )));
+    match core.execute(&instruction) {
+        Err(ImmuxError::VKV(VkvError::MissingJournal(_)))
+        | Err(ImmuxError::VKV(VkvError::CannotFindSuitableVersion)) => {
+            return Ok(MARSHALLED_INDEX_LAYOUT)
+        }
+        Err(error) => return Err(error),
+        Ok(Answer::DataAccess(DataAnswer::Read(DataReadAnswer::GetOneOk(answer)))) => {
+            match answer.value.inner() {
+                Some(data) if data.len() == 8 => {
+                    let mut bytes = [0u8; 8];
+                    bytes.copy_from_slice(data);
+                    return Ok(u8_array_to_u64(&bytes));
+                }
+                _ => return Ok(MARSHALLED_INDEX_LAYOUT),
+            }
+        }
+        Ok(answer) => return Err(ExecutorError::UnexpectedAnswerType(answer).into()),
+    }
+}
+
+/// Whether the index keys as of `height` are in the current layout, so selects can read them.
+/// Heights before a chain was migrated have to be answered from the units.
+pub fn is_index_layout_current(
+    height: Option<ChainHeight>,
+    core: &mut impl CoreStore,
+) -> ImmuxResult<bool> {
+    return Ok(get_index_layout(height, core)? >= CURRENT_INDEX_LAYOUT);
+}
+
+/// The id lists of numbers indexed under the names, from the current units of the grouping
+fn get_number_id_list_updates(
+    grouping: &GroupingLabel,
+    names: &PropertyNameList,
+    core: &mut impl CoreStore,
+) -> ImmuxResult<Vec<SetTargetSpec>> {
+    let get_many = |targets: GetManyTargetSpec| {
+        Instruction::DataAccess(DataInstruction::Read(DataReadInstruction::GetMany(
+            GetManyInstruction {
+                height: None,
+                targets,
+            },
+        )))
+    };
+    // Old and new keys of a number have the same length and type prefix, so the keys of every
+    // number are emptied, and the current lists written over them
+    let empty_list = StoreValue::new(Some(IdList::new(vec![]).marshal()));
+    let mut updates: BTreeMap<Vec<u8>, StoreValue> = BTreeMap::new();
+    for name in names.as_slice() {
+        let mut prefix = get_store_key_prefix_of_indexed_id_lists(grouping, name)
+            .as_slice()
+            .to_vec();
+        prefix.push(ContentTypePrefix::Float64 as u8);
+        let targets = GetManyTargetSpec::KeyPrefix(StoreKeyFragment::new(&prefix));
+        match core.execute(&get_many(targets))? {
+            Answer::DataAccess(DataAnswer::Read(DataReadAnswer::GetManyOk(answer))) => {
+                for (key, value) in answer.data {
+                    if value.inner().is_some() {
+                        updates.insert(key.as_slice().to_vec(), empty_list.clone());
+                    }
+                }
+            }
+            answer => return Err(ExecutorError::UnexpectedAnswerType(answer).into()),
+        }
+    }
+
+    let mut index = ReverseIndex::new();
+    let targets = GetManyTargetSpec::KeyPrefix(grouping.marshal().into());
+    match core.execute(&get_many(targets))? {
+        Answer::DataAccess(DataAnswer::Read(DataReadAnswer::GetManyOk(answer))) => {
+            for (key, value) in answer.data {
+                let id = match get_unit_specifier_of_key(&StoreKey::from(key)) {
+                    None => continue,
+                    Some(specifier) => specifier.get_id(),
+                };
+                let data = match value.inner() {
+                    None => continue,
+                    Some(data) => data,
+                };
+                let json = match UnitContent::parse_data(data)? {
+                    UnitContent::JsonString(json_string) => {
+                        match serde_json::from_str::<JsonValue>(&json_string) {
+                            Err(_error) => continue,
+                            Ok(json) => json,
+                        }
+                    }
+                    _ => continue,
+                };
+                for name in names.as_slice() {
+                    index.index_new_json(id, &json, name)?;
+                }
+            }
+        }
+        answer => return Err(ExecutorError::UnexpectedAnswerType(answer).into()),
+    }
+    for ((name, property_bytes), ids) in index {
+        let property = UnitContent::parse_data(&property_bytes)?;
+        if let UnitContent::Float64(_) = property {
+            let key = get_store_key_of_indexed_id_list(grouping, &name, &property);
+            updates.insert(
+                key.as_slice().to_vec(),
+                StoreValue::new(Some(ids.marshal())),
+            );
+        }
+    }
+
+    let targets = updates
+        .into_iter()
+        .map(|(key, value)| SetTargetSpec {
+            key: StoreKey::new(&key),
+            value,
+        })
+        .collect();
+    return Ok(targets);
+}
+
+/// Rewrites the index keys of a chain opened in an older layout, in one record at the top of
+/// the chain. Earlier heights keep their keys, which selects there do not read.
+pub fn migrate_index_layout(core: &mut impl CoreStore) -> ImmuxResult<()> {
+    if is_index_layout_current(None, core)? {
+        return Ok(());
+    }
+    let indexed_names_lists = get_all_indexed_names_lists(core)?;
+    // Chains without indexes get the mark with their first one
+    if indexed_names_lists.is_empty() {
+        return Ok(());
+    }
+    let mut targets = Vec::new();
+    for (grouping, names) in indexed_names_lists {
+        targets.extend(get_number_id_list_updates(&grouping, &names, core)?);
+    }
+    targets.push(get_index_layout_target());
+    let instruction = Instruction::DataAccess(DataInstruction::Write(
+        DataWriteInstruction::SetMany(SetManyInstruction { targets }),
+    ));
+    core.execute(&instruction)?;
+    return Ok(());
+}
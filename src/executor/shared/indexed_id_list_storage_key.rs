@@ -1,24 +1,62 @@
 use crate::config::KVKeySigil;
 use crate::declarations::basics::property_names::PropertyName;
-use crate::declarations::basics::{GroupingLabel, StoreKey, UnitContent};
+use crate::declarations::basics::unit_content::ContentTypePrefix;
+use crate::declarations::basics::{GroupingLabel, StoreKey, StoreKeyFragment, UnitContent};
+use crate::utils::{bool_to_u8, f64_to_sortable_u8_array};
 
-pub fn get_store_key_of_indexed_id_list(
+/// The bytes of an indexed property, which compare in the order of the properties they encode
+/// when both are strings, booleans or numbers.
+///
+/// The content type prefix comes first, so properties of one type share a key prefix.
+/// Other types keep their marshalled form and only support equality.
+pub fn get_sortable_property_bytes(property: &UnitContent) -> Vec<u8> {
+    let mut result = Vec::new();
+    match property {
+        UnitContent::String(string) => {
+            result.push(ContentTypePrefix::String as u8);
+            result.extend_from_slice(string.as_bytes());
+        }
+        UnitContent::Bool(boolean) => {
+            result.push(ContentTypePrefix::Boolean as u8);
+            result.push(bool_to_u8(*boolean));
+        }
+        UnitContent::Float64(number_f64) => {
+            result.push(ContentTypePrefix::Float64 as u8);
+            result.extend_from_slice(&f64_to_sortable_u8_array(*number_f64));
+        }
+        _ => result.extend(property.marshal()),
+    }
+    return result;
+}
+
+/// The prefix shared by the id lists of every property indexed under `name`
+pub fn get_store_key_prefix_of_indexed_id_lists(
     grouping: &GroupingLabel,
     name: &PropertyName,
-    property: &UnitContent,
-) -> StoreKey {
+) -> StoreKeyFragment {
     let mut key_bytes: Vec<u8> = Vec::new();
     key_bytes.push(KVKeySigil::ReverseIndexIdList as u8);
     key_bytes.extend(grouping.marshal());
     key_bytes.extend(name.marshal());
-    key_bytes.extend(property.marshal());
+    return StoreKeyFragment::new(&key_bytes);
+}
+
+pub fn get_store_key_of_indexed_id_list(
+    grouping: &GroupingLabel,
+    name: &PropertyName,
+    property: &UnitContent,
+) -> StoreKey {
+    let mut key_bytes: Vec<u8> = Vec::new();
+    key_bytes
+        .extend_from_slice(get_store_key_prefix_of_indexed_id_lists(grouping, name).as_slice());
+    key_bytes.extend(get_sortable_property_bytes(property));
     return StoreKey::new(&key_bytes);
 }
 
 #[cfg(test)]
 mod indexed_id_list_key_tests {
     use crate::declarations::basics::{GroupingLabel, PropertyName, UnitContent};
-    use crate::executor::shared::get_store_key_of_indexed_id_list;
+    use crate::executor::shared::{get_sortable_property_bytes, get_store_key_of_indexed_id_list};
 
     #[test]
     fn test_key_bytes() {
@@ -34,4 +72,33 @@ mod indexed_id_list_key_tests {
         ];
         assert_eq!(key.as_slice(), &expected)
     }
+
+    #[test]
+    fn test_sortable_property_bytes() {
+        let sorted = vec![
+            vec![
+                UnitContent::String(String::from("")),
+                UnitContent::String(String::from("a")),
+                UnitContent::String(String::from("ab")),
+                UnitContent::String(String::from("b")),
+            ],
+            vec![UnitContent::Bool(false), UnitContent::Bool(true)],
+            vec![
+                UnitContent::Float64(-300.0),
+                UnitContent::Float64(-2.5),
+                UnitContent::Float64(0.0),
+                UnitContent::Float64(0.5),
+                UnitContent::Float64(2.0),
+                UnitContent::Float64(300.0),
+            ],
+        ];
+        for properties in sorted {
+            for pair in properties.windows(2) {
+                let smaller = get_sortable_property_bytes(&pair[0]);
+                let larger = get_sortable_property_bytes(&pair[1]);
+                assert!(smaller < larger);
+                assert_eq!(smaller[0], larger[0]);
+            }
+        }
+    }
 }
@@ -2,15 +2,19 @@ use bincode::{deserialize, serialize};
 
 use crate::config::KVKeySigil;
 use crate::declarations::basics::property_names::PropertyNameList;
-use crate::declarations::basics::{GroupingLabel, PropertyNameListError, StoreKey, StoreValue};
+use crate::declarations::basics::{
+    GroupingLabel, PropertyNameListError, StoreKey, StoreKeyFragment, StoreValue,
+};
 use crate::declarations::errors::{ImmuxError, ImmuxResult};
 use crate::executor::errors::ExecutorError;
+use crate::executor::shared::get_index_layout_target;
 use crate::storage::core::CoreStore;
 use crate::storage::instructions::{
     Answer, DataAnswer, DataInstruction, DataReadAnswer, DataReadInstruction, DataWriteInstruction,
-    GetOneInstruction, Instruction, SetManyInstruction, SetTargetSpec,
+    GetManyInstruction, GetManyTargetSpec, GetOneInstruction, Instruction, SetManyInstruction,
+    SetTargetSpec,
 };
-use crate::storage::vkv::VkvError;
+use crate::storage::vkv::{ChainHeight, VkvError};
 
 fn get_indexed_names_list_store_key(grouping: &GroupingLabel) -> StoreKey {
     let mut key_bytes = Vec::new();
@@ -22,13 +26,23 @@ fn get_indexed_names_list_store_key(grouping: &GroupingLabel) -> StoreKey {
 pub fn get_indexed_names_list(
     grouping: &GroupingLabel,
     core: &mut impl CoreStore,
+) -> ImmuxResult<Option<PropertyNameList>> {
+    get_indexed_names_list_at_height(grouping, None, core)
+}
+
+/// The names indexed as of `height`; None reads the latest list
+pub fn get_indexed_names_list_at_height(
+    grouping: &GroupingLabel,
+    height: Option<ChainHeight>,
+    core: &mut impl CoreStore,
 ) -> ImmuxResult<Option<PropertyNameList>> {
     let key = get_indexed_names_list_store_key(grouping);
     let instruction = Instruction::DataAccess(DataInstruction::Read(DataReadInstruction::GetOne(
-        GetOneInstruction { key, height: None },
+        GetOneInstruction { key, height },
     )));
     return match core.execute(&instruction) {
-        Err(ImmuxError::VKV(VkvError::MissingJournal(_))) => Ok(None),
+        Err(ImmuxError::VKV(VkvError::MissingJournal(_)))
+        | Err(ImmuxError::VKV(VkvError::CannotFindSuitableVersion)) => Ok(None),
         Err(error) => Err(error),
         Ok(Answer::DataAccess(DataAnswer::Read(DataReadAnswer::GetOneOk(answer)))) => {
             match answer.value.inner() {
@@ -51,6 +65,43 @@ pub fn get_indexed_names_list_with_empty_fallback(
         .map(|maybe_list| maybe_list.unwrap_or(PropertyNameList::new(vec![])))
 }
 
+/// Every grouping with indexed names, ordered by grouping
+pub fn get_all_indexed_names_lists(
+    core: &mut impl CoreStore,
+) -> ImmuxResult<Vec<(GroupingLabel, PropertyNameList)>> {
+    let prefix = StoreKeyFragment::from(vec![KVKeySigil::GroupingIndexedNames as u8]);
+    let instruction = Instruction::DataAccess(DataInstruction::Read(DataReadInstruction::GetMany(
+        GetManyInstruction {
+            height: None,
+            targets: GetManyTargetSpec::KeyPrefix(prefix),
+        },
+    )));
+    match core.execute(&instruction) {
+        Err(error) => return Err(error),
+        Ok(Answer::DataAccess(DataAnswer::Read(DataReadAnswer::GetManyOk(answer)))) => {
+            let mut result = Vec::new();
+            for (key, value) in answer.data {
+                let key_bytes = key.as_slice();
+                // Units of groupings whose label length matches the sigil share the prefix
+                if key_bytes.len() < 2 || key_bytes.len() != 2 + key_bytes[1] as usize {
+                    continue;
+                }
+                let data = match value.inner() {
+                    None => continue,
+                    Some(data) => data,
+                };
+                match deserialize::<PropertyNameList>(data) {
+                    Err(_error) => continue,
+                    Ok(list) => result.push((GroupingLabel::new(&key_bytes[2..]), list)),
+                }
+            }
+            result.sort_by(|a, b| a.0.as_bytes().cmp(b.0.as_bytes()));
+            return Ok(result);
+        }
+        Ok(answer) => return Err(ExecutorError::UnexpectedAnswerType(answer).into()),
+    }
+}
+
 pub fn set_indexed_names_list(
     grouping: &GroupingLabel,
     indexed_names_list: &PropertyNameList,
@@ -62,10 +113,13 @@ pub fn set_indexed_names_list(
         Ok(data) => {
             let instruction = Instruction::DataAccess(DataInstruction::Write(
                 DataWriteInstruction::SetMany(SetManyInstruction {
-                    targets: vec![SetTargetSpec {
-                        key,
-                        value: StoreValue::new(Some(data)),
-                    }],
+                    targets: vec![
+                        SetTargetSpec {
+                            key,
+                            value: StoreValue::new(Some(data)),
+                        },
+                        get_index_layout_target(),
+                    ],
                 }),
             ));
             match core.execute(&instruction) {
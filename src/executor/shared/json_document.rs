@@ -0,0 +1,26 @@
+use bson::Bson;
+use serde_json::Value as JsonValue;
+
+use crate::declarations::basics::UnitContent;
+
+/// The content as a JSON object, if it is a JSON or BSON document
+pub fn get_json_document(content: &UnitContent) -> Option<JsonValue> {
+    let value = match content {
+        UnitContent::JsonString(json_string) => {
+            match serde_json::from_str::<JsonValue>(json_string) {
+                Err(_error) => return None,
+                Ok(value) => value,
+            }
+        }
+        UnitContent::BsonBytes(bytes) => match bson::decode_document(&mut bytes.as_slice()) {
+            Err(_error) => return None,
+            Ok(document) => JsonValue::from(Bson::Document(document)),
+        },
+        _ => return None,
+    };
+    if value.is_object() {
+        return Some(value);
+    } else {
+        return None;
+    }
+}
@@ -1,9 +1,25 @@
+mod current_namespace;
+mod index_layout;
 mod indexed_id_list_storage_key;
 mod indexed_names_list;
+mod json_document;
+mod projection;
+mod resolve_height;
 mod reverse_index;
+mod unit_specifier_of_key;
 
-pub use indexed_id_list_storage_key::get_store_key_of_indexed_id_list;
+pub use current_namespace::get_current_namespace;
+pub use index_layout::{get_index_layout_target, is_index_layout_current, migrate_index_layout};
+pub use indexed_id_list_storage_key::{
+    get_sortable_property_bytes, get_store_key_of_indexed_id_list,
+    get_store_key_prefix_of_indexed_id_lists,
+};
 pub use indexed_names_list::{
-    get_indexed_names_list, get_indexed_names_list_with_empty_fallback, set_indexed_names_list,
+    get_all_indexed_names_lists, get_indexed_names_list, get_indexed_names_list_at_height,
+    get_indexed_names_list_with_empty_fallback, set_indexed_names_list,
 };
-pub use reverse_index::{ReverseIndex, ReverseIndexError};
+pub use json_document::get_json_document;
+pub use projection::Projection;
+pub use resolve_height::{get_current_height, resolve_height, resolve_optional_height};
+pub use reverse_index::{get_indexed_property, ReverseIndex, ReverseIndexError};
+pub use unit_specifier_of_key::get_unit_specifier_of_key;
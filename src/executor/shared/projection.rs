@@ -0,0 +1,210 @@
+use std::collections::HashMap;
+
+use bson::{Bson, Document};
+use serde_json::{Map as JsonMap, Value as JsonValue};
+
+use crate::declarations::basics::{PropertyNameList, UnitContent};
+
+/// The requested fields of a document, arranged by the segments of their dot-separated paths
+#[derive(Debug, Default)]
+pub struct Projection {
+    // The field is kept as a whole, regardless of any nested paths also requested
+    whole: bool,
+    children: HashMap<String, Projection>,
+}
+
+impl Projection {
+    pub fn new(paths: &PropertyNameList) -> Self {
+        let mut root = Projection::default();
+        for path in paths.as_slice() {
+            let mut node = &mut root;
+            for segment in path.to_string().split('.') {
+                node = node
+                    .children
+                    .entry(segment.to_string())
+                    .or_insert_with(Projection::default);
+            }
+            node.whole = true;
+        }
+        return root;
+    }
+
+    /// The content trimmed to the requested fields, if it is a JSON or BSON document; other
+    /// content is returned as is
+    pub fn apply(&self, content: &UnitContent) -> UnitContent {
+        match content {
+            UnitContent::JsonString(json_string) => {
+                match serde_json::from_str::<JsonValue>(json_string) {
+                    Ok(JsonValue::Object(object)) => {
+                        let projected = JsonValue::Object(self.apply_to_json_object(&object));
+                        return UnitContent::JsonString(projected.to_string());
+                    }
+                    _ => return content.to_owned(),
+                }
+            }
+            UnitContent::BsonBytes(bytes) => {
+                let document = match bson::decode_document(&mut bytes.as_slice()) {
+                    Err(_error) => return content.to_owned(),
+                    Ok(document) => document,
+                };
+                let mut projected_bytes = Vec::new();
+                match bson::encode_document(
+                    &mut projected_bytes,
+                    &self.apply_to_bson_document(&document),
+                ) {
+                    Err(_error) => return content.to_owned(),
+                    Ok(_) => return UnitContent::BsonBytes(projected_bytes),
+                }
+            }
+            _ => return content.to_owned(),
+        }
+    }
+
+    fn apply_to_json_object(
+        &self,
+        object: &JsonMap<String, JsonValue>,
+    ) -> JsonMap<String, JsonValue> {
+        let mut result = JsonMap::new();
+        for (key, value) in object.iter() {
+            if let Some(child) = self.children.get(key) {
+                if let Some(projected) = child.apply_to_json_value(value) {
+                    result.insert(key.to_owned(), projected);
+                }
+            }
+        }
+        return result;
+    }
+
+    // Nested paths reach into each document of an array, dropping the other elements
+    fn apply_to_json_value(&self, value: &JsonValue) -> Option<JsonValue> {
+        if self.whole {
+            return Some(value.to_owned());
+        }
+        match value {
+            JsonValue::Object(object) => {
+                return Some(JsonValue::Object(self.apply_to_json_object(object)));
+            }
+            JsonValue::Array(elements) => {
+                let projected = elements
+                    .iter()
+                    .filter_map(|element| match element {
+                        JsonValue::Object(object) => {
+                            Some(JsonValue::Object(self.apply_to_json_object(object)))
+                        }
+                        _ => None,
+                    })
+                    .collect();
+                return Some(JsonValue::Array(projected));
+            }
+            _ => return None,
+        }
+    }
+
+    fn apply_to_bson_document(&self, document: &Document) -> Document {
+        let mut result = Document::new();
+        for (key, value) in document.iter() {
+            if let Some(child) = self.children.get(key) {
+                if let Some(projected) = child.apply_to_bson_value(value) {
+                    result.insert(key.to_owned(), projected);
+                }
+            }
+        }
+        return result;
+    }
+
+    fn apply_to_bson_value(&self, value: &Bson) -> Option<Bson> {
+        if self.whole {
+            return Some(value.to_owned());
+        }
+        match value {
+            Bson::Document(document) => {
+                return Some(Bson::Document(self.apply_to_bson_document(document)));
+            }
+            Bson::Array(elements) => {
+                let projected = elements
+                    .iter()
+                    .filter_map(|element| match element {
+                        Bson::Document(document) => {
+                            Some(Bson::Document(self.apply_to_bson_document(document)))
+                        }
+                        _ => None,
+                    })
+                    .collect();
+                return Some(Bson::Array(projected));
+            }
+            _ => return None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod projection_tests {
+    use bson::{bson, doc};
+    use serde_json::{json, Value as JsonValue};
+
+    use crate::declarations::basics::{PropertyName, PropertyNameList, UnitContent};
+    use crate::executor::shared::projection::Projection;
+
+    fn get_projection(paths: &[&str]) -> Projection {
+        let names = paths.iter().map(|path| PropertyName::from(*path)).collect();
+        return Projection::new(&PropertyNameList::new(names));
+    }
+
+    fn project_json(projection: &Projection, value: JsonValue) -> JsonValue {
+        match projection.apply(&UnitContent::JsonString(value.to_string())) {
+            UnitContent::JsonString(json_string) => serde_json::from_str(&json_string).unwrap(),
+            content => panic!("Unexpected content {:?}", content),
+        }
+    }
+
+    #[test]
+    fn test_json_projection() {
+        let document = json!({
+            "a": 1,
+            "b": {"c": 2, "d": 3},
+            "e": [{"c": 4, "f": 5}, 6],
+            "g": "seven",
+        });
+        let projection = get_projection(&["a", "b.c", "e.c", "g.h", "missing"]);
+        let expected = json!({"a": 1, "b": {"c": 2}, "e": [{"c": 4}]});
+        assert_eq!(project_json(&projection, document.clone()), expected);
+
+        // A whole field wins over its nested paths
+        let projection = get_projection(&["b.c", "b"]);
+        assert_eq!(
+            project_json(&projection, document),
+            json!({"b": {"c": 2, "d": 3}})
+        );
+    }
+
+    #[test]
+    fn test_bson_projection() {
+        let document = doc! {
+            "_id": 1,
+            "name": "Tom",
+            "address": {"city": "Paris", "street": "Rue"},
+        };
+        let mut bytes = Vec::new();
+        bson::encode_document(&mut bytes, &document).unwrap();
+        let projection = get_projection(&["_id", "address.city"]);
+        match projection.apply(&UnitContent::BsonBytes(bytes)) {
+            UnitContent::BsonBytes(projected) => {
+                let projected = bson::decode_document(&mut projected.as_slice()).unwrap();
+                assert_eq!(projected, doc! {"_id": 1, "address": {"city": "Paris"}});
+            }
+            content => panic!("Unexpected content {:?}", content),
+        }
+    }
+
+    #[test]
+    fn test_non_document_content() {
+        let projection = get_projection(&["a"]);
+        for content in vec![
+            UnitContent::String(String::from("a")),
+            UnitContent::Float64(1.0),
+            UnitContent::JsonString(String::from("[1, 2]")),
+        ] {
+            assert_eq!(projection.apply(&content), content);
+        }
+    }
+}
@@ -0,0 +1,118 @@
+use crate::declarations::commands::HeightSpecifier;
+use crate::declarations::errors::ImmuxResult;
+use crate::executor::errors::ExecutorError;
+use crate::storage::core::CoreStore;
+use crate::storage::instructions::{
+    Answer, DBSystemAnswer, DataAnswer, DataReadAnswer, GetHeightAtTimeInstruction,
+    GetHeightInstruction, ReadTagsInstruction,
+};
+use crate::storage::vkv::ChainHeight;
+
+pub fn resolve_height(
+    specifier: &HeightSpecifier,
+    core: &mut impl CoreStore,
+) -> ImmuxResult<ChainHeight> {
+    match specifier {
+        HeightSpecifier::Height(height) => return Ok(*height),
+        HeightSpecifier::Time(time) => {
+            let instruction = GetHeightAtTimeInstruction { time: *time };
+            match core.execute(&instruction.into()) {
+                Err(error) => return Err(error),
+                Ok(Answer::DataAccess(DataAnswer::Read(DataReadAnswer::GetHeightAtTimeOk(
+                    answer,
+                )))) => return Ok(answer.height),
+                Ok(answer) => return Err(ExecutorError::UnexpectedAnswerType(answer).into()),
+            }
+        }
+        HeightSpecifier::Tag(name) => {
+            let instruction = ReadTagsInstruction {};
+            match core.execute(&instruction.into()) {
+                Err(error) => return Err(error),
+                Ok(Answer::DBSystem(DBSystemAnswer::ReadTagsOk(answer))) => {
+                    match answer.tags.into_iter().find(|tag| &tag.name == name) {
+                        None => return Err(ExecutorError::MissingTag(name.to_owned()).into()),
+                        Some(tag) => return Ok(tag.height),
+                    }
+                }
+                Ok(answer) => return Err(ExecutorError::UnexpectedAnswerType(answer).into()),
+            }
+        }
+    }
+}
+
+pub fn resolve_optional_height(
+    specifier: &Option<HeightSpecifier>,
+    core: &mut impl CoreStore,
+) -> ImmuxResult<Option<ChainHeight>> {
+    match specifier {
+        None => return Ok(None),
+        Some(specifier) => return resolve_height(specifier, core).map(Some),
+    }
+}
+
+/// The height of the last record on the chain
+pub fn get_current_height(core: &mut impl CoreStore) -> ImmuxResult<ChainHeight> {
+    match core.execute(&GetHeightInstruction {}.into()) {
+        Err(error) => return Err(error),
+        Ok(Answer::DataAccess(DataAnswer::Read(DataReadAnswer::GetHeightOk(answer)))) => {
+            return Ok(answer.height)
+        }
+        Ok(answer) => return Err(ExecutorError::UnexpectedAnswerType(answer).into()),
+    }
+}
+
+#[cfg(test)]
+mod resolve_height_tests {
+    use crate::declarations::commands::HeightSpecifier;
+    use crate::executor::shared::{get_current_height, resolve_height};
+    use crate::executor::tests::FixtureCore;
+    use crate::storage::instructions::{
+        ChainTag, DBSystemInstruction, DataInstruction, DataReadInstruction,
+        GetHeightAtTimeOkAnswer, GetHeightOkAnswer, Instruction, ReadTagsOkAnswer,
+    };
+    use crate::storage::vkv::ChainHeight;
+
+    #[test]
+    fn test_resolve_height() {
+        let mut core = FixtureCore::new(Box::new(|instruction| match instruction {
+            Instruction::DataAccess(DataInstruction::Read(
+                DataReadInstruction::GetHeightAtTime(get_height_at_time),
+            )) => Ok(GetHeightAtTimeOkAnswer {
+                height: ChainHeight::new(get_height_at_time.time as u64 / 1000),
+            }
+            .into()),
+            Instruction::DBSystem(DBSystemInstruction::ReadTags(_)) => Ok(ReadTagsOkAnswer {
+                tags: vec![ChainTag {
+                    name: String::from("release"),
+                    height: ChainHeight::new(5),
+                }],
+            }
+            .into()),
+            Instruction::DataAccess(DataInstruction::Read(DataReadInstruction::GetHeight(_))) => {
+                Ok(GetHeightOkAnswer {
+                    height: ChainHeight::new(9),
+                }
+                .into())
+            }
+            _ => panic!("Unimplemented fixture instruction {:?}", instruction),
+        }));
+        let by_height = HeightSpecifier::Height(ChainHeight::new(3));
+        assert_eq!(
+            resolve_height(&by_height, &mut core).unwrap(),
+            ChainHeight::new(3)
+        );
+        let by_time = HeightSpecifier::Time(7000);
+        assert_eq!(
+            resolve_height(&by_time, &mut core).unwrap(),
+            ChainHeight::new(7)
+        );
+        let by_tag = HeightSpecifier::Tag(String::from("release"));
+        assert_eq!(
+            resolve_height(&by_tag, &mut core).unwrap(),
+            ChainHeight::new(5)
+        );
+        let missing_tag = HeightSpecifier::Tag(String::from("missing"));
+        assert!(resolve_height(&missing_tag, &mut core).is_err());
+        assert_eq!(get_current_height(&mut core).unwrap(), ChainHeight::new(9));
+    }
+}
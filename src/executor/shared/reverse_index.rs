@@ -19,6 +19,20 @@ pub enum ReverseIndexError {
 type Name = PropertyName;
 type Property = Vec<u8>;
 
+/// The content a JSON property is indexed as
+pub fn get_indexed_property(json_property: &JsonValue) -> ImmuxResult<UnitContent> {
+    match json_property {
+        JsonValue::String(string) => return Ok(UnitContent::String(string.clone())),
+        JsonValue::Bool(boolean) => return Ok(UnitContent::Bool(*boolean)),
+        JsonValue::Number(number) => match number.as_f64() {
+            Some(num) => return Ok(UnitContent::Float64(num)),
+            None => return Err(ReverseIndexError::UnexpectedNumberType.into()),
+        },
+        JsonValue::Null => return Ok(UnitContent::Nil),
+        _ => return Err(ReverseIndexError::UnimplementedIndexingPropertyType.into()),
+    }
+}
+
 /// {
 ///    age: {
 ///       1: [id1, id2],
@@ -85,22 +99,7 @@ impl ReverseIndex {
             None => return Ok(()),
             // Property does exist (but could be null)
             Some(json_property) => {
-                let property: UnitContent = match &json_property {
-                    JsonValue::String(string) => UnitContent::String(string.clone()),
-                    JsonValue::Bool(boolean) => UnitContent::Bool(*boolean),
-                    JsonValue::Number(number) => {
-                        let data = if let Some(num) = number.as_f64() {
-                            num
-                        } else {
-                            return Err(ReverseIndexError::UnexpectedNumberType.into());
-                        };
-                        UnitContent::Float64(data)
-                    }
-                    JsonValue::Null => UnitContent::Nil,
-                    _ => {
-                        return Err(ReverseIndexError::UnimplementedIndexingPropertyType.into());
-                    }
-                };
+                let property = get_indexed_property(json_property)?;
                 self.add_to_index(target_name, &property.marshal(), id);
                 Ok(())
             }
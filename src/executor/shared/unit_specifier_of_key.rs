@@ -0,0 +1,49 @@
+use std::convert::TryFrom;
+
+use crate::declarations::basics::{StoreKey, UnitSpecifier};
+
+/// Only keys that are exactly a marshaled specifier belong to units; index keys and other
+/// bookkeeping share the key space but are left out
+pub fn get_unit_specifier_of_key(key: &StoreKey) -> Option<UnitSpecifier> {
+    if key.as_slice().is_empty() {
+        return None;
+    }
+    match UnitSpecifier::try_from(key.to_owned()) {
+        Err(_error) => return None,
+        Ok(specifier) => {
+            if &StoreKey::from(specifier.clone()) == key {
+                return Some(specifier);
+            } else {
+                return None;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod unit_specifier_of_key_tests {
+    use crate::declarations::basics::{GroupingLabel, PropertyName, StoreKey, UnitContent, UnitId};
+    use crate::executor::shared::{get_store_key_of_indexed_id_list, get_unit_specifier_of_key};
+
+    #[test]
+    fn test_unit_key() {
+        let grouping = GroupingLabel::from("grouping");
+        let key = StoreKey::build(&grouping, UnitId::new(42));
+        let specifier = get_unit_specifier_of_key(&key).unwrap();
+        assert_eq!(specifier.get_grouping().marshal(), grouping.marshal());
+        assert_eq!(specifier.get_id(), UnitId::new(42));
+    }
+
+    #[test]
+    fn test_other_keys() {
+        let grouping = GroupingLabel::from("grouping");
+        let index_key = get_store_key_of_indexed_id_list(
+            &grouping,
+            &PropertyName::from("name"),
+            &UnitContent::String(String::from("value")),
+        );
+        assert!(get_unit_specifier_of_key(&index_key).is_none());
+        assert!(get_unit_specifier_of_key(&StoreKey::new(&[])).is_none());
+        assert!(get_unit_specifier_of_key(&StoreKey::new(&[1, b'g', 0, 0])).is_none());
+    }
+}
@@ -1,24 +1,38 @@
 #[cfg(test)]
 mod indexing_test {
+    use std::convert::TryFrom;
     use std::vec::IntoIter as VecIntoIter;
 
-    use serde_json::Value as JsonValue;
+    use serde_json::{json, Value as JsonValue};
 
     use immuxdb_dev_utils::reset_db_dir;
 
-    use crate::config::DEFAULT_PERMANENCE_PATH;
+    use crate::config::{KVKeySigil, DEFAULT_PERMANENCE_PATH};
     use crate::declarations::basics::{
-        GroupingLabel, NameProperty, PropertyName, PropertyNameList, UnitContent, UnitId,
+        GroupingLabel, IdList, NameProperty, PropertyName, PropertyNameList, StoreKey, StoreValue,
+        UnitContent, UnitId,
     };
     use crate::declarations::commands::{
-        Command, CreateIndexCommand, InsertCommand, InsertCommandSpec, Outcome, SelectCommand,
-        SelectCondition,
+        Command, CreateIndexCommand, HeightSpecifier, InsertCommand, InsertCommandSpec, Outcome,
+        SelectCommand, SelectCondition, SelectSort, SortOrder,
     };
+    use crate::declarations::errors::{ImmuxError, ImmuxResult};
+    use crate::executor::errors::ExecutorError;
     use crate::executor::execute::execute;
-    use crate::executor::shared::ReverseIndex;
+    use crate::executor::js_sandbox::JsError;
+    use crate::executor::shared::{
+        get_current_height, get_store_key_of_indexed_id_list,
+        get_store_key_prefix_of_indexed_id_lists, is_index_layout_current, migrate_index_layout,
+        ReverseIndex,
+    };
     use crate::storage::core::{CoreStore, ImmuxDBCore};
-    use crate::storage::instructions::StoreNamespace;
+    use crate::storage::instructions::{
+        Answer, DataAnswer, DataInstruction, DataReadAnswer, DataReadInstruction,
+        DataWriteInstruction, GetOneInstruction, Instruction, SetManyInstruction, SetTargetSpec,
+        StoreNamespace,
+    };
     use crate::storage::kv::KeyValueEngine;
+    use crate::storage::vkv::ChainHeight;
 
     type JsonTableRow = (UnitId, String);
 
@@ -212,6 +226,12 @@ mod indexing_test {
         let select_by_name_property = Command::Select(SelectCommand {
             grouping: grouping.to_owned(),
             condition: SelectCondition::NameProperty(name.to_owned(), content.to_owned()),
+            height: None,
+            skip: 0,
+            limit: None,
+            sort: None,
+            continuation: None,
+            projection: None,
         });
 
         match execute(select_by_name_property, core) {
@@ -316,4 +336,670 @@ mod indexing_test {
             r#"{"f64": "ss", "str": false, "bool": 1.0}"#, // keys containing various types
         ]);
     }
+
+    fn select_ids(
+        core: &mut ImmuxDBCore,
+        grouping: &GroupingLabel,
+        condition: SelectCondition,
+    ) -> Vec<UnitId> {
+        let select = Command::Select(SelectCommand {
+            grouping: grouping.to_owned(),
+            condition,
+            height: None,
+            skip: 0,
+            limit: None,
+            sort: None,
+            continuation: None,
+            projection: None,
+        });
+        match execute(select, core) {
+            Err(error) => panic!("Failed to execute select command: {:x?}", error),
+            Ok(Outcome::Select(select_outcome)) => {
+                return select_outcome.units.iter().map(|unit| unit.id).collect();
+            }
+            Ok(_) => panic!("Unexpected outcome type"),
+        }
+    }
+
+    #[test]
+    fn test_range_select() {
+        let grouping = GroupingLabel::from("grouping".as_bytes());
+
+        let mut core = reset_core("test_range_select");
+
+        let mut table = get_initial_data();
+        let extra_data = JsonTable::load_with_auto_id(
+            UnitId::new(table.size() as u128),
+            &[
+                r#"{"f64": -3.5, "str": "AA"}"#,
+                r#"{"f64": -0.5, "str": "a"}"#,
+                r#"{"f64": "2.0", "str": 2.0}"#,
+                r#"{}"#,
+            ],
+        );
+        table.merge(&extra_data);
+        insert_table_to_db(&table, &grouping, &mut core);
+
+        let f64_name = PropertyName::from("f64");
+        let str_name = PropertyName::from("str");
+        let number = |f: f64| UnitContent::Float64(f);
+        let string = |s: &str| UnitContent::String(String::from(s));
+        let cases: Vec<(SelectCondition, Vec<u128>)> = vec![
+            (
+                SelectCondition::LessThan(f64_name.clone(), number(2.0)),
+                vec![0, 11, 12],
+            ),
+            (
+                SelectCondition::LessThanOrEqual(f64_name.clone(), number(2.0)),
+                vec![0, 1, 2, 3, 9, 11, 12],
+            ),
+            (
+                SelectCondition::GreaterThan(f64_name.clone(), number(4.0)),
+                vec![4, 6, 10],
+            ),
+            (
+                SelectCondition::GreaterThanOrEqual(f64_name.clone(), number(4.0)),
+                vec![4, 6, 8, 10],
+            ),
+            (
+                SelectCondition::Between(f64_name.clone(), number(-1.0), number(2.1)),
+                vec![0, 1, 2, 3, 5, 7, 9, 12],
+            ),
+            (
+                SelectCondition::LessThan(f64_name.clone(), string("9")),
+                vec![13],
+            ),
+            (
+                SelectCondition::Between(str_name.clone(), string("B"), string("C")),
+                vec![1, 2, 3, 4, 6, 9, 10],
+            ),
+            (
+                SelectCondition::GreaterThan(str_name.clone(), string("X")),
+                vec![12],
+            ),
+        ];
+
+        let verify_cases = |core: &mut ImmuxDBCore| {
+            for (condition, expected_ids) in cases.iter() {
+                let mut ids = select_ids(core, &grouping, condition.to_owned());
+                ids.sort_by_key(|id| id.as_int());
+                let expected: Vec<UnitId> =
+                    expected_ids.iter().map(|id| UnitId::new(*id)).collect();
+                assert_eq!(ids, expected, "Unexpected result of {:?}", condition);
+            }
+            // Ordered by the property
+            let ids = select_ids(
+                core,
+                &grouping,
+                SelectCondition::LessThan(f64_name.clone(), number(2.0)),
+            );
+            assert_eq!(ids, vec![UnitId::new(11), UnitId::new(12), UnitId::new(0)]);
+        };
+
+        // Without an index, the whole grouping is filtered
+        verify_cases(&mut core);
+
+        let names = PropertyNameList::new(vec![f64_name.clone(), str_name.clone()]);
+        create_indices_for_grouping(&grouping, &mut core, &names);
+        verify_cases(&mut core);
+
+        // The index follows later insertions
+        let later_data = JsonTable::load_with_auto_id(
+            UnitId::new(table.size() as u128),
+            &[r#"{"f64": 3.0, "str": "Z"}"#],
+        );
+        insert_table_to_db(&later_data, &grouping, &mut core);
+        let ids = select_ids(
+            &mut core,
+            &grouping,
+            SelectCondition::Between(f64_name.clone(), number(2.1), number(4.0)),
+        );
+        assert_eq!(ids.len(), 4);
+        assert_eq!(ids[2], UnitId::new(15));
+    }
+
+    #[test]
+    fn test_index_layout_migration() {
+        let grouping = GroupingLabel::from("grouping".as_bytes());
+        let mut core = reset_core("test_index_layout_migration");
+        let table = get_initial_data();
+        insert_table_to_db(&table, &grouping, &mut core);
+        let f64_name = PropertyName::from("f64");
+        let names = PropertyNameList::new(vec![f64_name.clone()]);
+        create_indices_for_grouping(&grouping, &mut core, &names);
+
+        // Rewrite the number keys as they were before the sortable layout, without the layout mark
+        let mut targets = vec![SetTargetSpec {
+            key: StoreKey::from([&[KVKeySigil::ChainInfo as u8], &b"index_layout"[..]].concat()),
+            value: StoreValue::new(None),
+        }];
+        let reverse_index = ReverseIndex::from_jsons(table.get_inner(), &names).unwrap();
+        for ((name, property_bytes), ids) in reverse_index {
+            let property = UnitContent::parse_data(&property_bytes).unwrap();
+            targets.push(SetTargetSpec {
+                key: get_store_key_of_indexed_id_list(&grouping, &name, &property),
+                value: StoreValue::new(None),
+            });
+            let mut marshalled_key = get_store_key_prefix_of_indexed_id_lists(&grouping, &name)
+                .as_slice()
+                .to_vec();
+            marshalled_key.extend(property.marshal());
+            targets.push(SetTargetSpec {
+                key: StoreKey::from(marshalled_key),
+                value: StoreValue::new(Some(ids.marshal())),
+            });
+        }
+        let instruction = Instruction::DataAccess(DataInstruction::Write(
+            DataWriteInstruction::SetMany(SetManyInstruction { targets }),
+        ));
+        core.execute(&instruction).unwrap();
+        let old_height = get_current_height(&mut core).unwrap();
+
+        let number = |f: f64| UnitContent::Float64(f);
+        let cases: Vec<(SelectCondition, Vec<u128>)> = vec![
+            (
+                SelectCondition::LessThanOrEqual(f64_name.clone(), number(2.0)),
+                vec![0, 1, 2, 3, 9],
+            ),
+            (
+                SelectCondition::Between(f64_name.clone(), number(2.1), number(5.0)),
+                vec![4, 5, 7, 8],
+            ),
+            (
+                SelectCondition::NameProperty(f64_name.clone(), number(7.0)),
+                vec![6, 10],
+            ),
+        ];
+        let verify_cases = |core: &mut ImmuxDBCore, height: Option<ChainHeight>| {
+            for (condition, expected_ids) in cases.iter() {
+                let select = Command::Select(SelectCommand {
+                    grouping: grouping.to_owned(),
+                    condition: condition.to_owned(),
+                    height: height.map(HeightSpecifier::Height),
+                    skip: 0,
+                    limit: None,
+                    sort: None,
+                    continuation: None,
+                    projection: None,
+                });
+                let mut ids: Vec<UnitId> = match execute(select, core) {
+                    Ok(Outcome::Select(outcome)) => outcome.units.iter().map(|u| u.id).collect(),
+                    result => panic!("Unexpected result {:?}", result),
+                };
+                ids.sort_by_key(|id| id.as_int());
+                let expected: Vec<UnitId> =
+                    expected_ids.iter().map(|id| UnitId::new(*id)).collect();
+                assert_eq!(ids, expected, "Unexpected result of {:?}", condition);
+            }
+        };
+
+        // The old keys are not read, so selects fall back to the units
+        verify_cases(&mut core, None);
+
+        migrate_index_layout(&mut core).unwrap();
+        let migrated_height = get_current_height(&mut core).unwrap();
+        assert_eq!(migrated_height.as_u64(), old_height.as_u64() + 1);
+        assert!(is_index_layout_current(None, &mut core).unwrap());
+        assert!(!is_index_layout_current(Some(old_height), &mut core).unwrap());
+        let get_id_list = Instruction::DataAccess(DataInstruction::Read(
+            DataReadInstruction::GetOne(GetOneInstruction {
+                key: get_store_key_of_indexed_id_list(&grouping, &f64_name, &number(7.0)),
+                height: None,
+            }),
+        ));
+        match core.execute(&get_id_list) {
+            Ok(Answer::DataAccess(DataAnswer::Read(DataReadAnswer::GetOneOk(answer)))) => {
+                let data = answer.value.inner().to_owned().unwrap();
+                let id_list = IdList::try_from(data.as_slice()).unwrap();
+                assert_eq!(id_list.as_slice(), &[UnitId::new(6), UnitId::new(10)]);
+            }
+            result => panic!("Unexpected result {:?}", result),
+        }
+        verify_cases(&mut core, None);
+        verify_cases(&mut core, Some(old_height));
+
+        // A migrated chain is left alone
+        migrate_index_layout(&mut core).unwrap();
+        let height = get_current_height(&mut core).unwrap();
+        assert_eq!(height.as_u64(), migrated_height.as_u64());
+
+        // The index follows later insertions
+        let later_data =
+            JsonTable::load_with_auto_id(UnitId::new(table.size() as u128), &[r#"{"f64": 7.0}"#]);
+        insert_table_to_db(&later_data, &grouping, &mut core);
+        let ids = select_ids(
+            &mut core,
+            &grouping,
+            SelectCondition::NameProperty(f64_name.clone(), number(7.0)),
+        );
+        assert_eq!(ids.len(), 3);
+    }
+
+    #[test]
+    fn test_compound_select() {
+        let mut core = reset_core("test_compound_select");
+        let table = get_initial_data();
+
+        let equals = |name: &str, property: UnitContent| {
+            SelectCondition::NameProperty(PropertyName::from(name), property)
+        };
+        let string = |s: &str| UnitContent::String(String::from(s));
+        let cases: Vec<(SelectCondition, Vec<u128>)> = vec![
+            (
+                SelectCondition::And(vec![
+                    SelectCondition::GreaterThanOrEqual(
+                        PropertyName::from("f64"),
+                        UnitContent::Float64(2.0),
+                    ),
+                    equals("str", string("C")),
+                ]),
+                vec![2, 3, 9, 10],
+            ),
+            (
+                SelectCondition::Or(vec![
+                    equals("str", string("X")),
+                    equals("f64", UnitContent::Float64(1.0)),
+                ]),
+                vec![0, 5, 8],
+            ),
+            (
+                SelectCondition::Not(Box::new(equals("bool", UnitContent::Bool(true)))),
+                vec![4, 6, 10],
+            ),
+            (
+                SelectCondition::And(vec![
+                    SelectCondition::Or(vec![
+                        equals("str", string("B")),
+                        equals("str", string("D")),
+                    ]),
+                    SelectCondition::Not(Box::new(equals("f64", UnitContent::Float64(2.0)))),
+                ]),
+                vec![4, 6, 7],
+            ),
+            (
+                SelectCondition::And(vec![
+                    SelectCondition::Id(UnitId::new(3)),
+                    equals("str", string("C")),
+                ]),
+                vec![3],
+            ),
+            (SelectCondition::And(vec![]), (0..11).collect()),
+            (SelectCondition::Or(vec![]), vec![]),
+        ];
+
+        let name_groups: Vec<Vec<&str>> = vec![vec![], vec!["f64"], vec!["f64", "str", "bool"]];
+        for names in name_groups {
+            let grouping = GroupingLabel::from(format!("grouping-{}", names.join("-")).as_str());
+            insert_table_to_db(&table, &grouping, &mut core);
+            let name_list = PropertyNameList::new(
+                names
+                    .into_iter()
+                    .map(|name_str| PropertyName::from(name_str))
+                    .collect(),
+            );
+            create_indices_for_grouping(&grouping, &mut core, &name_list);
+
+            for (condition, expected_ids) in cases.iter() {
+                let mut ids = select_ids(&mut core, &grouping, condition.to_owned());
+                ids.sort_by_key(|id| id.as_int());
+                let expected: Vec<UnitId> =
+                    expected_ids.iter().map(|id| UnitId::new(*id)).collect();
+                assert_eq!(ids, expected, "Unexpected result of {:?}", condition);
+            }
+        }
+    }
+
+    #[test]
+    fn test_javascript_select() {
+        let mut core = reset_core("test_javascript_select");
+        let table = get_initial_data();
+        let grouping = GroupingLabel::from("grouping");
+        insert_table_to_db(&table, &grouping, &mut core);
+        let name_list = PropertyNameList::new(vec![PropertyName::from("str")]);
+        create_indices_for_grouping(&grouping, &mut core, &name_list);
+
+        let javascript = |code: &str| SelectCondition::JSCode(String::from(code));
+        let cases: Vec<(SelectCondition, Vec<u128>)> = vec![
+            (javascript("this.f64 > 4 && !this.bool"), vec![4, 6, 10]),
+            (
+                javascript("function() { return obj.str === 'X' }"),
+                vec![5, 8],
+            ),
+            (javascript("x => x.f64 % 2 === 0"), vec![1, 2, 3, 8, 9]),
+            (
+                SelectCondition::And(vec![
+                    SelectCondition::NameProperty(
+                        PropertyName::from("str"),
+                        UnitContent::String(String::from("C")),
+                    ),
+                    javascript("this.bool"),
+                ]),
+                vec![2, 3, 9],
+            ),
+            (
+                SelectCondition::Not(Box::new(javascript("this.f64 < 7"))),
+                vec![6, 10],
+            ),
+        ];
+        for (condition, expected_ids) in cases.iter() {
+            let mut ids = select_ids(&mut core, &grouping, condition.to_owned());
+            ids.sort_by_key(|id| id.as_int());
+            let expected: Vec<UnitId> = expected_ids.iter().map(|id| UnitId::new(*id)).collect();
+            assert_eq!(ids, expected, "Unexpected result of {:?}", condition);
+        }
+
+        let select_by_javascript = |code: &str| {
+            Command::Select(SelectCommand {
+                grouping: grouping.to_owned(),
+                condition: javascript(code),
+                height: None,
+                skip: 0,
+                limit: None,
+                sort: None,
+                continuation: None,
+                projection: None,
+            })
+        };
+        match execute(select_by_javascript("this.f64 >"), &mut core) {
+            Err(ImmuxError::JsSandbox(JsError::UnexpectedEnd)) => {}
+            result => panic!("Unexpected result {:?}", result),
+        }
+        match execute(select_by_javascript("while (true) {}"), &mut core) {
+            Err(ImmuxError::JsSandbox(JsError::StepLimitExceeded)) => {}
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+
+    fn select_page(
+        core: &mut ImmuxDBCore,
+        grouping: &GroupingLabel,
+        sort: Option<SelectSort>,
+        skip: usize,
+        limit: Option<usize>,
+        continuation: Option<String>,
+    ) -> ImmuxResult<(Vec<UnitId>, Option<String>)> {
+        let condition = SelectCondition::UnconditionalMatch;
+        return select_page_where(core, grouping, condition, sort, skip, limit, continuation);
+    }
+
+    fn select_page_where(
+        core: &mut ImmuxDBCore,
+        grouping: &GroupingLabel,
+        condition: SelectCondition,
+        sort: Option<SelectSort>,
+        skip: usize,
+        limit: Option<usize>,
+        continuation: Option<String>,
+    ) -> ImmuxResult<(Vec<UnitId>, Option<String>)> {
+        let select = Command::Select(SelectCommand {
+            grouping: grouping.to_owned(),
+            condition,
+            height: None,
+            skip,
+            limit,
+            sort,
+            continuation,
+            projection: None,
+        });
+        match execute(select, core)? {
+            Outcome::Select(select_outcome) => {
+                let ids = select_outcome.units.iter().map(|unit| unit.id).collect();
+                return Ok((ids, select_outcome.continuation));
+            }
+            _ => panic!("Unexpected outcome type"),
+        }
+    }
+
+    #[test]
+    fn test_paginated_select() {
+        let mut core = reset_core("test_paginated_select");
+        let table = get_initial_data();
+        let grouping = GroupingLabel::from("grouping");
+        insert_table_to_db(&table, &grouping, &mut core);
+
+        let f64_of = |ids: &[UnitId]| -> Vec<f64> {
+            ids.iter()
+                .map(|id| {
+                    let row = &table.inner[id.as_int() as usize];
+                    let json: JsonValue = serde_json::from_str(&row.1).unwrap();
+                    json["f64"].as_f64().unwrap()
+                })
+                .collect()
+        };
+        let by_f64 = |order: SortOrder| {
+            Some(SelectSort {
+                name: PropertyName::from("f64"),
+                order,
+            })
+        };
+        let ascending = vec![1.0, 2.0, 2.0, 2.0, 2.0, 2.1, 2.1, 4.0, 5.0, 7.0, 7.0];
+
+        let (ids, continuation) = select_page(
+            &mut core,
+            &grouping,
+            by_f64(SortOrder::Ascending),
+            0,
+            None,
+            None,
+        )
+        .unwrap();
+        assert_eq!(f64_of(&ids), ascending);
+        assert_eq!(continuation, None);
+
+        let (ids, _) = select_page(
+            &mut core,
+            &grouping,
+            by_f64(SortOrder::Descending),
+            0,
+            None,
+            None,
+        )
+        .unwrap();
+        let descending: Vec<f64> = ascending.iter().rev().cloned().collect();
+        assert_eq!(f64_of(&ids), descending);
+
+        let (ids, continuation) = select_page(
+            &mut core,
+            &grouping,
+            by_f64(SortOrder::Ascending),
+            4,
+            Some(3),
+            None,
+        )
+        .unwrap();
+        assert_eq!(f64_of(&ids), vec![2.0, 2.1, 2.1]);
+        assert!(continuation.is_some());
+
+        // Following continuations walks the result as of the first page, despite later inserts
+        let (mut all_ids, mut continuation) = select_page(
+            &mut core,
+            &grouping,
+            by_f64(SortOrder::Ascending),
+            0,
+            Some(4),
+            None,
+        )
+        .unwrap();
+        let late_data = JsonTable::load_with_auto_id(
+            UnitId::new(table.inner.len() as u128),
+            &[r#"{"f64": 0.0, "str": "Late", "bool": true}"#],
+        );
+        insert_table_to_db(&late_data, &grouping, &mut core);
+        while let Some(token) = continuation {
+            let (ids, next) = select_page(
+                &mut core,
+                &grouping,
+                by_f64(SortOrder::Ascending),
+                0,
+                Some(4),
+                Some(token),
+            )
+            .unwrap();
+            assert!(ids.len() <= 4);
+            all_ids.extend(ids);
+            continuation = next;
+        }
+        assert_eq!(f64_of(&all_ids), ascending);
+
+        match select_page(
+            &mut core,
+            &grouping,
+            None,
+            0,
+            None,
+            Some(String::from("not a token")),
+        ) {
+            Err(ImmuxError::Executor(ExecutorError::InvalidContinuation(_))) => {}
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+
+    #[test]
+    fn test_paginated_select_resumes_after_last_unit() {
+        let mut core = reset_core("test_paginated_select_resumes_after_last_unit");
+        let table = get_initial_data();
+        let grouping = GroupingLabel::from("grouping");
+        insert_table_to_db(&table, &grouping, &mut core);
+        let f64_name = PropertyName::from("f64");
+        let str_name = PropertyName::from("str");
+        create_indices_for_grouping(
+            &grouping,
+            &mut core,
+            &PropertyNameList::new(vec![f64_name.clone()]),
+        );
+
+        let number = |f: f64| UnitContent::Float64(f);
+        let string = |s: &str| UnitContent::String(String::from(s));
+        let by = |name: &PropertyName| {
+            Some(SelectSort {
+                name: name.clone(),
+                order: SortOrder::Ascending,
+            })
+        };
+        let queries: Vec<(SelectCondition, Option<SelectSort>)> = vec![
+            (SelectCondition::UnconditionalMatch, None),
+            (
+                SelectCondition::GreaterThanOrEqual(f64_name.clone(), number(2.0)),
+                None,
+            ),
+            (
+                SelectCondition::GreaterThan(f64_name.clone(), number(2.0)),
+                None,
+            ),
+            (
+                SelectCondition::Between(f64_name.clone(), number(2.0), number(5.0)),
+                by(&f64_name),
+            ),
+            (
+                SelectCondition::LessThan(f64_name.clone(), number(7.0)),
+                by(&str_name),
+            ),
+            (
+                SelectCondition::Between(str_name.clone(), string("B"), string("C")),
+                None,
+            ),
+        ];
+        for (condition, sort) in queries {
+            let (expected, _) = select_page_where(
+                &mut core,
+                &grouping,
+                condition.clone(),
+                sort.clone(),
+                0,
+                None,
+                None,
+            )
+            .unwrap();
+            // The same units as the materialized select, which does not read from key ranges
+            let materialized = SelectCondition::And(vec![condition.clone()]);
+            let (mut materialized_ids, _) =
+                select_page_where(&mut core, &grouping, materialized, None, 0, None, None).unwrap();
+            let mut expected_ids = expected.clone();
+            materialized_ids.sort();
+            expected_ids.sort();
+            assert_eq!(expected_ids, materialized_ids);
+            for limit in 1..4 {
+                let mut all_ids = vec![];
+                let mut continuation = None;
+                loop {
+                    let (ids, next) = select_page_where(
+                        &mut core,
+                        &grouping,
+                        condition.clone(),
+                        sort.clone(),
+                        0,
+                        Some(limit),
+                        continuation,
+                    )
+                    .unwrap();
+                    assert!(ids.len() <= limit);
+                    all_ids.extend(ids);
+                    continuation = next;
+                    if continuation.is_none() {
+                        break;
+                    }
+                }
+                assert_eq!(all_ids, expected, "Unexpected pages of {:?}", condition);
+            }
+        }
+
+        // A token only continues the query that issued it
+        let (_, continuation) = select_page(&mut core, &grouping, None, 0, Some(2), None).unwrap();
+        match select_page(
+            &mut core,
+            &grouping,
+            by(&f64_name),
+            0,
+            Some(2),
+            continuation,
+        ) {
+            Err(ImmuxError::Executor(ExecutorError::InvalidContinuation(_))) => {}
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+
+    #[test]
+    fn test_projected_select() {
+        let mut core = reset_core("test_projected_select");
+        let grouping = GroupingLabel::from("grouping");
+        let table = JsonTable::load_with_auto_id(
+            UnitId::new(0),
+            &[
+                r#"{"name": "Tom", "age": 30, "address": {"city": "Paris", "street": "Rue"}}"#,
+                r#"{"name": "Tim", "age": 3}"#,
+            ],
+        );
+        insert_table_to_db(&table, &grouping, &mut core);
+
+        let projection = PropertyNameList::new(vec![
+            PropertyName::from("name"),
+            PropertyName::from("address.city"),
+        ]);
+        let select = Command::Select(SelectCommand {
+            grouping: grouping.to_owned(),
+            condition: SelectCondition::UnconditionalMatch,
+            height: None,
+            skip: 0,
+            limit: None,
+            sort: Some(SelectSort {
+                name: PropertyName::from("age"),
+                order: SortOrder::Descending,
+            }),
+            continuation: None,
+            projection: Some(projection),
+        });
+        let documents: Vec<JsonValue> = match execute(select, &mut core) {
+            Ok(Outcome::Select(select_outcome)) => select_outcome
+                .units
+                .iter()
+                .map(|unit| serde_json::from_str(&unit.content.to_string()).unwrap())
+                .collect(),
+            result => panic!("Unexpected result {:?}", result),
+        };
+        let expected = vec![
+            json!({"name": "Tom", "address": {"city": "Paris"}}),
+            json!({"name": "Tim"}),
+        ];
+        assert_eq!(documents, expected);
+    }
 }
@@ -1,14 +1,31 @@
 /// Tests here do not use fixture core to isolate interface, and use ImmuxDBCore in stead
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
 use immuxdb_dev_utils::reset_db_dir;
 
-use crate::declarations::basics::{GroupingLabel, Unit, UnitContent, UnitId};
+use crate::declarations::basics::{
+    ChainName, GroupingLabel, PropertyName, StoreKey, StoreValue, Unit, UnitContent, UnitId,
+    UnitSpecifier,
+};
 use crate::declarations::commands::{
-    Command, InsertCommand, InsertCommandSpec, Outcome, SelectCommand, SelectCondition,
+    ChangeFeedCommand, Command, CreateIndexCommand, CreateTagCommand, DeleteTagCommand,
+    DiffCommand, FieldDiff, ForkChainCommand, HeightSpecifier, ImportChainCommand, InsertCommand,
+    InsertCommandSpec, InspectCommand, MergeChainCommand, MergeCommandResolution, Outcome,
+    PickChainCommand, ProveCommand, RemoveCommand, ReplayChainCommand, RevertAllCommand,
+    RevertCommandTargetSpec, RevertManyCommand, RevertRecordCommand, SelectCommand,
+    SelectCondition, SetRetentionCommand,
 };
-use crate::executor::execute::execute;
+use crate::declarations::errors::ImmuxError;
+use crate::executor::errors::ExecutorError;
+use crate::executor::execute::{execute, execute_with_meta};
+use crate::executor::shared::{get_current_height, get_current_namespace};
+use crate::executor::ChangeFeed;
+use crate::storage::archive::ArchiveError;
 use crate::storage::core::ImmuxDBCore;
-use crate::storage::instructions::StoreNamespace;
+use crate::storage::instructions::{MergeStrategy, RetentionPolicy, StoreNamespace};
 use crate::storage::kv::KeyValueEngine;
+use crate::storage::vkv::{ChainHeight, RecordMeta, VkvError};
 
 /// Insert some simple data and get them back.
 /// Inserts and Selects need to be tested together.
@@ -42,6 +59,12 @@ fn test_insert_and_select_by_unconditional_match() {
                 let select_command = Command::Select(SelectCommand {
                     grouping,
                     condition: SelectCondition::UnconditionalMatch,
+                    height: None,
+                    skip: 0,
+                    limit: None,
+                    sort: None,
+                    continuation: None,
+                    projection: None,
                 });
                 match execute(select_command, &mut core) {
                     Err(_error) => panic!("Failed to execute select command"),
@@ -62,3 +85,1322 @@ fn test_insert_and_select_by_unconditional_match() {
         },
     }
 }
+
+/// Remove an indexed unit, then bring it back by reverting to its last extant version.
+#[test]
+fn test_remove_and_revert() {
+    let data_root = format!("/tmp/immuxdb_test_remove/");
+    reset_db_dir(&data_root).unwrap();
+
+    let namespace = StoreNamespace::new("default".as_bytes());
+    let grouping = GroupingLabel::from("grouping".as_bytes());
+    let id = UnitId::new(1);
+    let content = UnitContent::JsonString(String::from(r#"{"name": "immux"}"#));
+    let name_property = SelectCondition::NameProperty(
+        PropertyName::from("name"),
+        UnitContent::String(String::from("immux")),
+    );
+
+    let mut core = ImmuxDBCore::new(&KeyValueEngine::Rocks, &data_root, &namespace).unwrap();
+
+    let create_index = Command::CreateIndex(CreateIndexCommand {
+        grouping: grouping.clone(),
+        name: PropertyName::from("name"),
+    });
+    execute(create_index, &mut core).unwrap();
+
+    let insert = Command::Insert(InsertCommand {
+        grouping: grouping.clone(),
+        targets: vec![InsertCommandSpec {
+            id,
+            content: content.clone(),
+        }],
+    });
+    execute(insert, &mut core).unwrap();
+
+    let remove = Command::Remove(RemoveCommand {
+        grouping: grouping.clone(),
+        ids: vec![id],
+    });
+    match execute(remove, &mut core) {
+        Ok(Outcome::Remove(outcome)) => assert_eq!(outcome.count, 1),
+        _ => panic!("Failed to execute remove command"),
+    }
+
+    let select_by_id = Command::Select(SelectCommand {
+        grouping: grouping.clone(),
+        condition: SelectCondition::Id(id),
+        height: None,
+        skip: 0,
+        limit: None,
+        sort: None,
+        continuation: None,
+        projection: None,
+    });
+    match execute(select_by_id.clone(), &mut core) {
+        Err(ImmuxError::Executor(ExecutorError::CannotFindId(missing_id))) => {
+            assert_eq!(missing_id, id)
+        }
+        _ => panic!("Removed unit should not be found"),
+    }
+
+    for condition in vec![SelectCondition::UnconditionalMatch, name_property.clone()] {
+        let select = Command::Select(SelectCommand {
+            grouping: grouping.clone(),
+            condition,
+            height: None,
+            skip: 0,
+            limit: None,
+            sort: None,
+            continuation: None,
+            projection: None,
+        });
+        match execute(select, &mut core) {
+            Ok(Outcome::Select(outcome)) => assert!(outcome.units.is_empty()),
+            _ => panic!("Failed to execute select command"),
+        }
+    }
+
+    let inspect = Command::Inspect(InspectCommand {
+        specifier: UnitSpecifier::new(grouping.clone(), id),
+        height: None,
+    });
+    let inspections = match execute(inspect, &mut core) {
+        Ok(Outcome::Inspect(outcome)) => outcome.inspections,
+        _ => panic!("Failed to execute inspect command"),
+    };
+    assert_eq!(inspections.len(), 2);
+    assert_eq!(inspections[0].content, Some(content.clone()));
+    assert_eq!(inspections[1].content, None);
+    assert!(inspections[1].height > inspections[0].height);
+
+    let revert = Command::RevertMany(RevertManyCommand {
+        specs: vec![RevertCommandTargetSpec {
+            specifier: UnitSpecifier::new(grouping.clone(), id),
+            target_height: inspections[0].height.into(),
+        }],
+    });
+    execute(revert, &mut core).unwrap();
+
+    let select_by_property = Command::Select(SelectCommand {
+        grouping: grouping.clone(),
+        condition: name_property,
+        height: None,
+        skip: 0,
+        limit: None,
+        sort: None,
+        continuation: None,
+        projection: None,
+    });
+    for select in vec![select_by_id, select_by_property] {
+        match execute(select, &mut core) {
+            Ok(Outcome::Select(outcome)) => {
+                assert_eq!(
+                    outcome.units,
+                    vec![Unit {
+                        id,
+                        content: content.clone()
+                    }]
+                )
+            }
+            _ => panic!("Reverted unit should be found"),
+        }
+    }
+}
+
+/// Every select condition can read the grouping as it was at an earlier height.
+#[test]
+fn test_select_at_height() {
+    let data_root = format!("/tmp/immuxdb_test_select_at_height/");
+    reset_db_dir(&data_root).unwrap();
+
+    let namespace = StoreNamespace::new("default".as_bytes());
+    let grouping = GroupingLabel::from("grouping".as_bytes());
+    let mut core = ImmuxDBCore::new(&KeyValueEngine::Rocks, &data_root, &namespace).unwrap();
+
+    let create_index = Command::CreateIndex(CreateIndexCommand {
+        grouping: grouping.clone(),
+        name: PropertyName::from("name"),
+    });
+    execute(create_index, &mut core).unwrap();
+
+    let old_unit = Unit {
+        id: UnitId::new(1),
+        content: UnitContent::JsonString(String::from(r#"{"name": "old"}"#)),
+    };
+    let new_unit = Unit {
+        id: UnitId::new(1),
+        content: UnitContent::JsonString(String::from(r#"{"name": "new"}"#)),
+    };
+    let later_unit = Unit {
+        id: UnitId::new(2),
+        content: UnitContent::JsonString(String::from(r#"{"name": "new"}"#)),
+    };
+    let mut heights = Vec::new();
+    for unit in &[&old_unit, &new_unit, &later_unit] {
+        let insert = Command::Insert(InsertCommand {
+            grouping: grouping.clone(),
+            targets: vec![InsertCommandSpec {
+                id: unit.id,
+                content: unit.content.clone(),
+            }],
+        });
+        execute(insert, &mut core).unwrap();
+        let inspect = Command::Inspect(InspectCommand {
+            specifier: UnitSpecifier::new(grouping.clone(), unit.id),
+            height: None,
+        });
+        match execute(inspect, &mut core) {
+            Ok(Outcome::Inspect(outcome)) => {
+                heights.push(outcome.inspections.last().unwrap().height)
+            }
+            _ => panic!("Failed to execute inspect command"),
+        }
+    }
+    let old_height = heights[0];
+
+    let select_at_old_height = |condition: SelectCondition| {
+        Command::Select(SelectCommand {
+            grouping: grouping.clone(),
+            condition,
+            height: Some(old_height.into()),
+            skip: 0,
+            limit: None,
+            sort: None,
+            continuation: None,
+            projection: None,
+        })
+    };
+    let by_name = |name: &str| {
+        SelectCondition::NameProperty(
+            PropertyName::from("name"),
+            UnitContent::String(String::from(name)),
+        )
+    };
+    let expectations = vec![
+        (SelectCondition::UnconditionalMatch, vec![old_unit.clone()]),
+        (SelectCondition::Id(UnitId::new(1)), vec![old_unit.clone()]),
+        (by_name("old"), vec![old_unit.clone()]),
+        (by_name("new"), vec![]),
+    ];
+    for (condition, expected_units) in expectations {
+        match execute(select_at_old_height(condition), &mut core) {
+            Ok(Outcome::Select(outcome)) => assert_eq!(outcome.units, expected_units),
+            _ => panic!("Failed to execute select command"),
+        }
+    }
+
+    match execute(
+        select_at_old_height(SelectCondition::Id(UnitId::new(2))),
+        &mut core,
+    ) {
+        Err(ImmuxError::Executor(ExecutorError::CannotFindId(_))) => {}
+        _ => panic!("Unit created later should not be found"),
+    }
+}
+
+#[test]
+fn test_prove_at_height() {
+    let data_root = format!("/tmp/immuxdb_test_prove_at_height/");
+    reset_db_dir(&data_root).unwrap();
+
+    let namespace = StoreNamespace::new("default".as_bytes());
+    let grouping = GroupingLabel::from("grouping".as_bytes());
+    let mut core = ImmuxDBCore::new(&KeyValueEngine::Rocks, &data_root, &namespace).unwrap();
+
+    let content = |name: &str| UnitContent::String(String::from(name));
+    let insert = |id: u128, name: &str| {
+        Command::Insert(InsertCommand {
+            grouping: grouping.clone(),
+            targets: vec![InsertCommandSpec {
+                id: UnitId::new(id),
+                content: content(name),
+            }],
+        })
+    };
+    for id in 1..=5 {
+        execute(insert(id, "old"), &mut core).unwrap();
+    }
+    execute(insert(3, "new"), &mut core).unwrap();
+
+    let specifier = UnitSpecifier::new(grouping.clone(), UnitId::new(3));
+    let prove = |height| {
+        Command::Prove(ProveCommand {
+            specifier: specifier.clone(),
+            height,
+        })
+    };
+    let key = StoreKey::from(specifier.clone());
+    let value_of = |content: &UnitContent| StoreValue::new(Some(content.marshal()));
+
+    let latest = match execute(prove(None), &mut core) {
+        Ok(Outcome::Prove(outcome)) => outcome,
+        _ => panic!("Failed to prove latest content"),
+    };
+    assert_eq!(latest.content, content("new"));
+    assert!(latest
+        .proof
+        .verify(&key, &value_of(&content("new")), &latest.root));
+    assert!(!latest
+        .proof
+        .verify(&key, &value_of(&content("old")), &latest.root));
+
+    let mut old_height = latest.height;
+    old_height.decrement();
+    let old = match execute(prove(Some(old_height.into())), &mut core) {
+        Ok(Outcome::Prove(outcome)) => outcome,
+        _ => panic!("Failed to prove old content"),
+    };
+    assert_eq!(old.height, old_height);
+    assert_eq!(old.content, content("old"));
+    assert!(old
+        .proof
+        .verify(&key, &value_of(&content("old")), &old.root));
+    assert_ne!(old.root, latest.root);
+    assert!(!old
+        .proof
+        .verify(&key, &value_of(&content("old")), &latest.root));
+
+    let missing = Command::Prove(ProveCommand {
+        specifier: UnitSpecifier::new(grouping.clone(), UnitId::new(100)),
+        height: None,
+    });
+    match execute(missing, &mut core) {
+        Err(ImmuxError::Executor(ExecutorError::CannotFindId(id))) => {
+            assert_eq!(id, UnitId::new(100))
+        }
+        _ => panic!("Missing unit should not be provable"),
+    }
+}
+
+#[test]
+fn test_fork_chain() {
+    let data_root = format!("/tmp/immuxdb_test_fork_chain/");
+    reset_db_dir(&data_root).unwrap();
+
+    let namespace = StoreNamespace::new("default".as_bytes());
+    let grouping = GroupingLabel::from("grouping".as_bytes());
+    let mut core = ImmuxDBCore::new(&KeyValueEngine::Rocks, &data_root, &namespace).unwrap();
+
+    let make_unit = |id: u128| Unit {
+        id: UnitId::new(id),
+        content: UnitContent::String(format!("unit {}", id)),
+    };
+    let insert = |units: &[Unit]| {
+        Command::Insert(InsertCommand {
+            grouping: grouping.clone(),
+            targets: units
+                .iter()
+                .map(|unit| InsertCommandSpec {
+                    id: unit.id,
+                    content: unit.content.clone(),
+                })
+                .collect(),
+        })
+    };
+    let select_all = || {
+        Command::Select(SelectCommand {
+            grouping: grouping.clone(),
+            condition: SelectCondition::UnconditionalMatch,
+            height: None,
+            skip: 0,
+            limit: None,
+            sort: None,
+            continuation: None,
+            projection: None,
+        })
+    };
+    let pick = |name: &str| {
+        Command::PickChain(PickChainCommand {
+            new_chain_name: ChainName::from(name),
+        })
+    };
+    let assert_units = |core: &mut ImmuxDBCore, expected: &[Unit]| match execute(select_all(), core)
+    {
+        Ok(Outcome::Select(mut outcome)) => {
+            outcome.units.sort_by_key(|unit| unit.id.as_int());
+            assert_eq!(outcome.units, expected)
+        }
+        _ => panic!("Failed to select units"),
+    };
+
+    let early_units = vec![make_unit(1), make_unit(2)];
+    execute(insert(&early_units), &mut core).unwrap();
+    let fork_height = get_current_height(&mut core).unwrap();
+    execute(insert(&[make_unit(3)]), &mut core).unwrap();
+
+    let fork = Command::ForkChain(ForkChainCommand {
+        source: ChainName::from("default"),
+        at_height: fork_height,
+        new_chain_name: ChainName::from("sandbox"),
+    });
+    match execute(fork, &mut core) {
+        Ok(Outcome::ForkChain(outcome)) => assert_eq!(outcome.height, fork_height),
+        _ => panic!("Failed to fork chain"),
+    }
+
+    execute(pick("sandbox"), &mut core).unwrap();
+    assert_units(&mut core, &early_units);
+    execute(insert(&[make_unit(9)]), &mut core).unwrap();
+    assert_units(&mut core, &[make_unit(1), make_unit(2), make_unit(9)]);
+
+    execute(pick("default"), &mut core).unwrap();
+    assert_units(&mut core, &[make_unit(1), make_unit(2), make_unit(3)]);
+}
+
+#[test]
+fn test_merge_chain() {
+    let data_root = format!("/tmp/immuxdb_test_merge_chain/");
+    reset_db_dir(&data_root).unwrap();
+
+    let namespace = StoreNamespace::new("default".as_bytes());
+    let grouping = GroupingLabel::from("grouping".as_bytes());
+    let mut core = ImmuxDBCore::new(&KeyValueEngine::Rocks, &data_root, &namespace).unwrap();
+
+    let content = |name: &str| UnitContent::String(String::from(name));
+    let insert = |id: u128, name: &str| {
+        Command::Insert(InsertCommand {
+            grouping: grouping.clone(),
+            targets: vec![InsertCommandSpec {
+                id: UnitId::new(id),
+                content: content(name),
+            }],
+        })
+    };
+    let pick = |name: &str| {
+        Command::PickChain(PickChainCommand {
+            new_chain_name: ChainName::from(name),
+        })
+    };
+    let merge = |resolutions: Vec<MergeCommandResolution>| {
+        Command::MergeChain(MergeChainCommand {
+            target: ChainName::from("default"),
+            source: ChainName::from("feature"),
+            resolutions,
+            default_strategy: MergeStrategy::Fail,
+        })
+    };
+    let select_id = |id: u128| {
+        Command::Select(SelectCommand {
+            grouping: grouping.clone(),
+            condition: SelectCondition::Id(UnitId::new(id)),
+            height: None,
+            skip: 0,
+            limit: None,
+            sort: None,
+            continuation: None,
+            projection: None,
+        })
+    };
+    let assert_content =
+        |core: &mut ImmuxDBCore, id: u128, name: &str| match execute(select_id(id), core) {
+            Ok(Outcome::Select(outcome)) => assert_eq!(outcome.units[0].content, content(name)),
+            _ => panic!("Failed to select unit {}", id),
+        };
+
+    execute(insert(1, "base"), &mut core).unwrap();
+    execute(insert(2, "base"), &mut core).unwrap();
+    let fork = Command::ForkChain(ForkChainCommand {
+        source: ChainName::from("default"),
+        at_height: ChainHeight::new(2),
+        new_chain_name: ChainName::from("feature"),
+    });
+    execute(fork, &mut core).unwrap();
+    execute(insert(2, "ours"), &mut core).unwrap();
+
+    execute(pick("feature"), &mut core).unwrap();
+    execute(insert(1, "feature"), &mut core).unwrap();
+    execute(insert(2, "theirs"), &mut core).unwrap();
+    execute(pick("default"), &mut core).unwrap();
+
+    let conflicting_unit = UnitSpecifier::new(grouping.clone(), UnitId::new(2));
+    match execute(merge(vec![]), &mut core) {
+        Err(ImmuxError::VKV(VkvError::MergeConflict(keys))) => {
+            assert_eq!(keys, vec![StoreKey::from(conflicting_unit.clone())])
+        }
+        _ => panic!("Conflicting merge should fail"),
+    }
+    assert_content(&mut core, 1, "base");
+
+    let resolutions = vec![MergeCommandResolution {
+        specifier: conflicting_unit,
+        strategy: MergeStrategy::Theirs,
+    }];
+    match execute(merge(resolutions), &mut core) {
+        Ok(Outcome::MergeChain(outcome)) => {
+            assert_eq!(outcome.base_height, ChainHeight::new(2));
+            assert_eq!(outcome.height, ChainHeight::new(5));
+            assert_eq!(outcome.conflicts.len(), 1);
+        }
+        _ => panic!("Failed to merge chains"),
+    }
+    assert_content(&mut core, 1, "feature");
+    assert_content(&mut core, 2, "theirs");
+}
+
+/// Units merged into an indexed grouping are indexed on the target, whatever each chain did to
+/// the id lists of that grouping.
+#[test]
+fn test_merge_indexed_chain() {
+    let data_root = format!("/tmp/immuxdb_test_merge_indexed_chain/");
+    reset_db_dir(&data_root).unwrap();
+
+    let namespace = StoreNamespace::new("default".as_bytes());
+    let grouping = GroupingLabel::from("grouping".as_bytes());
+    let mut core = ImmuxDBCore::new(&KeyValueEngine::Rocks, &data_root, &namespace).unwrap();
+
+    let insert = |id: u128, name: &str| {
+        Command::Insert(InsertCommand {
+            grouping: grouping.clone(),
+            targets: vec![InsertCommandSpec {
+                id: UnitId::new(id),
+                content: UnitContent::JsonString(format!(r#"{{"name": "{}"}}"#, name)),
+            }],
+        })
+    };
+    let pick = |name: &str| {
+        Command::PickChain(PickChainCommand {
+            new_chain_name: ChainName::from(name),
+        })
+    };
+    let select_by_name = |name: &str| {
+        Command::Select(SelectCommand {
+            grouping: grouping.clone(),
+            condition: SelectCondition::NameProperty(
+                PropertyName::from("name"),
+                UnitContent::String(String::from(name)),
+            ),
+            height: None,
+            skip: 0,
+            limit: None,
+            sort: None,
+            continuation: None,
+            projection: None,
+        })
+    };
+
+    let create_index = Command::CreateIndex(CreateIndexCommand {
+        grouping: grouping.clone(),
+        name: PropertyName::from("name"),
+    });
+    execute(create_index, &mut core).unwrap();
+    execute(insert(1, "x"), &mut core).unwrap();
+    let fork_height = get_current_height(&mut core).unwrap();
+    let fork = Command::ForkChain(ForkChainCommand {
+        source: ChainName::from("default"),
+        at_height: fork_height,
+        new_chain_name: ChainName::from("feature"),
+    });
+    execute(fork, &mut core).unwrap();
+    // Both chains change the id list of "x", but no unit twice
+    execute(insert(3, "x"), &mut core).unwrap();
+
+    execute(pick("feature"), &mut core).unwrap();
+    execute(insert(1, "y"), &mut core).unwrap();
+    execute(insert(2, "y"), &mut core).unwrap();
+    execute(pick("default"), &mut core).unwrap();
+
+    let merge = Command::MergeChain(MergeChainCommand {
+        target: ChainName::from("default"),
+        source: ChainName::from("feature"),
+        resolutions: vec![],
+        default_strategy: MergeStrategy::Fail,
+    });
+    match execute(merge, &mut core) {
+        Ok(Outcome::MergeChain(outcome)) => {
+            assert_eq!(outcome.base_height, fork_height);
+            assert!(outcome.conflicts.is_empty());
+        }
+        result => panic!("Failed to merge chains: {:?}", result),
+    }
+
+    let expectations = vec![("x", vec![3]), ("y", vec![1, 2])];
+    for (name, expected_ids) in expectations {
+        match execute(select_by_name(name), &mut core) {
+            Ok(Outcome::Select(outcome)) => {
+                let mut ids: Vec<UnitId> = outcome.units.iter().map(|unit| unit.id).collect();
+                ids.sort_by_key(|id| id.as_int());
+                let expected_ids: Vec<UnitId> = expected_ids.into_iter().map(UnitId::new).collect();
+                assert_eq!(ids, expected_ids);
+            }
+            _ => panic!("Failed to execute select command"),
+        }
+    }
+}
+
+/// The target's index entries are brought in line even when another chain is picked, which
+/// stays picked
+#[test]
+fn test_merge_into_chain_not_picked() {
+    let data_root = format!("/tmp/immuxdb_test_merge_into_chain_not_picked/");
+    reset_db_dir(&data_root).unwrap();
+
+    let namespace = StoreNamespace::new("default".as_bytes());
+    let grouping = GroupingLabel::from("grouping".as_bytes());
+    let mut core = ImmuxDBCore::new(&KeyValueEngine::Rocks, &data_root, &namespace).unwrap();
+
+    let insert = |id: u128, name: &str| {
+        Command::Insert(InsertCommand {
+            grouping: grouping.clone(),
+            targets: vec![InsertCommandSpec {
+                id: UnitId::new(id),
+                content: UnitContent::JsonString(format!(r#"{{"name": "{}"}}"#, name)),
+            }],
+        })
+    };
+    let pick = |name: &str| {
+        Command::PickChain(PickChainCommand {
+            new_chain_name: ChainName::from(name),
+        })
+    };
+
+    let create_index = Command::CreateIndex(CreateIndexCommand {
+        grouping: grouping.clone(),
+        name: PropertyName::from("name"),
+    });
+    execute(create_index, &mut core).unwrap();
+    execute(insert(1, "x"), &mut core).unwrap();
+    let fork = Command::ForkChain(ForkChainCommand {
+        source: ChainName::from("default"),
+        at_height: get_current_height(&mut core).unwrap(),
+        new_chain_name: ChainName::from("feature"),
+    });
+    execute(fork, &mut core).unwrap();
+    // Both chains change the id list of "y"
+    execute(insert(3, "y"), &mut core).unwrap();
+    execute(pick("feature"), &mut core).unwrap();
+    execute(insert(2, "y"), &mut core).unwrap();
+    // A chain without the index is picked while merging
+    execute(pick("other"), &mut core).unwrap();
+
+    let merge = Command::MergeChain(MergeChainCommand {
+        target: ChainName::from("default"),
+        source: ChainName::from("feature"),
+        resolutions: vec![],
+        default_strategy: MergeStrategy::Fail,
+    });
+    match execute(merge, &mut core) {
+        Ok(Outcome::MergeChain(outcome)) => assert!(outcome.conflicts.is_empty()),
+        result => panic!("Failed to merge chains: {:?}", result),
+    }
+    assert_eq!(
+        get_current_namespace(&mut core).unwrap(),
+        StoreNamespace::new(b"other")
+    );
+
+    execute(pick("default"), &mut core).unwrap();
+    let select = Command::Select(SelectCommand {
+        grouping: grouping.clone(),
+        condition: SelectCondition::NameProperty(
+            PropertyName::from("name"),
+            UnitContent::String(String::from("y")),
+        ),
+        height: None,
+        skip: 0,
+        limit: None,
+        sort: None,
+        continuation: None,
+        projection: None,
+    });
+    match execute(select, &mut core) {
+        Ok(Outcome::Select(outcome)) => {
+            let mut ids: Vec<UnitId> = outcome.units.iter().map(|unit| unit.id).collect();
+            ids.sort_by_key(|id| id.as_int());
+            assert_eq!(ids, vec![UnitId::new(2), UnitId::new(3)]);
+        }
+        _ => panic!("Failed to execute select command"),
+    }
+}
+
+#[test]
+fn test_diff() {
+    let data_root = format!("/tmp/immuxdb_test_diff/");
+    reset_db_dir(&data_root).unwrap();
+
+    let namespace = StoreNamespace::new("default".as_bytes());
+    let grouping = GroupingLabel::from("grouping".as_bytes());
+    let mut core = ImmuxDBCore::new(&KeyValueEngine::Rocks, &data_root, &namespace).unwrap();
+
+    let json = |text: &str| UnitContent::JsonString(String::from(text));
+    let insert = |id: u128, content: UnitContent| {
+        Command::Insert(InsertCommand {
+            grouping: grouping.clone(),
+            targets: vec![InsertCommandSpec {
+                id: UnitId::new(id),
+                content,
+            }],
+        })
+    };
+    execute(insert(1, json(r#"{"name": "old", "age": 1}"#)), &mut core).unwrap();
+    execute(
+        insert(2, UnitContent::String(String::from("gone"))),
+        &mut core,
+    )
+    .unwrap();
+    let from_height = get_current_height(&mut core).unwrap();
+    execute(insert(1, json(r#"{"name": "new", "age": 1}"#)), &mut core).unwrap();
+    execute(insert(3, UnitContent::Bool(true)), &mut core).unwrap();
+    let remove = Command::Remove(RemoveCommand {
+        grouping: grouping.clone(),
+        ids: vec![UnitId::new(2)],
+    });
+    execute(remove, &mut core).unwrap();
+
+    let diff = Command::Diff(DiffCommand {
+        grouping: Some(grouping.clone()),
+        from_height: from_height.into(),
+        to_height: None,
+    });
+    let mut changes = match execute(diff, &mut core) {
+        Ok(Outcome::Diff(outcome)) => outcome.changes,
+        _ => panic!("Failed to diff chain"),
+    };
+    changes.sort_by_key(|change| change.specifier.as_ref().unwrap().get_id().as_int());
+    assert_eq!(changes.len(), 3);
+
+    assert_eq!(changes[0].after, Some(json(r#"{"name": "new", "age": 1}"#)));
+    assert_eq!(
+        changes[0].fields,
+        vec![FieldDiff {
+            path: String::from("name"),
+            before: Some(serde_json::json!("old")),
+            after: Some(serde_json::json!("new")),
+        }]
+    );
+    assert_eq!(
+        changes[1].before,
+        Some(UnitContent::String(String::from("gone")))
+    );
+    assert!(changes[1].fields.is_empty());
+    assert_eq!(changes[2].before, None);
+    assert_eq!(changes[2].after, Some(UnitContent::Bool(true)));
+}
+
+#[test]
+fn test_change_feed() {
+    let data_root = format!("/tmp/immuxdb_test_change_feed/");
+    reset_db_dir(&data_root).unwrap();
+
+    let namespace = StoreNamespace::new("default".as_bytes());
+    let grouping = GroupingLabel::from("grouping".as_bytes());
+    let mut core = ImmuxDBCore::new(&KeyValueEngine::Rocks, &data_root, &namespace).unwrap();
+
+    let content = |name: &str| UnitContent::String(String::from(name));
+    let insert = |id: u128, name: &str| {
+        Command::Insert(InsertCommand {
+            grouping: grouping.clone(),
+            targets: vec![InsertCommandSpec {
+                id: UnitId::new(id),
+                content: content(name),
+            }],
+        })
+    };
+    execute(insert(1, "first"), &mut core).unwrap();
+    execute(insert(2, "second"), &mut core).unwrap();
+
+    let mut feed = ChangeFeed::new(&mut core, ChainHeight::new(0));
+    let events: Vec<(UnitId, Option<UnitContent>)> = feed
+        .by_ref()
+        .map(|event| event.unwrap())
+        .map(|event| (event.id, event.content))
+        .collect();
+    assert_eq!(
+        events,
+        vec![
+            (UnitId::new(1), Some(content("first"))),
+            (UnitId::new(2), Some(content("second"))),
+        ]
+    );
+    let resume_height = feed.get_height();
+
+    let remove = Command::Remove(RemoveCommand {
+        grouping: grouping.clone(),
+        ids: vec![UnitId::new(1)],
+    });
+    execute(remove, &mut core).unwrap();
+    execute(insert(3, "third"), &mut core).unwrap();
+
+    let events: Vec<(UnitId, Option<UnitContent>)> = ChangeFeed::new(&mut core, resume_height)
+        .map(|event| event.unwrap())
+        .map(|event| (event.id, event.content))
+        .collect();
+    assert_eq!(
+        events,
+        vec![
+            (UnitId::new(1), None),
+            (UnitId::new(3), Some(content("third"))),
+        ]
+    );
+}
+
+#[test]
+fn test_revert_all_at_time() {
+    let data_root = format!("/tmp/immuxdb_test_revert_all_at_time/");
+    reset_db_dir(&data_root).unwrap();
+
+    let namespace = StoreNamespace::new("default".as_bytes());
+    let grouping = GroupingLabel::from("grouping".as_bytes());
+    let mut core = ImmuxDBCore::new(&KeyValueEngine::Rocks, &data_root, &namespace).unwrap();
+
+    let content = |name: &str| UnitContent::String(String::from(name));
+    let insert = |name: &str| {
+        Command::Insert(InsertCommand {
+            grouping: grouping.clone(),
+            targets: vec![InsertCommandSpec {
+                id: UnitId::new(1),
+                content: content(name),
+            }],
+        })
+    };
+    let select = |height: Option<HeightSpecifier>| {
+        Command::Select(SelectCommand {
+            grouping: grouping.clone(),
+            condition: SelectCondition::Id(UnitId::new(1)),
+            height,
+            skip: 0,
+            limit: None,
+            sort: None,
+            continuation: None,
+            projection: None,
+        })
+    };
+    let now = || {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_micros()
+    };
+
+    execute(insert("before"), &mut core).unwrap();
+    thread::sleep(Duration::from_millis(5));
+    let checkpoint = HeightSpecifier::Time(now());
+    thread::sleep(Duration::from_millis(5));
+    execute(insert("after"), &mut core).unwrap();
+
+    match execute(select(Some(checkpoint.clone())), &mut core) {
+        Ok(Outcome::Select(outcome)) => assert_eq!(outcome.units[0].content, content("before")),
+        _ => panic!("Failed to select at time"),
+    }
+
+    let revert_all = Command::RevertAll(RevertAllCommand {
+        target_height: checkpoint,
+    });
+    execute(revert_all, &mut core).unwrap();
+    match execute(select(None), &mut core) {
+        Ok(Outcome::Select(outcome)) => assert_eq!(outcome.units[0].content, content("before")),
+        _ => panic!("Failed to select after reverting"),
+    }
+}
+
+#[test]
+fn test_revert_record() {
+    let data_root = format!("/tmp/immuxdb_test_revert_record/");
+    reset_db_dir(&data_root).unwrap();
+
+    let namespace = StoreNamespace::new("default".as_bytes());
+    let grouping = GroupingLabel::from("grouping".as_bytes());
+    let mut core = ImmuxDBCore::new(&KeyValueEngine::Rocks, &data_root, &namespace).unwrap();
+
+    let create_index = Command::CreateIndex(CreateIndexCommand {
+        grouping: grouping.clone(),
+        name: PropertyName::from("name"),
+    });
+    execute(create_index, &mut core).unwrap();
+
+    let content = |name: &str| UnitContent::JsonString(format!(r#"{{"name": "{}"}}"#, name));
+    let insert = |targets: Vec<(u128, &str)>| {
+        Command::Insert(InsertCommand {
+            grouping: grouping.clone(),
+            targets: targets
+                .into_iter()
+                .map(|(id, name)| InsertCommandSpec {
+                    id: UnitId::new(id),
+                    content: content(name),
+                })
+                .collect(),
+        })
+    };
+    execute(insert(vec![(1, "x")]), &mut core).unwrap();
+    execute(insert(vec![(1, "y"), (2, "y")]), &mut core).unwrap();
+    let inspect = Command::Inspect(InspectCommand {
+        specifier: UnitSpecifier::new(grouping.clone(), UnitId::new(2)),
+        height: None,
+    });
+    let bad_height = match execute(inspect, &mut core) {
+        Ok(Outcome::Inspect(outcome)) => outcome.inspections[0].height,
+        _ => panic!("Failed to execute inspect command"),
+    };
+    execute(insert(vec![(2, "z")]), &mut core).unwrap();
+
+    let revert_record = Command::RevertRecord(RevertRecordCommand {
+        height: bad_height.into(),
+    });
+    match execute(revert_record, &mut core) {
+        Ok(Outcome::RevertRecord(outcome)) => {
+            let ids = |specifiers: &Vec<UnitSpecifier>| -> Vec<UnitId> {
+                specifiers
+                    .iter()
+                    .map(|specifier| specifier.get_id())
+                    .collect()
+            };
+            assert_eq!(ids(&outcome.reverted), vec![UnitId::new(1)]);
+            assert_eq!(ids(&outcome.conflicts), vec![UnitId::new(2)]);
+        }
+        _ => panic!("Failed to execute revert record command"),
+    }
+
+    let select_by_name = |name: &str| {
+        Command::Select(SelectCommand {
+            grouping: grouping.clone(),
+            condition: SelectCondition::NameProperty(
+                PropertyName::from("name"),
+                UnitContent::String(String::from(name)),
+            ),
+            height: None,
+            skip: 0,
+            limit: None,
+            sort: None,
+            continuation: None,
+            projection: None,
+        })
+    };
+    let expectations = vec![("x", vec![1]), ("y", vec![]), ("z", vec![2])];
+    for (name, expected_ids) in expectations {
+        match execute(select_by_name(name), &mut core) {
+            Ok(Outcome::Select(outcome)) => {
+                let ids: Vec<UnitId> = outcome.units.iter().map(|unit| unit.id).collect();
+                let expected_ids: Vec<UnitId> = expected_ids.into_iter().map(UnitId::new).collect();
+                assert_eq!(ids, expected_ids);
+            }
+            _ => panic!("Failed to execute select command"),
+        }
+    }
+}
+
+/// A revert the store refuses must not leave the index pointing at the contents it would have
+/// restored.
+#[test]
+fn test_failed_revert_leaves_index() {
+    let data_root = format!("/tmp/immuxdb_test_failed_revert_leaves_index/");
+    reset_db_dir(&data_root).unwrap();
+
+    let namespace = StoreNamespace::new("default".as_bytes());
+    let grouping = GroupingLabel::from("grouping".as_bytes());
+    let id = UnitId::new(1);
+    let mut core = ImmuxDBCore::new(&KeyValueEngine::Rocks, &data_root, &namespace).unwrap();
+
+    let create_index = Command::CreateIndex(CreateIndexCommand {
+        grouping: grouping.clone(),
+        name: PropertyName::from("name"),
+    });
+    execute(create_index, &mut core).unwrap();
+
+    let insert = |id: UnitId, name: &str| {
+        Command::Insert(InsertCommand {
+            grouping: grouping.clone(),
+            targets: vec![InsertCommandSpec {
+                id,
+                content: UnitContent::JsonString(format!(r#"{{"name": "{}"}}"#, name)),
+            }],
+        })
+    };
+    execute(insert(id, "x"), &mut core).unwrap();
+    execute(insert(UnitId::new(2), "w"), &mut core).unwrap();
+    let inspect = Command::Inspect(InspectCommand {
+        specifier: UnitSpecifier::new(grouping.clone(), UnitId::new(2)),
+        height: None,
+    });
+    // Unit 1 reads "x" here, but was not updated at this height
+    let other_height = match execute(inspect, &mut core) {
+        Ok(Outcome::Inspect(outcome)) => outcome.inspections[0].height,
+        _ => panic!("Failed to execute inspect command"),
+    };
+    execute(insert(id, "y"), &mut core).unwrap();
+
+    let revert = Command::RevertMany(RevertManyCommand {
+        specs: vec![RevertCommandTargetSpec {
+            specifier: UnitSpecifier::new(grouping.clone(), id),
+            target_height: other_height.into(),
+        }],
+    });
+    match execute(revert, &mut core) {
+        Err(ImmuxError::VKV(VkvError::CannotFindSuitableVersion)) => (),
+        _ => panic!("Reverting to a height without an update should fail"),
+    }
+
+    let expectations = vec![("x", vec![]), ("y", vec![id])];
+    for (name, expected_ids) in expectations {
+        let select = Command::Select(SelectCommand {
+            grouping: grouping.clone(),
+            condition: SelectCondition::NameProperty(
+                PropertyName::from("name"),
+                UnitContent::String(String::from(name)),
+            ),
+            height: None,
+            skip: 0,
+            limit: None,
+            sort: None,
+            continuation: None,
+            projection: None,
+        });
+        match execute(select, &mut core) {
+            Ok(Outcome::Select(outcome)) => {
+                let ids: Vec<UnitId> = outcome.units.iter().map(|unit| unit.id).collect();
+                assert_eq!(ids, expected_ids);
+            }
+            _ => panic!("Failed to execute select command"),
+        }
+    }
+}
+
+#[test]
+fn test_tags_and_record_meta() {
+    let data_root = format!("/tmp/immuxdb_test_tags_and_record_meta/");
+    reset_db_dir(&data_root).unwrap();
+
+    let namespace = StoreNamespace::new("default".as_bytes());
+    let grouping = GroupingLabel::from("grouping".as_bytes());
+    let mut core = ImmuxDBCore::new(&KeyValueEngine::Rocks, &data_root, &namespace).unwrap();
+
+    let content = |name: &str| UnitContent::String(String::from(name));
+    let insert = |name: &str| {
+        Command::Insert(InsertCommand {
+            grouping: grouping.clone(),
+            targets: vec![InsertCommandSpec {
+                id: UnitId::new(1),
+                content: content(name),
+            }],
+        })
+    };
+    let select = |height: Option<HeightSpecifier>| {
+        Command::Select(SelectCommand {
+            grouping: grouping.clone(),
+            condition: SelectCondition::Id(UnitId::new(1)),
+            height,
+            skip: 0,
+            limit: None,
+            sort: None,
+            continuation: None,
+            projection: None,
+        })
+    };
+    let release = HeightSpecifier::Tag(String::from("release"));
+
+    let meta = RecordMeta {
+        author: Some(String::from("alice")),
+        message: Some(String::from("first release")),
+        client_id: Some(String::from("client")),
+    };
+    execute_with_meta(insert("released"), meta.clone(), &mut core).unwrap();
+    let create_tag = Command::CreateTag(CreateTagCommand {
+        name: String::from("release"),
+        height: None,
+    });
+    let tagged_height = match execute(create_tag, &mut core) {
+        Ok(Outcome::CreateTag(outcome)) => outcome.tag.height,
+        _ => panic!("Failed to create tag"),
+    };
+    execute(insert("unreleased"), &mut core).unwrap();
+
+    match execute(select(Some(release.clone())), &mut core) {
+        Ok(Outcome::Select(outcome)) => assert_eq!(outcome.units[0].content, content("released")),
+        _ => panic!("Failed to select at tag"),
+    }
+    let inspect = Command::Inspect(InspectCommand {
+        specifier: UnitSpecifier::new(grouping.clone(), UnitId::new(1)),
+        height: Some(release.clone()),
+    });
+    match execute(inspect, &mut core) {
+        Ok(Outcome::Inspect(outcome)) => {
+            assert_eq!(outcome.inspections.len(), 1);
+            assert_eq!(outcome.inspections[0].height, tagged_height);
+        }
+        _ => panic!("Failed to inspect at tag"),
+    }
+
+    let revert_all = Command::RevertAll(RevertAllCommand {
+        target_height: release.clone(),
+    });
+    execute(revert_all, &mut core).unwrap();
+    match execute(select(None), &mut core) {
+        Ok(Outcome::Select(outcome)) => assert_eq!(outcome.units[0].content, content("released")),
+        _ => panic!("Failed to select after reverting to tag"),
+    }
+
+    // Only the insert made with metadata carries it
+    let events: Vec<_> = ChangeFeed::new(&mut core, ChainHeight::new(0))
+        .map(|event| event.unwrap())
+        .collect();
+    assert_eq!(events.len(), 3);
+    assert_eq!(events[0].meta, meta);
+    assert_eq!(events[1].meta, RecordMeta::default());
+    assert_eq!(events[2].meta, RecordMeta::default());
+
+    match execute(Command::ListTags, &mut core) {
+        Ok(Outcome::ListTags(outcome)) => assert_eq!(outcome.tags.len(), 1),
+        _ => panic!("Failed to list tags"),
+    }
+    let delete_tag = Command::DeleteTag(DeleteTagCommand {
+        name: String::from("release"),
+    });
+    execute(delete_tag, &mut core).unwrap();
+    match execute(select(Some(release)), &mut core) {
+        Err(ImmuxError::Executor(ExecutorError::MissingTag(_))) => {}
+        _ => panic!("Selected at a deleted tag"),
+    }
+}
+
+#[test]
+fn test_retention_and_compaction() {
+    let data_root = format!("/tmp/immuxdb_test_retention_and_compaction/");
+    reset_db_dir(&data_root).unwrap();
+
+    let namespace = StoreNamespace::new("default".as_bytes());
+    let grouping = GroupingLabel::from("grouping".as_bytes());
+    let mut core = ImmuxDBCore::new(&KeyValueEngine::Rocks, &data_root, &namespace).unwrap();
+
+    let content = |version: u8| UnitContent::String(format!("version {}", version));
+    for version in 1..=4 {
+        let insert = Command::Insert(InsertCommand {
+            grouping: grouping.clone(),
+            targets: vec![InsertCommandSpec {
+                id: UnitId::new(1),
+                content: content(version),
+            }],
+        });
+        execute(insert, &mut core).unwrap();
+    }
+    let select = |height: u64| {
+        Command::Select(SelectCommand {
+            grouping: grouping.clone(),
+            condition: SelectCondition::Id(UnitId::new(1)),
+            height: Some(ChainHeight::new(height).into()),
+            skip: 0,
+            limit: None,
+            sort: None,
+            continuation: None,
+            projection: None,
+        })
+    };
+
+    let set_retention = Command::SetRetention(SetRetentionCommand {
+        grouping: None,
+        policy: Some(RetentionPolicy::KeepHeights(2)),
+    });
+    execute(set_retention, &mut core).unwrap();
+    match execute(Command::Compact, &mut core) {
+        Ok(Outcome::Compact(outcome)) => {
+            assert_eq!(outcome.markers[0].height, ChainHeight::new(3));
+            assert_eq!(outcome.pruned_records, 2);
+        }
+        _ => panic!("Failed to compact"),
+    }
+
+    match execute(select(3), &mut core) {
+        Ok(Outcome::Select(outcome)) => assert_eq!(outcome.units[0].content, content(3)),
+        _ => panic!("Failed to select at a kept height"),
+    }
+    match execute(select(1), &mut core) {
+        Err(ImmuxError::VKV(VkvError::HeightPruned(height, pruned_height))) => {
+            assert_eq!(height, ChainHeight::new(1));
+            assert_eq!(pruned_height, ChainHeight::new(3));
+        }
+        _ => panic!("Selected below the prune marker"),
+    }
+    let inspect = Command::Inspect(InspectCommand {
+        specifier: UnitSpecifier::new(grouping.clone(), UnitId::new(1)),
+        height: None,
+    });
+    match execute(inspect, &mut core) {
+        Ok(Outcome::Inspect(outcome)) => {
+            let heights: Vec<_> = outcome.inspections.iter().map(|i| i.height).collect();
+            assert_eq!(heights, vec![ChainHeight::new(3), ChainHeight::new(4)]);
+        }
+        _ => panic!("Failed to inspect compacted unit"),
+    }
+    match execute(Command::VerifyChain, &mut core) {
+        Ok(Outcome::VerifyChain(outcome)) => assert_eq!(outcome.broken_height, None),
+        _ => panic!("Failed to verify compacted chain"),
+    }
+    match execute(Command::GetRetention, &mut core) {
+        Ok(Outcome::GetRetention(outcome)) => {
+            assert_eq!(outcome.rules.len(), 1);
+            assert_eq!(outcome.markers.len(), 1);
+        }
+        _ => panic!("Failed to get retention"),
+    }
+}
+
+/// Rebuild an indexed chain on another engine and read it back from there.
+#[test]
+fn test_replay_chain() {
+    let data_root = format!("/tmp/immuxdb_test_replay_chain/");
+    let replayed_root = format!("/tmp/immuxdb_test_replay_chain_replayed/");
+    reset_db_dir(&data_root).unwrap();
+    reset_db_dir(&replayed_root).unwrap();
+
+    let namespace = StoreNamespace::new("default".as_bytes());
+    let grouping = GroupingLabel::from("grouping".as_bytes());
+    let mut core = ImmuxDBCore::new(&KeyValueEngine::Rocks, &data_root, &namespace).unwrap();
+
+    let create_index = Command::CreateIndex(CreateIndexCommand {
+        grouping: grouping.clone(),
+        name: PropertyName::from("name"),
+    });
+    execute(create_index, &mut core).unwrap();
+    for (id, name) in vec![(1, "immux"), (2, "immux"), (1, "db")] {
+        let insert = Command::Insert(InsertCommand {
+            grouping: grouping.clone(),
+            targets: vec![InsertCommandSpec {
+                id: UnitId::new(id),
+                content: UnitContent::JsonString(format!(r#"{{"name": "{}"}}"#, name)),
+            }],
+        });
+        execute(insert, &mut core).unwrap();
+    }
+    let verified = match execute(Command::VerifyChain, &mut core) {
+        Ok(Outcome::VerifyChain(outcome)) => outcome,
+        _ => panic!("Failed to verify chain"),
+    };
+
+    for (engine, data_root) in vec![
+        (KeyValueEngine::HashMap, String::new()),
+        (KeyValueEngine::Rocks, replayed_root.clone()),
+    ] {
+        let replay = Command::ReplayChain(ReplayChainCommand {
+            engine,
+            data_root,
+            chain: None,
+        });
+        match execute(replay, &mut core) {
+            Ok(Outcome::ReplayChain(outcome)) => {
+                assert_eq!(outcome.height, verified.height);
+                assert_eq!(outcome.head, verified.head);
+                assert!(outcome.mismatches.is_empty());
+            }
+            _ => panic!("Failed to replay chain"),
+        }
+    }
+
+    let mut replayed_core =
+        ImmuxDBCore::new(&KeyValueEngine::Rocks, &replayed_root, &namespace).unwrap();
+    let select = Command::Select(SelectCommand {
+        grouping: grouping.clone(),
+        condition: SelectCondition::NameProperty(
+            PropertyName::from("name"),
+            UnitContent::String(String::from("immux")),
+        ),
+        height: None,
+        skip: 0,
+        limit: None,
+        sort: None,
+        continuation: None,
+        projection: None,
+    });
+    match execute(select, &mut replayed_core) {
+        Ok(Outcome::Select(outcome)) => {
+            assert_eq!(outcome.units.len(), 1);
+            assert_eq!(outcome.units[0].id, UnitId::new(2));
+        }
+        _ => panic!("Failed to select from replayed chain"),
+    }
+}
+
+/// Export an indexed chain and import it back under another name.
+#[test]
+fn test_export_and_import_chain() {
+    let data_root = format!("/tmp/immuxdb_test_export_and_import_chain/");
+    reset_db_dir(&data_root).unwrap();
+
+    let namespace = StoreNamespace::new("default".as_bytes());
+    let grouping = GroupingLabel::from("grouping".as_bytes());
+    let mut core = ImmuxDBCore::new(&KeyValueEngine::Rocks, &data_root, &namespace).unwrap();
+
+    let create_index = Command::CreateIndex(CreateIndexCommand {
+        grouping: grouping.clone(),
+        name: PropertyName::from("name"),
+    });
+    execute(create_index, &mut core).unwrap();
+    for (id, name) in vec![(1, "immux"), (2, "db")] {
+        let insert = Command::Insert(InsertCommand {
+            grouping: grouping.clone(),
+            targets: vec![InsertCommandSpec {
+                id: UnitId::new(id),
+                content: UnitContent::JsonString(format!(r#"{{"name": "{}"}}"#, name)),
+            }],
+        });
+        execute(insert, &mut core).unwrap();
+    }
+    let exported = match execute(Command::ExportChain, &mut core) {
+        Ok(Outcome::ExportChain(outcome)) => outcome,
+        _ => panic!("Failed to export chain"),
+    };
+    assert_eq!(exported.chain, ChainName::from("default"));
+
+    let mut tampered = exported.archive.clone();
+    let middle = tampered.len() / 2;
+    tampered[middle] ^= 0xff;
+    let import = |archive: Vec<u8>| {
+        Command::ImportChain(ImportChainCommand {
+            archive,
+            chain: Some(ChainName::from("restored")),
+        })
+    };
+    match execute(import(tampered), &mut core) {
+        Err(ImmuxError::Archive(ArchiveError::ChecksumMismatch)) => {}
+        _ => panic!("Imported a tampered archive"),
+    }
+    match execute(import(exported.archive.clone()), &mut core) {
+        Ok(Outcome::ImportChain(outcome)) => {
+            assert_eq!(outcome.chain, ChainName::from("restored"));
+            assert_eq!(outcome.height, exported.height);
+            assert_eq!(outcome.head, exported.head);
+        }
+        _ => panic!("Failed to import chain"),
+    }
+    match execute(import(exported.archive.clone()), &mut core) {
+        Err(ImmuxError::VKV(VkvError::ImportTargetNotEmpty)) => {}
+        _ => panic!("Imported into a chain with records"),
+    }
+
+    let changes = |core: &mut ImmuxDBCore| {
+        let command = Command::ChangeFeed(ChangeFeedCommand {
+            after_height: ChainHeight::new(0),
+            limit: 10,
+        });
+        match execute(command, core) {
+            Ok(Outcome::ChangeFeed(outcome)) => outcome
+                .events
+                .iter()
+                .map(|event| (event.height, event.sys_time))
+                .collect::<Vec<_>>(),
+            _ => panic!("Failed to read changes"),
+        }
+    };
+    let original_changes = changes(&mut core);
+    let pick_restored = Command::PickChain(PickChainCommand {
+        new_chain_name: ChainName::from("restored"),
+    });
+    execute(pick_restored, &mut core).unwrap();
+    assert_eq!(changes(&mut core), original_changes);
+    let select = Command::Select(SelectCommand {
+        grouping: grouping.clone(),
+        condition: SelectCondition::NameProperty(
+            PropertyName::from("name"),
+            UnitContent::String(String::from("db")),
+        ),
+        height: None,
+        skip: 0,
+        limit: None,
+        sort: None,
+        continuation: None,
+        projection: None,
+    });
+    match execute(select, &mut core) {
+        Ok(Outcome::Select(outcome)) => {
+            assert_eq!(outcome.units.len(), 1);
+            assert_eq!(outcome.units[0].id, UnitId::new(2));
+        }
+        _ => panic!("Failed to select from imported chain"),
+    }
+}
@@ -0,0 +1,53 @@
+use crate::declarations::commands::{Outcome, VerifyChainOutcome};
+use crate::declarations::errors::{ImmuxError, ImmuxResult};
+use crate::executor::errors::ExecutorError;
+use crate::storage::core::CoreStore;
+use crate::storage::instructions::{Answer, DBSystemAnswer, VerifyChainInstruction};
+
+pub fn execute_verify_chain(core: &mut impl CoreStore) -> ImmuxResult<Outcome> {
+    match core.execute(&VerifyChainInstruction {}.into()) {
+        Err(error) => return Err(error),
+        Ok(Answer::DBSystem(DBSystemAnswer::VerifyChainOk(answer))) => {
+            return Ok(Outcome::VerifyChain(VerifyChainOutcome {
+                height: answer.height,
+                head: answer.head,
+                broken_height: answer.broken_height,
+            }));
+        }
+        Ok(answer) => {
+            return Err(ImmuxError::Executor(ExecutorError::UnexpectedAnswerType(
+                answer,
+            )))
+        }
+    }
+}
+
+#[cfg(test)]
+mod verify_chain_executor_tests {
+    use crate::declarations::commands::Outcome;
+    use crate::executor::tests::FixtureCore;
+    use crate::executor::verify_chain_executor::execute_verify_chain;
+    use crate::storage::instructions::{DBSystemInstruction, Instruction, VerifyChainOkAnswer};
+    use crate::storage::vkv::{ChainHeight, RecordHash};
+
+    #[test]
+    fn test_verify_chain() {
+        let mut core = FixtureCore::new(Box::new(|instruction| match instruction {
+            Instruction::DBSystem(DBSystemInstruction::VerifyChain(_)) => Ok(VerifyChainOkAnswer {
+                height: ChainHeight::new(10),
+                head: RecordHash::digest(&[b"head"]),
+                broken_height: Some(ChainHeight::new(3)),
+            }
+            .into()),
+            instruction => panic!("Unexpected instruction: {:?}", instruction),
+        }));
+        match execute_verify_chain(&mut core).unwrap() {
+            Outcome::VerifyChain(outcome) => {
+                assert_eq!(outcome.height, ChainHeight::new(10));
+                assert_eq!(outcome.head, RecordHash::digest(&[b"head"]));
+                assert_eq!(outcome.broken_height, Some(ChainHeight::new(3)));
+            }
+            outcome => panic!("Unexpected outcome {:?}", outcome),
+        }
+    }
+}
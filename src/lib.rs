@@ -8,6 +8,7 @@ pub mod utils;
 use crate::config::{save_config, ImmuxDBConfiguration, DEFAULT_CHAIN_NAME};
 use crate::cortices::tcp::setup_cortices;
 use crate::declarations::errors::ImmuxResult;
+use crate::executor::shared::migrate_index_layout;
 use crate::storage::core::ImmuxDBCore;
 use crate::storage::instructions::StoreNamespace;
 
@@ -17,6 +18,7 @@ pub fn run_immuxdb(config: &ImmuxDBConfiguration) -> ImmuxResult<()> {
         &config.data_root,
         &StoreNamespace::new(DEFAULT_CHAIN_NAME.as_bytes()),
     )?;
+    migrate_index_layout(&mut core)?;
     save_config(config, &mut core)?;
     setup_cortices(core, config)?;
     return Ok(());
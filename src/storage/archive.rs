@@ -0,0 +1,360 @@
+/*
+ *  Portable chain archives
+**/
+
+use bincode::{deserialize, serialize};
+use serde::{Deserialize, Serialize};
+
+use crate::config::DB_VERSION;
+use crate::declarations::basics::db_version::DBVersion;
+use crate::declarations::basics::{ChainName, GroupingLabel, PropertyNameList};
+use crate::declarations::errors::ImmuxError;
+use crate::storage::vkv::{ChainHeight, InstructionRecord, RecordHash};
+use crate::utils::{u32_to_u8_array, u8_array_to_u32, varint_decode, varint_encode};
+
+const ARCHIVE_MAGIC: &[u8; 8] = b"IMMUXARC";
+pub const ARCHIVE_FORMAT_VERSION: u32 = 2;
+// Archives whose records were sealed without their version and system time
+const UNTIMED_HASH_FORMAT_VERSION: u32 = 1;
+const CHECKSUM_LENGTH: usize = 32;
+
+#[derive(Debug)]
+pub enum ArchiveError {
+    UnexpectedMagic,
+    UnsupportedFormatVersion(u32),
+    // Archives written by a newer database may hold instructions this one cannot execute
+    UnsupportedDBVersion(DBVersion),
+    ChecksumMismatch,
+    Truncated,
+    CannotSerialize,
+    Parsing,
+    // The first height whose record does not follow the hash chain
+    BrokenChain(ChainHeight),
+    IndexMismatch(GroupingLabel),
+}
+
+impl From<ArchiveError> for ImmuxError {
+    fn from(error: ArchiveError) -> ImmuxError {
+        ImmuxError::Archive(error)
+    }
+}
+
+/// The properties indexed in a grouping
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct IndexDefinition {
+    pub grouping: GroupingLabel,
+    pub names: PropertyNameList,
+}
+
+/// A whole chain, independent of the key-value engine and of the storage layout it was kept in.
+///
+/// Marshalled as the magic bytes, the format version, the database version, the chain name and
+/// the index definitions, followed by the number of records and each record prefixed by its
+/// length. The SHA-256 of everything before it closes the archive.
+#[derive(Debug)]
+pub struct ChainArchive {
+    pub db_version: DBVersion,
+    pub chain: ChainName,
+    pub indexes: Vec<IndexDefinition>,
+    // Ordered from height 1
+    pub records: Vec<InstructionRecord>,
+}
+
+fn push_with_length(result: &mut Vec<u8>, data: &[u8]) {
+    result.extend(varint_encode(data.len() as u64));
+    result.extend_from_slice(data);
+}
+
+struct ArchiveReader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> ArchiveReader<'a> {
+    fn read_bytes(&mut self, length: usize) -> Result<&'a [u8], ArchiveError> {
+        if self.data.len() - self.position < length {
+            return Err(ArchiveError::Truncated);
+        }
+        let bytes = &self.data[self.position..self.position + length];
+        self.position += length;
+        return Ok(bytes);
+    }
+    fn read_u32(&mut self) -> Result<u32, ArchiveError> {
+        let mut bytes = [0; 4];
+        bytes.copy_from_slice(self.read_bytes(4)?);
+        return Ok(u8_array_to_u32(&bytes));
+    }
+    fn read_varint(&mut self) -> Result<u64, ArchiveError> {
+        match varint_decode(&self.data[self.position..]) {
+            Err(_error) => return Err(ArchiveError::Parsing),
+            Ok((value, width)) => {
+                self.position += width;
+                return Ok(value);
+            }
+        }
+    }
+    fn read_with_length(&mut self) -> Result<&'a [u8], ArchiveError> {
+        let length = self.read_varint()?;
+        return self.read_bytes(length as usize);
+    }
+}
+
+fn reseal_untimed_records(records: &mut [InstructionRecord]) -> Result<(), ArchiveError> {
+    let mut expected_previous_hash = RecordHash::genesis();
+    let mut previous_hash = RecordHash::genesis();
+    let mut height = ChainHeight::new(0);
+    for record in records.iter_mut() {
+        height.increment();
+        let untimed_hash = match record.compute_untimed_content_hash() {
+            Err(_error) => return Err(ArchiveError::BrokenChain(height)),
+            Ok(hash) => hash,
+        };
+        if record.previous_hash != expected_previous_hash || record.content_hash != untimed_hash {
+            return Err(ArchiveError::BrokenChain(height));
+        }
+        expected_previous_hash = record.chained_hash();
+        record.previous_hash = previous_hash;
+        record.content_hash = match record.compute_content_hash() {
+            Err(_error) => return Err(ArchiveError::CannotSerialize),
+            Ok(hash) => hash,
+        };
+        previous_hash = record.chained_hash();
+    }
+    return Ok(());
+}
+
+impl ChainArchive {
+    pub fn new(
+        chain: ChainName,
+        indexes: Vec<IndexDefinition>,
+        records: Vec<InstructionRecord>,
+    ) -> Self {
+        ChainArchive {
+            db_version: DB_VERSION,
+            chain,
+            indexes,
+            records,
+        }
+    }
+
+    pub fn marshal(&self) -> Result<Vec<u8>, ArchiveError> {
+        let mut result = Vec::new();
+        result.extend_from_slice(ARCHIVE_MAGIC);
+        result.extend_from_slice(&u32_to_u8_array(ARCHIVE_FORMAT_VERSION));
+        result.extend_from_slice(&self.db_version.marshal());
+        push_with_length(&mut result, self.chain.as_bytes());
+        match serialize(&self.indexes) {
+            Err(_error) => return Err(ArchiveError::CannotSerialize),
+            Ok(data) => push_with_length(&mut result, &data),
+        }
+        result.extend(varint_encode(self.records.len() as u64));
+        for record in &self.records {
+            match serialize(record) {
+                Err(_error) => return Err(ArchiveError::CannotSerialize),
+                Ok(data) => push_with_length(&mut result, &data),
+            }
+        }
+        let checksum = RecordHash::digest(&[&result]);
+        result.extend_from_slice(checksum.as_bytes());
+        return Ok(result);
+    }
+
+    /// Checks the checksum before reading anything, but not the hash chain of the records, except
+    /// in archives of the untimed format, whose records are checked and sealed again as they are
+    /// now
+    pub fn parse(data: &[u8]) -> Result<Self, ArchiveError> {
+        if data.len() < ARCHIVE_MAGIC.len() + CHECKSUM_LENGTH {
+            return Err(ArchiveError::Truncated);
+        }
+        let (content, checksum) = data.split_at(data.len() - CHECKSUM_LENGTH);
+        if RecordHash::digest(&[content]).as_bytes() != checksum {
+            return Err(ArchiveError::ChecksumMismatch);
+        }
+        let mut reader = ArchiveReader {
+            data: content,
+            position: 0,
+        };
+        if reader.read_bytes(ARCHIVE_MAGIC.len())? != ARCHIVE_MAGIC {
+            return Err(ArchiveError::UnexpectedMagic);
+        }
+        let format_version = reader.read_u32()?;
+        if format_version != ARCHIVE_FORMAT_VERSION && format_version != UNTIMED_HASH_FORMAT_VERSION
+        {
+            return Err(ArchiveError::UnsupportedFormatVersion(format_version));
+        }
+        let db_version = DBVersion::new(reader.read_u32()?);
+        if db_version.as_int() > DB_VERSION.as_int() {
+            return Err(ArchiveError::UnsupportedDBVersion(db_version));
+        }
+        let chain = ChainName::new(reader.read_with_length()?);
+        let indexes = match deserialize::<Vec<IndexDefinition>>(reader.read_with_length()?) {
+            Err(_error) => return Err(ArchiveError::Parsing),
+            Ok(indexes) => indexes,
+        };
+        let record_count = reader.read_varint()?;
+        let mut records = Vec::new();
+        for _ in 0..record_count {
+            match deserialize::<InstructionRecord>(reader.read_with_length()?) {
+                Err(_error) => return Err(ArchiveError::Parsing),
+                Ok(record) => records.push(record),
+            }
+        }
+        if reader.position != content.len() {
+            return Err(ArchiveError::Parsing);
+        }
+        if format_version == UNTIMED_HASH_FORMAT_VERSION {
+            reseal_untimed_records(&mut records)?;
+        }
+        return Ok(ChainArchive {
+            db_version,
+            chain,
+            indexes,
+            records,
+        });
+    }
+
+    /// Recomputes the hash chain of the records, returning the head it leads to
+    pub fn verify_chain(&self) -> Result<RecordHash, ArchiveError> {
+        let mut expected_previous_hash = RecordHash::genesis();
+        let mut height = ChainHeight::new(0);
+        for record in &self.records {
+            height.increment();
+            let content_hash = match record.compute_content_hash() {
+                Err(_error) => return Err(ArchiveError::BrokenChain(height)),
+                Ok(hash) => hash,
+            };
+            if record.previous_hash != expected_previous_hash || record.content_hash != content_hash
+            {
+                return Err(ArchiveError::BrokenChain(height));
+            }
+            expected_previous_hash = record.chained_hash();
+        }
+        return Ok(expected_previous_hash);
+    }
+}
+
+#[cfg(test)]
+mod archive_tests {
+    use crate::declarations::basics::{
+        ChainName, GroupingLabel, PropertyName, PropertyNameList, StoreKey, StoreValue,
+    };
+    use crate::storage::archive::{
+        ArchiveError, ChainArchive, IndexDefinition, CHECKSUM_LENGTH, UNTIMED_HASH_FORMAT_VERSION,
+    };
+    use crate::storage::instructions::{Instruction, SetManyInstruction, SetTargetSpec};
+    use crate::storage::vkv::{ChainHeight, InstructionRecord, RecordHash};
+    use crate::utils::u32_to_u8_array;
+
+    fn make_archive() -> ChainArchive {
+        let mut records = Vec::new();
+        let mut previous_hash = RecordHash::genesis();
+        for byte in 1..=3 {
+            let instruction: Instruction = SetManyInstruction {
+                targets: vec![SetTargetSpec {
+                    key: StoreKey::from("key"),
+                    value: StoreValue::new(Some(vec![byte])),
+                }],
+            }
+            .into();
+            let mut record = InstructionRecord::from(instruction);
+            record.previous_hash = previous_hash;
+            record.content_hash = record.compute_content_hash().unwrap();
+            previous_hash = record.chained_hash();
+            records.push(record);
+        }
+        let indexes = vec![IndexDefinition {
+            grouping: GroupingLabel::from("grouping"),
+            names: PropertyNameList::new(vec![PropertyName::from("name")]),
+        }];
+        ChainArchive::new(ChainName::from("chain"), indexes, records)
+    }
+
+    #[test]
+    fn test_archive_reversibility() {
+        let archive = make_archive();
+        let head = archive.verify_chain().unwrap();
+        let data = archive.marshal().unwrap();
+        assert_eq!(&data[0..8], b"IMMUXARC");
+        let parsed = ChainArchive::parse(&data).unwrap();
+        assert_eq!(parsed.db_version, archive.db_version);
+        assert_eq!(parsed.chain, archive.chain);
+        assert_eq!(parsed.indexes.len(), 1);
+        assert_eq!(parsed.indexes[0].grouping.as_bytes(), b"grouping");
+        assert_eq!(parsed.records.len(), 3);
+        for (parsed_record, record) in parsed.records.iter().zip(archive.records.iter()) {
+            assert_eq!(parsed_record.sys_time, record.sys_time);
+            assert_eq!(parsed_record.chained_hash(), record.chained_hash());
+        }
+        assert_eq!(parsed.verify_chain().unwrap(), head);
+    }
+
+    #[test]
+    fn test_archive_checksum() {
+        let mut data = make_archive().marshal().unwrap();
+        let middle = data.len() / 2;
+        data[middle] ^= 0xff;
+        match ChainArchive::parse(&data) {
+            Err(ArchiveError::ChecksumMismatch) => {}
+            result => panic!("Unexpected result {:?}", result),
+        }
+        match ChainArchive::parse(&data[0..10]) {
+            Err(ArchiveError::Truncated) => {}
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+
+    #[test]
+    fn test_archive_broken_chain() {
+        let mut archive = make_archive();
+        archive.records[2].sys_time += 1;
+        match archive.verify_chain() {
+            Err(ArchiveError::BrokenChain(height)) => assert_eq!(height, ChainHeight::new(3)),
+            result => panic!("Unexpected result {:?}", result),
+        }
+        archive.records[1].instruction = archive.records[0].instruction.to_owned();
+        let data = archive.marshal().unwrap();
+        let parsed = ChainArchive::parse(&data).unwrap();
+        match parsed.verify_chain() {
+            Err(ArchiveError::BrokenChain(height)) => assert_eq!(height, ChainHeight::new(2)),
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+
+    fn seal_untimed(archive: &mut ChainArchive) {
+        let mut previous_hash = RecordHash::genesis();
+        for record in archive.records.iter_mut() {
+            record.previous_hash = previous_hash;
+            record.content_hash = record.compute_untimed_content_hash().unwrap();
+            previous_hash = record.chained_hash();
+        }
+    }
+
+    fn marshal_untimed(archive: &ChainArchive) -> Vec<u8> {
+        let mut data = archive.marshal().unwrap();
+        data.truncate(data.len() - CHECKSUM_LENGTH);
+        data[8..12].copy_from_slice(&u32_to_u8_array(UNTIMED_HASH_FORMAT_VERSION));
+        let checksum = RecordHash::digest(&[&data]);
+        data.extend_from_slice(checksum.as_bytes());
+        data
+    }
+
+    #[test]
+    fn test_parse_untimed_archive() {
+        let archive = make_archive();
+        let head = archive.verify_chain().unwrap();
+        let mut untimed = make_archive();
+        untimed.records = archive.records.clone();
+        seal_untimed(&mut untimed);
+        assert!(untimed.verify_chain().is_err());
+
+        let parsed = ChainArchive::parse(&marshal_untimed(&untimed)).unwrap();
+        assert_eq!(parsed.records.len(), 3);
+        assert_eq!(parsed.verify_chain().unwrap(), head);
+
+        untimed.records[1].instruction = untimed.records[0].instruction.to_owned();
+        match ChainArchive::parse(&marshal_untimed(&untimed)) {
+            Err(ArchiveError::BrokenChain(height)) => assert_eq!(height, ChainHeight::new(2)),
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+}
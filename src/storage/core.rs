@@ -1,5 +1,11 @@
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::time::Duration;
+
 use crate::declarations::errors::{ImmuxError, ImmuxResult};
-use crate::storage::instructions::{Answer, Instruction, StoreNamespace};
+use crate::storage::instructions::{
+    Answer, DBSystemInstruction, DataInstruction, Instruction, StoreNamespace,
+};
 use crate::storage::kv::KeyValueEngine;
 use crate::storage::tkv::{ImmuxDBTransactionKeyValueStore, TransactionKeyValueStore};
 
@@ -7,8 +13,121 @@ pub trait CoreStore {
     fn execute(&mut self, instruction: &Instruction) -> ImmuxResult<Answer>;
 }
 
+/// A core shared between the threads serving the cortices
+pub type SharedCore = Arc<LockedCore>;
+
+/// The core behind its lock, with a signal for the threads waiting for it to be written
+pub struct LockedCore {
+    core: Mutex<ImmuxDBCore>,
+    written: Condvar,
+}
+
+impl LockedCore {
+    pub fn new(core: ImmuxDBCore) -> Self {
+        LockedCore {
+            core: Mutex::new(core),
+            written: Condvar::new(),
+        }
+    }
+}
+
+/// Wakes the threads waiting for a write when released after one
+pub struct CoreGuard<'a> {
+    // Only None while waiting
+    guard: Option<MutexGuard<'a, ImmuxDBCore>>,
+    locked_core: &'a LockedCore,
+    writes_when_locked: u64,
+}
+
+impl<'a> CoreGuard<'a> {
+    fn new(guard: MutexGuard<'a, ImmuxDBCore>, locked_core: &'a LockedCore) -> Self {
+        let writes_when_locked = guard.writes;
+        CoreGuard {
+            guard: Some(guard),
+            locked_core,
+            writes_when_locked,
+        }
+    }
+
+    /// Releases the core until another thread has written to it or `timeout` runs out
+    pub fn wait_for_write(mut self, timeout: Duration) -> Self {
+        let guard = match self.guard.take() {
+            None => unreachable!("Core guard without its lock"),
+            Some(guard) => guard,
+        };
+        let writes = guard.writes;
+        let written = &self.locked_core.written;
+        let guard = match written.wait_timeout_while(guard, timeout, |core| core.writes == writes) {
+            Err(poisoned) => poisoned.into_inner().0,
+            Ok((guard, _timeout)) => guard,
+        };
+        return CoreGuard::new(guard, self.locked_core);
+    }
+}
+
+impl<'a> Deref for CoreGuard<'a> {
+    type Target = ImmuxDBCore;
+
+    fn deref(&self) -> &ImmuxDBCore {
+        match &self.guard {
+            None => unreachable!("Core guard without its lock"),
+            Some(guard) => return guard,
+        }
+    }
+}
+
+impl<'a> DerefMut for CoreGuard<'a> {
+    fn deref_mut(&mut self) -> &mut ImmuxDBCore {
+        match &mut self.guard {
+            None => unreachable!("Core guard without its lock"),
+            Some(guard) => return guard,
+        }
+    }
+}
+
+impl<'a> Drop for CoreGuard<'a> {
+    fn drop(&mut self) {
+        if let Some(guard) = &self.guard {
+            if guard.writes != self.writes_when_locked {
+                self.locked_core.written.notify_all();
+            }
+        }
+    }
+}
+
+pub fn lock_core(core: &SharedCore) -> CoreGuard<'_> {
+    match core.core.lock() {
+        // The lock is taken over from a thread that panicked (e.g. on a malformed message), as
+        // that must not take every other connection down with it. A command panicking halfway
+        // keeps what it had written so far; merge writes nothing before its final instruction,
+        // and compaction saves a prune marker only once its history is dropped. Its record meta
+        // is reset while unwinding (see `execute_with_meta`). The core may stay on the chain the
+        // panicking connection had picked, which later connections start on.
+        Err(poisoned) => return CoreGuard::new(poisoned.into_inner(), core),
+        Ok(guard) => return CoreGuard::new(guard, core),
+    }
+}
+
+/// Whether threads waiting for new records should look again after the instruction. Switching
+/// chains counts, as it changes the records they read.
+fn may_write(instruction: &Instruction) -> bool {
+    match instruction {
+        Instruction::DataAccess(DataInstruction::Read(_)) => return false,
+        Instruction::DBSystem(DBSystemInstruction::ReadNamespace(_))
+        | Instruction::DBSystem(DBSystemInstruction::VerifyChain(_))
+        | Instruction::DBSystem(DBSystemInstruction::ReadTags(_))
+        | Instruction::DBSystem(DBSystemInstruction::SetRecordMeta(_))
+        | Instruction::DBSystem(DBSystemInstruction::ReadRetention(_))
+        | Instruction::DBSystem(DBSystemInstruction::Replay(_))
+        | Instruction::DBSystem(DBSystemInstruction::ExportRecords(_)) => return false,
+        _ => return true,
+    }
+}
+
 pub struct ImmuxDBCore {
     tkv: ImmuxDBTransactionKeyValueStore,
+    // Instructions executed that may have written, counted for the threads waiting on new records
+    writes: u64,
 }
 
 impl ImmuxDBCore {
@@ -18,13 +137,65 @@ impl ImmuxDBCore {
         namespace: &StoreNamespace,
     ) -> Result<ImmuxDBCore, ImmuxError> {
         let tkv = ImmuxDBTransactionKeyValueStore::new(engine_choice, data_root, namespace)?;
-        let core = ImmuxDBCore { tkv };
+        let core = ImmuxDBCore { tkv, writes: 0 };
         Ok(core)
     }
 }
 
 impl CoreStore for ImmuxDBCore {
     fn execute(&mut self, instruction: &Instruction) -> ImmuxResult<Answer> {
+        if may_write(instruction) {
+            self.writes += 1;
+        }
         self.tkv.execute(instruction)
     }
 }
+
+#[cfg(test)]
+mod core_tests {
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    use immuxdb_dev_utils::reset_db_dir;
+
+    use crate::declarations::basics::{StoreKey, StoreValue};
+    use crate::storage::core::{lock_core, CoreStore, ImmuxDBCore, LockedCore, SharedCore};
+    use crate::storage::instructions::{SetManyInstruction, SetTargetSpec, StoreNamespace};
+    use crate::storage::kv::KeyValueEngine;
+
+    #[test]
+    fn test_wait_for_write() {
+        let data_root = "/tmp/immuxdb_test_wait_for_write/";
+        reset_db_dir(data_root).unwrap();
+        let namespace = StoreNamespace::new(b"default");
+        let core = ImmuxDBCore::new(&KeyValueEngine::HashMap, data_root, &namespace).unwrap();
+        let core: SharedCore = Arc::new(LockedCore::new(core));
+
+        let start = Instant::now();
+        let guard = lock_core(&core);
+        let writer = {
+            let core = core.clone();
+            thread::spawn(move || {
+                let instruction = SetManyInstruction {
+                    targets: vec![SetTargetSpec {
+                        key: StoreKey::from("key"),
+                        value: StoreValue::new(Some(vec![1])),
+                    }],
+                };
+                lock_core(&core).execute(&instruction.into()).unwrap();
+            })
+        };
+        let guard = guard.wait_for_write(Duration::from_secs(20));
+        assert!(start.elapsed() < Duration::from_secs(10));
+        drop(guard);
+        writer.join().unwrap();
+
+        // Without a write the wait runs out
+        let start = Instant::now();
+        let timeout = Duration::from_millis(100);
+        let guard = lock_core(&core).wait_for_write(timeout);
+        assert!(start.elapsed() >= timeout);
+        drop(guard);
+    }
+}
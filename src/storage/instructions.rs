@@ -3,9 +3,11 @@ use serde::{Deserialize, Serialize};
 use crate::declarations::basics::{
     BoxedStoreKey, BoxedStoreValue, StoreKey, StoreKeyFragment, StoreValue,
 };
-use crate::storage::kv::KVNamespace;
+use crate::storage::kv::{KVNamespace, KeyValueEngine};
 use crate::storage::tkv::TransactionId;
-use crate::storage::vkv::{ChainHeight, UnitJournal};
+use crate::storage::vkv::{
+    ChainHeight, InstructionRecord, MerkleProof, RecordHash, RecordMeta, UnitJournal,
+};
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct SetTargetSpec {
@@ -57,6 +59,8 @@ pub struct AbortTransactionInstruction {
 pub enum GetManyTargetSpec {
     Keys(Vec<StoreKey>),
     KeyPrefix(StoreKeyFragment),
+    // At most this many keys starting with the prefix and sorting after the key, in key order
+    KeyRange(StoreKeyFragment, Option<StoreKey>, usize),
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -65,6 +69,14 @@ pub struct GetManyInstruction {
     pub targets: GetManyTargetSpec,
 }
 
+impl From<GetManyInstruction> for Instruction {
+    fn from(instruction: GetManyInstruction) -> Instruction {
+        Instruction::DataAccess(DataInstruction::Read(DataReadInstruction::GetMany(
+            instruction,
+        )))
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct GetOneInstruction {
     pub height: Option<ChainHeight>,
@@ -97,11 +109,93 @@ pub struct RevertManyInstruction {
     pub targets: Vec<RevertTargetSpec>,
 }
 
+impl From<RevertManyInstruction> for Instruction {
+    fn from(instruction: RevertManyInstruction) -> Instruction {
+        Instruction::DataAccess(DataInstruction::Write(DataWriteInstruction::RevertMany(
+            instruction,
+        )))
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct RevertAllInstruction {
     pub target_height: ChainHeight,
 }
 
+impl From<RevertAllInstruction> for Instruction {
+    fn from(instruction: RevertAllInstruction) -> Instruction {
+        Instruction::DataAccess(DataInstruction::Write(DataWriteInstruction::RevertAll(
+            instruction,
+        )))
+    }
+}
+
+/// Undoes the instruction recorded at `height`, restoring the keys it wrote to their earlier
+/// values, except for keys written again since
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RevertInstruction {
+    pub height: ChainHeight,
+}
+
+impl From<RevertInstruction> for Instruction {
+    fn from(instruction: RevertInstruction) -> Instruction {
+        Instruction::DataAccess(DataInstruction::Write(DataWriteInstruction::Revert(
+            instruction,
+        )))
+    }
+}
+
+/// Restores `keys`, which the record at `height` wrote, to their values just below it, and writes
+/// `index_targets` in the same batch. The index targets are derived from the restored values, so
+/// a key written again since fails the whole revert instead of being left as it is.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RevertKeysInstruction {
+    pub height: ChainHeight,
+    pub keys: Vec<StoreKey>,
+    pub index_targets: Vec<SetTargetSpec>,
+}
+
+impl From<RevertKeysInstruction> for Instruction {
+    fn from(instruction: RevertKeysInstruction) -> Instruction {
+        Instruction::DataAccess(DataInstruction::Write(DataWriteInstruction::RevertKeys(
+            instruction,
+        )))
+    }
+}
+
+/// Like `RevertManyInstruction`, with the index entries derived from the reverted values written
+/// in the same batch
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct IndexedRevertManyInstruction {
+    pub targets: Vec<RevertTargetSpec>,
+    pub index_targets: Vec<SetTargetSpec>,
+}
+
+impl From<IndexedRevertManyInstruction> for Instruction {
+    fn from(instruction: IndexedRevertManyInstruction) -> Instruction {
+        Instruction::DataAccess(DataInstruction::Write(
+            DataWriteInstruction::IndexedRevertMany(instruction),
+        ))
+    }
+}
+
+/// Records that the chain's records below `height` were pruned, and that the first kept record
+/// links to `head`. Only written by compaction, which anchors its prune marker in the hash chain
+/// with it.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PruneChainInstruction {
+    pub height: ChainHeight,
+    pub head: RecordHash,
+}
+
+impl From<PruneChainInstruction> for Instruction {
+    fn from(instruction: PruneChainInstruction) -> Instruction {
+        Instruction::DataAccess(DataInstruction::Write(DataWriteInstruction::PruneChain(
+            instruction,
+        )))
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct SwitchNamespaceInstruction {
     pub new_namespace: StoreNamespace,
@@ -110,6 +204,212 @@ pub struct SwitchNamespaceInstruction {
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ReadNamespaceInstruction {}
 
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct VerifyChainInstruction {}
+
+impl From<VerifyChainInstruction> for Instruction {
+    fn from(instruction: VerifyChainInstruction) -> Instruction {
+        Instruction::DBSystem(DBSystemInstruction::VerifyChain(instruction))
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ForkNamespaceInstruction {
+    pub source: StoreNamespace,
+    pub at_height: ChainHeight,
+    pub new_namespace: StoreNamespace,
+}
+
+impl From<ForkNamespaceInstruction> for Instruction {
+    fn from(instruction: ForkNamespaceInstruction) -> Instruction {
+        Instruction::DBSystem(DBSystemInstruction::ForkNamespace(instruction))
+    }
+}
+
+/// How to settle a key changed differently on both chains since their common base
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum MergeStrategy {
+    Ours,
+    Theirs,
+    Fail,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MergeResolution {
+    pub key: StoreKey,
+    pub strategy: MergeStrategy,
+}
+
+/// Conflicting keys without a resolution of their own use `default_strategy`. A dry run only
+/// works out what the merge would leave, without failing on conflicts or writing anything.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MergeNamespaceInstruction {
+    pub target: StoreNamespace,
+    pub source: StoreNamespace,
+    pub resolutions: Vec<MergeResolution>,
+    pub default_strategy: MergeStrategy,
+    pub dry_run: bool,
+    // Keys the source changed outside this list are neither merged nor checked for conflicts
+    pub keys: Option<Vec<StoreKey>>,
+    // Written along with the last merged record
+    pub index_targets: Vec<SetTargetSpec>,
+}
+
+impl From<MergeNamespaceInstruction> for Instruction {
+    fn from(instruction: MergeNamespaceInstruction) -> Instruction {
+        Instruction::DBSystem(DBSystemInstruction::MergeNamespace(instruction))
+    }
+}
+
+/// A name for a height, like a git tag
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ChainTag {
+    pub name: String,
+    pub height: ChainHeight,
+}
+
+/// Tags `height`, or the current height if None. Existing tags are not moved.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SetTagInstruction {
+    pub name: String,
+    pub height: Option<ChainHeight>,
+}
+
+impl From<SetTagInstruction> for Instruction {
+    fn from(instruction: SetTagInstruction) -> Instruction {
+        Instruction::DBSystem(DBSystemInstruction::SetTag(instruction))
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RemoveTagInstruction {
+    pub name: String,
+}
+
+impl From<RemoveTagInstruction> for Instruction {
+    fn from(instruction: RemoveTagInstruction) -> Instruction {
+        Instruction::DBSystem(DBSystemInstruction::RemoveTag(instruction))
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ReadTagsInstruction {}
+
+impl From<ReadTagsInstruction> for Instruction {
+    fn from(instruction: ReadTagsInstruction) -> Instruction {
+        Instruction::DBSystem(DBSystemInstruction::ReadTags(instruction))
+    }
+}
+
+/// Attaches `meta` to every record written after it, until the next one replaces it
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SetRecordMetaInstruction {
+    pub meta: RecordMeta,
+}
+
+impl From<SetRecordMetaInstruction> for Instruction {
+    fn from(instruction: SetRecordMetaInstruction) -> Instruction {
+        Instruction::DBSystem(DBSystemInstruction::SetRecordMeta(instruction))
+    }
+}
+
+/// How much history a chain or grouping keeps when compacted. `KeepHeights(n)` keeps the state at
+/// each of the last `n` heights of the chain readable, however few of them wrote to a given unit,
+/// and `KeepSince(time)` keeps the state from `time` on, in microseconds since the Unix epoch.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum RetentionPolicy {
+    KeepHeights(u64),
+    KeepSince(u128),
+}
+
+/// A policy for the keys starting with `scope`, or the whole chain if None
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RetentionRule {
+    pub scope: Option<StoreKeyFragment>,
+    pub policy: RetentionPolicy,
+}
+
+/// History of the keys starting with `scope`, or of the whole chain if None, was pruned below
+/// `height`. `head` is the chain head at the height just below, which the first kept record
+/// links to, so the rest of the chain can still be verified.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PruneMarker {
+    pub scope: Option<StoreKeyFragment>,
+    pub height: ChainHeight,
+    pub head: RecordHash,
+}
+
+/// Sets the policy for `scope`, replacing any earlier one; None removes it
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SetRetentionInstruction {
+    pub scope: Option<StoreKeyFragment>,
+    pub policy: Option<RetentionPolicy>,
+}
+
+impl From<SetRetentionInstruction> for Instruction {
+    fn from(instruction: SetRetentionInstruction) -> Instruction {
+        Instruction::DBSystem(DBSystemInstruction::SetRetention(instruction))
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ReadRetentionInstruction {}
+
+impl From<ReadRetentionInstruction> for Instruction {
+    fn from(instruction: ReadRetentionInstruction) -> Instruction {
+        Instruction::DBSystem(DBSystemInstruction::ReadRetention(instruction))
+    }
+}
+
+/// Prunes history as the retention rules allow
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CompactInstruction {}
+
+impl From<CompactInstruction> for Instruction {
+    fn from(instruction: CompactInstruction) -> Instruction {
+        Instruction::DBSystem(DBSystemInstruction::Compact(instruction))
+    }
+}
+
+/// Re-executes the current chain into a fresh store, named after the current chain unless
+/// `namespace` is given
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ReplayInstruction {
+    pub engine: KeyValueEngine,
+    pub data_root: String,
+    pub namespace: Option<StoreNamespace>,
+}
+
+impl From<ReplayInstruction> for Instruction {
+    fn from(instruction: ReplayInstruction) -> Instruction {
+        Instruction::DBSystem(DBSystemInstruction::Replay(instruction))
+    }
+}
+
+/// Reads every record of the current chain, in order
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ExportRecordsInstruction {}
+
+impl From<ExportRecordsInstruction> for Instruction {
+    fn from(instruction: ExportRecordsInstruction) -> Instruction {
+        Instruction::DBSystem(DBSystemInstruction::ExportRecords(instruction))
+    }
+}
+
+/// Rebuilds a chain in the empty `namespace` from records starting at height 1, keeping their
+/// timestamps
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ImportRecordsInstruction {
+    pub namespace: StoreNamespace,
+    pub records: Vec<InstructionRecord>,
+}
+
+impl From<ImportRecordsInstruction> for Instruction {
+    fn from(instruction: ImportRecordsInstruction) -> Instruction {
+        Instruction::DBSystem(DBSystemInstruction::ImportRecords(instruction))
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct GetJournalInstruction {
     pub key: StoreKey,
@@ -123,11 +423,89 @@ impl From<GetJournalInstruction> for Instruction {
     }
 }
 
+/// Reads `key` along with a Merkle proof of its value against the state root of the chain
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GetProofInstruction {
+    pub height: Option<ChainHeight>,
+    pub key: StoreKey,
+}
+
+impl From<GetProofInstruction> for Instruction {
+    fn from(instruction: GetProofInstruction) -> Instruction {
+        Instruction::DataAccess(DataInstruction::Read(DataReadInstruction::GetProof(
+            instruction,
+        )))
+    }
+}
+
+/// Keys written above `from_height` up to and including `to_height` (the latest height if None),
+/// optionally only those starting with `scope`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DiffInstruction {
+    pub from_height: ChainHeight,
+    pub to_height: Option<ChainHeight>,
+    pub scope: Option<StoreKeyFragment>,
+}
+
+impl From<DiffInstruction> for Instruction {
+    fn from(instruction: DiffInstruction) -> Instruction {
+        Instruction::DataAccess(DataInstruction::Read(DataReadInstruction::Diff(
+            instruction,
+        )))
+    }
+}
+
+/// Records above `after_height`, oldest first and at most `limit` of them
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GetChangesInstruction {
+    pub after_height: ChainHeight,
+    pub limit: usize,
+}
+
+impl From<GetChangesInstruction> for Instruction {
+    fn from(instruction: GetChangesInstruction) -> Instruction {
+        Instruction::DataAccess(DataInstruction::Read(DataReadInstruction::GetChanges(
+            instruction,
+        )))
+    }
+}
+
+/// `time` is in microseconds since the Unix epoch, like the system time in instruction records
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GetHeightAtTimeInstruction {
+    pub time: u128,
+}
+
+impl From<GetHeightAtTimeInstruction> for Instruction {
+    fn from(instruction: GetHeightAtTimeInstruction) -> Instruction {
+        Instruction::DataAccess(DataInstruction::Read(DataReadInstruction::GetHeightAtTime(
+            instruction,
+        )))
+    }
+}
+
+/// The height of the latest record on the chain
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GetHeightInstruction {}
+
+impl From<GetHeightInstruction> for Instruction {
+    fn from(instruction: GetHeightInstruction) -> Instruction {
+        Instruction::DataAccess(DataInstruction::Read(DataReadInstruction::GetHeight(
+            instruction,
+        )))
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub enum DataReadInstruction {
     GetOne(GetOneInstruction),
     GetMany(GetManyInstruction),
     GetJournal(GetJournalInstruction),
+    GetProof(GetProofInstruction),
+    Diff(DiffInstruction),
+    GetChanges(GetChangesInstruction),
+    GetHeightAtTime(GetHeightAtTimeInstruction),
+    GetHeight(GetHeightInstruction),
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -135,6 +513,10 @@ pub enum DataWriteInstruction {
     SetMany(SetManyInstruction),
     RevertMany(RevertManyInstruction),
     RevertAll(RevertAllInstruction),
+    Revert(RevertInstruction),
+    RevertKeys(RevertKeysInstruction),
+    IndexedRevertMany(IndexedRevertManyInstruction),
+    PruneChain(PruneChainInstruction),
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -178,6 +560,19 @@ impl From<TransactionMetaInstruction> for Instruction {
 pub enum DBSystemInstruction {
     SwitchNamespace(SwitchNamespaceInstruction),
     ReadNamespace(ReadNamespaceInstruction),
+    VerifyChain(VerifyChainInstruction),
+    ForkNamespace(ForkNamespaceInstruction),
+    MergeNamespace(MergeNamespaceInstruction),
+    SetTag(SetTagInstruction),
+    RemoveTag(RemoveTagInstruction),
+    ReadTags(ReadTagsInstruction),
+    SetRecordMeta(SetRecordMetaInstruction),
+    SetRetention(SetRetentionInstruction),
+    ReadRetention(ReadRetentionInstruction),
+    Compact(CompactInstruction),
+    Replay(ReplayInstruction),
+    ExportRecords(ExportRecordsInstruction),
+    ImportRecords(ImportRecordsInstruction),
 }
 
 impl From<DBSystemInstruction> for Instruction {
@@ -262,6 +657,19 @@ impl From<RevertAllOkAnswer> for Answer {
     }
 }
 
+#[derive(Debug)]
+pub struct RevertRecordOkAnswer {
+    pub reverted_keys: Vec<StoreKey>,
+    // Keys written again after the reverted height, which are left as they are
+    pub conflicts: Vec<StoreKey>,
+}
+
+impl From<RevertRecordOkAnswer> for Answer {
+    fn from(answer: RevertRecordOkAnswer) -> Answer {
+        Answer::DataAccess(DataAnswer::Write(DataWriteAnswer::RevertRecordOk(answer)))
+    }
+}
+
 #[derive(Debug)]
 pub struct SwitchNamespaceOkAnswer {
     pub new_namespace: StoreNamespace,
@@ -284,6 +692,182 @@ impl From<ReadNamespaceOkAnswer> for Answer {
     }
 }
 
+#[derive(Debug)]
+pub struct VerifyChainOkAnswer {
+    pub height: ChainHeight,
+    pub head: RecordHash,
+    pub broken_height: Option<ChainHeight>,
+}
+
+impl From<VerifyChainOkAnswer> for Answer {
+    fn from(answer: VerifyChainOkAnswer) -> Answer {
+        Answer::DBSystem(DBSystemAnswer::VerifyChainOk(answer))
+    }
+}
+
+#[derive(Debug)]
+pub struct ForkNamespaceOkAnswer {
+    pub new_namespace: StoreNamespace,
+    pub height: ChainHeight,
+    pub head: RecordHash,
+}
+
+impl From<ForkNamespaceOkAnswer> for Answer {
+    fn from(answer: ForkNamespaceOkAnswer) -> Answer {
+        Answer::DBSystem(DBSystemAnswer::ForkNamespaceOk(answer))
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct MergeConflict {
+    pub key: StoreKey,
+    pub strategy: MergeStrategy,
+}
+
+#[derive(Debug)]
+pub struct MergeNamespaceOkAnswer {
+    pub base_height: ChainHeight,
+    pub height: ChainHeight,
+    // Source heights replayed onto the target, in order
+    pub merged_heights: Vec<ChainHeight>,
+    pub conflicts: Vec<MergeConflict>,
+    // The values the merge leaves the keys it changes with, by key
+    pub merged_targets: Vec<SetTargetSpec>,
+}
+
+impl From<MergeNamespaceOkAnswer> for Answer {
+    fn from(answer: MergeNamespaceOkAnswer) -> Answer {
+        Answer::DBSystem(DBSystemAnswer::MergeNamespaceOk(answer))
+    }
+}
+
+#[derive(Debug)]
+pub struct SetTagOkAnswer {
+    pub tag: ChainTag,
+}
+
+impl From<SetTagOkAnswer> for Answer {
+    fn from(answer: SetTagOkAnswer) -> Answer {
+        Answer::DBSystem(DBSystemAnswer::SetTagOk(answer))
+    }
+}
+
+/// `tag` is the removed tag, with the height it pointed at
+#[derive(Debug)]
+pub struct RemoveTagOkAnswer {
+    pub tag: ChainTag,
+}
+
+impl From<RemoveTagOkAnswer> for Answer {
+    fn from(answer: RemoveTagOkAnswer) -> Answer {
+        Answer::DBSystem(DBSystemAnswer::RemoveTagOk(answer))
+    }
+}
+
+/// Tags ordered by name
+#[derive(Debug)]
+pub struct ReadTagsOkAnswer {
+    pub tags: Vec<ChainTag>,
+}
+
+impl From<ReadTagsOkAnswer> for Answer {
+    fn from(answer: ReadTagsOkAnswer) -> Answer {
+        Answer::DBSystem(DBSystemAnswer::ReadTagsOk(answer))
+    }
+}
+
+#[derive(Debug)]
+pub struct SetRecordMetaOkAnswer {}
+
+impl From<SetRecordMetaOkAnswer> for Answer {
+    fn from(answer: SetRecordMetaOkAnswer) -> Answer {
+        Answer::DBSystem(DBSystemAnswer::SetRecordMetaOk(answer))
+    }
+}
+
+#[derive(Debug)]
+pub struct SetRetentionOkAnswer {}
+
+impl From<SetRetentionOkAnswer> for Answer {
+    fn from(answer: SetRetentionOkAnswer) -> Answer {
+        Answer::DBSystem(DBSystemAnswer::SetRetentionOk(answer))
+    }
+}
+
+#[derive(Debug)]
+pub struct ReadRetentionOkAnswer {
+    pub rules: Vec<RetentionRule>,
+    pub markers: Vec<PruneMarker>,
+}
+
+impl From<ReadRetentionOkAnswer> for Answer {
+    fn from(answer: ReadRetentionOkAnswer) -> Answer {
+        Answer::DBSystem(DBSystemAnswer::ReadRetentionOk(answer))
+    }
+}
+
+/// `markers` are all the markers left after compacting, whether moved by it or not
+#[derive(Debug)]
+pub struct CompactOkAnswer {
+    pub markers: Vec<PruneMarker>,
+    pub pruned_records: u64,
+    pub pruned_versions: u64,
+}
+
+impl From<CompactOkAnswer> for Answer {
+    fn from(answer: CompactOkAnswer) -> Answer {
+        Answer::DBSystem(DBSystemAnswer::CompactOk(answer))
+    }
+}
+
+/// A derived entry that differs between the original and the replayed store, None where one
+/// of them does not have it
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ReplayMismatch {
+    pub kvkey: Vec<u8>,
+    pub original: Option<Vec<u8>>,
+    pub replayed: Option<Vec<u8>>,
+}
+
+#[derive(Debug)]
+pub struct ReplayOkAnswer {
+    pub height: ChainHeight,
+    pub head: RecordHash,
+    pub mismatches: Vec<ReplayMismatch>,
+}
+
+impl From<ReplayOkAnswer> for Answer {
+    fn from(answer: ReplayOkAnswer) -> Answer {
+        Answer::DBSystem(DBSystemAnswer::ReplayOk(answer))
+    }
+}
+
+#[derive(Debug)]
+pub struct ExportRecordsOkAnswer {
+    pub height: ChainHeight,
+    pub head: RecordHash,
+    pub records: Vec<InstructionRecord>,
+}
+
+impl From<ExportRecordsOkAnswer> for Answer {
+    fn from(answer: ExportRecordsOkAnswer) -> Answer {
+        Answer::DBSystem(DBSystemAnswer::ExportRecordsOk(answer))
+    }
+}
+
+#[derive(Debug)]
+pub struct ImportRecordsOkAnswer {
+    pub namespace: StoreNamespace,
+    pub height: ChainHeight,
+    pub head: RecordHash,
+}
+
+impl From<ImportRecordsOkAnswer> for Answer {
+    fn from(answer: ImportRecordsOkAnswer) -> Answer {
+        Answer::DBSystem(DBSystemAnswer::ImportRecordsOk(answer))
+    }
+}
+
 #[derive(Debug)]
 pub struct GetJournalOkAnswer {
     pub journal: UnitJournal,
@@ -295,11 +879,97 @@ impl From<GetJournalOkAnswer> for Answer {
     }
 }
 
+#[derive(Debug)]
+pub struct GetProofOkAnswer {
+    pub height: ChainHeight,
+    pub value: StoreValue,
+    pub root: RecordHash,
+    pub proof: MerkleProof,
+}
+
+impl From<GetProofOkAnswer> for Answer {
+    fn from(answer: GetProofOkAnswer) -> Answer {
+        Answer::DataAccess(DataAnswer::Read(DataReadAnswer::GetProofOk(answer)))
+    }
+}
+
+/// A key's value at both ends of a diff; absent keys have an empty value
+#[derive(Debug)]
+pub struct KeyChange {
+    pub key: StoreKey,
+    pub before: StoreValue,
+    pub after: StoreValue,
+}
+
+#[derive(Debug)]
+pub struct DiffOkAnswer {
+    pub from_height: ChainHeight,
+    pub to_height: ChainHeight,
+    pub changes: Vec<KeyChange>,
+}
+
+impl From<DiffOkAnswer> for Answer {
+    fn from(answer: DiffOkAnswer) -> Answer {
+        Answer::DataAccess(DataAnswer::Read(DataReadAnswer::DiffOk(answer)))
+    }
+}
+
+/// The values one record left its keys with
+#[derive(Debug)]
+pub struct RecordChange {
+    pub height: ChainHeight,
+    pub sys_time: u128,
+    pub meta: RecordMeta,
+    pub targets: Vec<SetTargetSpec>,
+}
+
+/// `height` is the last height read, from which the next request can resume
+#[derive(Debug)]
+pub struct GetChangesOkAnswer {
+    pub height: ChainHeight,
+    pub changes: Vec<RecordChange>,
+}
+
+impl From<GetChangesOkAnswer> for Answer {
+    fn from(answer: GetChangesOkAnswer) -> Answer {
+        Answer::DataAccess(DataAnswer::Read(DataReadAnswer::GetChangesOk(answer)))
+    }
+}
+
+/// The last height reached at or before the requested time; zero if the chain was still empty
+#[derive(Debug)]
+pub struct GetHeightAtTimeOkAnswer {
+    pub height: ChainHeight,
+}
+
+impl From<GetHeightAtTimeOkAnswer> for Answer {
+    fn from(answer: GetHeightAtTimeOkAnswer) -> Answer {
+        Answer::DataAccess(DataAnswer::Read(DataReadAnswer::GetHeightAtTimeOk(answer)))
+    }
+}
+
+/// Zero if the chain is still empty
+#[derive(Debug)]
+pub struct GetHeightOkAnswer {
+    pub height: ChainHeight,
+}
+
+impl From<GetHeightOkAnswer> for Answer {
+    fn from(answer: GetHeightOkAnswer) -> Answer {
+        Answer::DataAccess(DataAnswer::Read(DataReadAnswer::GetHeightOk(answer)))
+    }
+}
+
 #[derive(Debug)]
 pub enum DataReadAnswer {
     GetManyOk(GetManyOkAnswer),
     GetOneOk(GetOneOkAnswer),
     GetJournalOk(GetJournalOkAnswer),
+    GetProofOk(GetProofOkAnswer),
+    DiffOk(DiffOkAnswer),
+    GetChangesOk(GetChangesOkAnswer),
+    GetHeightAtTimeOk(GetHeightAtTimeOkAnswer),
+    GetHeightOk(GetHeightOkAnswer),
 }
 
 #[derive(Debug)]
@@ -307,6 +977,7 @@ pub enum DataWriteAnswer {
     SetOk(SetOkAnswer),
     RevertOk(RevertOkAnswer),
     RevertAllOk(RevertAllOkAnswer),
+    RevertRecordOk(RevertRecordOkAnswer),
 }
 
 #[derive(Debug)]
@@ -333,6 +1004,19 @@ pub enum TransactionMetaAnswer {
 pub enum DBSystemAnswer {
     SwitchNamespaceOk(SwitchNamespaceOkAnswer),
     ReadNamespaceOk(ReadNamespaceOkAnswer),
+    VerifyChainOk(VerifyChainOkAnswer),
+    ForkNamespaceOk(ForkNamespaceOkAnswer),
+    MergeNamespaceOk(MergeNamespaceOkAnswer),
+    SetTagOk(SetTagOkAnswer),
+    RemoveTagOk(RemoveTagOkAnswer),
+    ReadTagsOk(ReadTagsOkAnswer),
+    SetRecordMetaOk(SetRecordMetaOkAnswer),
+    SetRetentionOk(SetRetentionOkAnswer),
+    ReadRetentionOk(ReadRetentionOkAnswer),
+    CompactOk(CompactOkAnswer),
+    ReplayOk(ReplayOkAnswer),
+    ExportRecordsOk(ExportRecordsOkAnswer),
+    ImportRecordsOk(ImportRecordsOkAnswer),
 }
 
 #[derive(Debug)]
@@ -57,6 +57,13 @@ impl KeyValueStore for HashMapStore {
         }
         Ok(())
     }
+    fn atomic_batch_delete(&mut self, keys: &[KVKey]) -> ImmuxResult<()> {
+        let hashmap = &mut self.hashmaps[self.current_node_index].hashmap;
+        for key in keys {
+            hashmap.remove(key);
+        }
+        Ok(())
+    }
     fn switch_namespace(&mut self, namespace: &KVNamespace) -> ImmuxResult<()> {
         for (index, node) in self.hashmaps.iter().enumerate() {
             if node.name == *namespace {
@@ -91,4 +98,33 @@ impl KeyValueStore for HashMapStore {
         }
         Box::new(result)
     }
+
+    fn filter_range(
+        &self,
+        prefix: &KVKeySegment,
+        after: Option<&KVKey>,
+        limit: usize,
+    ) -> Box<Vec<(BoxedKVKey, BoxedKVValue)>> {
+        let node = &self.hashmaps[self.current_node_index];
+        let mut keys: Vec<&KVKey> = node
+            .hashmap
+            .keys()
+            .filter(|key| key.as_bytes().starts_with(prefix.as_bytes()))
+            .filter(|key| match after {
+                None => true,
+                Some(after) => key.as_bytes() > after.as_bytes(),
+            })
+            .collect();
+        keys.sort_by(|a, b| a.as_bytes().cmp(b.as_bytes()));
+        let mut result: Vec<(BoxedKVKey, BoxedKVValue)> = Vec::new();
+        for key in keys.into_iter().take(limit) {
+            if let Some(value) = node.hashmap.get(key) {
+                result.push((
+                    BoxedKVKey::from(key.clone()),
+                    BoxedKVValue::from(value.clone()),
+                ))
+            }
+        }
+        Box::new(result)
+    }
 }
@@ -15,16 +15,24 @@ impl From<KVError> for ImmuxError {
     }
 }
 
-pub trait KeyValueStore {
+pub trait KeyValueStore: Send {
     fn get(&self, kvkey: &KVKey) -> ImmuxResult<Option<KVValue>>;
     fn set(&mut self, kvkey: &KVKey, value: &KVValue) -> ImmuxResult<()>;
     fn atomic_batch_set(&mut self, pairs: &[(KVKey, KVValue)]) -> ImmuxResult<()>;
+    fn atomic_batch_delete(&mut self, keys: &[KVKey]) -> ImmuxResult<()>;
     fn switch_namespace(&mut self, namespace: &KVNamespace) -> ImmuxResult<()>;
     fn read_namespace(&self) -> KVNamespace;
     fn filter_prefix(&self, prefix: &KVKeySegment) -> Box<Vec<(BoxedKVKey, BoxedKVValue)>>;
+    // At most `limit` pairs whose keys start with `prefix` and sort after `after`, in key order
+    fn filter_range(
+        &self,
+        prefix: &KVKeySegment,
+        after: Option<&KVKey>,
+        limit: usize,
+    ) -> Box<Vec<(BoxedKVKey, BoxedKVValue)>>;
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub enum KeyValueEngine {
     HashMap,
     Rocks,
@@ -212,6 +220,41 @@ mod base_kv_tests {
         Ok(())
     }
 
+    /// Read the keys under a prefix page by page, each page starting after the last key of the
+    /// one before.
+    fn test_range_filter(store: &mut impl KeyValueStore) -> Result<(), Box<dyn Error>> {
+        let input_data: Vec<(KVKey, KVValue)> = (0..1_000)
+            .map(|i| {
+                let mut key = vec![0x10];
+                key.extend_from_slice(&u64_to_u8_array(i * 7919 % 1_000));
+                (KVKey::from(key), KVValue::from(u64_to_u8_array(i).to_vec()))
+            })
+            .collect();
+        store.atomic_batch_set(&input_data)?;
+        store.set(&KVKey::from(vec![0x11, 0x00]), &KVValue::from(vec![0x00]))?;
+
+        let prefix: KVKeySegment = KVKey::from(vec![0x10]).into();
+        let mut expected_keys: Vec<Vec<u8>> = input_data
+            .iter()
+            .map(|(key, _value)| key.as_bytes().to_vec())
+            .collect();
+        expected_keys.sort();
+
+        let mut keys_from_store: Vec<Vec<u8>> = Vec::new();
+        let mut after: Option<KVKey> = None;
+        loop {
+            let page = store.filter_range(&prefix, after.as_ref(), 64);
+            assert!(page.len() <= 64);
+            match page.last() {
+                None => break,
+                Some((key, _value)) => after = Some(KVKey::new(key.as_bytes())),
+            }
+            keys_from_store.extend(page.iter().map(|(key, _value)| key.as_bytes().to_vec()));
+        }
+        assert_eq!(keys_from_store, expected_keys);
+        Ok(())
+    }
+
     fn test_set_many(store: &mut impl KeyValueStore) -> Result<(), Box<dyn Error>> {
         let data_tables: Vec<Vec<(KVKey, KVValue)>> = vec![
             vec![("a", 1), ("b", 2), ("c", 3)], // base data
@@ -368,6 +411,26 @@ mod base_kv_tests {
         Ok(())
     }
 
+    /// Deleted keys read back as absent and are left out of prefix filters
+    fn test_batch_delete(store: &mut impl KeyValueStore) -> Result<(), Box<dyn Error>> {
+        let pairs: Vec<(KVKey, KVValue)> = vec![
+            (KVKey::from("key-1"), KVValue::from("value-1")),
+            (KVKey::from("key-2"), KVValue::from("value-2")),
+            (KVKey::from("key-3"), KVValue::from("value-3")),
+        ];
+        store.atomic_batch_set(&pairs)?;
+        store.atomic_batch_delete(&[KVKey::from("key-1"), KVKey::from("key-3")])?;
+        assert_eq!(store.get(&KVKey::from("key-1"))?, None);
+        assert_eq!(store.get(&KVKey::from("key-3"))?, None);
+        assert_eq!(
+            store.get(&KVKey::from("key-2"))?,
+            Some(KVValue::from("value-2"))
+        );
+        let remaining = store.filter_prefix(&KVKeySegment::from("key-"));
+        assert_eq!(remaining.len(), 1);
+        Ok(())
+    }
+
     // ========================================
     //    Apply tests to hashmap and rocks
     // ========================================
@@ -412,6 +475,16 @@ mod base_kv_tests {
         test_prefix_filter(&mut get_rocks_store("test_prefix_filter_rocks"))
     }
 
+    #[test]
+    fn test_range_filter_hashmap() -> Result<(), Box<dyn Error>> {
+        test_range_filter(&mut get_hashmap_store())
+    }
+
+    #[test]
+    fn test_range_filter_rocks() -> Result<(), Box<dyn Error>> {
+        test_range_filter(&mut get_rocks_store("test_range_filter_rocks"))
+    }
+
     #[test]
     fn test_set_many_hashmap() -> Result<(), Box<dyn Error>> {
         test_set_many(&mut get_hashmap_store())
@@ -489,4 +562,14 @@ mod base_kv_tests {
     fn test_set_many_identical_keys_rocks() -> Result<(), Box<dyn Error>> {
         test_set_many_identical_keys(&mut get_rocks_store("test_set_many_identical_keys"))
     }
+
+    #[test]
+    fn test_batch_delete_hashmap() -> Result<(), Box<dyn Error>> {
+        test_batch_delete(&mut get_hashmap_store())
+    }
+
+    #[test]
+    fn test_batch_delete_rocks() -> Result<(), Box<dyn Error>> {
+        test_batch_delete(&mut get_rocks_store("test_batch_delete"))
+    }
 }
@@ -18,7 +18,9 @@ pub enum RocksEngineError {
     GetError(RocksError),
     PutError(RocksError),
     BatchPutError(RocksError),
+    BatchDeleteError(RocksError),
     BatchWriteError(RocksError),
+    NoOpenDatabase(KVNamespace),
 }
 
 impl From<RocksEngineError> for ImmuxError {
@@ -27,10 +29,17 @@ impl From<RocksEngineError> for ImmuxError {
     }
 }
 
+// Namespaces switched away from keep their handle open, as connections on different chains
+// switch back and forth between messages
+const MAX_IDLE_HANDLES: usize = 8;
+
 pub struct RocksStore {
     data_root: String,
     namespace: KVNamespace,
-    db: DB,
+    // Only None if neither the requested nor the previous namespace could be reopened
+    db: Option<DB>,
+    // Most recently used first
+    idle_dbs: Vec<(KVNamespace, DB)>,
     extractor: PrefixExtractor,
 }
 
@@ -63,16 +72,33 @@ impl RocksStore {
         let store = RocksStore {
             namespace: namespace.to_owned(),
             data_root: data_root.to_string(),
-            db,
+            db: Some(db),
+            idle_dbs: Vec::new(),
             extractor: prefix_extractor,
         };
         Ok(store)
     }
+
+    fn take_idle_db(&mut self, namespace: &KVNamespace) -> Option<DB> {
+        let index = self
+            .idle_dbs
+            .iter()
+            .position(|(idle_namespace, _db)| idle_namespace == namespace)?;
+        let (_namespace, db) = self.idle_dbs.remove(index);
+        return Some(db);
+    }
+
+    fn get_db(&self) -> ImmuxResult<&DB> {
+        match &self.db {
+            None => Err(RocksEngineError::NoOpenDatabase(self.namespace.to_owned()).into()),
+            Some(db) => Ok(db),
+        }
+    }
 }
 
 impl KeyValueStore for RocksStore {
     fn get(&self, key: &KVKey) -> ImmuxResult<Option<KVValue>> {
-        match self.db.get(key.as_bytes()) {
+        match self.get_db()?.get(key.as_bytes()) {
             Ok(Some(value)) => Ok(Some(value.to_vec().into())),
             Ok(None) => Ok(None),
             Err(error) => Err(RocksEngineError::GetError(error).into()),
@@ -80,7 +106,7 @@ impl KeyValueStore for RocksStore {
     }
 
     fn set(&mut self, key: &KVKey, value: &KVValue) -> ImmuxResult<()> {
-        match self.db.put(key.as_bytes(), value.as_bytes()) {
+        match self.get_db()?.put(key.as_bytes(), value.as_bytes()) {
             Err(error) => Err(RocksEngineError::PutError(error).into()),
             Ok(_) => Ok(()),
         }
@@ -95,15 +121,49 @@ impl KeyValueStore for RocksStore {
                 Ok(_) => {}
             };
         }
-        match self.db.write(batch) {
+        match self.get_db()?.write(batch) {
+            Err(error) => Err(RocksEngineError::BatchWriteError(error).into()),
+            Ok(_) => Ok(()),
+        }
+    }
+
+    fn atomic_batch_delete(&mut self, keys: &[KVKey]) -> ImmuxResult<()> {
+        let mut batch = WriteBatch::default();
+        for key in keys {
+            match batch.delete(key.as_bytes()) {
+                Err(error) => return Err(RocksEngineError::BatchDeleteError(error).into()),
+                Ok(_) => {}
+            };
+        }
+        match self.get_db()?.write(batch) {
             Err(error) => Err(RocksEngineError::BatchWriteError(error).into()),
             Ok(_) => Ok(()),
         }
     }
 
     fn switch_namespace(&mut self, namespace: &KVNamespace) -> ImmuxResult<()> {
+        // RocksDB refuses to lock a path this process already holds, so a namespace with an open
+        // handle is switched to by taking that handle
+        if namespace == &self.namespace && self.db.is_some() {
+            return Ok(());
+        }
+        if let Some(db) = self.db.take() {
+            self.idle_dbs.insert(0, (self.namespace.to_owned(), db));
+        }
+        let db = match self.take_idle_db(namespace) {
+            Some(db) => db,
+            None => match get_new_db(&self.data_root, namespace, self.extractor) {
+                Ok(db) => db,
+                Err(error) => {
+                    let namespace = self.namespace.to_owned();
+                    self.db = self.take_idle_db(&namespace);
+                    return Err(error);
+                }
+            },
+        };
         self.namespace = namespace.to_owned();
-        self.db = get_new_db(&self.data_root, namespace, self.extractor)?;
+        self.db = Some(db);
+        self.idle_dbs.truncate(MAX_IDLE_HANDLES);
         Ok(())
     }
 
@@ -112,9 +172,12 @@ impl KeyValueStore for RocksStore {
     }
 
     fn filter_prefix(&self, prefix: &KVKeySegment) -> Box<Vec<(BoxedKVKey, BoxedKVValue)>> {
+        let db = match &self.db {
+            None => return Box::new(vec![]),
+            Some(db) => db,
+        };
         let read_options = ReadOptions::default();
-        let iterator = self
-            .db
+        let iterator = db
             .iterator_opt(
                 IteratorMode::From(prefix.as_bytes(), Direction::Forward),
                 &read_options,
@@ -125,19 +188,89 @@ impl KeyValueStore for RocksStore {
             .collect();
         Box::new(data)
     }
+
+    fn filter_range(
+        &self,
+        prefix: &KVKeySegment,
+        after: Option<&KVKey>,
+        limit: usize,
+    ) -> Box<Vec<(BoxedKVKey, BoxedKVValue)>> {
+        let db = match &self.db {
+            None => return Box::new(vec![]),
+            Some(db) => db,
+        };
+        let start = match after {
+            None => prefix.as_bytes(),
+            Some(after) => after.as_bytes(),
+        };
+        let read_options = ReadOptions::default();
+        let data: Vec<_> = db
+            .iterator_opt(IteratorMode::From(start, Direction::Forward), &read_options)
+            .skip_while(|pair| Some(&pair.0[..]) == after.map(|after| after.as_bytes()))
+            .take_while(|pair| pair.0.starts_with(prefix.as_bytes()))
+            .take(limit)
+            .map(|item| (BoxedKVKey::new(item.0), BoxedKVValue::new(item.1)))
+            .collect();
+        Box::new(data)
+    }
 }
 
 #[cfg(test)]
 mod rocks_specific_tests {
-    use crate::storage::kv::{KVNamespace, RocksStore};
+    use immuxdb_dev_utils::reset_db_dir;
+
+    use crate::storage::kv::{KVKey, KVNamespace, KVValue, KeyValueStore, RocksStore};
+
+    fn prefix_extract(key: &[u8]) -> &[u8] {
+        return key;
+    }
 
     #[test]
     #[should_panic]
     fn test_invalid_path_error() {
-        fn prefix_extract(key: &[u8]) -> &[u8] {
-            return key;
-        }
         let ns = KVNamespace::from("");
         RocksStore::new("\0\\", &ns, prefix_extract).unwrap();
     }
+
+    #[test]
+    fn test_switch_to_open_namespace() {
+        let data_root = "/tmp/test_switch_to_open_namespace/";
+        reset_db_dir(data_root).unwrap();
+        let main = KVNamespace::from("main");
+        let other = KVNamespace::from("other");
+        let mut store = RocksStore::new(data_root, &main, prefix_extract).unwrap();
+        let key = KVKey::from("key");
+        let value = KVValue::from("value");
+        store.set(&key, &value).unwrap();
+
+        store.switch_namespace(&main).unwrap();
+        assert_eq!(store.get(&key).unwrap(), Some(value.clone()));
+
+        store.switch_namespace(&other).unwrap();
+        assert_eq!(store.get(&key).unwrap(), None);
+        store.switch_namespace(&main).unwrap();
+        assert_eq!(store.get(&key).unwrap(), Some(value));
+    }
+
+    #[test]
+    fn test_switch_between_many_namespaces() {
+        let data_root = "/tmp/test_switch_between_many_namespaces/";
+        reset_db_dir(data_root).unwrap();
+        let namespaces: Vec<KVNamespace> = (0..super::MAX_IDLE_HANDLES * 2)
+            .map(|i| KVNamespace::from(format!("chain_{}", i).as_str()))
+            .collect();
+        let mut store = RocksStore::new(data_root, &namespaces[0], prefix_extract).unwrap();
+        let key = KVKey::from("key");
+        for namespace in &namespaces {
+            store.switch_namespace(namespace).unwrap();
+            let value = KVValue::from(namespace.as_bytes());
+            store.set(&key, &value).unwrap();
+        }
+        for namespace in namespaces.iter().rev().chain(namespaces.iter()) {
+            store.switch_namespace(namespace).unwrap();
+            assert_eq!(store.read_namespace(), namespace.to_owned());
+            let value = KVValue::from(namespace.as_bytes());
+            assert_eq!(store.get(&key).unwrap(), Some(value));
+        }
+    }
 }
@@ -1,3 +1,4 @@
+pub mod archive;
 pub mod core;
 pub mod instructions;
 pub mod kv;
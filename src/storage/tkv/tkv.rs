@@ -1,7 +1,36 @@
+/*
+ *  Transactional key-value store
+ *
+ *  Writes inside a transaction are buffered in memory and only reach VKV on commit, where they
+ *  are flattened into a single SetMany, i.e. one chain height. Reads see the chain as it was
+ *  when the transaction started, followed by the buffered writes at the heights after it, one
+ *  height each, so that reverts inside the transaction can address the transaction's own writes.
+ *  Heights committed by others meanwhile are not part of that view, and a commit fails if
+ *  another one has written any of its keys since.
+**/
+
+use std::cmp::min;
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::config::INITIAL_TRANSACTION_ID_DATA;
+use crate::declarations::basics::{BoxedStoreKey, BoxedStoreValue, StoreKey, StoreValue};
 use crate::declarations::errors::{ImmuxError, ImmuxResult};
-use crate::storage::instructions::{Answer, Instruction, StoreNamespace};
+use crate::storage::instructions::{
+    AbortTransactionOkAnswer, Answer, CommitTransactionOkAnswer, DBSystemInstruction, DataAnswer,
+    DataInstruction, DataReadAnswer, DataReadInstruction, DataWriteAnswer, DataWriteInstruction,
+    GetJournalInstruction, GetJournalOkAnswer, GetManyInstruction, GetManyOkAnswer,
+    GetManyTargetSpec, GetOneInstruction, GetOneOkAnswer, IndexedRevertManyInstruction,
+    Instruction, RevertAllOkAnswer, RevertInstruction, RevertKeysInstruction,
+    RevertManyInstruction, RevertOkAnswer, RevertRecordOkAnswer, SetManyInstruction, SetOkAnswer,
+    SetTargetSpec, StartTransactionOkAnswer, StoreNamespace, TransactionMetaAnswer,
+    TransactionMetaInstruction, TransactionPendingAnswer, TransactionalDataAnswer,
+};
 use crate::storage::kv::KeyValueEngine;
-use crate::storage::vkv::{ImmuxDBVersionedKeyValueStore, VersionedKeyValueStore};
+use crate::storage::tkv::TransactionId;
+use crate::storage::vkv::{
+    extract_affected_keys, ChainHeight, HeightList, ImmuxDBVersionedKeyValueStore, UnitJournal,
+    VersionedKeyValueStore, VkvError,
+};
 
 #[derive(Debug)]
 pub enum TransactionError {
@@ -10,14 +39,95 @@ pub enum TransactionError {
     UnexpectedAnswer,
     AbortInstructionError,
     CannotSwitchNamespaceWhileTransactionIsOngoing,
+    CannotCompactWhileTransactionIsOngoing,
+    // Keys committed by others since the transaction started; the transaction is dropped
+    WriteConflict(Vec<StoreKey>),
 }
 
 pub trait TransactionKeyValueStore {
     fn execute(&mut self, instruction: &Instruction) -> Result<Answer, ImmuxError>;
 }
 
+struct Transaction {
+    base_height: ChainHeight,
+    // updates[i] holds the values written at height (base_height + i + 1) of the transaction
+    updates: Vec<Vec<SetTargetSpec>>,
+}
+
+impl Transaction {
+    fn new(base_height: ChainHeight) -> Self {
+        Transaction {
+            base_height,
+            updates: Vec::new(),
+        }
+    }
+
+    fn get_update_height(&self, update_index: usize) -> ChainHeight {
+        ChainHeight::new(self.base_height.as_u64() + update_index as u64 + 1)
+    }
+
+    fn next_height(&self) -> ChainHeight {
+        self.get_update_height(self.updates.len())
+    }
+
+    /// Number of buffered updates visible at `height`, or None if `height` is in the snapshot
+    /// (in which case only committed data is relevant).
+    fn visible_update_count(&self, height: &Option<ChainHeight>) -> Option<usize> {
+        match height {
+            None => return Some(self.updates.len()),
+            Some(height) => {
+                if height <= &self.base_height {
+                    return None;
+                } else {
+                    let count = (height.as_u64() - self.base_height.as_u64()) as usize;
+                    return Some(min(count, self.updates.len()));
+                }
+            }
+        }
+    }
+
+    /// The committed height a read at `height` sees. Heights above the base height are the
+    /// transaction's own, whose buffered writes are read on top of the base height.
+    fn get_snapshot_height(&self, height: &Option<ChainHeight>) -> ChainHeight {
+        match height {
+            Some(height) if height <= &self.base_height => return *height,
+            _ => return self.base_height,
+        }
+    }
+
+    fn find_buffered_value(&self, key: &StoreKey, visible_count: usize) -> Option<StoreValue> {
+        for update in self.updates[..visible_count].iter().rev() {
+            for target in update.iter().rev() {
+                if &target.key == key {
+                    return Some(target.value.clone());
+                }
+            }
+        }
+        return None;
+    }
+
+    fn buffered_keys(&self) -> BTreeSet<StoreKey> {
+        self.updates
+            .iter()
+            .flat_map(|update| update.iter().map(|target| target.key.clone()))
+            .collect()
+    }
+
+    fn flatten_updates(&self, visible_count: usize) -> BTreeMap<StoreKey, StoreValue> {
+        let mut result = BTreeMap::new();
+        for update in &self.updates[..visible_count] {
+            for target in update {
+                result.insert(target.key.clone(), target.value.clone());
+            }
+        }
+        return result;
+    }
+}
+
 pub struct ImmuxDBTransactionKeyValueStore {
     vkv: ImmuxDBVersionedKeyValueStore,
+    transactions: BTreeMap<TransactionId, Transaction>,
+    next_transaction_id: TransactionId,
 }
 
 impl ImmuxDBTransactionKeyValueStore {
@@ -27,24 +137,538 @@ impl ImmuxDBTransactionKeyValueStore {
         namespace: &StoreNamespace,
     ) -> Result<ImmuxDBTransactionKeyValueStore, ImmuxError> {
         let vkv = ImmuxDBVersionedKeyValueStore::new(engine_choice, data_root, namespace)?;
-        let tkv = ImmuxDBTransactionKeyValueStore { vkv };
+        let tkv = ImmuxDBTransactionKeyValueStore {
+            vkv,
+            transactions: BTreeMap::new(),
+            next_transaction_id: TransactionId::new(INITIAL_TRANSACTION_ID_DATA),
+        };
         return Ok(tkv);
     }
 
     fn pass_to_vkv(&mut self, instruction: &Instruction) -> ImmuxResult<Answer> {
         self.vkv.execute(instruction)
     }
+
+    fn get_transaction(&self, transaction_id: &TransactionId) -> ImmuxResult<&Transaction> {
+        match self.transactions.get(transaction_id) {
+            None => Err(TransactionError::TransactionNotStarted.into()),
+            Some(transaction) => Ok(transaction),
+        }
+    }
+
+    fn read_committed(
+        &mut self,
+        key: &StoreKey,
+        height: Option<ChainHeight>,
+    ) -> ImmuxResult<StoreValue> {
+        let instruction: Instruction = GetOneInstruction {
+            height,
+            key: key.to_owned(),
+        }
+        .into();
+        match self.pass_to_vkv(&instruction)? {
+            Answer::DataAccess(DataAnswer::Read(DataReadAnswer::GetOneOk(answer))) => {
+                return Ok(answer.value);
+            }
+            _ => return Err(TransactionError::UnexpectedAnswer.into()),
+        }
+    }
+
+    fn read_in_transaction(
+        &mut self,
+        transaction_id: &TransactionId,
+        key: &StoreKey,
+        height: Option<ChainHeight>,
+    ) -> ImmuxResult<StoreValue> {
+        let transaction = self.get_transaction(transaction_id)?;
+        let snapshot_height = transaction.get_snapshot_height(&height);
+        match transaction.visible_update_count(&height) {
+            None => return self.read_committed(key, Some(snapshot_height)),
+            Some(count) => match transaction.find_buffered_value(key, count) {
+                Some(value) => return Ok(value),
+                None => match self.read_committed(key, Some(snapshot_height)) {
+                    // Keys first committed after the transaction started do not exist in it
+                    Err(ImmuxError::VKV(VkvError::CannotFindSuitableVersion)) => {
+                        return Err(VkvError::MissingJournal(key.to_owned()).into());
+                    }
+                    result => return result,
+                },
+            },
+        }
+    }
+
+    fn get_journal_in_transaction(
+        &mut self,
+        transaction_id: &TransactionId,
+        key: &StoreKey,
+    ) -> ImmuxResult<UnitJournal> {
+        let instruction: Instruction = GetJournalInstruction {
+            key: key.to_owned(),
+        }
+        .into();
+        let base_height = self.get_transaction(transaction_id)?.base_height;
+        let committed_journal = match self.pass_to_vkv(&instruction) {
+            Err(ImmuxError::VKV(VkvError::MissingJournal(_))) => None,
+            Err(error) => return Err(error),
+            Ok(Answer::DataAccess(DataAnswer::Read(DataReadAnswer::GetJournalOk(answer)))) => {
+                // Updates committed since the transaction started are not part of its snapshot
+                let journal = answer.journal;
+                let heights: Vec<ChainHeight> = journal
+                    .update_heights
+                    .iter()
+                    .filter(|height| height <= &base_height)
+                    .collect();
+                if heights.is_empty() {
+                    None
+                } else if heights.len() == journal.update_heights.iter().count() {
+                    Some(journal)
+                } else {
+                    Some(UnitJournal {
+                        value: self.read_committed(key, Some(base_height))?,
+                        update_heights: HeightList::new(&heights),
+                    })
+                }
+            }
+            Ok(_) => return Err(TransactionError::UnexpectedAnswer.into()),
+        };
+        let transaction = self.get_transaction(transaction_id)?;
+        let mut journal = committed_journal;
+        for (index, update) in transaction.updates.iter().enumerate() {
+            for target in update {
+                if &target.key == key {
+                    let height = transaction.get_update_height(index);
+                    journal = match journal {
+                        None => Some(UnitJournal {
+                            value: target.value.clone(),
+                            update_heights: HeightList::new(&[height]),
+                        }),
+                        Some(mut existing_journal) => {
+                            existing_journal.value = target.value.clone();
+                            existing_journal.update_heights.push(height);
+                            Some(existing_journal)
+                        }
+                    }
+                }
+            }
+        }
+        match journal {
+            None => return Err(VkvError::MissingJournal(key.to_owned()).into()),
+            Some(journal) => return Ok(journal),
+        }
+    }
+
+    fn get_prefix_in_transaction(
+        &mut self,
+        transaction_id: &TransactionId,
+        get_many: &GetManyInstruction,
+        prefix: &StoreKey,
+    ) -> ImmuxResult<Vec<(BoxedStoreKey, BoxedStoreValue)>> {
+        let committed_read: Instruction = GetManyInstruction {
+            height: Some(
+                self.get_transaction(transaction_id)?
+                    .get_snapshot_height(&get_many.height),
+            ),
+            targets: get_many.targets.to_owned(),
+        }
+        .into();
+        let committed_data = match self.pass_to_vkv(&committed_read)? {
+            Answer::DataAccess(DataAnswer::Read(DataReadAnswer::GetManyOk(answer))) => answer.data,
+            _ => return Err(TransactionError::UnexpectedAnswer.into()),
+        };
+        let transaction = self.get_transaction(transaction_id)?;
+        let buffered_values: BTreeMap<StoreKey, StoreValue> =
+            match transaction.visible_update_count(&get_many.height) {
+                None => BTreeMap::new(),
+                Some(count) => transaction
+                    .flatten_updates(count)
+                    .into_iter()
+                    .filter(|(key, _value)| key.as_slice().starts_with(prefix.as_slice()))
+                    .collect(),
+            };
+        let mut data: Vec<(BoxedStoreKey, BoxedStoreValue)> = committed_data
+            .into_iter()
+            .filter(|(key, _value)| !buffered_values.contains_key(&StoreKey::new(key.as_slice())))
+            .collect();
+        for (key, value) in buffered_values {
+            if value.inner().is_some() {
+                data.push((BoxedStoreKey::new(key.as_slice().to_vec()), value.into()));
+            }
+        }
+        return Ok(data);
+    }
+
+    fn execute_read_in_transaction(
+        &mut self,
+        transaction_id: &TransactionId,
+        read: &DataReadInstruction,
+    ) -> ImmuxResult<DataReadAnswer> {
+        match read {
+            DataReadInstruction::GetOne(get_one) => {
+                let value =
+                    self.read_in_transaction(transaction_id, &get_one.key, get_one.height)?;
+                return Ok(DataReadAnswer::GetOneOk(GetOneOkAnswer { value }));
+            }
+            DataReadInstruction::GetMany(get_many) => match &get_many.targets {
+                GetManyTargetSpec::Keys(keys) => {
+                    let mut data: Vec<(BoxedStoreKey, BoxedStoreValue)> =
+                        Vec::with_capacity(keys.len());
+                    for key in keys {
+                        let value =
+                            self.read_in_transaction(transaction_id, key, get_many.height)?;
+                        data.push((key.to_owned().into(), value.into()));
+                    }
+                    return Ok(DataReadAnswer::GetManyOk(GetManyOkAnswer { data }));
+                }
+                GetManyTargetSpec::KeyPrefix(prefix) => {
+                    let data = self.get_prefix_in_transaction(transaction_id, get_many, prefix)?;
+                    return Ok(DataReadAnswer::GetManyOk(GetManyOkAnswer { data }));
+                }
+                GetManyTargetSpec::KeyRange(prefix, after, limit) => {
+                    // Buffered writes may fall anywhere in the range, so the whole prefix is
+                    // merged before the range is taken from it
+                    let get_prefix = GetManyInstruction {
+                        height: get_many.height,
+                        targets: GetManyTargetSpec::KeyPrefix(prefix.to_owned()),
+                    };
+                    let mut data =
+                        self.get_prefix_in_transaction(transaction_id, &get_prefix, prefix)?;
+                    data.sort_by(|a, b| a.0.as_slice().cmp(b.0.as_slice()));
+                    let data = data
+                        .into_iter()
+                        .filter(|(key, _value)| match after {
+                            None => true,
+                            Some(after) => key.as_slice() > after.as_slice(),
+                        })
+                        .take(*limit)
+                        .collect();
+                    return Ok(DataReadAnswer::GetManyOk(GetManyOkAnswer { data }));
+                }
+            },
+            DataReadInstruction::GetJournal(get_journal) => {
+                let journal = self.get_journal_in_transaction(transaction_id, &get_journal.key)?;
+                return Ok(DataReadAnswer::GetJournalOk(GetJournalOkAnswer { journal }));
+            }
+            DataReadInstruction::GetProof(get_proof) => {
+                // Proofs are about committed chain state, so buffered writes are left out
+                match self.pass_to_vkv(&get_proof.to_owned().into())? {
+                    Answer::DataAccess(DataAnswer::Read(answer)) => return Ok(answer),
+                    _ => return Err(TransactionError::UnexpectedAnswer.into()),
+                }
+            }
+            DataReadInstruction::Diff(diff) => {
+                // Like proofs, diffs compare heights already on the chain
+                match self.pass_to_vkv(&diff.to_owned().into())? {
+                    Answer::DataAccess(DataAnswer::Read(answer)) => return Ok(answer),
+                    _ => return Err(TransactionError::UnexpectedAnswer.into()),
+                }
+            }
+            DataReadInstruction::GetChanges(get_changes) => {
+                // The feed only carries committed records
+                match self.pass_to_vkv(&get_changes.to_owned().into())? {
+                    Answer::DataAccess(DataAnswer::Read(answer)) => return Ok(answer),
+                    _ => return Err(TransactionError::UnexpectedAnswer.into()),
+                }
+            }
+            DataReadInstruction::GetHeightAtTime(get_height_at_time) => {
+                match self.pass_to_vkv(&get_height_at_time.to_owned().into())? {
+                    Answer::DataAccess(DataAnswer::Read(answer)) => return Ok(answer),
+                    _ => return Err(TransactionError::UnexpectedAnswer.into()),
+                }
+            }
+            DataReadInstruction::GetHeight(get_height) => {
+                // The committed height; buffered writes have no height on the chain yet
+                match self.pass_to_vkv(&get_height.to_owned().into())? {
+                    Answer::DataAccess(DataAnswer::Read(answer)) => return Ok(answer),
+                    _ => return Err(TransactionError::UnexpectedAnswer.into()),
+                }
+            }
+        }
+    }
+
+    fn execute_write_in_transaction(
+        &mut self,
+        transaction_id: &TransactionId,
+        write: &DataWriteInstruction,
+    ) -> ImmuxResult<DataWriteAnswer> {
+        let transaction = self.get_transaction(transaction_id)?;
+        let next_height = transaction.next_height();
+        let base_height = transaction.base_height;
+        let (targets, answer) = match write {
+            // Only compaction writes these, outside any transaction
+            DataWriteInstruction::PruneChain(_) => {
+                return Err(VkvError::UnexpectedInstruction.into());
+            }
+            DataWriteInstruction::SetMany(set_many) => {
+                let count = set_many.targets.len();
+                (
+                    set_many.targets.clone(),
+                    DataWriteAnswer::SetOk(SetOkAnswer { count }),
+                )
+            }
+            DataWriteInstruction::RevertMany(RevertManyInstruction {
+                targets: revert_targets,
+            })
+            | DataWriteInstruction::IndexedRevertMany(IndexedRevertManyInstruction {
+                targets: revert_targets,
+                ..
+            }) => {
+                let mut targets = Vec::with_capacity(revert_targets.len());
+                for target in revert_targets {
+                    if target.height >= next_height {
+                        return Err(VkvError::TryingToRevertToFuture.into());
+                    }
+                    let value =
+                        self.read_in_transaction(transaction_id, &target.key, Some(target.height))?;
+                    targets.push(SetTargetSpec {
+                        key: target.key.clone(),
+                        value,
+                    });
+                }
+                (targets, DataWriteAnswer::RevertOk(RevertOkAnswer {}))
+            }
+            DataWriteInstruction::RevertAll(revert_all) => {
+                let target_height = revert_all.target_height;
+                if target_height >= next_height {
+                    return Err(VkvError::TryingToRevertToFuture.into());
+                }
+                let mut affected_keys = transaction.buffered_keys();
+                if target_height < base_height {
+                    let first_changed_height = ChainHeight::new(target_height.as_u64() + 1);
+                    let committed_keys =
+                        extract_affected_keys(&self.vkv, first_changed_height, base_height)?;
+                    affected_keys.extend(committed_keys);
+                }
+                let mut targets = Vec::with_capacity(affected_keys.len());
+                for key in &affected_keys {
+                    let value =
+                        self.read_in_transaction(transaction_id, key, Some(target_height))?;
+                    targets.push(SetTargetSpec {
+                        key: key.clone(),
+                        value,
+                    });
+                }
+                let reverted_keys = affected_keys.into_iter().collect();
+                (
+                    targets,
+                    DataWriteAnswer::RevertAllOk(RevertAllOkAnswer { reverted_keys }),
+                )
+            }
+            DataWriteInstruction::Revert(RevertInstruction { height })
+            | DataWriteInstruction::RevertKeys(RevertKeysInstruction { height, .. }) => {
+                let height = *height;
+                // Only committed records can be reverted, as buffered writes have no record yet
+                if height.is_zero() || height > base_height {
+                    return Err(VkvError::InvalidRevertHeight(height).into());
+                }
+                let mut previous_height = height;
+                previous_height.decrement();
+                let record_keys = extract_affected_keys(&self.vkv, height, height)?;
+                let only = match write {
+                    DataWriteInstruction::RevertKeys(revert_keys) => Some(&revert_keys.keys),
+                    _ => None,
+                };
+                let keys = match only {
+                    None => record_keys,
+                    Some(keys) => {
+                        for key in keys {
+                            if !record_keys.contains(key) {
+                                return Err(VkvError::KeyNotInRecord(height, key.to_owned()).into());
+                            }
+                        }
+                        keys.to_owned()
+                    }
+                };
+                let mut targets = Vec::with_capacity(keys.len());
+                let mut conflicts = Vec::new();
+                for key in keys {
+                    let journal = self.get_journal_in_transaction(transaction_id, &key)?;
+                    if journal
+                        .update_heights
+                        .iter()
+                        .any(|update_height| update_height > height)
+                    {
+                        if only.is_some() {
+                            return Err(VkvError::RevertConflict(key).into());
+                        }
+                        conflicts.push(key);
+                        continue;
+                    }
+                    let value =
+                        match self.read_in_transaction(transaction_id, &key, Some(previous_height))
+                        {
+                            Err(ImmuxError::VKV(VkvError::CannotFindSuitableVersion)) => {
+                                StoreValue::new(None)
+                            }
+                            Err(error) => return Err(error),
+                            Ok(value) => value,
+                        };
+                    targets.push(SetTargetSpec { key, value });
+                }
+                let reverted_keys = targets.iter().map(|target| target.key.clone()).collect();
+                (
+                    targets,
+                    DataWriteAnswer::RevertRecordOk(RevertRecordOkAnswer {
+                        reverted_keys,
+                        conflicts,
+                    }),
+                )
+            }
+        };
+        // Index targets are buffered with the reverted keys, so they commit or abort together
+        let targets = match write {
+            DataWriteInstruction::RevertKeys(RevertKeysInstruction { index_targets, .. })
+            | DataWriteInstruction::IndexedRevertMany(IndexedRevertManyInstruction {
+                index_targets,
+                ..
+            }) => {
+                let mut targets = targets;
+                targets.extend(index_targets.iter().cloned());
+                targets
+            }
+            _ => targets,
+        };
+        match self.transactions.get_mut(transaction_id) {
+            None => return Err(TransactionError::TransactionNotStarted.into()),
+            Some(transaction) => transaction.updates.push(targets),
+        }
+        return Ok(answer);
+    }
+
+    fn start_transaction(&mut self) -> TransactionMetaAnswer {
+        let transaction_id = self.next_transaction_id;
+        self.next_transaction_id.increment();
+        let has_active_transactions = !self.transactions.is_empty();
+        let transaction = Transaction::new(self.vkv.get_current_height());
+        self.transactions.insert(transaction_id, transaction);
+        if has_active_transactions {
+            return TransactionMetaAnswer::AppendTransactionOk(TransactionPendingAnswer {
+                transaction_id,
+            });
+        } else {
+            return TransactionMetaAnswer::StartTransactionOk(StartTransactionOkAnswer {
+                transaction_id,
+            });
+        }
+    }
+
+    fn commit_transaction(
+        &mut self,
+        transaction_id: &TransactionId,
+    ) -> ImmuxResult<TransactionMetaAnswer> {
+        let transaction = self.get_transaction(transaction_id)?;
+        let base_height = transaction.base_height;
+        let targets: Vec<SetTargetSpec> = transaction
+            .flatten_updates(transaction.updates.len())
+            .into_iter()
+            .map(|(key, value)| SetTargetSpec { key, value })
+            .collect();
+        if !targets.is_empty() {
+            // The first commit wins, as this one's values were worked out from an older snapshot
+            let mut first_changed_height = base_height;
+            first_changed_height.increment();
+            let committed_keys: BTreeSet<StoreKey> = extract_affected_keys(
+                &self.vkv,
+                first_changed_height,
+                self.vkv.get_current_height(),
+            )?
+            .into_iter()
+            .collect();
+            let conflicts: Vec<StoreKey> = targets
+                .iter()
+                .filter(|target| committed_keys.contains(&target.key))
+                .map(|target| target.key.to_owned())
+                .collect();
+            if !conflicts.is_empty() {
+                self.transactions.remove(transaction_id);
+                return Err(TransactionError::WriteConflict(conflicts).into());
+            }
+            // The whole transaction lands on the chain as one instruction, hence one height
+            let instruction: Instruction = SetManyInstruction { targets }.into();
+            self.pass_to_vkv(&instruction)?;
+        }
+        self.transactions.remove(transaction_id);
+        let next_active_transaction_id = self.transactions.keys().next().cloned();
+        return Ok(TransactionMetaAnswer::CommitTransactionOk(
+            CommitTransactionOkAnswer {
+                committed_transaction_id: transaction_id.to_owned(),
+                next_active_transaction_id,
+            },
+        ));
+    }
+
+    fn abort_transaction(
+        &mut self,
+        transaction_id: &TransactionId,
+    ) -> ImmuxResult<TransactionMetaAnswer> {
+        match self.transactions.remove(transaction_id) {
+            None => return Err(TransactionError::TransactionNotStarted.into()),
+            Some(_transaction) => {
+                return Ok(TransactionMetaAnswer::AbortTransactionOk(
+                    AbortTransactionOkAnswer {
+                        transaction_id: transaction_id.to_owned(),
+                    },
+                ));
+            }
+        }
+    }
 }
 
 impl TransactionKeyValueStore for ImmuxDBTransactionKeyValueStore {
     fn execute(&mut self, instruction: &Instruction) -> Result<Answer, ImmuxError> {
-        self.pass_to_vkv(instruction)
+        match instruction {
+            Instruction::TransactionMeta(meta_instruction) => {
+                let answer = match meta_instruction {
+                    TransactionMetaInstruction::StartTransaction => self.start_transaction(),
+                    TransactionMetaInstruction::CommitTransaction(commit) => {
+                        self.commit_transaction(&commit.transaction_id)?
+                    }
+                    TransactionMetaInstruction::AbortTransaction(abort) => {
+                        self.abort_transaction(&abort.transaction_id)?
+                    }
+                };
+                return Ok(Answer::TransactionMeta(answer));
+            }
+            Instruction::TransactionalData(transactional_instruction) => {
+                let transaction_id = &transactional_instruction.transaction_id;
+                let answer = match &transactional_instruction.plain_instruction {
+                    DataInstruction::Read(read) => {
+                        DataAnswer::Read(self.execute_read_in_transaction(transaction_id, read)?)
+                    }
+                    DataInstruction::Write(write) => {
+                        DataAnswer::Write(self.execute_write_in_transaction(transaction_id, write)?)
+                    }
+                };
+                return Ok(Answer::TransactionalData(TransactionalDataAnswer {
+                    transaction_id: transaction_id.to_owned(),
+                    answer,
+                }));
+            }
+            Instruction::DBSystem(DBSystemInstruction::SwitchNamespace(_))
+            | Instruction::DBSystem(DBSystemInstruction::ForkNamespace(_))
+            | Instruction::DBSystem(DBSystemInstruction::MergeNamespace(_)) => {
+                if !self.transactions.is_empty() {
+                    return Err(
+                        TransactionError::CannotSwitchNamespaceWhileTransactionIsOngoing.into(),
+                    );
+                }
+                return self.pass_to_vkv(instruction);
+            }
+            // Transactions read the chain as of their start, which compaction may prune
+            Instruction::DBSystem(DBSystemInstruction::Compact(_)) => {
+                if !self.transactions.is_empty() {
+                    return Err(TransactionError::CannotCompactWhileTransactionIsOngoing.into());
+                }
+                return self.pass_to_vkv(instruction);
+            }
+            _ => return self.pass_to_vkv(instruction),
+        }
     }
 }
 
 #[cfg(test)]
 mod tkv_tests {
-    use crate::declarations::errors::ImmuxResult;
+    use crate::declarations::basics::StoreKey;
+    use crate::declarations::errors::{ImmuxError, ImmuxResult};
     use crate::storage::instructions::{
         Answer, CommitTransactionInstruction, DataAnswer, DataWriteAnswer, Instruction,
         TransactionMetaAnswer, TransactionMetaInstruction, TransactionalDataAnswer,
@@ -53,17 +677,16 @@ mod tkv_tests {
         get_start_transaction_instruction, TKVTestCore,
     };
     use crate::storage::tkv::transaction_id::TransactionId;
-    use crate::storage::tkv::TransactionKeyValueStore;
+    use crate::storage::tkv::{TransactionError, TransactionKeyValueStore};
+    use crate::storage::vkv::VersionedKeyValueStore;
 
     #[test]
-    #[ignore]
     fn tkv_start_transaction() {
         let mut core = TKVTestCore::new("tkv_start_transaction");
         core.start_transaction().unwrap();
     }
 
     #[test]
-    #[ignore]
     fn tkv_set_answer_type() {
         let mut core = TKVTestCore::new("tkv_set_answer_type");
         let (tid_int, _) = core.start_transaction().unwrap();
@@ -83,7 +706,6 @@ mod tkv_tests {
     }
 
     #[test]
-    #[ignore]
     #[should_panic]
     fn tkv_commit_transaction_not_started() {
         let mut core = TKVTestCore::new("tkv_commit_transaction_not_started");
@@ -92,7 +714,6 @@ mod tkv_tests {
     }
 
     #[test]
-    #[ignore]
     #[should_panic]
     fn tkv_abort_transaction_not_started() {
         let mut core = TKVTestCore::new("tkv_abort_transaction_not_started");
@@ -101,7 +722,6 @@ mod tkv_tests {
     }
 
     #[test]
-    #[ignore]
     fn test_abort_transaction() {
         let key = "test_key";
         let mut core = TKVTestCore::new("test_abort_transaction");
@@ -117,7 +737,39 @@ mod tkv_tests {
     }
 
     #[test]
-    #[ignore]
+    fn test_read_own_writes() -> ImmuxResult<()> {
+        let mut core = TKVTestCore::new("test_read_own_writes");
+        let (tid, _) = core.start_transaction()?;
+        core.transactional_set("test_key", "test_value", tid)?;
+        assert_eq!(
+            core.transactional_get("test_key", tid)?,
+            Some("test_value".to_string())
+        );
+        core.commit_transaction(tid)?;
+        assert_eq!(core.simple_get("test_key")?, Some("test_value".to_string()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_commit_occupies_one_height() -> ImmuxResult<()> {
+        let mut core = TKVTestCore::new("test_commit_occupies_one_height");
+        let (tid, _) = core.start_transaction()?;
+        core.transactional_set("key_a", "value_a1", tid)?;
+        core.transactional_set("key_b", "value_b1", tid)?;
+        core.transactional_set("key_a", "value_a2", tid)?;
+        core.commit_transaction(tid)?;
+
+        let journal_a = core.simple_get_journal("key_a")?;
+        let journal_b = core.simple_get_journal("key_b")?;
+        let heights_a: Vec<_> = journal_a.update_heights.iter().collect();
+        let heights_b: Vec<_> = journal_b.update_heights.iter().collect();
+        assert_eq!(heights_a.len(), 1);
+        assert_eq!(heights_a, heights_b);
+        assert_eq!(core.simple_get("key_a")?, Some("value_a2".to_string()));
+        Ok(())
+    }
+
+    #[test]
     fn test_revert_one() -> ImmuxResult<()> {
         let mut core = TKVTestCore::new("test_revert_one");
         let (tid, _) = core.start_transaction()?;
@@ -133,7 +785,6 @@ mod tkv_tests {
     }
 
     #[test]
-    #[ignore]
     fn test_revert_all() -> ImmuxResult<()> {
         let mut core = TKVTestCore::new("test_revert_all");
         let (tid, _) = core.start_transaction()?;
@@ -149,7 +800,111 @@ mod tkv_tests {
     }
 
     #[test]
-    #[ignore]
+    fn test_journal_in_transaction() -> ImmuxResult<()> {
+        let mut core = TKVTestCore::new("test_journal_in_transaction");
+        let (tid, _) = core.start_transaction()?;
+        core.transactional_set("test_key", "test_value1", tid)?;
+        core.commit_transaction(tid)?;
+
+        let (tid, _) = core.start_transaction()?;
+        let (other_tid, _) = core.start_transaction()?;
+        core.transactional_set("other_key", "other_value", other_tid)?;
+        core.commit_transaction(other_tid)?;
+
+        // Buffered writes follow the snapshot, whatever was committed since
+        core.transactional_set("test_key", "test_value2", tid)?;
+        core.transactional_set("test_key", "test_value3", tid)?;
+        let journal = core.transactional_get_journal("test_key", tid)?;
+        let heights: Vec<u64> = journal
+            .update_heights
+            .iter()
+            .map(|height| height.as_u64())
+            .collect();
+        assert_eq!(heights, vec![1, 2, 3]);
+        core.transactional_revert("test_key", 2, tid)?;
+        core.commit_transaction(tid)?;
+
+        assert_eq!(
+            core.simple_get("test_key")?,
+            Some("test_value2".to_string())
+        );
+        assert_eq!(
+            core.simple_get("other_key")?,
+            Some("other_value".to_string())
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_revert_record() -> ImmuxResult<()> {
+        let mut core = TKVTestCore::new("test_revert_record");
+        for values in &[vec!["a1", "b1"], vec!["a2", "b2"], vec!["b3"]] {
+            let (tid, _) = core.start_transaction()?;
+            for value in values {
+                core.transactional_set(&value[..1], value, tid)?;
+            }
+            core.commit_transaction(tid)?;
+        }
+        let mut height = core.tkv.vkv.get_current_height();
+        height.decrement();
+
+        let (tid, _) = core.start_transaction()?;
+        match core.transactional_revert_record(height, tid)? {
+            Answer::TransactionalData(TransactionalDataAnswer {
+                answer: DataAnswer::Write(DataWriteAnswer::RevertRecordOk(answer)),
+                ..
+            }) => {
+                assert_eq!(answer.reverted_keys, vec![StoreKey::from("a")]);
+                assert_eq!(answer.conflicts, vec![StoreKey::from("b")]);
+            }
+            answer => panic!("Unexpected answer {:?}", answer),
+        }
+        core.commit_transaction(tid)?;
+        assert_eq!(core.simple_get("a")?, Some("a1".to_string()));
+        assert_eq!(core.simple_get("b")?, Some("b3".to_string()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_interleaved_write_conflict() -> ImmuxResult<()> {
+        let mut core = TKVTestCore::new("test_interleaved_write_conflict");
+        let (tid1, _) = core.start_transaction()?;
+        let (tid2, _) = core.start_transaction()?;
+        core.transactional_set("test_key", "value1", tid1)?;
+        core.transactional_set("test_key", "value2", tid2)?;
+        core.transactional_set("other_key", "other_value", tid2)?;
+        core.commit_transaction(tid1)?;
+
+        match core.commit_transaction(tid2) {
+            Err(ImmuxError::Transaction(TransactionError::WriteConflict(keys))) => {
+                assert_eq!(keys, vec![StoreKey::from("test_key")]);
+            }
+            result => panic!("Expected a write conflict, got {:?}", result),
+        }
+        assert_eq!(core.simple_get("test_key")?, Some("value1".to_string()));
+        assert_eq!(core.simple_get("other_key")?, None);
+        assert!(core.abort_transaction(tid2).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_interleaved_disjoint_writes() -> ImmuxResult<()> {
+        let mut core = TKVTestCore::new("test_interleaved_disjoint_writes");
+        let (tid1, _) = core.start_transaction()?;
+        let (tid2, _) = core.start_transaction()?;
+        core.transactional_set("key_a", "value_a", tid1)?;
+        core.transactional_set("key_b", "value_b", tid2)?;
+        core.commit_transaction(tid1)?;
+
+        // The second transaction reads from the snapshot it started with
+        assert_eq!(core.transactional_get("key_a", tid2).ok(), None);
+        core.commit_transaction(tid2)?;
+        assert_eq!(core.simple_get("key_a")?, Some("value_a".to_string()));
+        assert_eq!(core.simple_get("key_b")?, Some("value_b".to_string()));
+        Ok(())
+    }
+
+    #[test]
     fn test_multiple_concurrent_transactions() -> ImmuxResult<()> {
         let mut core = TKVTestCore::new("test_multiple_concurrent_transactions");
 
@@ -196,21 +951,24 @@ mod tkv_tests {
 
 #[cfg(test)]
 mod tkv_test_utils {
+    use immuxdb_dev_utils::reset_db_dir;
+
     use crate::config::{ImmuxDBConfiguration, DEFAULT_PERMANENCE_PATH};
     use crate::declarations::basics::{StoreKey, StoreValue};
     use crate::declarations::errors::{ImmuxError, ImmuxResult};
     use crate::storage::instructions::{
         AbortTransactionInstruction, Answer, CommitTransactionInstruction, DataAnswer,
         DataInstruction, DataReadAnswer, DataReadInstruction, DataWriteInstruction,
-        GetOneInstruction, Instruction, RevertAllInstruction, RevertManyInstruction,
-        RevertTargetSpec, SetManyInstruction, SetTargetSpec, StoreNamespace, TransactionMetaAnswer,
-        TransactionMetaInstruction, TransactionalDataInstruction,
+        GetJournalInstruction, GetOneInstruction, Instruction, RevertAllInstruction,
+        RevertInstruction, RevertManyInstruction, RevertTargetSpec, SetManyInstruction,
+        SetTargetSpec, StoreNamespace, TransactionMetaAnswer, TransactionMetaInstruction,
+        TransactionalDataAnswer, TransactionalDataInstruction,
     };
     use crate::storage::tkv::transaction_id::TransactionId;
     use crate::storage::tkv::{
         ImmuxDBTransactionKeyValueStore, TransactionError, TransactionKeyValueStore,
     };
-    use crate::storage::vkv::{ChainHeight, VkvError};
+    use crate::storage::vkv::{ChainHeight, UnitJournal, VkvError};
     use crate::utils::utf8_to_string;
 
     pub struct TKVTestCore {
@@ -220,6 +978,7 @@ mod tkv_test_utils {
     impl TKVTestCore {
         pub fn new(ns: &str) -> TKVTestCore {
             let config = ImmuxDBConfiguration::default();
+            reset_db_dir(&format!("{}{}", DEFAULT_PERMANENCE_PATH, ns)).unwrap();
             let namespace = StoreNamespace::new(ns.as_bytes());
             let tkv = ImmuxDBTransactionKeyValueStore::new(
                 &config.engine_choice,
@@ -293,6 +1052,59 @@ mod tkv_test_utils {
                 }
             }
         }
+        pub fn transactional_get(
+            &mut self,
+            key_str: &str,
+            tid_int: u64,
+        ) -> Result<Option<String>, ImmuxError> {
+            let key = StoreKey::from(key_str.as_bytes().to_vec());
+            let get = Instruction::TransactionalData(TransactionalDataInstruction {
+                plain_instruction: DataInstruction::Read(DataReadInstruction::GetOne(
+                    GetOneInstruction { height: None, key },
+                )),
+                transaction_id: TransactionId::new(tid_int),
+            });
+            match self.tkv.execute(&get)? {
+                Answer::TransactionalData(TransactionalDataAnswer {
+                    answer: DataAnswer::Read(DataReadAnswer::GetOneOk(answer)),
+                    ..
+                }) => match answer.value.inner() {
+                    None => Ok(None),
+                    Some(data) => Ok(Some(utf8_to_string(data))),
+                },
+                _answer => return Err(ImmuxError::Transaction(TransactionError::UnexpectedAnswer)),
+            }
+        }
+        pub fn simple_get_journal(&mut self, key_str: &str) -> Result<UnitJournal, ImmuxError> {
+            let key = StoreKey::from(key_str.as_bytes().to_vec());
+            let get_journal: Instruction = GetJournalInstruction { key }.into();
+            match self.tkv.execute(&get_journal)? {
+                Answer::DataAccess(DataAnswer::Read(DataReadAnswer::GetJournalOk(answer))) => {
+                    Ok(answer.journal)
+                }
+                _answer => return Err(ImmuxError::Transaction(TransactionError::UnexpectedAnswer)),
+            }
+        }
+        pub fn transactional_get_journal(
+            &mut self,
+            key_str: &str,
+            tid_int: u64,
+        ) -> Result<UnitJournal, ImmuxError> {
+            let key = StoreKey::from(key_str.as_bytes().to_vec());
+            let get_journal = Instruction::TransactionalData(TransactionalDataInstruction {
+                plain_instruction: DataInstruction::Read(DataReadInstruction::GetJournal(
+                    GetJournalInstruction { key },
+                )),
+                transaction_id: TransactionId::new(tid_int),
+            });
+            match self.tkv.execute(&get_journal)? {
+                Answer::TransactionalData(TransactionalDataAnswer {
+                    answer: DataAnswer::Read(DataReadAnswer::GetJournalOk(answer)),
+                    ..
+                }) => Ok(answer.journal),
+                _answer => return Err(ImmuxError::Transaction(TransactionError::UnexpectedAnswer)),
+            }
+        }
         pub fn transactional_revert_all(
             &mut self,
             target_height: u64,
@@ -324,6 +1136,19 @@ mod tkv_test_utils {
             });
             return self.tkv.execute(&instruction);
         }
+        pub fn transactional_revert_record(
+            &mut self,
+            height: ChainHeight,
+            transaction_id: u64,
+        ) -> Result<Answer, ImmuxError> {
+            let instruction = Instruction::TransactionalData(TransactionalDataInstruction {
+                plain_instruction: DataInstruction::Write(DataWriteInstruction::Revert(
+                    RevertInstruction { height },
+                )),
+                transaction_id: TransactionId::new(transaction_id),
+            });
+            return self.tkv.execute(&instruction);
+        }
         pub fn commit_transaction(&mut self, id: u64) -> ImmuxResult<Answer> {
             let commit_transaction = Instruction::TransactionMeta(
                 TransactionMetaInstruction::CommitTransaction(CommitTransactionInstruction {
@@ -33,13 +33,16 @@ impl HeightList {
         result
     }
 
-    pub fn parse(data: &[u8]) -> Result<Self, ChainHeightError> {
+    pub fn parse(data: &[u8]) -> Result<(Self, usize), ChainHeightError> {
         let (length, offset) = varint_decode(data).map_err(|_| ChainHeightError::ParseError)?;
         let expected_end = offset + length as usize;
         if data.len() < expected_end {
             return Err(ChainHeightError::UnexpectedLength(data.len()));
         } else {
-            return Ok(HeightList(data[offset..expected_end].to_vec()));
+            return Ok((
+                HeightList(data[offset..expected_end].to_vec()),
+                expected_end,
+            ));
         }
     }
 
@@ -105,7 +108,7 @@ mod height_list_tests {
             0x00, // height 2,
             0xfd, 0xff, 0x00, // height 3
         ];
-        let parsed = HeightList::parse(&data).unwrap();
+        let (parsed, _) = HeightList::parse(&data).unwrap();
         let expected = HeightList::new(&[
             ChainHeight::new(0x12345678),
             ChainHeight::new(0),
@@ -114,10 +117,25 @@ mod height_list_tests {
         assert_eq!(parsed, expected)
     }
 
+    #[test]
+    fn test_parse_width() {
+        let data = [
+            0x02, // data length
+            0x01, 0x02, // heights
+            0xaa, 0xbb, // unrelated data
+        ];
+        let (parsed, width) = HeightList::parse(&data).unwrap();
+        assert_eq!(
+            parsed,
+            HeightList::new(&[ChainHeight::new(1), ChainHeight::new(2)])
+        );
+        assert_eq!(width, 3)
+    }
+
     #[test]
     fn test_parse_empty() {
         let data = [0x00];
-        let parsed = HeightList::parse(&data).unwrap();
+        let (parsed, _) = HeightList::parse(&data).unwrap();
         let expected = HeightList::new(&[]);
         assert_eq!(parsed, expected)
     }
@@ -134,7 +152,7 @@ mod height_list_tests {
                 .collect();
             let list = HeightList::new(&heights);
             let serialized = list.marshal();
-            let parsed = HeightList::parse(&serialized).unwrap();
+            let (parsed, _) = HeightList::parse(&serialized).unwrap();
             assert_eq!(list, parsed)
         }
     }
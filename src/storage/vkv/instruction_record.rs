@@ -1,11 +1,14 @@
 use std::time::{SystemTime, UNIX_EPOCH};
 
+use bincode::{serialize, Error as BincodeError};
 use serde::{Deserialize, Serialize};
 
 use crate::config::DB_VERSION;
 use crate::declarations::basics::db_version::DBVersion;
 use crate::declarations::basics::StoreKey;
-use crate::storage::instructions::Instruction;
+use crate::storage::instructions::{Instruction, StoreNamespace};
+use crate::storage::vkv::chain_height::ChainHeight;
+use crate::storage::vkv::record_hash::RecordHash;
 
 fn now() -> u128 {
     let start = SystemTime::now();
@@ -15,7 +18,22 @@ fn now() -> u128 {
         .unwrap_or(0)
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+/// The chain and height a merged record was replayed from
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct MergeOrigin {
+    pub namespace: StoreNamespace,
+    pub height: ChainHeight,
+}
+
+/// Who wrote a record and why, as given with the command that wrote it
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct RecordMeta {
+    pub author: Option<String>,
+    pub message: Option<String>,
+    pub client_id: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct InstructionRecord {
     pub instruction: Instruction,
     pub version: DBVersion,
@@ -23,6 +41,55 @@ pub struct InstructionRecord {
 
     // Only for some instructions that do not include all keys that would be affected
     pub affected_keys: Option<Vec<StoreKey>>,
+
+    // Hash of the record one height below, or genesis for the first record
+    pub previous_hash: RecordHash,
+    // Hash of the serialized instruction, affected keys, merge origin, metadata, version and
+    // system time
+    pub content_hash: RecordHash,
+
+    // Only for records replayed from another chain by a merge
+    pub merged_from: Option<MergeOrigin>,
+
+    pub meta: RecordMeta,
+}
+
+impl InstructionRecord {
+    pub fn compute_content_hash(&self) -> Result<RecordHash, BincodeError> {
+        let instruction_bytes = serialize(&self.instruction)?;
+        let affected_keys_bytes = serialize(&self.affected_keys)?;
+        let merged_from_bytes = serialize(&self.merged_from)?;
+        let meta_bytes = serialize(&self.meta)?;
+        let version_bytes = serialize(&self.version)?;
+        let sys_time_bytes = serialize(&self.sys_time)?;
+        Ok(RecordHash::digest(&[
+            &instruction_bytes,
+            &affected_keys_bytes,
+            &merged_from_bytes,
+            &meta_bytes,
+            &version_bytes,
+            &sys_time_bytes,
+        ]))
+    }
+
+    /// The content hash of records sealed before the version and system time were hashed
+    pub fn compute_untimed_content_hash(&self) -> Result<RecordHash, BincodeError> {
+        let instruction_bytes = serialize(&self.instruction)?;
+        let affected_keys_bytes = serialize(&self.affected_keys)?;
+        let merged_from_bytes = serialize(&self.merged_from)?;
+        let meta_bytes = serialize(&self.meta)?;
+        Ok(RecordHash::digest(&[
+            &instruction_bytes,
+            &affected_keys_bytes,
+            &merged_from_bytes,
+            &meta_bytes,
+        ]))
+    }
+
+    /// The link to this record, which the record one height above stores as its previous hash
+    pub fn chained_hash(&self) -> RecordHash {
+        RecordHash::digest(&[self.previous_hash.as_bytes(), self.content_hash.as_bytes()])
+    }
 }
 
 impl From<Instruction> for InstructionRecord {
@@ -32,6 +99,34 @@ impl From<Instruction> for InstructionRecord {
             version: DB_VERSION,
             sys_time: now(),
             affected_keys: None,
+            previous_hash: RecordHash::genesis(),
+            content_hash: RecordHash::genesis(),
+            merged_from: None,
+            meta: RecordMeta::default(),
+        }
+    }
+}
+
+/// Records written before instruction records were chained by hash
+#[derive(Serialize, Deserialize, Debug)]
+pub struct LegacyInstructionRecord {
+    pub instruction: Instruction,
+    pub version: DBVersion,
+    pub sys_time: u128,
+    pub affected_keys: Option<Vec<StoreKey>>,
+}
+
+impl From<LegacyInstructionRecord> for InstructionRecord {
+    fn from(legacy: LegacyInstructionRecord) -> InstructionRecord {
+        InstructionRecord {
+            instruction: legacy.instruction,
+            version: legacy.version,
+            sys_time: legacy.sys_time,
+            affected_keys: legacy.affected_keys,
+            previous_hash: RecordHash::genesis(),
+            content_hash: RecordHash::genesis(),
+            merged_from: None,
+            meta: RecordMeta::default(),
         }
     }
 }
@@ -1,6 +1,11 @@
 use crate::declarations::basics::StoreValue;
 use crate::declarations::errors::ImmuxResult;
 use crate::storage::vkv::height_list::HeightList;
+use crate::storage::vkv::{ChainHeight, VkvError};
+use crate::utils::{varint_decode, varint_encode};
+
+/// Update heights sealed together into one page of a journal
+pub const JOURNAL_PAGE_SIZE: usize = 128;
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct UnitJournal {
@@ -17,7 +22,7 @@ impl UnitJournal {
     }
     pub fn parse(data: &[u8]) -> ImmuxResult<Self> {
         let (value, value_width) = StoreValue::parse(&data)?;
-        let update_heights = HeightList::parse(&data[value_width..])?;
+        let (update_heights, _) = HeightList::parse(&data[value_width..])?;
         return Ok(UnitJournal {
             value,
             update_heights,
@@ -25,11 +30,77 @@ impl UnitJournal {
     }
 }
 
+/// What is stored of a journal under its key: the latest value, the most recent update heights,
+/// and how many pages of older update heights were sealed before them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JournalHead {
+    pub value: StoreValue,
+    pub recent_heights: HeightList,
+    pub page_count: u64,
+}
+
+impl JournalHead {
+    pub fn new(value: &StoreValue, height: ChainHeight) -> Self {
+        JournalHead {
+            value: value.to_owned(),
+            recent_heights: HeightList::new(&[height]),
+            page_count: 0,
+        }
+    }
+
+    pub fn marshal(&self) -> Vec<u8> {
+        let mut result = Vec::new();
+        result.extend(self.value.marshal());
+        result.extend(self.recent_heights.marshal());
+        result.extend(varint_encode(self.page_count));
+        return result;
+    }
+
+    /// Journals written before paging have no page count, which parse as heads without pages
+    pub fn parse(data: &[u8]) -> ImmuxResult<Self> {
+        let (value, value_width) = StoreValue::parse(&data)?;
+        let (recent_heights, heights_width) = HeightList::parse(&data[value_width..])?;
+        let rest = &data[value_width + heights_width..];
+        let page_count = if rest.is_empty() {
+            0
+        } else {
+            match varint_decode(rest) {
+                Err(_error) => return Err(VkvError::JournalParsing.into()),
+                Ok((page_count, _)) => page_count,
+            }
+        };
+        return Ok(JournalHead {
+            value,
+            recent_heights,
+            page_count,
+        });
+    }
+
+    pub fn get_latest_height(&self) -> Option<ChainHeight> {
+        self.recent_heights.iter().last()
+    }
+
+    /// Records an update, returning the recent heights to be stored as page `page_count - 1` if
+    /// they filled up a page
+    pub fn push(&mut self, value: &StoreValue, height: ChainHeight) -> Option<HeightList> {
+        self.value = value.to_owned();
+        if self.recent_heights.iter().count() < JOURNAL_PAGE_SIZE {
+            self.recent_heights.push(height);
+            return None;
+        } else {
+            let sealed_page =
+                std::mem::replace(&mut self.recent_heights, HeightList::new(&[height]));
+            self.page_count += 1;
+            return Some(sealed_page);
+        }
+    }
+}
+
 #[cfg(test)]
 mod journal_tests {
     use crate::declarations::basics::StoreValue;
     use crate::storage::vkv::height_list::HeightList;
-    use crate::storage::vkv::journal::UnitJournal;
+    use crate::storage::vkv::journal::{JournalHead, UnitJournal, JOURNAL_PAGE_SIZE};
     use crate::storage::vkv::ChainHeight;
 
     #[test]
@@ -98,4 +169,48 @@ mod journal_tests {
             }
         }
     }
+
+    #[test]
+    fn test_journal_head_serialize_reversibility() {
+        let head = JournalHead {
+            value: StoreValue::new(Some(vec![1, 2, 3])),
+            recent_heights: HeightList::new(&[ChainHeight::new(0xf0), ChainHeight::new(0xff00)]),
+            page_count: 0x1234,
+        };
+        let parsed = JournalHead::parse(&head.marshal()).unwrap();
+        assert_eq!(head, parsed);
+    }
+
+    #[test]
+    fn test_parse_journal_as_head() {
+        let journal = UnitJournal {
+            value: StoreValue::new(Some(vec![0xaa])),
+            update_heights: HeightList::new(&[ChainHeight::new(1), ChainHeight::new(2)]),
+        };
+        let head = JournalHead::parse(&journal.marshal()).unwrap();
+        assert_eq!(head.value, journal.value);
+        assert_eq!(head.recent_heights, journal.update_heights);
+        assert_eq!(head.page_count, 0);
+    }
+
+    #[test]
+    fn test_journal_head_push() {
+        let value = StoreValue::new(Some(vec![1]));
+        let mut head = JournalHead::new(&value, ChainHeight::new(1));
+        for height in 2..=JOURNAL_PAGE_SIZE as u64 {
+            assert_eq!(head.push(&value, ChainHeight::new(height)), None);
+        }
+        assert_eq!(head.page_count, 0);
+
+        let last_value = StoreValue::new(Some(vec![2]));
+        let next_height = ChainHeight::new(JOURNAL_PAGE_SIZE as u64 + 1);
+        let sealed_page = head.push(&last_value, next_height).unwrap();
+        let sealed_heights: Vec<ChainHeight> = sealed_page.iter().collect();
+        assert_eq!(sealed_heights.len(), JOURNAL_PAGE_SIZE);
+        assert_eq!(sealed_heights[0], ChainHeight::new(1));
+        assert_eq!(head.page_count, 1);
+        assert_eq!(head.recent_heights, HeightList::new(&[next_height]));
+        assert_eq!(head.get_latest_height(), Some(next_height));
+        assert_eq!(head.value, last_value);
+    }
 }
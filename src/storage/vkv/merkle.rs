@@ -0,0 +1,476 @@
+/*
+ *  Merkle tree over the present keys of a chain at some height.
+ *
+ *  The tree is a sparse binary tree in which each key follows the path given by the bits of its
+ *  hash. A leaf sits at the shallowest depth where no other key shares its path, and an inner
+ *  node always has at least two leaves below it, so the root only depends on the keys and values
+ *  in the tree, not on the order they were written in. Nodes are stored under their hashes and
+ *  never changed, so the roots of earlier heights stay provable as the tree grows.
+**/
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::declarations::basics::{StoreKey, StoreValue};
+use crate::storage::vkv::record_hash::{RecordHash, RECORD_HASH_LENGTH};
+use crate::utils::varint_encode;
+
+// Distinct leaf and node prefixes keep a leaf from being passed off as an inner node
+const LEAF_PREFIX: u8 = 0x00;
+const NODE_PREFIX: u8 = 0x01;
+
+/// Which side of the running hash the sibling sits on
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum MerkleSide {
+    Left,
+    Right,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MerkleProofStep {
+    pub sibling: RecordHash,
+    pub side: MerkleSide,
+}
+
+/// Sibling hashes from a leaf up to the root
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MerkleProof {
+    pub steps: Vec<MerkleProofStep>,
+}
+
+#[derive(Debug)]
+pub enum StateNodeError {
+    UnexpectedLength(usize),
+    UnexpectedPrefix(u8),
+}
+
+/// A node of the state tree, stored under its hash. Leaves only keep the path of their key, as
+/// their hash already covers the key and its value.
+#[derive(Debug, Clone, PartialEq)]
+pub enum StateNode {
+    Leaf(RecordHash),
+    Inner(RecordHash, RecordHash),
+}
+
+impl StateNode {
+    pub fn marshal(&self) -> Vec<u8> {
+        let mut result = Vec::with_capacity(1 + RECORD_HASH_LENGTH * 2);
+        match self {
+            StateNode::Leaf(path) => {
+                result.push(LEAF_PREFIX);
+                result.extend_from_slice(path.as_bytes());
+            }
+            StateNode::Inner(left, right) => {
+                result.push(NODE_PREFIX);
+                result.extend_from_slice(left.as_bytes());
+                result.extend_from_slice(right.as_bytes());
+            }
+        }
+        return result;
+    }
+
+    pub fn parse(data: &[u8]) -> Result<Self, StateNodeError> {
+        let parse_hash = |bytes: &[u8]| match RecordHash::parse(bytes) {
+            Err(_error) => Err(StateNodeError::UnexpectedLength(data.len())),
+            Ok(hash) => Ok(hash),
+        };
+        match data.first() {
+            None => return Err(StateNodeError::UnexpectedLength(0)),
+            Some(&LEAF_PREFIX) => return Ok(StateNode::Leaf(parse_hash(&data[1..])?)),
+            Some(&NODE_PREFIX) => {
+                if data.len() != 1 + RECORD_HASH_LENGTH * 2 {
+                    return Err(StateNodeError::UnexpectedLength(data.len()));
+                }
+                let (left, right) = data[1..].split_at(RECORD_HASH_LENGTH);
+                return Ok(StateNode::Inner(parse_hash(left)?, parse_hash(right)?));
+            }
+            Some(prefix) => return Err(StateNodeError::UnexpectedPrefix(*prefix)),
+        }
+    }
+}
+
+pub fn hash_merkle_leaf(key: &StoreKey, value: &StoreValue) -> RecordHash {
+    let key_bytes = key.as_slice();
+    let key_length = varint_encode(key_bytes.len() as u64);
+    RecordHash::digest(&[&[LEAF_PREFIX], &key_length, key_bytes, &value.marshal()])
+}
+
+fn hash_merkle_node(left: &RecordHash, right: &RecordHash) -> RecordHash {
+    RecordHash::digest(&[&[NODE_PREFIX], left.as_bytes(), right.as_bytes()])
+}
+
+/// The root of a tree without keys, which also stands for every empty subtree
+pub fn get_empty_state_root() -> RecordHash {
+    RecordHash::genesis()
+}
+
+pub fn get_state_path(key: &StoreKey) -> RecordHash {
+    RecordHash::digest(&[key.as_slice()])
+}
+
+/// Whether the path turns right at `depth`
+fn is_right_at(path: &RecordHash, depth: usize) -> bool {
+    (path.as_bytes()[depth / 8] >> (7 - depth % 8)) & 1 == 1
+}
+
+/// The root after a batch of changes, with the nodes it added, which are to be saved with it
+pub struct StateTreeUpdate {
+    pub root: RecordHash,
+    pub nodes: Vec<(RecordHash, StateNode)>,
+}
+
+struct StateTreeWriter<'a, E> {
+    load: &'a dyn Fn(&RecordHash) -> Result<StateNode, E>,
+    nodes: HashMap<RecordHash, StateNode>,
+}
+
+impl<'a, E> StateTreeWriter<'a, E> {
+    fn get(&self, hash: &RecordHash) -> Result<StateNode, E> {
+        match self.nodes.get(hash) {
+            Some(node) => return Ok(node.to_owned()),
+            None => return (self.load)(hash),
+        }
+    }
+
+    fn put_inner(&mut self, left: RecordHash, right: RecordHash) -> RecordHash {
+        let hash = hash_merkle_node(&left, &right);
+        self.nodes.insert(hash, StateNode::Inner(left, right));
+        return hash;
+    }
+
+    fn insert(
+        &mut self,
+        node_hash: RecordHash,
+        depth: usize,
+        path: &RecordHash,
+        leaf_hash: RecordHash,
+    ) -> Result<RecordHash, E> {
+        if node_hash == get_empty_state_root() {
+            self.nodes.insert(leaf_hash, StateNode::Leaf(*path));
+            return Ok(leaf_hash);
+        }
+        match self.get(&node_hash)? {
+            StateNode::Leaf(existing_path) => {
+                self.nodes.insert(leaf_hash, StateNode::Leaf(*path));
+                if &existing_path == path {
+                    return Ok(leaf_hash);
+                }
+                return Ok(self.split(depth, (node_hash, existing_path), (leaf_hash, *path)));
+            }
+            StateNode::Inner(left, right) => {
+                if is_right_at(path, depth) {
+                    let right = self.insert(right, depth + 1, path, leaf_hash)?;
+                    return Ok(self.put_inner(left, right));
+                } else {
+                    let left = self.insert(left, depth + 1, path, leaf_hash)?;
+                    return Ok(self.put_inner(left, right));
+                }
+            }
+        }
+    }
+
+    /// Inner nodes from `depth` down to where the paths of the two leaves part
+    fn split(
+        &mut self,
+        depth: usize,
+        a: (RecordHash, RecordHash),
+        b: (RecordHash, RecordHash),
+    ) -> RecordHash {
+        let a_is_right = is_right_at(&a.1, depth);
+        if a_is_right == is_right_at(&b.1, depth) {
+            let child = self.split(depth + 1, a, b);
+            if a_is_right {
+                return self.put_inner(get_empty_state_root(), child);
+            } else {
+                return self.put_inner(child, get_empty_state_root());
+            }
+        } else if a_is_right {
+            return self.put_inner(b.0, a.0);
+        } else {
+            return self.put_inner(a.0, b.0);
+        }
+    }
+
+    fn remove(
+        &mut self,
+        node_hash: RecordHash,
+        depth: usize,
+        path: &RecordHash,
+    ) -> Result<RecordHash, E> {
+        if node_hash == get_empty_state_root() {
+            return Ok(node_hash);
+        }
+        match self.get(&node_hash)? {
+            StateNode::Leaf(existing_path) => {
+                if &existing_path == path {
+                    return Ok(get_empty_state_root());
+                }
+                return Ok(node_hash);
+            }
+            StateNode::Inner(left, right) => {
+                let (left, right) = if is_right_at(path, depth) {
+                    (left, self.remove(right, depth + 1, path)?)
+                } else {
+                    (self.remove(left, depth + 1, path)?, right)
+                };
+                // A leaf left alone below the node takes its place
+                let empty = get_empty_state_root();
+                for (child, other) in &[(left, right), (right, left)] {
+                    if other == &empty {
+                        if child == &empty {
+                            return Ok(empty);
+                        }
+                        if let StateNode::Leaf(_) = self.get(child)? {
+                            return Ok(*child);
+                        }
+                    }
+                }
+                return Ok(self.put_inner(left, right));
+            }
+        }
+    }
+}
+
+/// Applies `changes` to the tree at `root`, where a key with an empty value is taken out
+pub fn update_state_tree<E>(
+    root: RecordHash,
+    changes: &[(StoreKey, StoreValue)],
+    load: &dyn Fn(&RecordHash) -> Result<StateNode, E>,
+) -> Result<StateTreeUpdate, E> {
+    let mut writer = StateTreeWriter {
+        load,
+        nodes: HashMap::new(),
+    };
+    let mut root = root;
+    for (key, value) in changes {
+        let path = get_state_path(key);
+        root = match value.inner() {
+            None => writer.remove(root, 0, &path)?,
+            Some(_) => writer.insert(root, 0, &path, hash_merkle_leaf(key, value))?,
+        };
+    }
+    // Nodes replaced by later changes in the batch are left out, so that the nodes saved only
+    // depend on the trees at each height
+    let mut nodes = Vec::new();
+    let mut pending = vec![root];
+    while let Some(hash) = pending.pop() {
+        if let Some(node) = writer.nodes.remove(&hash) {
+            if let StateNode::Inner(left, right) = &node {
+                pending.push(*left);
+                pending.push(*right);
+            }
+            nodes.push((hash, node));
+        }
+    }
+    return Ok(StateTreeUpdate { root, nodes });
+}
+
+/// The proof for the leaf of `key` in the tree at `root`, or None if the key is not in it
+pub fn prove_state_tree<E>(
+    root: RecordHash,
+    key: &StoreKey,
+    load: &dyn Fn(&RecordHash) -> Result<StateNode, E>,
+) -> Result<Option<MerkleProof>, E> {
+    let path = get_state_path(key);
+    let mut node_hash = root;
+    let mut steps = Vec::new();
+    let mut depth = 0;
+    while node_hash != get_empty_state_root() {
+        match load(&node_hash)? {
+            StateNode::Leaf(leaf_path) => {
+                if leaf_path != path {
+                    return Ok(None);
+                }
+                steps.reverse();
+                return Ok(Some(MerkleProof { steps }));
+            }
+            StateNode::Inner(left, right) => {
+                if is_right_at(&path, depth) {
+                    steps.push(MerkleProofStep {
+                        sibling: left,
+                        side: MerkleSide::Left,
+                    });
+                    node_hash = right;
+                } else {
+                    steps.push(MerkleProofStep {
+                        sibling: right,
+                        side: MerkleSide::Right,
+                    });
+                    node_hash = left;
+                }
+                depth += 1;
+            }
+        }
+    }
+    return Ok(None);
+}
+
+impl MerkleProof {
+    pub fn compute_root(&self, leaf: &RecordHash) -> RecordHash {
+        let mut hash = *leaf;
+        for step in &self.steps {
+            hash = match step.side {
+                MerkleSide::Left => hash_merkle_node(&step.sibling, &hash),
+                MerkleSide::Right => hash_merkle_node(&hash, &step.sibling),
+            };
+        }
+        hash
+    }
+
+    /// Checks that `key` held `value` in the tree whose root is `root`, at the place its path
+    /// leads to
+    pub fn verify(&self, key: &StoreKey, value: &StoreValue, root: &RecordHash) -> bool {
+        let path = get_state_path(key);
+        let depth = self.steps.len();
+        if depth > RECORD_HASH_LENGTH * 8 {
+            return false;
+        }
+        for (index, step) in self.steps.iter().enumerate() {
+            let is_right = step.side == MerkleSide::Left;
+            if is_right_at(&path, depth - 1 - index) != is_right {
+                return false;
+            }
+        }
+        let leaf = hash_merkle_leaf(key, value);
+        return &self.compute_root(&leaf) == root;
+    }
+}
+
+#[cfg(test)]
+mod merkle_tests {
+    use std::cell::RefCell;
+    use std::collections::HashMap;
+
+    use crate::declarations::basics::{StoreKey, StoreValue};
+    use crate::storage::vkv::merkle::{
+        get_empty_state_root, prove_state_tree, update_state_tree, StateNode,
+    };
+    use crate::storage::vkv::RecordHash;
+
+    struct NodeStore {
+        nodes: RefCell<HashMap<RecordHash, StateNode>>,
+    }
+
+    impl NodeStore {
+        fn new() -> Self {
+            NodeStore {
+                nodes: RefCell::new(HashMap::new()),
+            }
+        }
+        fn update(&self, root: RecordHash, changes: &[(StoreKey, StoreValue)]) -> RecordHash {
+            let load = |hash: &RecordHash| match self.nodes.borrow().get(hash) {
+                None => Err(()),
+                Some(node) => Ok(node.to_owned()),
+            };
+            let update = update_state_tree(root, changes, &load).unwrap();
+            for (hash, node) in update.nodes {
+                assert_eq!(StateNode::parse(&node.marshal()).unwrap(), node);
+                self.nodes.borrow_mut().insert(hash, node);
+            }
+            update.root
+        }
+        fn prove(
+            &self,
+            root: RecordHash,
+            key: &StoreKey,
+        ) -> Option<crate::storage::vkv::MerkleProof> {
+            let load = |hash: &RecordHash| match self.nodes.borrow().get(hash) {
+                None => Err(()),
+                Some(node) => Ok(node.to_owned()),
+            };
+            prove_state_tree(root, key, &load).unwrap()
+        }
+    }
+
+    fn make_pairs(count: u8) -> Vec<(StoreKey, StoreValue)> {
+        (0..count)
+            .map(|i| (StoreKey::new(&[i]), StoreValue::new(Some(vec![i, i]))))
+            .collect()
+    }
+
+    #[test]
+    fn test_every_key_proves_against_root() {
+        for count in 1..=17 {
+            let store = NodeStore::new();
+            let pairs = make_pairs(count);
+            let root = store.update(get_empty_state_root(), &pairs);
+            for (key, value) in &pairs {
+                let proof = store.prove(root, key).unwrap();
+                assert!(proof.verify(key, value, &root));
+            }
+            assert!(store.prove(root, &StoreKey::new(&[0xff])).is_none());
+        }
+    }
+
+    #[test]
+    fn test_root_is_independent_of_history() {
+        let store = NodeStore::new();
+        let pairs = make_pairs(20);
+        let all_at_once = store.update(get_empty_state_root(), &pairs);
+
+        let mut one_by_one = get_empty_state_root();
+        for pair in pairs.iter().rev() {
+            one_by_one = store.update(one_by_one, &[pair.to_owned()]);
+        }
+        assert_eq!(one_by_one, all_at_once);
+
+        // Taking keys out leaves the tree as if they had never been in it
+        let extra = make_pairs(30)[20..].to_vec();
+        let grown = store.update(all_at_once, &extra);
+        assert_ne!(grown, all_at_once);
+        let removed: Vec<_> = extra
+            .into_iter()
+            .map(|(key, _value)| (key, StoreValue::new(None)))
+            .collect();
+        assert_eq!(store.update(grown, &removed), all_at_once);
+        let everything: Vec<_> = pairs
+            .into_iter()
+            .map(|(key, _value)| (key, StoreValue::new(None)))
+            .collect();
+        assert_eq!(
+            store.update(all_at_once, &everything),
+            get_empty_state_root()
+        );
+    }
+
+    #[test]
+    fn test_earlier_roots_stay_provable() {
+        let store = NodeStore::new();
+        let key = StoreKey::new(&[3]);
+        let old_root = store.update(get_empty_state_root(), &make_pairs(5));
+        let new_value = StoreValue::new(Some(vec![0xff]));
+        let new_root = store.update(old_root, &[(key.clone(), new_value.clone())]);
+
+        let old_proof = store.prove(old_root, &key).unwrap();
+        assert!(old_proof.verify(&key, &StoreValue::new(Some(vec![3, 3])), &old_root));
+        assert!(!old_proof.verify(&key, &new_value, &old_root));
+        let new_proof = store.prove(new_root, &key).unwrap();
+        assert!(new_proof.verify(&key, &new_value, &new_root));
+    }
+
+    #[test]
+    fn test_wrong_value_fails() {
+        let store = NodeStore::new();
+        let pairs = make_pairs(5);
+        let root = store.update(get_empty_state_root(), &pairs);
+        let (key, _value) = &pairs[3];
+        let proof = store.prove(root, key).unwrap();
+        assert!(!proof.verify(key, &StoreValue::new(Some(vec![0xff])), &root));
+        let (other_key, other_value) = &pairs[2];
+        assert!(!proof.verify(other_key, other_value, &root));
+    }
+
+    #[test]
+    fn test_proof_is_logarithmic() {
+        let store = NodeStore::new();
+        let pairs = make_pairs(100);
+        let root = store.update(get_empty_state_root(), &pairs);
+        let longest = pairs
+            .iter()
+            .map(|(key, _value)| store.prove(root, key).unwrap().steps.len())
+            .max()
+            .unwrap();
+        assert!(longest <= 20);
+    }
+}
@@ -2,13 +2,17 @@ mod chain_height;
 mod height_list;
 mod instruction_record;
 mod journal;
+mod merkle;
+mod record_hash;
 mod vkv;
 mod vkv_tests;
 
 pub use chain_height::{ChainHeight, ChainHeightError};
 pub use height_list::HeightList;
-pub use instruction_record::InstructionRecord;
+pub use instruction_record::{InstructionRecord, MergeOrigin, RecordMeta};
 pub use journal::UnitJournal;
+pub use merkle::{hash_merkle_leaf, MerkleProof, MerkleProofStep, MerkleSide};
+pub use record_hash::{RecordHash, RecordHashError, RECORD_HASH_LENGTH};
 pub use vkv::{
     extract_affected_keys, ImmuxDBVersionedKeyValueStore, VersionedKeyValueStore, VkvError,
 };
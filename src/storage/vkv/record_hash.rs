@@ -0,0 +1,84 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+pub const RECORD_HASH_LENGTH: usize = 32;
+
+#[derive(Debug)]
+pub enum RecordHashError {
+    UnexpectedLength(usize),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct RecordHash([u8; RECORD_HASH_LENGTH]);
+
+impl RecordHash {
+    /// The hash preceding the first record of every chain
+    pub fn genesis() -> Self {
+        RecordHash([0; RECORD_HASH_LENGTH])
+    }
+
+    pub fn digest(segments: &[&[u8]]) -> Self {
+        let mut hasher = Sha256::new();
+        for segment in segments {
+            hasher.input(segment);
+        }
+        let mut bytes = [0; RECORD_HASH_LENGTH];
+        bytes.copy_from_slice(hasher.result().as_slice());
+        RecordHash(bytes)
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    pub fn marshal(&self) -> Vec<u8> {
+        self.0.to_vec()
+    }
+
+    pub fn parse(data: &[u8]) -> Result<Self, RecordHashError> {
+        if data.len() != RECORD_HASH_LENGTH {
+            return Err(RecordHashError::UnexpectedLength(data.len()));
+        }
+        let mut bytes = [0; RECORD_HASH_LENGTH];
+        bytes.copy_from_slice(data);
+        return Ok(RecordHash(bytes));
+    }
+}
+
+impl fmt::Display for RecordHash {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for byte in self.0.iter() {
+            write!(f, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod record_hash_tests {
+    use crate::storage::vkv::record_hash::{RecordHash, RECORD_HASH_LENGTH};
+
+    #[test]
+    fn test_digest_is_sha256() {
+        let hash = RecordHash::digest(&[b"ab", b"c"]);
+        assert_eq!(
+            hash.to_string(),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[test]
+    fn test_marshal_parse_reversibility() {
+        let hash = RecordHash::digest(&[b"immux"]);
+        let parsed = RecordHash::parse(&hash.marshal()).unwrap();
+        assert_eq!(parsed, hash);
+    }
+
+    #[test]
+    fn test_parse_wrong_length() {
+        assert!(RecordHash::parse(&[0; RECORD_HASH_LENGTH - 1]).is_err());
+        assert!(RecordHash::parse(&[0; RECORD_HASH_LENGTH + 1]).is_err());
+    }
+}
@@ -2,29 +2,63 @@
  *  Versioned key-value store
 **/
 
-use std::cmp::{min, Ordering};
+use std::cmp::{max, min, Ordering};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::convert::TryFrom;
 
 use bincode::{deserialize, serialize, Error as BincodeError};
 
-use crate::config::{KVKeySigil, MAX_RECURSION};
+use crate::config::KVKeySigil;
 
-use crate::declarations::basics::{BoxedStoreKey, BoxedStoreValue, StoreKey, StoreValue};
+use crate::declarations::basics::db_version::DBVersion;
+use crate::declarations::basics::{
+    BoxedStoreKey, BoxedStoreValue, StoreKey, StoreKeyFragment, StoreValue,
+};
 use crate::declarations::errors::{ImmuxError, ImmuxResult};
 use crate::storage::instructions::{
-    Answer, DBSystemAnswer, DBSystemInstruction, DataAnswer, DataInstruction, DataReadAnswer,
-    DataReadInstruction, DataWriteAnswer, DataWriteInstruction, GetJournalOkAnswer,
-    GetManyOkAnswer, GetManyTargetSpec, GetOneOkAnswer, Instruction, ReadNamespaceOkAnswer,
-    RevertAllOkAnswer, RevertOkAnswer, SetOkAnswer, StoreNamespace, SwitchNamespaceOkAnswer,
+    Answer, ChainTag, CompactOkAnswer, DBSystemAnswer, DBSystemInstruction, DataAnswer,
+    DataInstruction, DataReadAnswer, DataReadInstruction, DataWriteAnswer, DataWriteInstruction,
+    DiffInstruction, DiffOkAnswer, ExportRecordsOkAnswer, ForkNamespaceInstruction,
+    ForkNamespaceOkAnswer, GetChangesInstruction, GetChangesOkAnswer, GetHeightAtTimeOkAnswer,
+    GetHeightOkAnswer, GetJournalOkAnswer, GetManyOkAnswer, GetManyTargetSpec, GetOneOkAnswer,
+    GetProofInstruction, GetProofOkAnswer, ImportRecordsInstruction, ImportRecordsOkAnswer,
+    Instruction, KeyChange, MergeConflict, MergeNamespaceInstruction, MergeNamespaceOkAnswer,
+    MergeStrategy, PruneChainInstruction, PruneMarker, ReadNamespaceOkAnswer,
+    ReadRetentionOkAnswer, ReadTagsOkAnswer, RecordChange, RemoveTagInstruction, RemoveTagOkAnswer,
+    ReplayMismatch, ReplayOkAnswer, RetentionPolicy, RetentionRule, RevertAllOkAnswer,
+    RevertOkAnswer, RevertRecordOkAnswer, RevertTargetSpec, SetManyInstruction, SetOkAnswer,
+    SetRecordMetaOkAnswer, SetRetentionInstruction, SetRetentionOkAnswer, SetTagInstruction,
+    SetTagOkAnswer, SetTargetSpec, StoreNamespace, SwitchNamespaceOkAnswer, VerifyChainOkAnswer,
 };
 use crate::storage::kv::{
-    HashMapStore, KVKey, KVKeySegment, KVNamespace, KVValue, KeyValueEngine, KeyValueStore,
-    RocksStore,
+    BoxedKVKey, BoxedKVValue, HashMapStore, KVKey, KVKeySegment, KVNamespace, KVValue,
+    KeyValueEngine, KeyValueStore, RocksStore,
 };
 use crate::storage::vkv::chain_height::ChainHeight;
 use crate::storage::vkv::height_list::HeightList;
-use crate::storage::vkv::journal::UnitJournal;
+use crate::storage::vkv::instruction_record::{LegacyInstructionRecord, MergeOrigin, RecordMeta};
+use crate::storage::vkv::journal::{JournalHead, UnitJournal, JOURNAL_PAGE_SIZE};
+use crate::storage::vkv::merkle::{
+    get_empty_state_root, prove_state_tree, update_state_tree, StateNode, StateNodeError,
+    StateTreeUpdate,
+};
+use crate::storage::vkv::record_hash::RecordHash;
 use crate::storage::vkv::InstructionRecord;
+use crate::utils::{varint_decode, varint_encode};
+
+/// Chains written before storage layouts were recorded only keep instruction records and unit
+/// journals. The current layout also chains the records by hash, and keeps the version of each
+/// key at each height, the time, affected keys and state root of each height, and journals
+/// paged.
+const LEGACY_STORAGE_LAYOUT: u64 = 0;
+const CURRENT_STORAGE_LAYOUT: u64 = 1;
+
+const STORAGE_LAYOUT_FIELD: &[u8] = b"storage_layout";
+const CHAIN_HEAD_FIELD: &[u8] = b"chain_head";
+const TAGS_FIELD: &[u8] = b"tags";
+const RETENTION_FIELD: &[u8] = b"retention";
+const PRUNE_MARKERS_FIELD: &[u8] = b"pruned";
+const RESEALED_HEIGHT_FIELD: &[u8] = b"resealed_height";
 
 #[derive(Debug)]
 pub enum VkvError {
@@ -41,8 +75,47 @@ pub enum VkvError {
     UpdateRecordParsing,
     JournalParsing,
     MissingJournal(StoreKey),
+    MissingJournalPage(StoreKey, u64),
     TryingToRevertToFuture,
-    TooManyRecursionInFindingValue,
+    MissingVersion(StoreKey, ChainHeight),
+    VersionParsing,
+    VersionLayoutParsing,
+    ResealedHeightParsing,
+    ChainHeadParsing,
+    ProofTargetMissing(StoreKey),
+    TryingToForkFromFuture,
+    ForkTargetNotEmpty,
+    RecordReplayMismatch(ChainHeight),
+    ReplayTargetNotEmpty,
+    ImportTargetNotEmpty,
+    MergeWithItself,
+    MergeConflict(Vec<StoreKey>),
+    InvalidDiffRange(ChainHeight, ChainHeight),
+    MissingChainTime(ChainHeight),
+    ChainTimeParsing,
+    InvalidRevertHeight(ChainHeight),
+    // A key restricted reverts require restored was written again after the record
+    RevertConflict(StoreKey),
+    KeyNotInRecord(ChainHeight, StoreKey),
+    TagExists(String),
+    MissingTag(String),
+    TryingToTagFuture(ChainHeight),
+    CannotSerializeTags,
+    TagsParsing,
+    // The requested height, and the height history was pruned below
+    HeightPruned(ChainHeight, ChainHeight),
+    InvalidRetention(RetentionPolicy),
+    CannotSerializeRetention,
+    RetentionParsing,
+    MissingAffectedKeys(ChainHeight),
+    CannotSerializeAffectedKeys,
+    AffectedKeysParsing,
+    MissingStateRoot(ChainHeight),
+    StateRootParsing,
+    MissingStateNode(RecordHash),
+    StateNodeParsing(StateNodeError),
+    // The value read at the height does not match the state root kept for it
+    StateRootMismatch(ChainHeight, StoreKey),
 }
 
 fn prefix_extractor(key: &[u8]) -> &[u8] {
@@ -52,7 +125,10 @@ fn prefix_extractor(key: &[u8]) -> &[u8] {
             Err(_) => return &key[0..1],
             Ok(sigil) => match sigil {
                 KVKeySigil::UnitJournal => {
-                    let grouping_name_length = key[1];
+                    let grouping_name_length = match key.get(1) {
+                        None => return &key[0..1],
+                        Some(length) => *length,
+                    };
                     let prefix_length = 1 + 1 + (grouping_name_length as usize);
                     let end = min(prefix_length, key.len());
                     return &key[0..end];
@@ -70,10 +146,65 @@ fn get_journal_kvkey(store_key: &StoreKey) -> KVKey {
     return journal_key_bytes.into();
 }
 
+fn get_journal_page_kvkey(store_key: &StoreKey, page: u64) -> KVKey {
+    let mut page_key_bytes = Vec::new();
+    page_key_bytes.push(KVKeySigil::UnitJournalPage as u8);
+    page_key_bytes.extend_from_slice(store_key.as_slice());
+    page_key_bytes.extend_from_slice(&page.to_be_bytes());
+    return page_key_bytes.into();
+}
+
+fn get_journal_prefix(key_prefix: &StoreKeyFragment) -> KVKeySegment {
+    let mut result = Vec::with_capacity(1 + key_prefix.as_slice().len());
+    result.push(KVKeySigil::UnitJournal as u8);
+    result.extend_from_slice(key_prefix.as_slice());
+    return result.into();
+}
+
 fn extract_journal_store_key(key: &KVKey) -> StoreKey {
     StoreKey::new(&key.as_bytes()[1..])
 }
 
+fn get_version_kvkey(store_key: &StoreKey, height: &ChainHeight) -> KVKey {
+    // Heights are fixed-width big-endian, so versions of a key sort by height
+    let mut version_key_bytes = Vec::new();
+    version_key_bytes.push(KVKeySigil::UnitVersion as u8);
+    version_key_bytes.extend_from_slice(store_key.as_slice());
+    version_key_bytes.extend_from_slice(&height.as_u64().to_be_bytes());
+    return version_key_bytes.into();
+}
+
+fn get_chain_info_kvkey(field: &[u8]) -> KVKey {
+    let mut result = Vec::new();
+    result.push(KVKeySigil::ChainInfo as u8);
+    result.extend_from_slice(field);
+    result.into()
+}
+
+fn get_storage_layout_kvkey() -> KVKey {
+    get_chain_info_kvkey(STORAGE_LAYOUT_FIELD)
+}
+
+fn get_chain_head_kvkey() -> KVKey {
+    get_chain_info_kvkey(CHAIN_HEAD_FIELD)
+}
+
+fn get_tags_kvkey() -> KVKey {
+    get_chain_info_kvkey(TAGS_FIELD)
+}
+
+fn get_retention_kvkey() -> KVKey {
+    get_chain_info_kvkey(RETENTION_FIELD)
+}
+
+fn get_prune_markers_kvkey() -> KVKey {
+    get_chain_info_kvkey(PRUNE_MARKERS_FIELD)
+}
+
+fn get_resealed_height_kvkey() -> KVKey {
+    get_chain_info_kvkey(RESEALED_HEIGHT_FIELD)
+}
+
 fn get_chain_height_kvkey() -> KVKey {
     KVKey::from(vec![KVKeySigil::ChainHeight as u8])
 }
@@ -85,12 +216,128 @@ fn get_instruction_kvkey(height: &ChainHeight) -> KVKey {
     result.into()
 }
 
+fn get_chain_time_kvkey(height: &ChainHeight) -> KVKey {
+    let mut result = Vec::new();
+    result.push(KVKeySigil::HeightToChainTime as u8);
+    result.extend(height.marshal());
+    result.into()
+}
+
+fn get_affected_keys_kvkey(height: &ChainHeight) -> KVKey {
+    let mut result = Vec::new();
+    result.push(KVKeySigil::HeightToAffectedKeys as u8);
+    result.extend(height.marshal());
+    result.into()
+}
+
+fn get_state_node_kvkey(hash: &RecordHash) -> KVKey {
+    let mut result = Vec::new();
+    result.push(KVKeySigil::StateTreeNode as u8);
+    result.extend_from_slice(hash.as_bytes());
+    result.into()
+}
+
+fn get_state_root_kvkey(height: &ChainHeight) -> KVKey {
+    let mut result = Vec::new();
+    result.push(KVKeySigil::HeightToStateRoot as u8);
+    result.extend(height.marshal());
+    result.into()
+}
+
+// Entries derived from instruction records, which a replay must reproduce exactly
+const REPLAY_DERIVED_SIGILS: [u8; 7] = [
+    KVKeySigil::UnitJournal as u8,
+    KVKeySigil::UnitJournalPage as u8,
+    KVKeySigil::UnitVersion as u8,
+    KVKeySigil::HeightToChainTime as u8,
+    KVKeySigil::HeightToAffectedKeys as u8,
+    KVKeySigil::StateTreeNode as u8,
+    KVKeySigil::HeightToStateRoot as u8,
+];
+
+fn collect_kv_pairs(pairs: Vec<(BoxedKVKey, BoxedKVValue)>) -> BTreeMap<Vec<u8>, Vec<u8>> {
+    let mut result = BTreeMap::new();
+    for (kvkey, kvvalue) in pairs {
+        result.insert(kvkey.as_bytes().to_vec(), kvvalue.as_bytes().to_vec());
+    }
+    return result;
+}
+
 fn get_fallback_height() -> ChainHeight {
     ChainHeight::new(0)
 }
 
+/// The last of the ascending `heights` not above `height`
+fn find_height_at_or_below(heights: &[ChainHeight], height: &ChainHeight) -> Option<ChainHeight> {
+    match heights.binary_search(height) {
+        Ok(index) => return Some(heights[index]),
+        Err(0) => return None,
+        Err(insertion_index) => return Some(heights[insertion_index - 1]),
+    }
+}
+
+/// Pairs storing a journal from scratch, with all but the last (partial) page of `heights` sealed
+fn get_paged_journal_kv_pairs(
+    key: &StoreKey,
+    value: &StoreValue,
+    heights: &[ChainHeight],
+) -> Vec<(KVKey, KVValue)> {
+    let page_count = heights.len().saturating_sub(1) / JOURNAL_PAGE_SIZE;
+    let mut result = Vec::with_capacity(page_count + 1);
+    for (page, page_heights) in heights
+        .chunks(JOURNAL_PAGE_SIZE)
+        .take(page_count)
+        .enumerate()
+    {
+        let page_value = KVValue::new(&HeightList::new(page_heights).marshal());
+        result.push((get_journal_page_kvkey(key, page as u64), page_value));
+    }
+    let head = JournalHead {
+        value: value.to_owned(),
+        recent_heights: HeightList::new(&heights[page_count * JOURNAL_PAGE_SIZE..]),
+        page_count: page_count as u64,
+    };
+    result.push((get_journal_kvkey(key), KVValue::new(&head.marshal())));
+    return result;
+}
+
+/// Pairs saving the root of `update` at `height`, along with the nodes it added
+fn get_state_update_kv_pairs(
+    height: &ChainHeight,
+    update: StateTreeUpdate,
+) -> Vec<(KVKey, KVValue)> {
+    let mut result = Vec::with_capacity(update.nodes.len() + 1);
+    for (hash, node) in update.nodes {
+        result.push((get_state_node_kvkey(&hash), KVValue::new(&node.marshal())));
+    }
+    result.push((
+        get_state_root_kvkey(height),
+        KVValue::new(&update.root.marshal()),
+    ));
+    return result;
+}
+
+/// The height below which history of `key` was pruned, or zero if it was not
+fn find_pruned_height(markers: &[PruneMarker], key: &StoreKey) -> ChainHeight {
+    let mut pruned_height = get_fallback_height();
+    for marker in markers {
+        let covers_key = match &marker.scope {
+            None => true,
+            Some(scope) => key.as_slice().starts_with(scope.as_slice()),
+        };
+        if covers_key && marker.height > pruned_height {
+            pruned_height = marker.height;
+        }
+    }
+    return pruned_height;
+}
+
 pub struct ImmuxDBVersionedKeyValueStore {
     pub kv_engine: Box<dyn KeyValueStore>,
+    // Attached to every record written, as set by the command being executed
+    record_meta: RecordMeta,
+    // Version and system time of the record being replayed, which are part of its hash
+    replayed_stamp: Option<(DBVersion, u128)>,
 }
 
 impl ImmuxDBVersionedKeyValueStore {
@@ -106,7 +353,12 @@ impl ImmuxDBVersionedKeyValueStore {
                 Box::new(RocksStore::new(data_root, &kv_namespace, prefix_extractor)?)
             }
         };
-        let store = ImmuxDBVersionedKeyValueStore { kv_engine: engine };
+        let mut store = ImmuxDBVersionedKeyValueStore {
+            kv_engine: engine,
+            record_meta: RecordMeta::default(),
+            replayed_stamp: None,
+        };
+        store.migrate_storage_layout()?;
         Ok(store)
     }
 
@@ -131,7 +383,10 @@ impl ImmuxDBVersionedKeyValueStore {
         let key = get_instruction_kvkey(height);
         match self.kv_engine.get(&key) {
             Err(_error) => Err(VkvError::GetInstructionRecordFail.into()),
-            Ok(None) => Err(VkvError::GetInstructionRecordFail.into()),
+            Ok(None) => match self.check_chain_retained(height) {
+                Err(error) => Err(error),
+                Ok(_) => Err(VkvError::GetInstructionRecordFail.into()),
+            },
             Ok(Some(value)) => match deserialize::<InstructionRecord>(value.as_bytes()) {
                 Err(_error) => Err(VkvError::DeserializationFail.into()),
                 Ok(instruction_record) => Ok(instruction_record),
@@ -154,146 +409,1753 @@ impl ImmuxDBVersionedKeyValueStore {
         }
     }
 
-    fn get_journal(&self, key: &StoreKey) -> ImmuxResult<UnitJournal> {
+    fn new_instruction_record(&self, instruction: Instruction) -> InstructionRecord {
+        let mut record = InstructionRecord::from(instruction);
+        record.meta = self.record_meta.to_owned();
+        if let Some((version, sys_time)) = self.replayed_stamp {
+            record.version = version;
+            record.sys_time = sys_time;
+        }
+        return record;
+    }
+
+    /// Microseconds since the Unix epoch at which the chain reached `height`. Unlike the system
+    /// time in records, chain time never goes down as the chain grows, so heights can be found
+    /// from a time by binary search.
+    fn load_chain_time(&self, height: &ChainHeight) -> ImmuxResult<u128> {
+        if height.is_zero() {
+            return Ok(0);
+        }
+        match self.kv_engine.get(&get_chain_time_kvkey(height)) {
+            Err(error) => Err(error),
+            Ok(None) => Err(VkvError::MissingChainTime(height.to_owned()).into()),
+            Ok(Some(value)) => {
+                let mut bytes = [0u8; 16];
+                if value.as_bytes().len() != bytes.len() {
+                    return Err(VkvError::ChainTimeParsing.into());
+                }
+                bytes.copy_from_slice(value.as_bytes());
+                Ok(u128::from_be_bytes(bytes))
+            }
+        }
+    }
+
+    fn get_chain_time_kv_pair(
+        &self,
+        height: &ChainHeight,
+        record: &InstructionRecord,
+    ) -> ImmuxResult<(KVKey, KVValue)> {
+        let mut previous_height = height.to_owned();
+        previous_height.decrement();
+        let time = max(record.sys_time, self.load_chain_time(&previous_height)?);
+        let value = KVValue::new(&time.to_be_bytes());
+        return Ok((get_chain_time_kvkey(height), value));
+    }
+
+    fn load_affected_keys(&self, height: &ChainHeight) -> ImmuxResult<Vec<StoreKey>> {
+        match self.kv_engine.get(&get_affected_keys_kvkey(height)) {
+            Err(error) => Err(error),
+            Ok(None) => match self.check_chain_retained(height) {
+                Err(error) => Err(error),
+                Ok(_) => Err(VkvError::MissingAffectedKeys(height.to_owned()).into()),
+            },
+            Ok(Some(value)) => match deserialize::<Vec<StoreKey>>(value.as_bytes()) {
+                Err(_error) => Err(VkvError::AffectedKeysParsing.into()),
+                Ok(keys) => Ok(keys),
+            },
+        }
+    }
+
+    fn get_affected_keys_kv_pair(
+        &self,
+        height: &ChainHeight,
+        record: &InstructionRecord,
+    ) -> ImmuxResult<(KVKey, KVValue)> {
+        let keys = get_record_affected_keys(record)?;
+        match serialize(&keys) {
+            Err(_error) => Err(VkvError::CannotSerializeAffectedKeys.into()),
+            Ok(serialized) => Ok((get_affected_keys_kvkey(height), KVValue::new(&serialized))),
+        }
+    }
+
+    /// Root of the state tree after the record at `height`, where height zero has no keys
+    fn load_state_root(&self, height: &ChainHeight) -> ImmuxResult<RecordHash> {
+        if height.is_zero() {
+            return Ok(get_empty_state_root());
+        }
+        match self.kv_engine.get(&get_state_root_kvkey(height)) {
+            Err(error) => Err(error),
+            Ok(None) => match self.check_chain_retained(height) {
+                Err(error) => Err(error),
+                Ok(_) => Err(VkvError::MissingStateRoot(height.to_owned()).into()),
+            },
+            Ok(Some(value)) => match RecordHash::parse(value.as_bytes()) {
+                Err(_error) => Err(VkvError::StateRootParsing.into()),
+                Ok(root) => Ok(root),
+            },
+        }
+    }
+
+    fn load_state_node(&self, hash: &RecordHash) -> ImmuxResult<StateNode> {
+        match self.kv_engine.get(&get_state_node_kvkey(hash)) {
+            Err(error) => Err(error),
+            Ok(None) => Err(VkvError::MissingStateNode(hash.to_owned()).into()),
+            Ok(Some(value)) => match StateNode::parse(value.as_bytes()) {
+                Err(error) => Err(VkvError::StateNodeParsing(error).into()),
+                Ok(node) => Ok(node),
+            },
+        }
+    }
+
+    /// Pairs saving the state root at `height`, which is the root one height below with
+    /// `changes` applied, along with the tree nodes it added
+    fn get_state_kv_pairs(
+        &self,
+        height: &ChainHeight,
+        changes: &[(StoreKey, StoreValue)],
+    ) -> ImmuxResult<Vec<(KVKey, KVValue)>> {
+        let mut previous_height = height.to_owned();
+        previous_height.decrement();
+        let previous_root = self.load_state_root(&previous_height)?;
+        let load = |hash: &RecordHash| self.load_state_node(hash);
+        let update = update_state_tree(previous_root, changes, &load)?;
+        return Ok(get_state_update_kv_pairs(height, update));
+    }
+
+    /// The last height the chain reached at or before `time`, or zero if it is before the first
+    fn get_height_at_time(&self, time: u128) -> ImmuxResult<ChainHeight> {
+        let mut low = 0;
+        let mut high = self.get_height().as_u64();
+        while low < high {
+            let middle = low + (high - low + 1) / 2;
+            if self.load_chain_time(&ChainHeight::new(middle))? <= time {
+                low = middle;
+            } else {
+                high = middle - 1;
+            }
+        }
+        return Ok(ChainHeight::new(low));
+    }
+
+    fn get_chain_head(&self) -> ImmuxResult<RecordHash> {
+        match self.kv_engine.get(&get_chain_head_kvkey()) {
+            Err(error) => Err(error),
+            Ok(None) => Ok(RecordHash::genesis()),
+            Ok(Some(value)) => match RecordHash::parse(value.as_bytes()) {
+                Err(_error) => Err(VkvError::ChainHeadParsing.into()),
+                Ok(hash) => Ok(hash),
+            },
+        }
+    }
+
+    /// Links the record after `previous_hash`, returning the new chain head to be saved with it
+    fn seal_instruction_record(
+        &self,
+        record: &mut InstructionRecord,
+        previous_hash: RecordHash,
+    ) -> ImmuxResult<(KVKey, KVValue)> {
+        record.previous_hash = previous_hash;
+        record.content_hash = match record.compute_content_hash() {
+            Err(error) => return Err(VkvError::CannotSerializeInstructionMeta(error).into()),
+            Ok(hash) => hash,
+        };
+        let head = record.chained_hash();
+        return Ok((get_chain_head_kvkey(), KVValue::new(&head.marshal())));
+    }
+
+    /// Recomputes the hash chain from the first record, reporting the first height that does not
+    /// match. A mismatching head with intact records is reported at the current height, and a
+    /// prune marker that the last prune record does not match at the marker height.
+    pub fn verify_chain(&self) -> ImmuxResult<VerifyChainOkAnswer> {
+        let current_height = self.get_height();
+        let mut expected_previous_hash = RecordHash::genesis();
+        let mut height = ChainHeight::new(1);
+        // A pruned chain is verified from its first kept record, against the head in the marker
+        let chain_marker = self.get_chain_prune_marker()?;
+        if let Some(marker) = &chain_marker {
+            expected_previous_hash = marker.head;
+            height = marker.height;
+        }
+        let mut last_prune: Option<PruneChainInstruction> = None;
+        while height <= current_height {
+            let is_intact = match self.load_instruction_record(&height) {
+                Err(_error) => false,
+                Ok(record) => match record.compute_content_hash() {
+                    Err(_error) => false,
+                    Ok(content_hash) => {
+                        if record.previous_hash == expected_previous_hash
+                            && record.content_hash == content_hash
+                        {
+                            expected_previous_hash = record.chained_hash();
+                            if let Instruction::DataAccess(DataInstruction::Write(
+                                DataWriteInstruction::PruneChain(prune),
+                            )) = record.instruction
+                            {
+                                last_prune = Some(prune);
+                            }
+                            true
+                        } else {
+                            false
+                        }
+                    }
+                },
+            };
+            if !is_intact {
+                return Ok(VerifyChainOkAnswer {
+                    height: current_height,
+                    head: expected_previous_hash,
+                    broken_height: Some(height),
+                });
+            }
+            height.increment();
+        }
+        if let Some(marker) = chain_marker {
+            let recorded_prune = PruneChainInstruction {
+                height: marker.height,
+                head: marker.head,
+            };
+            if last_prune != Some(recorded_prune) {
+                return Ok(VerifyChainOkAnswer {
+                    height: current_height,
+                    head: expected_previous_hash,
+                    broken_height: Some(marker.height),
+                });
+            }
+        }
+        let broken_height = match self.get_chain_head() {
+            Ok(head) if head == expected_previous_hash => None,
+            _ => Some(current_height),
+        };
+        return Ok(VerifyChainOkAnswer {
+            height: current_height,
+            head: expected_previous_hash,
+            broken_height,
+        });
+    }
+
+    /// Forking leaves the store on the namespace and record metadata it was on, whether or not
+    /// the fork succeeds
+    fn fork_namespace(
+        &mut self,
+        fork: &ForkNamespaceInstruction,
+    ) -> ImmuxResult<ForkNamespaceOkAnswer> {
+        let original_namespace = self.kv_engine.read_namespace();
+        let original_meta = self.record_meta.to_owned();
+        let result = self.replay_into_fork(fork);
+        self.record_meta = original_meta;
+        self.kv_engine.switch_namespace(&original_namespace)?;
+        return result;
+    }
+
+    /// Re-executes the source records up to the fork height in the new namespace, so that
+    /// journals and versions are derived exactly as they were on the source, then restores the
+    /// source records verbatim to keep their timestamps.
+    fn replay_into_fork(
+        &mut self,
+        fork: &ForkNamespaceInstruction,
+    ) -> ImmuxResult<ForkNamespaceOkAnswer> {
+        if fork.source == fork.new_namespace {
+            return Err(VkvError::ForkTargetNotEmpty.into());
+        }
+        self.kv_engine
+            .switch_namespace(&fork.source.to_owned().into())?;
+        self.migrate_storage_layout()?;
+        if fork.at_height > self.get_height() {
+            return Err(VkvError::TryingToForkFromFuture.into());
+        }
+        let mut records: Vec<InstructionRecord> = Vec::new();
+        let mut height = ChainHeight::new(1);
+        while height <= fork.at_height {
+            records.push(self.load_instruction_record(&height)?);
+            height.increment();
+        }
+
+        self.kv_engine
+            .switch_namespace(&fork.new_namespace.to_owned().into())?;
+        if self.get_height() != get_fallback_height() {
+            return Err(VkvError::ForkTargetNotEmpty.into());
+        }
+        self.migrate_storage_layout()?;
+        for record in records {
+            self.replay_record(&record)?;
+        }
+        return Ok(ForkNamespaceOkAnswer {
+            new_namespace: fork.new_namespace.to_owned(),
+            height: self.get_height(),
+            head: self.get_chain_head()?,
+        });
+    }
+
+    /// Re-executes a record on top of this chain, so that journals and versions are derived as
+    /// they were where the record was first written, then restores the record verbatim. Leaves
+    /// the record metadata set to that of the record.
+    fn replay_record(&mut self, record: &InstructionRecord) -> ImmuxResult<()> {
+        self.record_meta = record.meta.to_owned();
+        self.replayed_stamp = Some((record.version, record.sys_time));
+        let result = match &record.instruction {
+            Instruction::DataAccess(DataInstruction::Write(DataWriteInstruction::SetMany(
+                set_many,
+            ))) => self
+                .set_many(set_many, record.merged_from.to_owned())
+                .map(|_| ()),
+            instruction => self.execute(instruction).map(|_| ()),
+        };
+        self.replayed_stamp = None;
+        result?;
+        let height = self.get_height();
+        let replayed_record = self.load_instruction_record(&height)?;
+        if replayed_record.chained_hash() != record.chained_hash() {
+            return Err(VkvError::RecordReplayMismatch(height).into());
+        }
+        let instruction_kv_pair = self.get_instruction_record_kv_pair(&height, record)?;
+        let chain_time_kv_pair = self.get_chain_time_kv_pair(&height, record)?;
+        self.kv_engine
+            .atomic_batch_set(&[instruction_kv_pair, chain_time_kv_pair])?;
+        return Ok(());
+    }
+
+    /// Re-executes every record of this chain into an empty store, then compares everything
+    /// derived from the records, byte for byte. Chains compacted below their first record no
+    /// longer have the records to replay.
+    pub fn replay_into(
+        &self,
+        target: &mut ImmuxDBVersionedKeyValueStore,
+    ) -> ImmuxResult<ReplayOkAnswer> {
+        if target.get_height() != get_fallback_height() {
+            return Err(VkvError::ReplayTargetNotEmpty.into());
+        }
+        let original_meta = target.record_meta.to_owned();
+        let current_height = self.get_height();
+        let mut height = ChainHeight::new(1);
+        while height <= current_height {
+            let record = self.load_instruction_record(&height)?;
+            if let Err(error) = target.replay_record(&record) {
+                target.record_meta = original_meta;
+                return Err(error);
+            }
+            height.increment();
+        }
+        target.record_meta = original_meta;
+
+        let mut mismatches = Vec::new();
+        for sigil in REPLAY_DERIVED_SIGILS.iter() {
+            let prefix = KVKeySegment::from(vec![*sigil]);
+            let original = collect_kv_pairs(*self.kv_engine.filter_prefix(&prefix));
+            let mut replayed = collect_kv_pairs(*target.kv_engine.filter_prefix(&prefix));
+            for (kvkey, value) in original {
+                let replayed_value = replayed.remove(&kvkey);
+                if replayed_value.as_ref() != Some(&value) {
+                    mismatches.push(ReplayMismatch {
+                        kvkey,
+                        original: Some(value),
+                        replayed: replayed_value,
+                    });
+                }
+            }
+            for (kvkey, value) in replayed {
+                mismatches.push(ReplayMismatch {
+                    kvkey,
+                    original: None,
+                    replayed: Some(value),
+                });
+            }
+        }
+        mismatches.sort_by(|a, b| a.kvkey.cmp(&b.kvkey));
+        return Ok(ReplayOkAnswer {
+            height: target.get_height(),
+            head: target.get_chain_head()?,
+            mismatches,
+        });
+    }
+
+    /// Records below a prune marker are gone, so compacted chains cannot be exported whole
+    fn export_records(&self) -> ImmuxResult<ExportRecordsOkAnswer> {
+        let current_height = self.get_height();
+        let mut records = Vec::new();
+        let mut height = ChainHeight::new(1);
+        while height <= current_height {
+            records.push(self.load_instruction_record(&height)?);
+            height.increment();
+        }
+        return Ok(ExportRecordsOkAnswer {
+            height: current_height,
+            head: self.get_chain_head()?,
+            records,
+        });
+    }
+
+    /// Like forking, importing leaves the store on the namespace and record metadata it was on
+    fn import_records(
+        &mut self,
+        import: &ImportRecordsInstruction,
+    ) -> ImmuxResult<ImportRecordsOkAnswer> {
+        let original_namespace = self.kv_engine.read_namespace();
+        let original_meta = self.record_meta.to_owned();
+        let result = self.replay_into_import(import);
+        self.record_meta = original_meta;
+        self.kv_engine.switch_namespace(&original_namespace)?;
+        return result;
+    }
+
+    fn replay_into_import(
+        &mut self,
+        import: &ImportRecordsInstruction,
+    ) -> ImmuxResult<ImportRecordsOkAnswer> {
+        self.kv_engine
+            .switch_namespace(&import.namespace.to_owned().into())?;
+        if self.get_height() != get_fallback_height() {
+            return Err(VkvError::ImportTargetNotEmpty.into());
+        }
+        self.migrate_storage_layout()?;
+        for record in &import.records {
+            self.replay_record(record)?;
+        }
+        return Ok(ImportRecordsOkAnswer {
+            namespace: import.namespace.to_owned(),
+            height: self.get_height(),
+            head: self.get_chain_head()?,
+        });
+    }
+
+    /// Like forking, merging leaves the store on the namespace it was on
+    fn merge_namespace(
+        &mut self,
+        merge: &MergeNamespaceInstruction,
+    ) -> ImmuxResult<MergeNamespaceOkAnswer> {
+        let original_namespace = self.kv_engine.read_namespace();
+        let result = self.merge_into_target(merge);
+        self.kv_engine.switch_namespace(&original_namespace)?;
+        return result;
+    }
+
+    /// Replays what the source changed since the common base onto the target as new heights,
+    /// one per source height. Keys changed to different values on both chains are conflicts,
+    /// settled by their strategy before anything is written.
+    fn merge_into_target(
+        &mut self,
+        merge: &MergeNamespaceInstruction,
+    ) -> ImmuxResult<MergeNamespaceOkAnswer> {
+        if merge.source == merge.target {
+            return Err(VkvError::MergeWithItself.into());
+        }
+        self.kv_engine
+            .switch_namespace(&merge.source.to_owned().into())?;
+        self.migrate_storage_layout()?;
+        let source_hashes = self.load_chained_hashes()?;
+
+        self.kv_engine
+            .switch_namespace(&merge.target.to_owned().into())?;
+        self.migrate_storage_layout()?;
+        let target_height = self.get_height();
+        // Records are chained by hash, so the chains agree on every height below a matching one
+        let mut base_height = get_fallback_height();
+        for source_hash in &source_hashes {
+            let mut height = base_height;
+            height.increment();
+            if height > target_height
+                || &self.load_instruction_record(&height)?.chained_hash() != source_hash
+            {
+                break;
+            }
+            base_height = height;
+        }
+        let (source_base_height, target_base_height) =
+            self.find_last_merge(&merge.source, base_height)?;
+        let mut target_changes: HashMap<StoreKey, StoreValue> = HashMap::new();
+        let mut first_target_change = target_base_height;
+        first_target_change.increment();
+        for key in extract_affected_keys(&self, first_target_change, target_height)? {
+            let value = self.get_journal_head(&key)?.value;
+            target_changes.insert(key, value);
+        }
+
+        self.kv_engine
+            .switch_namespace(&merge.source.to_owned().into())?;
+        let effects = self.load_effects_after(source_base_height)?;
+        self.kv_engine
+            .switch_namespace(&merge.target.to_owned().into())?;
+
+        let is_merged = |key: &StoreKey| match &merge.keys {
+            None => true,
+            Some(keys) => keys.contains(key),
+        };
+        let mut source_changes: HashMap<&StoreKey, &StoreValue> = HashMap::new();
+        for (_height, targets) in &effects {
+            for target in targets {
+                if is_merged(&target.key) {
+                    source_changes.insert(&target.key, &target.value);
+                }
+            }
+        }
+        let mut conflicts: Vec<MergeConflict> = Vec::new();
+        for (key, source_value) in source_changes.iter() {
+            match target_changes.get(*key) {
+                Some(target_value) if target_value != *source_value => {
+                    let strategy = match merge
+                        .resolutions
+                        .iter()
+                        .find(|resolution| &resolution.key == *key)
+                    {
+                        None => merge.default_strategy,
+                        Some(resolution) => resolution.strategy,
+                    };
+                    conflicts.push(MergeConflict {
+                        key: (*key).to_owned(),
+                        strategy,
+                    });
+                }
+                _ => {}
+            }
+        }
+        conflicts.sort_by(|a, b| a.key.cmp(&b.key));
+        let failed_keys: Vec<StoreKey> = conflicts
+            .iter()
+            .filter(|conflict| conflict.strategy == MergeStrategy::Fail)
+            .map(|conflict| conflict.key.to_owned())
+            .collect();
+        if !failed_keys.is_empty() && !merge.dry_run {
+            return Err(VkvError::MergeConflict(failed_keys).into());
+        }
+        let kept_keys: HashSet<StoreKey> = conflicts
+            .iter()
+            .filter(|conflict| conflict.strategy == MergeStrategy::Ours)
+            .map(|conflict| conflict.key.to_owned())
+            .collect();
+        let mut merged_targets: Vec<SetTargetSpec> = source_changes
+            .into_iter()
+            .filter(|(key, _value)| !kept_keys.contains(*key))
+            .map(|(key, value)| SetTargetSpec {
+                key: key.to_owned(),
+                value: value.to_owned(),
+            })
+            .collect();
+        merged_targets.sort_by(|a, b| a.key.cmp(&b.key));
+        let merged_heights: Vec<ChainHeight> = effects.iter().map(|(height, _)| *height).collect();
+        if merge.dry_run {
+            return Ok(MergeNamespaceOkAnswer {
+                base_height,
+                height: target_height,
+                merged_heights,
+                conflicts,
+                merged_targets,
+            });
+        }
+
+        let last_height = merged_heights.last().cloned();
+        for (height, targets) in effects {
+            let mut set_many = SetManyInstruction {
+                targets: targets
+                    .into_iter()
+                    .filter(|target| is_merged(&target.key) && !kept_keys.contains(&target.key))
+                    .collect(),
+            };
+            if Some(height) == last_height {
+                set_many.targets.extend(merge.index_targets.iter().cloned());
+            }
+            let origin = MergeOrigin {
+                namespace: merge.source.to_owned(),
+                height,
+            };
+            self.set_many(&set_many, Some(origin))?;
+        }
+        return Ok(MergeNamespaceOkAnswer {
+            base_height,
+            height: self.get_height(),
+            merged_heights,
+            conflicts,
+            merged_targets,
+        });
+    }
+
+    fn load_chained_hashes(&self) -> ImmuxResult<Vec<RecordHash>> {
+        let current_height = self.get_height();
+        let mut hashes = Vec::new();
+        let mut height = ChainHeight::new(1);
+        while height <= current_height {
+            hashes.push(self.load_instruction_record(&height)?.chained_hash());
+            height.increment();
+        }
+        return Ok(hashes);
+    }
+
+    /// The source and target heights of the latest earlier merge from `source` above the common
+    /// base, or the common base on both sides if there has been none
+    fn find_last_merge(
+        &self,
+        source: &StoreNamespace,
+        base_height: ChainHeight,
+    ) -> ImmuxResult<(ChainHeight, ChainHeight)> {
+        let mut height = self.get_height();
+        while height > base_height {
+            if let Some(origin) = self.load_instruction_record(&height)?.merged_from {
+                if &origin.namespace == source {
+                    return Ok((origin.height, height));
+                }
+            }
+            height.decrement();
+        }
+        return Ok((base_height, base_height));
+    }
+
+    /// The values each record above `base_height` left its keys with, by height
+    fn load_effects_after(
+        &self,
+        base_height: ChainHeight,
+    ) -> ImmuxResult<Vec<(ChainHeight, Vec<SetTargetSpec>)>> {
+        let current_height = self.get_height();
+        let mut effects = Vec::new();
+        let mut height = base_height;
+        height.increment();
+        while height <= current_height {
+            let record = self.load_instruction_record(&height)?;
+            effects.push((height, self.load_record_targets(record, &height)?));
+            height.increment();
+        }
+        return Ok(effects);
+    }
+
+    /// The values the record at `height` left its keys with
+    fn load_record_targets(
+        &self,
+        record: InstructionRecord,
+        height: &ChainHeight,
+    ) -> ImmuxResult<Vec<SetTargetSpec>> {
+        let keys: Vec<StoreKey> = match record.instruction {
+            Instruction::DataAccess(DataInstruction::Write(write)) => match write {
+                DataWriteInstruction::SetMany(set_many) => set_many
+                    .targets
+                    .into_iter()
+                    .map(|target| target.key)
+                    .collect(),
+                DataWriteInstruction::RevertMany(revert_many) => revert_many
+                    .targets
+                    .into_iter()
+                    .map(|target| target.key)
+                    .collect(),
+                DataWriteInstruction::IndexedRevertMany(revert_many) => revert_many
+                    .targets
+                    .into_iter()
+                    .map(|target| target.key)
+                    .chain(
+                        revert_many
+                            .index_targets
+                            .into_iter()
+                            .map(|target| target.key),
+                    )
+                    .collect(),
+                DataWriteInstruction::RevertAll(_)
+                | DataWriteInstruction::Revert(_)
+                | DataWriteInstruction::RevertKeys(_) => match record.affected_keys {
+                    None => vec![],
+                    Some(keys) => keys,
+                },
+                DataWriteInstruction::PruneChain(_) => vec![],
+            },
+            _ => return Err(VkvError::UnexpectedInstruction.into()),
+        };
+        let mut targets = Vec::with_capacity(keys.len());
+        for key in keys {
+            let value = self.load_version(&key, height)?;
+            targets.push(SetTargetSpec { key, value });
+        }
+        return Ok(targets);
+    }
+
+    fn get_changes(&self, get_changes: &GetChangesInstruction) -> ImmuxResult<GetChangesOkAnswer> {
+        let current_height = self.get_height();
+        let mut height = get_changes.after_height;
+        let mut changes = Vec::new();
+        while height < current_height && changes.len() < get_changes.limit {
+            height.increment();
+            let record = self.load_instruction_record(&height)?;
+            let sys_time = record.sys_time;
+            let meta = record.meta.to_owned();
+            changes.push(RecordChange {
+                height,
+                sys_time,
+                meta,
+                targets: self.load_record_targets(record, &height)?,
+            });
+        }
+        return Ok(GetChangesOkAnswer { height, changes });
+    }
+
+    /// Tags are chain info rather than records, so tagging does not move the chain
+    fn load_tags(&self) -> ImmuxResult<Vec<ChainTag>> {
+        match self.kv_engine.get(&get_tags_kvkey()) {
+            Err(error) => Err(error),
+            Ok(None) => Ok(vec![]),
+            Ok(Some(value)) => match deserialize::<Vec<ChainTag>>(value.as_bytes()) {
+                Err(_error) => Err(VkvError::TagsParsing.into()),
+                Ok(tags) => Ok(tags),
+            },
+        }
+    }
+
+    fn save_tags(&mut self, tags: &[ChainTag]) -> ImmuxResult<()> {
+        match serialize(tags) {
+            Err(_error) => Err(VkvError::CannotSerializeTags.into()),
+            Ok(serialized) => self
+                .kv_engine
+                .set(&get_tags_kvkey(), &KVValue::new(&serialized)),
+        }
+    }
+
+    fn set_tag(&mut self, set_tag: &SetTagInstruction) -> ImmuxResult<SetTagOkAnswer> {
+        let current_height = self.get_height();
+        let height = match set_tag.height {
+            None => current_height,
+            Some(height) => height,
+        };
+        if height > current_height {
+            return Err(VkvError::TryingToTagFuture(height).into());
+        }
+        let mut tags = self.load_tags()?;
+        let index = match tags.binary_search_by(|tag| tag.name.cmp(&set_tag.name)) {
+            Ok(_index) => return Err(VkvError::TagExists(set_tag.name.to_owned()).into()),
+            Err(index) => index,
+        };
+        let tag = ChainTag {
+            name: set_tag.name.to_owned(),
+            height,
+        };
+        tags.insert(index, tag.clone());
+        self.save_tags(&tags)?;
+        return Ok(SetTagOkAnswer { tag });
+    }
+
+    fn remove_tag(&mut self, remove_tag: &RemoveTagInstruction) -> ImmuxResult<RemoveTagOkAnswer> {
+        let mut tags = self.load_tags()?;
+        match tags.binary_search_by(|tag| tag.name.cmp(&remove_tag.name)) {
+            Err(_index) => return Err(VkvError::MissingTag(remove_tag.name.to_owned()).into()),
+            Ok(index) => {
+                let tag = tags.remove(index);
+                self.save_tags(&tags)?;
+                return Ok(RemoveTagOkAnswer { tag });
+            }
+        }
+    }
+
+    /// Rules ordered by scope, so the chain's own rule comes first
+    fn load_retention_rules(&self) -> ImmuxResult<Vec<RetentionRule>> {
+        match self.kv_engine.get(&get_retention_kvkey()) {
+            Err(error) => Err(error),
+            Ok(None) => Ok(vec![]),
+            Ok(Some(value)) => match deserialize::<Vec<RetentionRule>>(value.as_bytes()) {
+                Err(_error) => Err(VkvError::RetentionParsing.into()),
+                Ok(rules) => Ok(rules),
+            },
+        }
+    }
+
+    fn set_retention(
+        &mut self,
+        set_retention: &SetRetentionInstruction,
+    ) -> ImmuxResult<SetRetentionOkAnswer> {
+        if let Some(RetentionPolicy::KeepHeights(0)) = set_retention.policy {
+            return Err(VkvError::InvalidRetention(RetentionPolicy::KeepHeights(0)).into());
+        }
+        let mut rules = self.load_retention_rules()?;
+        rules.retain(|rule| rule.scope != set_retention.scope);
+        if let Some(policy) = set_retention.policy {
+            rules.push(RetentionRule {
+                scope: set_retention.scope.to_owned(),
+                policy,
+            });
+        }
+        rules.sort_by(|a, b| a.scope.cmp(&b.scope));
+        match serialize(&rules) {
+            Err(_error) => return Err(VkvError::CannotSerializeRetention.into()),
+            Ok(serialized) => {
+                self.kv_engine
+                    .set(&get_retention_kvkey(), &KVValue::new(&serialized))?;
+                return Ok(SetRetentionOkAnswer {});
+            }
+        }
+    }
+
+    fn load_prune_markers(&self) -> ImmuxResult<Vec<PruneMarker>> {
+        match self.kv_engine.get(&get_prune_markers_kvkey()) {
+            Err(error) => Err(error),
+            Ok(None) => Ok(vec![]),
+            Ok(Some(value)) => match deserialize::<Vec<PruneMarker>>(value.as_bytes()) {
+                Err(_error) => Err(VkvError::RetentionParsing.into()),
+                Ok(markers) => Ok(markers),
+            },
+        }
+    }
+
+    fn get_prune_markers_kv_pair(&self, markers: &[PruneMarker]) -> ImmuxResult<(KVKey, KVValue)> {
+        match serialize(markers) {
+            Err(_error) => Err(VkvError::CannotSerializeRetention.into()),
+            Ok(serialized) => Ok((get_prune_markers_kvkey(), KVValue::new(&serialized))),
+        }
+    }
+
+    fn save_prune_markers(&mut self, markers: &[PruneMarker]) -> ImmuxResult<()> {
+        let (kvkey, kvvalue) = self.get_prune_markers_kv_pair(markers)?;
+        return self.kv_engine.set(&kvkey, &kvvalue);
+    }
+
+    /// Saves `markers` along with a record of the chain's own marker `marker` at a new height, so
+    /// that the marker is covered by the hash chain
+    fn anchor_prune_markers(
+        &mut self,
+        marker: &PruneMarker,
+        markers: &[PruneMarker],
+    ) -> ImmuxResult<()> {
+        let next_height = self.increment_chain_height();
+        let instruction: Instruction = PruneChainInstruction {
+            height: marker.height,
+            head: marker.head,
+        }
+        .into();
+        let mut record = self.new_instruction_record(instruction);
+        let chain_head_kv_pair =
+            self.seal_instruction_record(&mut record, self.get_chain_head()?)?;
+        let mut kv_pairs = vec![
+            self.get_instruction_record_kv_pair(&next_height, &record)?,
+            self.get_chain_time_kv_pair(&next_height, &record)?,
+            self.get_affected_keys_kv_pair(&next_height, &record)?,
+            chain_head_kv_pair,
+            self.get_height_kv_pair(next_height),
+            self.get_prune_markers_kv_pair(markers)?,
+        ];
+        kv_pairs.extend(self.get_state_kv_pairs(&next_height, &[])?);
+        return self.kv_engine.atomic_batch_set(&kv_pairs);
+    }
+
+    fn get_chain_prune_marker(&self) -> ImmuxResult<Option<PruneMarker>> {
+        let markers = self.load_prune_markers()?;
+        return Ok(markers.into_iter().find(|marker| marker.scope.is_none()));
+    }
+
+    /// Fails if the records of the whole chain were pruned below `height`
+    fn check_chain_retained(&self, height: &ChainHeight) -> ImmuxResult<()> {
+        if let Some(marker) = self.get_chain_prune_marker()? {
+            if height < &marker.height {
+                return Err(VkvError::HeightPruned(height.to_owned(), marker.height).into());
+            }
+        }
+        return Ok(());
+    }
+
+    /// Tells a version of `key` at `height` that was pruned apart from one that never existed,
+    /// which is reported as `error`
+    fn get_missing_version_error(
+        &self,
+        key: &StoreKey,
+        height: &ChainHeight,
+        error: VkvError,
+    ) -> ImmuxError {
+        match self.load_prune_markers() {
+            Err(error) => return error,
+            Ok(markers) => {
+                let pruned_height = find_pruned_height(&markers, key);
+                if height < &pruned_height {
+                    return VkvError::HeightPruned(height.to_owned(), pruned_height).into();
+                } else {
+                    return error.into();
+                }
+            }
+        }
+    }
+
+    /// The lowest height whose state `policy` keeps readable
+    fn get_retention_height(&self, policy: &RetentionPolicy) -> ImmuxResult<ChainHeight> {
+        match policy {
+            RetentionPolicy::KeepHeights(count) => {
+                let current_height = self.get_height().as_u64();
+                return Ok(ChainHeight::new(current_height.saturating_sub(*count) + 1));
+            }
+            RetentionPolicy::KeepSince(time) => {
+                let height = self.get_height_at_time(*time)?;
+                return Ok(max(height, ChainHeight::new(1)));
+            }
+        }
+    }
+
+    /// Applies the chain's rule, then each grouping rule that prunes further than the chain.
+    /// Journals are collapsed before records are dropped, and a marker is only saved once its
+    /// history is gone, so a marker never claims a pruning that did not finish, and running again
+    /// finishes what an interrupted run left behind. Moving the chain's marker also records it at
+    /// a new height, which verification checks the marker against. Chain times are kept, so
+    /// heights can still be found from times below the marker, while state roots go with the
+    /// records.
+    fn compact(&mut self) -> ImmuxResult<CompactOkAnswer> {
+        let mut markers = self.load_prune_markers()?;
+        let mut chain_pruned_height = match markers.iter().find(|marker| marker.scope.is_none()) {
+            None => get_fallback_height(),
+            Some(marker) => marker.height,
+        };
+        let mut pruned_records = 0;
+        let mut pruned_versions = 0;
+        for rule in self.load_retention_rules()? {
+            let existing_index = markers.iter().position(|marker| marker.scope == rule.scope);
+            let mut height = self.get_retention_height(&rule.policy)?;
+            if let Some(index) = existing_index {
+                height = max(height, markers[index].height);
+            }
+            if height.as_u64() <= 1 || (rule.scope.is_some() && height <= chain_pruned_height) {
+                continue;
+            }
+            let marker = PruneMarker {
+                scope: rule.scope.to_owned(),
+                height,
+                head: self.load_instruction_record(&height)?.previous_hash,
+            };
+            let is_moved = match existing_index {
+                None => true,
+                Some(index) => markers[index] != marker,
+            };
+            match &rule.scope {
+                None => {
+                    let record_heights = self.find_records_below(height);
+                    if let Some(lowest_height) = record_heights.last() {
+                        let mut last_pruned_height = height;
+                        last_pruned_height.decrement();
+                        let keys = extract_affected_keys(self, *lowest_height, last_pruned_height)?;
+                        pruned_versions += self.collapse_journals(keys, height)?;
+                    }
+                    let record_kvkeys: Vec<KVKey> = record_heights
+                        .iter()
+                        .map(|record_height| get_instruction_kvkey(record_height))
+                        .chain(record_heights.iter().map(get_affected_keys_kvkey))
+                        .chain(record_heights.iter().map(get_state_root_kvkey))
+                        .collect();
+                    self.kv_engine.atomic_batch_delete(&record_kvkeys)?;
+                    pruned_records += record_heights.len() as u64;
+                    chain_pruned_height = height;
+                }
+                Some(scope) => {
+                    let keys = self.find_journal_keys(scope);
+                    pruned_versions += self.collapse_journals(keys, height)?;
+                }
+            }
+            match existing_index {
+                None => markers.push(marker.clone()),
+                Some(index) => markers[index] = marker.clone(),
+            }
+            if rule.scope.is_none() && is_moved {
+                self.anchor_prune_markers(&marker, &markers)?;
+            } else {
+                self.save_prune_markers(&markers)?;
+            }
+        }
+        return Ok(CompactOkAnswer {
+            markers,
+            pruned_records,
+            pruned_versions,
+        });
+    }
+
+    /// Heights of the records still kept below `height`, highest first. Records are dropped from
+    /// the lowest up in one batch, so the first one missing ends the search.
+    fn find_records_below(&self, height: ChainHeight) -> Vec<ChainHeight> {
+        let mut record_heights = Vec::new();
+        let mut record_height = height;
+        record_height.decrement();
+        while !record_height.is_zero() {
+            match self.kv_engine.get(&get_instruction_kvkey(&record_height)) {
+                Ok(Some(_value)) => record_heights.push(record_height),
+                _ => break,
+            }
+            record_height.decrement();
+        }
+        return record_heights;
+    }
+
+    fn find_journal_keys(&self, scope: &StoreKeyFragment) -> Vec<StoreKey> {
+        return self
+            .kv_engine
+            .filter_prefix(&get_journal_prefix(scope))
+            .into_iter()
+            .map(|(kvkey, _kvvalue)| extract_journal_store_key(&kvkey.into()))
+            .collect();
+    }
+
+    /// Drops the versions of `keys` written before the one in effect at `height`, which the
+    /// collapsed journal starts from, returning how many were dropped. The versions go before the
+    /// journals are rewritten, so an interrupted collapse still lists the versions it has to drop.
+    fn collapse_journals(&mut self, keys: Vec<StoreKey>, height: ChainHeight) -> ImmuxResult<u64> {
+        let mut journal_kv_pairs: Vec<(KVKey, KVValue)> = Vec::new();
+        let mut version_kvkeys: Vec<KVKey> = Vec::new();
+        let mut page_kvkeys: Vec<KVKey> = Vec::new();
+        for key in keys {
+            let page_count = self.get_journal_head(&key)?.page_count;
+            let journal = self.get_journal(&key)?;
+            let heights: Vec<ChainHeight> = journal.update_heights.iter().collect();
+            let base_index = match heights.binary_search(&height) {
+                Ok(index) => index,
+                Err(0) => continue,
+                Err(insertion_index) => insertion_index - 1,
+            };
+            if base_index == 0 {
+                continue;
+            }
+            for pruned_height in &heights[..base_index] {
+                version_kvkeys.push(get_version_kvkey(&key, pruned_height));
+            }
+            let kept_heights = &heights[base_index..];
+            let kept_page_count = (kept_heights.len() - 1) / JOURNAL_PAGE_SIZE;
+            for page in kept_page_count as u64..page_count {
+                page_kvkeys.push(get_journal_page_kvkey(&key, page));
+            }
+            journal_kv_pairs.extend(get_paged_journal_kv_pairs(
+                &key,
+                &journal.value,
+                kept_heights,
+            ));
+        }
+        self.kv_engine.atomic_batch_delete(&version_kvkeys)?;
+        self.kv_engine.atomic_batch_set(&journal_kv_pairs)?;
+        // Pages past the collapsed journals are no longer read, and are written over as they grow
+        self.kv_engine.atomic_batch_delete(&page_kvkeys)?;
+        return Ok(version_kvkeys.len() as u64);
+    }
+
+    fn get_journal_head(&self, key: &StoreKey) -> ImmuxResult<JournalHead> {
         let kvkey = get_journal_kvkey(key);
         match self.kv_engine.get(&kvkey) {
             Err(error) => Err(error),
             Ok(None) => Err(VkvError::MissingJournal(key.to_owned()).into()),
-            Ok(Some(value)) => match UnitJournal::parse(value.as_bytes()) {
+            Ok(Some(value)) => match JournalHead::parse(value.as_bytes()) {
                 Err(_) => Err(VkvError::CannotSerializeJournal.into()),
-                Ok(journal) => Ok(journal),
+                Ok(head) => Ok(head),
+            },
+        }
+    }
+
+    fn load_journal_page(&self, key: &StoreKey, page: u64) -> ImmuxResult<Vec<ChainHeight>> {
+        let kvkey = get_journal_page_kvkey(key, page);
+        match self.kv_engine.get(&kvkey) {
+            Err(error) => Err(error),
+            Ok(None) => Err(VkvError::MissingJournalPage(key.to_owned(), page).into()),
+            Ok(Some(value)) => match HeightList::parse(value.as_bytes()) {
+                Err(_) => Err(VkvError::JournalParsing.into()),
+                Ok((heights, _)) => Ok(heights.iter().collect()),
             },
         }
     }
 
-    fn update_journal(
-        &mut self,
-        store_key: &StoreKey,
-        value: &StoreValue,
-        height: ChainHeight,
-    ) -> UnitJournal {
-        let journal: UnitJournal = match self.get_journal(store_key) {
-            Err(_error) => UnitJournal {
-                value: value.to_owned(),
-                update_heights: HeightList::new(&[height]),
-            },
-            Ok(mut existing_journal) => {
-                existing_journal.update_heights.push(height);
-                existing_journal.value = value.to_owned();
-                existing_journal
+    /// The whole journal, with the heights of every sealed page
+    fn get_journal(&self, key: &StoreKey) -> ImmuxResult<UnitJournal> {
+        let head = self.get_journal_head(key)?;
+        let mut heights = Vec::new();
+        for page in 0..head.page_count {
+            heights.extend(self.load_journal_page(key, page)?);
+        }
+        heights.extend(head.recent_heights.iter());
+        return Ok(UnitJournal {
+            value: head.value,
+            update_heights: HeightList::new(&heights),
+        });
+    }
+
+    /// The last height not above `height` at which `key` was updated. Sealed pages are only read
+    /// when the recent heights do not reach that far back, and then by binary search.
+    fn find_update_height(
+        &self,
+        key: &StoreKey,
+        head: &JournalHead,
+        height: &ChainHeight,
+    ) -> ImmuxResult<Option<ChainHeight>> {
+        let recent_heights: Vec<ChainHeight> = head.recent_heights.iter().collect();
+        if let Some(update_height) = find_height_at_or_below(&recent_heights, height) {
+            return Ok(Some(update_height));
+        }
+        let mut low = 0;
+        let mut high = head.page_count;
+        let mut found_heights = vec![];
+        while low < high {
+            let middle = low + (high - low) / 2;
+            let page_heights = self.load_journal_page(key, middle)?;
+            match page_heights.first() {
+                Some(first_height) if first_height <= height => {
+                    found_heights = page_heights;
+                    low = middle + 1;
+                }
+                _ => high = middle,
+            }
+        }
+        return Ok(find_height_at_or_below(&found_heights, height));
+    }
+
+    /// Pairs recording an update of `key` at `height`, sealing a page if the head filled one up
+    fn get_journal_update_kv_pairs(
+        &self,
+        key: &StoreKey,
+        value: &StoreValue,
+        height: ChainHeight,
+    ) -> Vec<(KVKey, KVValue)> {
+        let mut result = Vec::with_capacity(2);
+        let head = match self.get_journal_head(key) {
+            Err(_error) => JournalHead::new(value, height),
+            Ok(mut existing_head) => {
+                if let Some(sealed_page) = existing_head.push(value, height) {
+                    let page = existing_head.page_count - 1;
+                    result.push((
+                        get_journal_page_kvkey(key, page),
+                        KVValue::new(&sealed_page.marshal()),
+                    ));
+                }
+                existing_head
+            }
+        };
+        result.push((get_journal_kvkey(key), KVValue::new(&head.marshal())));
+        return result;
+    }
+
+    /// The value of the journal's key at `height`, or None if it is absent there
+    fn resolve_journal_value(
+        &self,
+        store_key: &StoreKey,
+        head: JournalHead,
+        height: Option<ChainHeight>,
+        markers: &[PruneMarker],
+    ) -> ImmuxResult<Option<StoreValue>> {
+        let value = match height {
+            None => head.value,
+            Some(height) => match self.find_update_height(store_key, &head, &height)? {
+                None => {
+                    let pruned_height = find_pruned_height(markers, store_key);
+                    if height < pruned_height {
+                        return Err(VkvError::HeightPruned(height, pruned_height).into());
+                    }
+                    // The key did not exist yet at that height
+                    return Ok(None);
+                }
+                Some(update_height) => {
+                    if Some(update_height) == head.get_latest_height() {
+                        head.value
+                    } else {
+                        self.load_version(store_key, &update_height)?
+                    }
+                }
+            },
+        };
+        match value.inner() {
+            None => return Ok(None),
+            Some(_) => return Ok(Some(value)),
+        }
+    }
+
+    /// Values of at most `limit` keys starting with `key_prefix` and sorting after `after`, in
+    /// key order, skipping keys absent at `height`. Journals are read in batches until enough
+    /// keys are present.
+    fn get_values_in_range(
+        &self,
+        key_prefix: &StoreKeyFragment,
+        after: Option<&StoreKey>,
+        limit: usize,
+        height: Option<ChainHeight>,
+    ) -> ImmuxResult<Vec<(StoreKey, StoreValue)>> {
+        let basekey_prefix = get_journal_prefix(key_prefix);
+        let markers = match height {
+            None => vec![],
+            Some(_) => self.load_prune_markers()?,
+        };
+        let mut cursor = after.map(get_journal_kvkey);
+        let mut result = Vec::new();
+        while result.len() < limit {
+            let batch_size = limit - result.len();
+            let pairs = self
+                .kv_engine
+                .filter_range(&basekey_prefix, cursor.as_ref(), batch_size);
+            let is_exhausted = pairs.len() < batch_size;
+            for (kvkey, kvvalue) in pairs.into_iter() {
+                let kvkey: KVKey = kvkey.into();
+                let store_key = extract_journal_store_key(&kvkey);
+                let head = JournalHead::parse(kvvalue.as_bytes())?;
+                if let Some(value) =
+                    self.resolve_journal_value(&store_key, head, height, &markers)?
+                {
+                    result.push((store_key, value));
+                }
+                cursor = Some(kvkey);
+            }
+            if is_exhausted {
+                break;
+            }
+        }
+        return Ok(result);
+    }
+
+    /// Values of keys starting with `key_prefix`, skipping keys absent at `height`
+    fn get_values_by_prefix(
+        &self,
+        key_prefix: &StoreKeyFragment,
+        height: Option<ChainHeight>,
+    ) -> ImmuxResult<Vec<(StoreKey, StoreValue)>> {
+        let basekey_prefix = get_journal_prefix(key_prefix);
+        let base_pairs = self.kv_engine.filter_prefix(&basekey_prefix);
+
+        let parsed_pairs: Vec<(StoreKey, Box<JournalHead>)> = {
+            let mut result = Vec::with_capacity(base_pairs.len());
+            for pair in base_pairs.into_iter() {
+                // Remove Sigil
+                let (kvkey, kvvalue) = pair;
+                let store_key = extract_journal_store_key(&kvkey.into());
+                let head = JournalHead::parse(kvvalue.as_bytes())?;
+                result.push((store_key, Box::new(head)));
+            }
+            result
+        };
+        let markers = match height {
+            None => vec![],
+            Some(_) => self.load_prune_markers()?,
+        };
+        let mut result = Vec::with_capacity(parsed_pairs.len());
+        for pair in parsed_pairs {
+            let (store_key, head) = pair;
+            if let Some(value) = self.resolve_journal_value(&store_key, *head, height, &markers)? {
+                result.push((store_key, value));
+            }
+        }
+        return Ok(result);
+    }
+
+    /// Proves the value against the state root kept for the height, which covers every present
+    /// key of the chain
+    fn get_proof(&self, get_proof: &GetProofInstruction) -> ImmuxResult<GetProofOkAnswer> {
+        let height = match get_proof.height {
+            None => self.get_height(),
+            Some(height) => height,
+        };
+        let root = self.load_state_root(&height)?;
+        let load = |hash: &RecordHash| self.load_state_node(hash);
+        let proof = match prove_state_tree(root, &get_proof.key, &load)? {
+            None => return Err(VkvError::ProofTargetMissing(get_proof.key.to_owned()).into()),
+            Some(proof) => proof,
+        };
+        let value = self.get_value_after_height(&get_proof.key, &height)?;
+        if !proof.verify(&get_proof.key, &value, &root) {
+            return Err(VkvError::StateRootMismatch(height, get_proof.key.to_owned()).into());
+        }
+        return Ok(GetProofOkAnswer {
+            height,
+            value,
+            root,
+            proof,
+        });
+    }
+
+    /// Keys are compared by their values at both ends of the range, so a key changed and then
+    /// changed back within it is left out
+    fn diff(&self, diff: &DiffInstruction) -> ImmuxResult<DiffOkAnswer> {
+        let current_height = self.get_height();
+        let to_height = match diff.to_height {
+            None => current_height,
+            Some(height) => height,
+        };
+        if diff.from_height > to_height || to_height > current_height {
+            return Err(VkvError::InvalidDiffRange(diff.from_height, to_height).into());
+        }
+        self.check_chain_retained(&diff.from_height)?;
+        let mut first_changed_height = diff.from_height;
+        first_changed_height.increment();
+        let mut changes = Vec::new();
+        for key in extract_affected_keys(self, first_changed_height, to_height)? {
+            if let Some(scope) = &diff.scope {
+                if !key.as_slice().starts_with(scope.as_slice()) {
+                    continue;
+                }
+            }
+            let before = self.get_value_or_empty(&key, &diff.from_height)?;
+            let after = self.get_value_or_empty(&key, &to_height)?;
+            if before != after {
+                changes.push(KeyChange { key, before, after });
+            }
+        }
+        return Ok(DiffOkAnswer {
+            from_height: diff.from_height,
+            to_height,
+            changes,
+        });
+    }
+
+    fn get_value_or_empty(&self, key: &StoreKey, height: &ChainHeight) -> ImmuxResult<StoreValue> {
+        match self.get_value_after_height(key, height) {
+            Err(ImmuxError::VKV(VkvError::CannotFindSuitableVersion)) => {
+                return Ok(StoreValue::new(None))
+            }
+            Err(error) => return Err(error),
+            Ok(value) => return Ok(value),
+        }
+    }
+
+    fn get_latest_value(&mut self, key: &StoreKey) -> ImmuxResult<StoreValue> {
+        self.get_journal_head(key).map(|head| head.value)
+    }
+
+    fn get_version_kv_pair(
+        &self,
+        key: &StoreKey,
+        height: &ChainHeight,
+        value: &StoreValue,
+    ) -> (KVKey, KVValue) {
+        let kvkey = get_version_kvkey(key, height);
+        let kvvalue = KVValue::new(&value.marshal());
+        return (kvkey, kvvalue);
+    }
+
+    fn load_version(&self, key: &StoreKey, height: &ChainHeight) -> ImmuxResult<StoreValue> {
+        let kvkey = get_version_kvkey(key, height);
+        match self.kv_engine.get(&kvkey) {
+            Err(error) => Err(error),
+            Ok(None) => Err(self.get_missing_version_error(
+                key,
+                height,
+                VkvError::MissingVersion(key.to_owned(), height.to_owned()),
+            )),
+            Ok(Some(value)) => match StoreValue::parse(value.as_bytes()) {
+                Err(_error) => Err(VkvError::VersionParsing.into()),
+                Ok((store_value, _)) => Ok(store_value),
+            },
+        }
+    }
+
+    fn get_value_after_height(
+        &self,
+        key: &StoreKey,
+        requested_height: &ChainHeight,
+    ) -> ImmuxResult<StoreValue> {
+        match self.get_journal_head(key) {
+            Err(error) => return Err(error),
+            Ok(head) => match self.find_update_height(key, &head, requested_height)? {
+                None => {
+                    return Err(self.get_missing_version_error(
+                        key,
+                        requested_height,
+                        VkvError::CannotFindSuitableVersion,
+                    ))
+                }
+                Some(update_height) => {
+                    if Some(update_height) == head.get_latest_height() {
+                        // The latest version is kept in the journal itself
+                        return Ok(head.value);
+                    } else {
+                        return self.load_version(key, &update_height);
+                    }
+                }
+            },
+        }
+    }
+
+    fn get_storage_layout(&self) -> ImmuxResult<u64> {
+        match self.kv_engine.get(&get_storage_layout_kvkey()) {
+            Err(error) => Err(error),
+            Ok(None) => Ok(LEGACY_STORAGE_LAYOUT),
+            Ok(Some(value)) => match varint_decode(value.as_bytes()) {
+                Err(_error) => Err(VkvError::VersionLayoutParsing.into()),
+                Ok((layout, _)) => Ok(layout),
+            },
+        }
+    }
+
+    /// Brings a chain written before storage layouts were recorded to the current layout. Every
+    /// step can run again over what it already wrote, and resealing picks up where it stopped, so
+    /// a migration interrupted midway is finished the next time the chain is opened.
+    fn migrate_storage_layout(&mut self) -> ImmuxResult<()> {
+        if self.get_storage_layout()? >= CURRENT_STORAGE_LAYOUT {
+            return Ok(());
+        }
+        self.reseal_records()?;
+        self.materialize_versions()?;
+        self.index_chain_times()?;
+        self.page_journals()?;
+        self.index_affected_keys()?;
+        // Roots are computed from the versions and affected keys
+        self.index_state_roots()?;
+        let layout = KVValue::new(&varint_encode(CURRENT_STORAGE_LAYOUT));
+        self.kv_engine.set(&get_storage_layout_kvkey(), &layout)?;
+        return self
+            .kv_engine
+            .atomic_batch_delete(&[get_resealed_height_kvkey()]);
+    }
+
+    /// The last height rewritten by a reseal, or zero if none was
+    fn get_resealed_height(&self) -> ImmuxResult<ChainHeight> {
+        match self.kv_engine.get(&get_resealed_height_kvkey()) {
+            Err(error) => Err(error),
+            Ok(None) => Ok(get_fallback_height()),
+            Ok(Some(value)) => match ChainHeight::parse(value.as_bytes()) {
+                Err(_error) => Err(VkvError::ResealedHeightParsing.into()),
+                Ok((height, _)) => Ok(height),
+            },
+        }
+    }
+
+    /// Rewrites the legacy records in the current shape, linking them from the first height up.
+    /// Each record is saved with the chain head and its height, from which an interrupted reseal
+    /// continues.
+    fn reseal_records(&mut self) -> ImmuxResult<()> {
+        let current_height = self.get_height();
+        let mut height = self.get_resealed_height()?;
+        let mut previous_hash = if height.is_zero() {
+            RecordHash::genesis()
+        } else {
+            self.get_chain_head()?
+        };
+        height.increment();
+        while height <= current_height {
+            let instruction_kvkey = get_instruction_kvkey(&height);
+            let mut record: InstructionRecord = match self.kv_engine.get(&instruction_kvkey) {
+                Err(_error) => return Err(VkvError::GetInstructionRecordFail.into()),
+                Ok(None) => return Err(VkvError::GetInstructionRecordFail.into()),
+                Ok(Some(value)) => match deserialize::<LegacyInstructionRecord>(value.as_bytes()) {
+                    Err(_error) => return Err(VkvError::DeserializationFail.into()),
+                    Ok(legacy_record) => legacy_record.into(),
+                },
+            };
+            let chain_head_kv_pair = self.seal_instruction_record(&mut record, previous_hash)?;
+            previous_hash = record.chained_hash();
+            let instruction_kv_pair = self.get_instruction_record_kv_pair(&height, &record)?;
+            let resealed_height_kv_pair =
+                (get_resealed_height_kvkey(), KVValue::new(&height.marshal()));
+            self.kv_engine.atomic_batch_set(&[
+                instruction_kv_pair,
+                chain_head_kv_pair,
+                resealed_height_kv_pair,
+            ])?;
+            height.increment();
+        }
+        return Ok(());
+    }
+
+    /// Legacy chains get a state root at every height, starting from the empty state
+    fn index_state_roots(&mut self) -> ImmuxResult<()> {
+        let current_height = self.get_height();
+        let mut height = ChainHeight::new(1);
+        while height <= current_height {
+            let mut changes = Vec::new();
+            for key in self.load_affected_keys(&height)? {
+                let value = self.get_value_after_height(&key, &height)?;
+                changes.push((key, value));
             }
-        };
-        return journal;
+            let kv_pairs = self.get_state_kv_pairs(&height, &changes)?;
+            self.kv_engine.atomic_batch_set(&kv_pairs)?;
+            height.increment();
+        }
+        return Ok(());
     }
 
-    fn get_latest_value(&mut self, key: &StoreKey) -> ImmuxResult<StoreValue> {
-        self.get_journal(key).map(|journal| journal.value)
+    /// Journals written before paging keep every update height under the journal key itself
+    fn page_journals(&mut self) -> ImmuxResult<()> {
+        let prefix = KVKeySegment::from(vec![KVKeySigil::UnitJournal as u8]);
+        for (kvkey, kvvalue) in self.kv_engine.filter_prefix(&prefix).into_iter() {
+            let key = extract_journal_store_key(&kvkey.into());
+            let head = JournalHead::parse(kvvalue.as_bytes())?;
+            if head.page_count > 0 || head.recent_heights.iter().count() <= JOURNAL_PAGE_SIZE {
+                continue;
+            }
+            let heights: Vec<ChainHeight> = head.recent_heights.iter().collect();
+            let kv_pairs = get_paged_journal_kv_pairs(&key, &head.value, &heights);
+            self.kv_engine.atomic_batch_set(&kv_pairs)?;
+        }
+        return Ok(());
     }
 
-    /// Created to to prevent infinite loops
-    fn get_value_after_height_recurse(
-        &self,
-        key: &StoreKey,
-        requested_height: &ChainHeight,
-        recurse_time: u16,
-    ) -> ImmuxResult<StoreValue> {
-        if recurse_time > MAX_RECURSION {
-            return Err(VkvError::TooManyRecursionInFindingValue.into());
+    /// Chains written before the affected keys index only have the keys in each record
+    fn index_affected_keys(&mut self) -> ImmuxResult<()> {
+        let current_height = self.get_height();
+        let mut height = ChainHeight::new(1);
+        while height <= current_height {
+            let record = self.load_instruction_record(&height)?;
+            let (kvkey, kvvalue) = self.get_affected_keys_kv_pair(&height, &record)?;
+            self.kv_engine.set(&kvkey, &kvvalue)?;
+            height.increment();
         }
-        match self.get_journal(key) {
-            Err(error) => return Err(error),
-            Ok(journal) => {
-                let possible_heights: Vec<_> = journal
-                    .update_heights
-                    .iter()
-                    .take_while(|h| h <= requested_height)
-                    .collect();
-                for height in possible_heights.into_iter().rev() {
-                    let record = self.load_instruction_record(&height)?;
-                    let instruction = &record.instruction;
-                    match instruction {
-                        Instruction::DataAccess(DataInstruction::Write(write)) => match write {
-                            DataWriteInstruction::SetMany(set_many) => {
-                                for target in &set_many.targets {
-                                    if target.key == *key {
-                                        return Ok(target.value.clone());
-                                    }
-                                }
-                            }
-                            DataWriteInstruction::RevertMany(revert_many) => {
-                                for target in &revert_many.targets {
-                                    if target.key == *key {
-                                        return Ok(self.get_value_after_height_recurse(
-                                            key,
-                                            &target.height,
-                                            recurse_time + 1,
-                                        )?);
-                                    }
-                                }
-                                return Err(VkvError::CannotFindSuitableVersion.into());
+        return Ok(());
+    }
+
+    /// Chains written before chain times were indexed only have the system time in each record
+    fn index_chain_times(&mut self) -> ImmuxResult<()> {
+        let current_height = self.get_height();
+        let mut height = ChainHeight::new(1);
+        while height <= current_height {
+            let record = self.load_instruction_record(&height)?;
+            let (kvkey, kvvalue) = self.get_chain_time_kv_pair(&height, &record)?;
+            self.kv_engine.set(&kvkey, &kvvalue)?;
+            height.increment();
+        }
+        return Ok(());
+    }
+
+    /// Chains written before the materialized version store only have instruction records.
+    /// Replay them in height order to fill in the version of every key at every update height.
+    fn materialize_versions(&mut self) -> ImmuxResult<()> {
+        let current_height = self.get_height();
+        let mut height = ChainHeight::new(1);
+        while height <= current_height {
+            let record = self.load_instruction_record(&height)?;
+            let mut version_kv_pairs: Vec<(KVKey, KVValue)> = Vec::new();
+            match &record.instruction {
+                Instruction::DataAccess(DataInstruction::Write(write)) => match write {
+                    DataWriteInstruction::SetMany(set_many) => {
+                        for target in &set_many.targets {
+                            version_kv_pairs.push(self.get_version_kv_pair(
+                                &target.key,
+                                &height,
+                                &target.value,
+                            ));
+                        }
+                    }
+                    DataWriteInstruction::RevertMany(revert_many) => {
+                        for target in &revert_many.targets {
+                            let value = self.get_value_after_height(&target.key, &target.height)?;
+                            version_kv_pairs.push(self.get_version_kv_pair(
+                                &target.key,
+                                &height,
+                                &value,
+                            ));
+                        }
+                    }
+                    DataWriteInstruction::RevertAll(revert_all) => {
+                        if let Some(affected_keys) = &record.affected_keys {
+                            for key in affected_keys {
+                                let value =
+                                    self.get_value_after_height(key, &revert_all.target_height)?;
+                                version_kv_pairs
+                                    .push(self.get_version_kv_pair(key, &height, &value));
                             }
-                            DataWriteInstruction::RevertAll(revert_all) => {
-                                return Ok(self.get_value_after_height_recurse(
-                                    key,
-                                    &revert_all.target_height,
-                                    recurse_time + 1,
-                                )?);
+                        }
+                    }
+                    DataWriteInstruction::Revert(revert) => {
+                        if let Some(affected_keys) = &record.affected_keys {
+                            let mut previous_height = revert.height;
+                            previous_height.decrement();
+                            for key in affected_keys {
+                                let value = self.get_value_or_empty(key, &previous_height)?;
+                                version_kv_pairs
+                                    .push(self.get_version_kv_pair(key, &height, &value));
                             }
-                        },
-                        _ => return Err(VkvError::UnexpectedInstruction.into()),
+                        }
                     }
-                }
-                return Err(VkvError::CannotFindSuitableVersion.into());
+                    // Only written by layouts that already materialize versions
+                    DataWriteInstruction::RevertKeys(_)
+                    | DataWriteInstruction::IndexedRevertMany(_)
+                    | DataWriteInstruction::PruneChain(_) => {
+                        return Err(VkvError::UnexpectedInstruction.into());
+                    }
+                },
+                _ => return Err(VkvError::UnexpectedInstruction.into()),
             }
+            // Later heights resolve reverts against these versions, so write them height by height
+            self.kv_engine.atomic_batch_set(&version_kv_pairs)?;
+            height.increment();
         }
+        return Ok(());
     }
 
-    fn get_value_after_height(
-        &self,
-        key: &StoreKey,
-        requested_height: &ChainHeight,
-    ) -> ImmuxResult<StoreValue> {
-        self.get_value_after_height_recurse(key, requested_height, 0)
+    fn set_many(
+        &mut self,
+        set_many: &SetManyInstruction,
+        merged_from: Option<MergeOrigin>,
+    ) -> ImmuxResult<SetOkAnswer> {
+        let next_height = self.increment_chain_height();
+        let mut target_kv_pairs: Vec<(KVKey, KVValue)> =
+            Vec::with_capacity(set_many.targets.len() * 2 + 3);
+        for target in &set_many.targets {
+            target_kv_pairs.extend(self.get_journal_update_kv_pairs(
+                &target.key,
+                &target.value,
+                next_height,
+            ));
+            target_kv_pairs.push(self.get_version_kv_pair(
+                &target.key,
+                &next_height,
+                &target.value,
+            ));
+        }
+        let changes: Vec<(StoreKey, StoreValue)> = set_many
+            .targets
+            .iter()
+            .map(|target| (target.key.to_owned(), target.value.to_owned()))
+            .collect();
+        target_kv_pairs.extend(self.get_state_kv_pairs(&next_height, &changes)?);
+
+        let mut record = self.new_instruction_record(set_many.to_owned().into());
+        record.merged_from = merged_from;
+        let chain_head_kv_pair =
+            self.seal_instruction_record(&mut record, self.get_chain_head()?)?;
+        let instruction_kv_pair = self.get_instruction_record_kv_pair(&next_height, &record)?;
+        let chain_time_kv_pair = self.get_chain_time_kv_pair(&next_height, &record)?;
+        let affected_keys_kv_pair = self.get_affected_keys_kv_pair(&next_height, &record)?;
+        let height_kv_pair = self.get_height_kv_pair(next_height);
+        target_kv_pairs.push(instruction_kv_pair);
+        target_kv_pairs.push(chain_time_kv_pair);
+        target_kv_pairs.push(affected_keys_kv_pair);
+        target_kv_pairs.push(chain_head_kv_pair);
+        target_kv_pairs.push(height_kv_pair);
+        match self.kv_engine.atomic_batch_set(&target_kv_pairs) {
+            Err(error) => return Err(error),
+            Ok(_) => {}
+        }
+        let count = set_many.targets.len();
+        return Ok(SetOkAnswer { count });
     }
 
-    fn get_reverted_journal(
+    /// Keys the record at `height` wrote are restored to their values just below it, like
+    /// `git revert`. Keys written again since are reported as conflicts and left as they are,
+    /// unless the revert is restricted to `only` some keys, in which case they fail it. Index
+    /// targets are written in the same batch. The new record lists the keys it wrote, so nothing
+    /// is written if there are none.
+    fn revert_record(
         &mut self,
-        key: &StoreKey,
-        target_height: ChainHeight,
+        height: ChainHeight,
+        only: Option<&[StoreKey]>,
+        index_targets: &[SetTargetSpec],
+        instruction: Instruction,
         next_height: ChainHeight,
-    ) -> ImmuxResult<UnitJournal> {
-        fn find_appropriate_height(
-            heights: &HeightList,
-            requested_height: &ChainHeight,
-        ) -> Option<ChainHeight> {
-            let mut last_valid_height = None;
-            for height in heights.iter() {
-                if &height > requested_height {
-                    break;
-                } else if &height == requested_height {
-                    last_valid_height = Some(height);
-                    break;
+    ) -> ImmuxResult<RevertRecordOkAnswer> {
+        if height.is_zero() || height >= next_height {
+            return Err(VkvError::InvalidRevertHeight(height).into());
+        }
+        self.check_chain_retained(&height)?;
+        let mut previous_height = height;
+        previous_height.decrement();
+        let record_keys = extract_affected_keys(&self, height, height)?;
+        let keys = match only {
+            None => record_keys,
+            Some(keys) => {
+                for key in keys {
+                    if !record_keys.contains(key) {
+                        return Err(VkvError::KeyNotInRecord(height, key.to_owned()).into());
+                    }
+                }
+                keys.to_vec()
+            }
+        };
+        let mut reverted_keys = Vec::new();
+        let mut conflicts = Vec::new();
+        let mut changes = Vec::new();
+        let mut target_kv_pairs: Vec<(KVKey, KVValue)> =
+            Vec::with_capacity((keys.len() + index_targets.len()) * 2 + 4);
+        for key in keys {
+            // Index entries are rebuilt from the restored units rather than rewound
+            if index_targets.iter().any(|target| target.key == key) {
+                continue;
+            }
+            if self.get_journal_head(&key)?.get_latest_height() > Some(height) {
+                if only.is_some() {
+                    return Err(VkvError::RevertConflict(key).into());
                 }
+                conflicts.push(key);
+                continue;
+            }
+            let value = self.get_value_or_empty(&key, &previous_height)?;
+            target_kv_pairs.extend(self.get_journal_update_kv_pairs(&key, &value, next_height));
+            target_kv_pairs.push(self.get_version_kv_pair(&key, &next_height, &value));
+            changes.push((key.to_owned(), value));
+            reverted_keys.push(key);
+        }
+        if reverted_keys.is_empty() && index_targets.is_empty() {
+            return Ok(RevertRecordOkAnswer {
+                reverted_keys,
+                conflicts,
+            });
+        }
+        let mut written_keys = reverted_keys.clone();
+        for target in index_targets {
+            target_kv_pairs.extend(self.get_journal_update_kv_pairs(
+                &target.key,
+                &target.value,
+                next_height,
+            ));
+            target_kv_pairs.push(self.get_version_kv_pair(
+                &target.key,
+                &next_height,
+                &target.value,
+            ));
+            changes.push((target.key.to_owned(), target.value.to_owned()));
+            written_keys.push(target.key.to_owned());
+        }
+        target_kv_pairs.extend(self.get_state_kv_pairs(&next_height, &changes)?);
+
+        let mut record = self.new_instruction_record(instruction);
+        record.affected_keys = Some(written_keys);
+        let chain_head_kv_pair =
+            self.seal_instruction_record(&mut record, self.get_chain_head()?)?;
+        target_kv_pairs.push(self.get_instruction_record_kv_pair(&next_height, &record)?);
+        target_kv_pairs.push(self.get_chain_time_kv_pair(&next_height, &record)?);
+        target_kv_pairs.push(self.get_affected_keys_kv_pair(&next_height, &record)?);
+        target_kv_pairs.push(chain_head_kv_pair);
+        target_kv_pairs.push(self.get_height_kv_pair(next_height));
+        self.kv_engine.atomic_batch_set(&target_kv_pairs)?;
+        return Ok(RevertRecordOkAnswer {
+            reverted_keys,
+            conflicts,
+        });
+    }
+
+    /// Every target is set back to the value it was updated to at its height, together with the
+    /// index targets, in one batch
+    fn revert_many(
+        &mut self,
+        targets: &[RevertTargetSpec],
+        index_targets: &[SetTargetSpec],
+        instruction: Instruction,
+        next_height: ChainHeight,
+    ) -> ImmuxResult<RevertOkAnswer> {
+        let mut kv_pairs: Vec<(KVKey, KVValue)> = Vec::new();
+        let mut changes = Vec::with_capacity(targets.len() + index_targets.len());
+        for target in targets {
+            if index_targets.iter().any(|index| index.key == target.key) {
+                continue;
             }
-            last_valid_height
+            let (value, reverted_kv_pairs) =
+                self.get_reverted_kv_pairs(&target.key, target.height, next_height)?;
+            kv_pairs.extend(reverted_kv_pairs);
+            changes.push((target.key.to_owned(), value));
+        }
+        for target in index_targets {
+            kv_pairs.extend(self.get_journal_update_kv_pairs(
+                &target.key,
+                &target.value,
+                next_height,
+            ));
+            kv_pairs.push(self.get_version_kv_pair(&target.key, &next_height, &target.value));
+            changes.push((target.key.to_owned(), target.value.to_owned()));
         }
+        kv_pairs.extend(self.get_state_kv_pairs(&next_height, &changes)?);
+        let mut record = self.new_instruction_record(instruction);
+        let chain_head_kv_pair =
+            self.seal_instruction_record(&mut record, self.get_chain_head()?)?;
+        kv_pairs.push(self.get_instruction_record_kv_pair(&next_height, &record)?);
+        kv_pairs.push(self.get_chain_time_kv_pair(&next_height, &record)?);
+        kv_pairs.push(self.get_affected_keys_kv_pair(&next_height, &record)?);
+        kv_pairs.push(chain_head_kv_pair);
+        kv_pairs.push(self.get_height_kv_pair(next_height));
+        self.kv_engine.atomic_batch_set(&kv_pairs)?;
+        return Ok(RevertOkAnswer {});
+    }
 
+    /// The value `key` was updated to at `target_height`, with the pairs setting it back to it
+    fn get_reverted_kv_pairs(
+        &self,
+        key: &StoreKey,
+        target_height: ChainHeight,
+        next_height: ChainHeight,
+    ) -> ImmuxResult<(StoreValue, Vec<(KVKey, KVValue)>)> {
         if target_height >= next_height {
             return Err(VkvError::TryingToRevertToFuture.into());
         }
-        match self.get_journal(key) {
+        match self.get_journal_head(key) {
             Err(error) => Err(error),
-            Ok(mut journal) => {
-                match find_appropriate_height(&journal.update_heights, &target_height) {
-                    None => Err(VkvError::CannotFindSuitableVersion.into()),
-                    Some(height) => {
-                        let value = self.get_value_after_height(key, &height)?;
-                        journal.update_heights.push(next_height);
-                        journal.value = value;
-                        return Ok(journal);
-                    }
+            Ok(head) => match self.find_update_height(key, &head, &target_height)? {
+                Some(height) if height == target_height => {
+                    let value = self.get_value_after_height(key, &height)?;
+                    let mut result = self.get_journal_update_kv_pairs(key, &value, next_height);
+                    result.push(self.get_version_kv_pair(key, &next_height, &value));
+                    return Ok((value, result));
                 }
-            }
+                _ => Err(self.get_missing_version_error(
+                    key,
+                    &target_height,
+                    VkvError::CannotFindSuitableVersion,
+                )),
+            },
         }
     }
 
@@ -332,45 +2194,62 @@ fn byte_array_compare(key_a: &StoreKey, key_b: &StoreKey) -> Ordering {
     return Ordering::Equal;
 }
 
+/// Keys written by the record, as kept in the affected keys index
+fn get_record_affected_keys(record: &InstructionRecord) -> ImmuxResult<Vec<StoreKey>> {
+    let mut affected_keys: Vec<StoreKey> = vec![];
+    match &record.instruction {
+        Instruction::DBSystem(_) => (),
+        Instruction::TransactionMeta(_) => (),
+        Instruction::DataAccess(DataInstruction::Read(_)) => (),
+        Instruction::DataAccess(DataInstruction::Write(write_instruction)) => {
+            match write_instruction {
+                DataWriteInstruction::SetMany(set) => {
+                    for target in &set.targets {
+                        affected_keys.push(target.key.to_owned())
+                    }
+                }
+                DataWriteInstruction::RevertMany(revert_many) => {
+                    for target in &revert_many.targets {
+                        affected_keys.push(target.key.to_owned())
+                    }
+                }
+                DataWriteInstruction::IndexedRevertMany(revert_many) => {
+                    for target in &revert_many.targets {
+                        affected_keys.push(target.key.to_owned())
+                    }
+                    for target in &revert_many.index_targets {
+                        affected_keys.push(target.key.to_owned())
+                    }
+                }
+                DataWriteInstruction::RevertAll(_)
+                | DataWriteInstruction::Revert(_)
+                | DataWriteInstruction::RevertKeys(_) => {
+                    if let Some(keys) = &record.affected_keys {
+                        affected_keys.extend(keys.to_owned())
+                    }
+                }
+                DataWriteInstruction::PruneChain(_) => (),
+            };
+        }
+        _ => {
+            return Err(ImmuxError::VKV(VkvError::UnexpectedInstruction));
+        }
+    }
+    return Ok(affected_keys);
+}
+
+/// Keys written from `target_height` to `current_height` inclusive, read from the affected keys
+/// index rather than the records. Every height in the range must have been written.
 pub fn extract_affected_keys(
     store: &ImmuxDBVersionedKeyValueStore,
     target_height: ChainHeight,
     current_height: ChainHeight,
 ) -> ImmuxResult<Vec<StoreKey>> {
     let mut affected_keys: Vec<StoreKey> = vec![];
-    let mut height = current_height;
-    while height >= target_height {
-        if let Ok(record) = store.load_instruction_record(&height) {
-            let instruction = record.instruction;
-            match instruction {
-                Instruction::DBSystem(_) => (),
-                Instruction::TransactionMeta(_) => (),
-                Instruction::DataAccess(DataInstruction::Read(_)) => (),
-                Instruction::DataAccess(DataInstruction::Write(write_instruction)) => {
-                    match write_instruction {
-                        DataWriteInstruction::SetMany(set) => {
-                            for target in set.targets {
-                                affected_keys.push(target.key)
-                            }
-                        }
-                        DataWriteInstruction::RevertMany(revert_many) => {
-                            for target in revert_many.targets {
-                                affected_keys.push(target.key)
-                            }
-                        }
-                        DataWriteInstruction::RevertAll(_revert_all) => {
-                            if let Some(keys) = record.affected_keys {
-                                affected_keys.extend(keys)
-                            }
-                        }
-                    };
-                }
-                _ => {
-                    return Err(ImmuxError::VKV(VkvError::UnexpectedInstruction));
-                }
-            }
-        }
-        height.decrement();
+    let mut height = max(target_height, ChainHeight::new(1));
+    while height <= current_height {
+        affected_keys.extend(store.load_affected_keys(&height)?);
+        height.increment();
     }
     affected_keys.sort_unstable_by(|a, b| byte_array_compare(a, b));
     affected_keys.dedup_by(|a, b| byte_array_compare(a, b) == Ordering::Equal);
@@ -390,11 +2269,14 @@ impl VersionedKeyValueStore for ImmuxDBVersionedKeyValueStore {
                         .switch_namespace(&set_namespace.new_namespace.to_owned().into())
                         {
                             Err(error) => Err(error),
-                            Ok(_) => Ok(Answer::DBSystem(DBSystemAnswer::SwitchNamespaceOk(
-                                SwitchNamespaceOkAnswer {
-                                    new_namespace: self.kv_engine.read_namespace().into(),
-                                },
-                            ))),
+                            Ok(_) => {
+                                self.migrate_storage_layout()?;
+                                Ok(Answer::DBSystem(DBSystemAnswer::SwitchNamespaceOk(
+                                    SwitchNamespaceOkAnswer {
+                                        new_namespace: self.kv_engine.read_namespace().into(),
+                                    },
+                                )))
+                            }
                         }
                 }
                 DBSystemInstruction::ReadNamespace(_get_namespace) => {
@@ -404,71 +2286,103 @@ impl VersionedKeyValueStore for ImmuxDBVersionedKeyValueStore {
                         },
                     )));
                 }
+                DBSystemInstruction::VerifyChain(_verify_chain) => {
+                    return Ok(self.verify_chain()?.into());
+                }
+                DBSystemInstruction::ForkNamespace(fork_namespace) => {
+                    return Ok(self.fork_namespace(fork_namespace)?.into());
+                }
+                DBSystemInstruction::MergeNamespace(merge_namespace) => {
+                    return Ok(self.merge_namespace(merge_namespace)?.into());
+                }
+                DBSystemInstruction::SetTag(set_tag) => {
+                    return Ok(self.set_tag(set_tag)?.into());
+                }
+                DBSystemInstruction::RemoveTag(remove_tag) => {
+                    return Ok(self.remove_tag(remove_tag)?.into());
+                }
+                DBSystemInstruction::ReadTags(_read_tags) => {
+                    let tags = self.load_tags()?;
+                    return Ok(ReadTagsOkAnswer { tags }.into());
+                }
+                DBSystemInstruction::SetRecordMeta(set_record_meta) => {
+                    self.record_meta = set_record_meta.meta.to_owned();
+                    return Ok(SetRecordMetaOkAnswer {}.into());
+                }
+                DBSystemInstruction::SetRetention(set_retention) => {
+                    return Ok(self.set_retention(set_retention)?.into());
+                }
+                DBSystemInstruction::ReadRetention(_read_retention) => {
+                    let rules = self.load_retention_rules()?;
+                    let markers = self.load_prune_markers()?;
+                    return Ok(ReadRetentionOkAnswer { rules, markers }.into());
+                }
+                DBSystemInstruction::Compact(_compact) => {
+                    return Ok(self.compact()?.into());
+                }
+                DBSystemInstruction::Replay(replay) => {
+                    let namespace = match &replay.namespace {
+                        None => self.kv_engine.read_namespace().into(),
+                        Some(namespace) => namespace.to_owned(),
+                    };
+                    let mut target = ImmuxDBVersionedKeyValueStore::new(
+                        &replay.engine,
+                        &replay.data_root,
+                        &namespace,
+                    )?;
+                    return Ok(self.replay_into(&mut target)?.into());
+                }
+                DBSystemInstruction::ExportRecords(_export_records) => {
+                    return Ok(self.export_records()?.into());
+                }
+                DBSystemInstruction::ImportRecords(import_records) => {
+                    return Ok(self.import_records(import_records)?.into());
+                }
             },
 
             Instruction::DataAccess(DataInstruction::Read(read_instruction)) => {
                 match read_instruction {
-                    DataReadInstruction::GetMany(get_many) => {
-                        match &get_many.targets {
-                            GetManyTargetSpec::Keys(keys) => {
-                                let mut data: Vec<(BoxedStoreKey, BoxedStoreValue)> =
-                                    Vec::with_capacity(keys.len());
-                                for key in keys {
-                                    let value = match get_many.height {
-                                        None => self.get_latest_value(&key)?,
-                                        Some(height) => {
-                                            self.get_value_after_height(key, &height)?
-                                        }
-                                    };
-                                    data.push((key.to_owned().into(), value.into()))
-                                }
-                                return Ok(Answer::DataAccess(DataAnswer::Read(
-                                    DataReadAnswer::GetManyOk(GetManyOkAnswer { data }),
-                                )));
-                            }
-                            GetManyTargetSpec::KeyPrefix(key_prefix) => {
-                                let basekey_prefix: KVKeySegment = {
-                                    let mut result =
-                                        Vec::with_capacity(1 + key_prefix.as_slice().len());
-                                    result.push(KVKeySigil::UnitJournal as u8);
-                                    result.extend_from_slice(key_prefix.as_slice());
-                                    result.into()
-                                };
-                                let base_pairs = self.kv_engine.filter_prefix(&basekey_prefix);
-
-                                let parsed_pairs: Vec<(BoxedStoreKey, Box<UnitJournal>)> = {
-                                    let mut result = Vec::with_capacity(base_pairs.len());
-                                    for pair in base_pairs.into_iter() {
-                                        // Remove Sigil
-                                        let (kvkey, kvvalue) = pair;
-                                        let store_key = extract_journal_store_key(&kvkey.into());
-                                        let journal = UnitJournal::parse(kvvalue.as_bytes())?;
-                                        result.push((store_key.into(), Box::new(journal)));
-                                    }
-                                    result
-                                };
-                                let data: Vec<(BoxedStoreKey, BoxedStoreValue)> = {
-                                    let mut result = Vec::with_capacity(parsed_pairs.len());
-                                    for pair in parsed_pairs {
-                                        let journal = pair.1;
-                                        match journal.value.inner() {
-                                            None => {}
-                                            Some(data) => {
-                                                let value =
-                                                    BoxedStoreValue::new(Some(data.clone()));
-                                                result.push((pair.0, value));
-                                            }
-                                        }
-                                    }
-                                    result
+                    DataReadInstruction::GetMany(get_many) => match &get_many.targets {
+                        GetManyTargetSpec::Keys(keys) => {
+                            let mut data: Vec<(BoxedStoreKey, BoxedStoreValue)> =
+                                Vec::with_capacity(keys.len());
+                            for key in keys {
+                                let value = match get_many.height {
+                                    None => self.get_latest_value(&key)?,
+                                    Some(height) => self.get_value_after_height(key, &height)?,
                                 };
-
-                                return Ok(Answer::DataAccess(DataAnswer::Read(
-                                    DataReadAnswer::GetManyOk(GetManyOkAnswer { data }),
-                                )));
+                                data.push((key.to_owned().into(), value.into()))
                             }
+                            return Ok(Answer::DataAccess(DataAnswer::Read(
+                                DataReadAnswer::GetManyOk(GetManyOkAnswer { data }),
+                            )));
                         }
-                    }
+                        GetManyTargetSpec::KeyPrefix(key_prefix) => {
+                            let data: Vec<(BoxedStoreKey, BoxedStoreValue)> = self
+                                .get_values_by_prefix(key_prefix, get_many.height)?
+                                .into_iter()
+                                .map(|(key, value)| (key.into(), value.into()))
+                                .collect();
+                            return Ok(Answer::DataAccess(DataAnswer::Read(
+                                DataReadAnswer::GetManyOk(GetManyOkAnswer { data }),
+                            )));
+                        }
+                        GetManyTargetSpec::KeyRange(key_prefix, after, limit) => {
+                            let data: Vec<(BoxedStoreKey, BoxedStoreValue)> = self
+                                .get_values_in_range(
+                                    key_prefix,
+                                    after.as_ref(),
+                                    *limit,
+                                    get_many.height,
+                                )?
+                                .into_iter()
+                                .map(|(key, value)| (key.into(), value.into()))
+                                .collect();
+                            return Ok(Answer::DataAccess(DataAnswer::Read(
+                                DataReadAnswer::GetManyOk(GetManyOkAnswer { data }),
+                            )));
+                        }
+                    },
                     DataReadInstruction::GetOne(get_one) => {
                         let result = match get_one.height {
                             None => self.get_latest_value(&get_one.key)?,
@@ -486,114 +2400,129 @@ impl VersionedKeyValueStore for ImmuxDBVersionedKeyValueStore {
                             ))),
                         }
                     }
+                    DataReadInstruction::GetProof(get_proof) => {
+                        return Ok(self.get_proof(get_proof)?.into());
+                    }
+                    DataReadInstruction::Diff(diff) => {
+                        return Ok(self.diff(diff)?.into());
+                    }
+                    DataReadInstruction::GetChanges(get_changes) => {
+                        return Ok(self.get_changes(get_changes)?.into());
+                    }
+                    DataReadInstruction::GetHeightAtTime(get_height_at_time) => {
+                        let height = self.get_height_at_time(get_height_at_time.time)?;
+                        return Ok(GetHeightAtTimeOkAnswer { height }.into());
+                    }
+                    DataReadInstruction::GetHeight(_get_height) => {
+                        return Ok(GetHeightOkAnswer {
+                            height: self.get_height(),
+                        }
+                        .into());
+                    }
                 }
             }
             Instruction::DataAccess(DataInstruction::Write(write_instruction)) => {
                 // Only data writes triggers height increment and instruction record saving
                 let next_height = self.increment_chain_height();
                 match write_instruction {
+                    // Only compaction writes these, along with the prune marker
+                    DataWriteInstruction::PruneChain(_) => {
+                        return Err(VkvError::UnexpectedInstruction.into());
+                    }
                     DataWriteInstruction::SetMany(set_many) => {
-                        let mut target_kv_pairs: Vec<(KVKey, KVValue)> = set_many
-                            .targets
-                            .iter()
-                            .map(|target| {
-                                let journal: UnitJournal =
-                                    self.update_journal(&target.key, &target.value, next_height);
-                                let kvkey = get_journal_kvkey(&target.key);
-                                let value = KVValue::new(&journal.marshal());
-                                return (kvkey, value);
-                            })
-                            .collect();
-
-                        let record: InstructionRecord = instruction.to_owned().into();
-                        let instruction_kv_pair =
-                            self.get_instruction_record_kv_pair(&next_height, &record)?;
-                        let height_kv_pair = self.get_height_kv_pair(next_height);
-                        target_kv_pairs.push(instruction_kv_pair);
-                        target_kv_pairs.push(height_kv_pair);
-                        match self.kv_engine.atomic_batch_set(&target_kv_pairs) {
-                            Err(error) => return Err(error),
-                            Ok(_) => {}
-                        }
-                        let count = set_many.targets.len();
-                        return Ok(Answer::DataAccess(DataAnswer::Write(
-                            DataWriteAnswer::SetOk(SetOkAnswer { count }),
-                        )));
+                        return Ok(self.set_many(set_many, None)?.into());
                     }
-                    DataWriteInstruction::RevertMany(revert) => {
-                        let target_kv_pairs: ImmuxResult<Vec<(KVKey, KVValue)>> = revert
-                            .targets
-                            .iter()
-                            .map(|target| {
-                                let reverted_journal = self.get_reverted_journal(
-                                    &target.key,
-                                    target.height,
-                                    next_height,
-                                )?;
-                                let kvkey = get_journal_kvkey(&target.key);
-                                let value = KVValue::new(&reverted_journal.marshal());
-                                Ok((kvkey, value))
-                            })
-                            .collect();
-
-                        match target_kv_pairs {
-                            Ok(mut kv_pairs) => {
-                                let record: InstructionRecord = instruction.to_owned().into();
-                                let instruction_kv_pair =
-                                    self.get_instruction_record_kv_pair(&next_height, &record)?;
-                                let height_kv_pair = self.get_height_kv_pair(next_height);
-                                kv_pairs.push(instruction_kv_pair);
-                                kv_pairs.push(height_kv_pair);
-                                match self.kv_engine.atomic_batch_set(&kv_pairs) {
-                                    Err(error) => return Err(error),
-                                    Ok(_) => {
-                                        return Ok(Answer::DataAccess(DataAnswer::Write(
-                                            DataWriteAnswer::RevertOk(RevertOkAnswer {}),
-                                        )));
-                                    }
-                                }
-                            }
-                            Err(error) => return Err(error),
-                        }
+                    DataWriteInstruction::Revert(revert) => {
+                        return Ok(self
+                            .revert_record(
+                                revert.height,
+                                None,
+                                &[],
+                                instruction.to_owned(),
+                                next_height,
+                            )?
+                            .into());
+                    }
+                    DataWriteInstruction::RevertKeys(revert_keys) => {
+                        return Ok(self
+                            .revert_record(
+                                revert_keys.height,
+                                Some(&revert_keys.keys),
+                                &revert_keys.index_targets,
+                                instruction.to_owned(),
+                                next_height,
+                            )?
+                            .into());
+                    }
+                    DataWriteInstruction::RevertMany(revert_many) => {
+                        return Ok(self
+                            .revert_many(
+                                &revert_many.targets,
+                                &[],
+                                instruction.to_owned(),
+                                next_height,
+                            )?
+                            .into());
+                    }
+                    DataWriteInstruction::IndexedRevertMany(revert_many) => {
+                        return Ok(self
+                            .revert_many(
+                                &revert_many.targets,
+                                &revert_many.index_targets,
+                                instruction.to_owned(),
+                                next_height,
+                            )?
+                            .into());
                     }
                     DataWriteInstruction::RevertAll(revert_all) => {
                         let target_height = revert_all.target_height;
                         if target_height >= next_height {
                             return Err(VkvError::TryingToRevertToFuture.into());
                         }
+                        self.check_chain_retained(&target_height)?;
 
                         // Find affected keys
                         let affected_keys =
-                            extract_affected_keys(&self, target_height, next_height)?;
-
-                        let target_kv_pairs: ImmuxResult<Vec<(KVKey, KVValue)>> =
-                            affected_keys
-                                .iter()
-                                .map(|affected_key| {
-                                    let reverted_journal = self.get_reverted_journal(
-                                        affected_key,
-                                        target_height,
-                                        next_height,
-                                    )?;
-                                    let kvkey = get_journal_kvkey(affected_key);
-                                    let value = KVValue::new(&reverted_journal.marshal());
-                                    Ok((kvkey, value))
-                                })
-                                .collect();
+                            extract_affected_keys(&self, target_height, self.get_height())?;
+
+                        let mut changes = Vec::with_capacity(affected_keys.len());
+                        let target_kv_pairs: ImmuxResult<Vec<(KVKey, KVValue)>> = affected_keys
+                            .iter()
+                            .map(|affected_key| {
+                                let (value, kv_pairs) = self.get_reverted_kv_pairs(
+                                    affected_key,
+                                    target_height,
+                                    next_height,
+                                )?;
+                                changes.push((affected_key.to_owned(), value));
+                                Ok(kv_pairs)
+                            })
+                            .collect::<ImmuxResult<Vec<_>>>()
+                            .map(|pairs| pairs.into_iter().flatten().collect());
 
                         match target_kv_pairs {
                             Ok(mut kv_pairs) => {
-                                let record: InstructionRecord = {
-                                    let mut result: InstructionRecord =
-                                        instruction.to_owned().into();
+                                kv_pairs.extend(self.get_state_kv_pairs(&next_height, &changes)?);
+                                let mut record: InstructionRecord = {
+                                    let mut result =
+                                        self.new_instruction_record(instruction.to_owned());
                                     result.affected_keys = Some(affected_keys.clone());
                                     result
                                 };
+                                let chain_head_kv_pair = self
+                                    .seal_instruction_record(&mut record, self.get_chain_head()?)?;
 
                                 let instruction_kv_pair =
                                     self.get_instruction_record_kv_pair(&next_height, &record)?;
+                                let chain_time_kv_pair =
+                                    self.get_chain_time_kv_pair(&next_height, &record)?;
+                                let affected_keys_kv_pair =
+                                    self.get_affected_keys_kv_pair(&next_height, &record)?;
                                 let height_kv_pair = self.get_height_kv_pair(next_height);
                                 kv_pairs.push(instruction_kv_pair);
+                                kv_pairs.push(chain_time_kv_pair);
+                                kv_pairs.push(affected_keys_kv_pair);
+                                kv_pairs.push(chain_head_kv_pair);
                                 kv_pairs.push(height_kv_pair);
 
                                 match self.kv_engine.atomic_batch_set(&kv_pairs) {
@@ -627,11 +2556,12 @@ impl VersionedKeyValueStore for ImmuxDBVersionedKeyValueStore {
 mod vkv_helper_tests {
     use super::{
         get_chain_height_kvkey, get_fallback_height, get_instruction_kvkey, get_journal_kvkey,
-        prefix_extractor,
+        get_version_kvkey, prefix_extractor,
     };
     use crate::config::KVKeySigil;
     use crate::config::KVKeySigil::{
-        ChainInfo, GroupingIndexedNames, HeightToInstructionRecord, ReverseIndexIdList, UnitJournal,
+        ChainInfo, GroupingIndexedNames, HeightToInstructionRecord, ReverseIndexIdList,
+        UnitJournal, UnitVersion,
     };
     use crate::declarations::basics::StoreKey;
     use crate::storage::kv::KVKey;
@@ -668,6 +2598,15 @@ mod vkv_helper_tests {
         )
     }
 
+    #[test]
+    fn test_get_version_kvkey() {
+        let store_key = StoreKey::new(&[0x01, 0x00, 0xff]);
+        let version_kvkey = get_version_kvkey(&store_key, &ChainHeight::new(0x1314));
+        let mut expected = vec![UnitVersion as u8, 0x01, 0x00, 0xff];
+        expected.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0x13, 0x14]);
+        assert_eq!(version_kvkey.as_bytes(), expected.as_slice())
+    }
+
     #[test]
     fn test_prefix_extractor() {
         let fixture: Vec<(Vec<u8>, Vec<u8>)> = vec![
@@ -687,6 +2626,7 @@ mod vkv_helper_tests {
                 vec![GroupingIndexedNames as u8, 0x00, 0x00],
                 vec![GroupingIndexedNames as u8],
             ),
+            (vec![UnitVersion as u8, 0x00, 0x00], vec![UnitVersion as u8]),
             // normal unit journal
             (
                 vec![UnitJournal as u8, 0x03, 0x00, 0x01, 0x02, 0xff, 0xff, 0xff],
@@ -697,6 +2637,8 @@ mod vkv_helper_tests {
                 vec![UnitJournal as u8, 0xff, 0x00, 0x01],
                 vec![UnitJournal as u8, 0xff, 0x00, 0x01],
             ),
+            // bare sigil, as when scanning all journals
+            (vec![UnitJournal as u8], vec![UnitJournal as u8]),
         ];
         for (key, expected_prefix) in fixture {
             let prefix = prefix_extractor(&key);
@@ -704,3 +2646,347 @@ mod vkv_helper_tests {
         }
     }
 }
+
+#[cfg(test)]
+mod vkv_storage_layout_tests {
+    use bincode::serialize;
+
+    use super::{
+        extract_affected_keys, get_instruction_kvkey, get_journal_kvkey, get_journal_page_kvkey,
+        ImmuxDBVersionedKeyValueStore, VersionedKeyValueStore, CURRENT_STORAGE_LAYOUT,
+        LEGACY_STORAGE_LAYOUT,
+    };
+    use crate::config::KVKeySigil;
+    use crate::declarations::basics::{StoreKey, StoreValue};
+    use crate::storage::instructions::{
+        Answer, DataAnswer, DataReadAnswer, GetOneInstruction, Instruction, RevertAllInstruction,
+        RevertManyInstruction, RevertTargetSpec, SetManyInstruction, SetTargetSpec, StoreNamespace,
+    };
+    use crate::storage::kv::{KVKey, KVKeySegment, KVValue, KeyValueEngine};
+    use crate::storage::vkv::instruction_record::LegacyInstructionRecord;
+    use crate::storage::vkv::journal::JOURNAL_PAGE_SIZE;
+    use crate::storage::vkv::{ChainHeight, RecordHash};
+
+    fn get_at_height(
+        vkv: &mut ImmuxDBVersionedKeyValueStore,
+        key: &StoreKey,
+        height: u64,
+    ) -> Option<StoreValue> {
+        let get: Instruction = GetOneInstruction {
+            height: Some(ChainHeight::new(height)),
+            key: key.to_owned(),
+        }
+        .into();
+        match vkv.execute(&get) {
+            Err(_error) => None,
+            Ok(Answer::DataAccess(DataAnswer::Read(DataReadAnswer::GetOneOk(answer)))) => {
+                Some(answer.value)
+            }
+            Ok(answer) => panic!("Unexpected answer {:?}", answer),
+        }
+    }
+
+    /// Leaves the chain as it was stored before storage layouts were recorded: records without
+    /// hashes, journals keeping all heights in one value, and nothing else
+    fn make_legacy_chain(vkv: &mut ImmuxDBVersionedKeyValueStore, keys: &[&StoreKey]) {
+        for height in 1..=vkv.get_height().as_u64() {
+            let height = ChainHeight::new(height);
+            let record = vkv.load_instruction_record(&height).unwrap();
+            let legacy_record = LegacyInstructionRecord {
+                instruction: record.instruction,
+                version: record.version,
+                sys_time: record.sys_time,
+                affected_keys: record.affected_keys,
+            };
+            let legacy_value = KVValue::new(&serialize(&legacy_record).unwrap());
+            vkv.kv_engine
+                .set(&get_instruction_kvkey(&height), &legacy_value)
+                .unwrap();
+        }
+        for key in keys {
+            let journal = vkv.get_journal(key).unwrap();
+            vkv.kv_engine
+                .set(&get_journal_kvkey(key), &KVValue::new(&journal.marshal()))
+                .unwrap();
+        }
+        for sigil in &[
+            KVKeySigil::ChainInfo as u8,
+            KVKeySigil::UnitVersion as u8,
+            KVKeySigil::HeightToChainTime as u8,
+            KVKeySigil::UnitJournalPage as u8,
+            KVKeySigil::HeightToAffectedKeys as u8,
+            KVKeySigil::StateTreeNode as u8,
+            KVKeySigil::HeightToStateRoot as u8,
+        ] {
+            let prefix = KVKeySegment::from(vec![*sigil]);
+            let kvkeys: Vec<KVKey> = vkv
+                .kv_engine
+                .filter_prefix(&prefix)
+                .into_iter()
+                .map(|(kvkey, _kvvalue)| KVKey::new(kvkey.as_bytes()))
+                .collect();
+            vkv.kv_engine.atomic_batch_delete(&kvkeys).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_migrate_legacy_layout() {
+        let ns = StoreNamespace::new(b"test_migrate_legacy_layout");
+        let mut vkv =
+            ImmuxDBVersionedKeyValueStore::new(&KeyValueEngine::HashMap, "", &ns).unwrap();
+        let key_a = StoreKey::from("a");
+        let key_b = StoreKey::from("b");
+        let key_c = StoreKey::from("c");
+        let keys = [&key_a, &key_b, &key_c];
+        let set = |keys: &[&StoreKey], byte: u8| -> Instruction {
+            SetManyInstruction {
+                targets: keys
+                    .iter()
+                    .map(|key| SetTargetSpec {
+                        key: key.to_owned().to_owned(),
+                        value: StoreValue::new(Some(vec![byte])),
+                    })
+                    .collect(),
+            }
+            .into()
+        };
+        let revert = |key: &StoreKey, height: u64| -> Instruction {
+            RevertManyInstruction {
+                targets: vec![RevertTargetSpec {
+                    key: key.to_owned(),
+                    height: ChainHeight::new(height),
+                }],
+            }
+            .into()
+        };
+        let mut history: Vec<Instruction> = vec![
+            set(&[&key_a], 1),         // height 1
+            set(&[&key_b], 2),         // height 2
+            set(&[&key_a], 3),         // height 3
+            revert(&key_a, 1),         // height 4
+            set(&[&key_a, &key_b], 5), // height 5
+            revert(&key_a, 4),         // height 6
+            RevertAllInstruction {
+                target_height: ChainHeight::new(5),
+            }
+            .into(), // height 7
+        ];
+        // Enough updates of one key to fill two journal pages
+        for i in 0..JOURNAL_PAGE_SIZE * 2 + 10 {
+            history.push(set(&[&key_c], i as u8));
+        }
+        for instruction in &history {
+            vkv.execute(instruction).unwrap();
+        }
+        let last_height = history.len() as u64;
+        let heights: Vec<ChainHeight> = (1..=last_height).map(ChainHeight::new).collect();
+        let snapshot = |vkv: &mut ImmuxDBVersionedKeyValueStore| -> Vec<Option<StoreValue>> {
+            let mut result = Vec::new();
+            for height in 1..=last_height {
+                for key in &keys {
+                    result.push(get_at_height(vkv, key, height));
+                }
+            }
+            result
+        };
+        let expected = snapshot(&mut vkv);
+        let expected_head = vkv.verify_chain().unwrap().head;
+        let load_all = |vkv: &ImmuxDBVersionedKeyValueStore| {
+            let times: Vec<u128> = heights
+                .iter()
+                .map(|height| vkv.load_chain_time(height).unwrap())
+                .collect();
+            let affected_keys: Vec<Vec<StoreKey>> = heights
+                .iter()
+                .map(|height| extract_affected_keys(vkv, *height, *height).unwrap())
+                .collect();
+            let roots: Vec<RecordHash> = heights
+                .iter()
+                .map(|height| vkv.load_state_root(height).unwrap())
+                .collect();
+            (times, affected_keys, roots)
+        };
+        let expected_indexes = load_all(&vkv);
+        let journal = vkv.get_journal(&key_c).unwrap();
+        assert_eq!(vkv.get_journal_head(&key_c).unwrap().page_count, 2);
+
+        make_legacy_chain(&mut vkv, &keys);
+        assert_eq!(vkv.get_storage_layout().unwrap(), LEGACY_STORAGE_LAYOUT);
+        assert_ne!(snapshot(&mut vkv), expected);
+        assert_eq!(
+            vkv.verify_chain().unwrap().broken_height,
+            Some(ChainHeight::new(1))
+        );
+
+        vkv.migrate_storage_layout().unwrap();
+        assert_eq!(vkv.get_storage_layout().unwrap(), CURRENT_STORAGE_LAYOUT);
+        assert_eq!(snapshot(&mut vkv), expected);
+        let verification = vkv.verify_chain().unwrap();
+        assert_eq!(verification.broken_height, None);
+        assert_eq!(verification.head, expected_head);
+        assert_eq!(load_all(&vkv), expected_indexes);
+        assert_eq!(vkv.get_journal_head(&key_c).unwrap().page_count, 2);
+        assert_eq!(vkv.get_journal(&key_c).unwrap(), journal);
+    }
+
+    #[test]
+    fn test_resume_interrupted_migration() {
+        let ns = StoreNamespace::new(b"test_resume_interrupted_migration");
+        let mut vkv =
+            ImmuxDBVersionedKeyValueStore::new(&KeyValueEngine::HashMap, "", &ns).unwrap();
+        let key = StoreKey::from("key");
+        for byte in 1..=5 {
+            let set: Instruction = SetManyInstruction {
+                targets: vec![SetTargetSpec {
+                    key: key.clone(),
+                    value: StoreValue::new(Some(vec![byte])),
+                }],
+            }
+            .into();
+            vkv.execute(&set).unwrap();
+        }
+        let expected_head = vkv.verify_chain().unwrap().head;
+        make_legacy_chain(&mut vkv, &[&key]);
+
+        // Resealing stopped after the first two records
+        let (kvkey, kvvalue) = vkv.get_height_kv_pair(ChainHeight::new(2));
+        vkv.kv_engine.set(&kvkey, &kvvalue).unwrap();
+        vkv.reseal_records().unwrap();
+        let (kvkey, kvvalue) = vkv.get_height_kv_pair(ChainHeight::new(5));
+        vkv.kv_engine.set(&kvkey, &kvvalue).unwrap();
+        assert_eq!(vkv.get_storage_layout().unwrap(), LEGACY_STORAGE_LAYOUT);
+        assert_eq!(vkv.get_resealed_height().unwrap(), ChainHeight::new(2));
+
+        vkv.migrate_storage_layout().unwrap();
+        assert_eq!(vkv.get_storage_layout().unwrap(), CURRENT_STORAGE_LAYOUT);
+        assert!(vkv.get_resealed_height().unwrap().is_zero());
+        let verification = vkv.verify_chain().unwrap();
+        assert_eq!(verification.broken_height, None);
+        assert_eq!(verification.head, expected_head);
+        assert_eq!(
+            get_at_height(&mut vkv, &key, 3),
+            Some(StoreValue::new(Some(vec![3])))
+        );
+    }
+
+    #[test]
+    fn test_historical_read_loads_few_journal_pages() {
+        let ns = StoreNamespace::new(b"test_historical_read_loads_few_journal_pages");
+        let mut vkv =
+            ImmuxDBVersionedKeyValueStore::new(&KeyValueEngine::HashMap, "", &ns).unwrap();
+        let key = StoreKey::from("key");
+        let value_at = |height: u64| StoreValue::new(Some(height.to_be_bytes().to_vec()));
+        let page_count = 16;
+        let end = JOURNAL_PAGE_SIZE as u64 * page_count + 10;
+        for height in 1..=end {
+            let set: Instruction = SetManyInstruction {
+                targets: vec![SetTargetSpec {
+                    key: key.clone(),
+                    value: value_at(height),
+                }],
+            }
+            .into();
+            vkv.execute(&set).unwrap();
+        }
+        assert_eq!(vkv.get_journal_head(&key).unwrap().page_count, page_count);
+
+        // Heights in the first page are found by bisecting pages 8, 4, 2, 1 and 0, so reads there
+        // do not notice the other pages missing
+        let unvisited_pages: Vec<KVKey> = (0..page_count)
+            .filter(|page| ![0, 1, 2, 4, 8].contains(page))
+            .map(|page| get_journal_page_kvkey(&key, page))
+            .collect();
+        vkv.kv_engine.atomic_batch_delete(&unvisited_pages).unwrap();
+        for height in 1..=JOURNAL_PAGE_SIZE as u64 {
+            assert_eq!(
+                get_at_height(&mut vkv, &key, height),
+                Some(value_at(height))
+            );
+        }
+        let middle_height = JOURNAL_PAGE_SIZE as u64 * 10;
+        assert_eq!(get_at_height(&mut vkv, &key, middle_height), None);
+
+        // Heights after the sealed pages are read from the journal head alone
+        let all_pages: Vec<KVKey> = (0..page_count)
+            .map(|page| get_journal_page_kvkey(&key, page))
+            .collect();
+        vkv.kv_engine.atomic_batch_delete(&all_pages).unwrap();
+        for height in end - 9..=end {
+            assert_eq!(
+                get_at_height(&mut vkv, &key, height),
+                Some(value_at(height))
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod vkv_compaction_tests {
+    use super::{
+        get_version_kvkey, ImmuxDBVersionedKeyValueStore, VersionedKeyValueStore, VkvError,
+    };
+    use crate::declarations::basics::{StoreKey, StoreValue};
+    use crate::declarations::errors::ImmuxError;
+    use crate::storage::instructions::{
+        GetOneInstruction, Instruction, RetentionPolicy, SetManyInstruction,
+        SetRetentionInstruction, SetTargetSpec, StoreNamespace,
+    };
+    use crate::storage::kv::{KVKey, KeyValueEngine};
+    use crate::storage::vkv::ChainHeight;
+
+    #[test]
+    fn test_resume_interrupted_compaction() {
+        let ns = StoreNamespace::new(b"test_resume_interrupted_compaction");
+        let mut vkv =
+            ImmuxDBVersionedKeyValueStore::new(&KeyValueEngine::HashMap, "", &ns).unwrap();
+        let key = StoreKey::from("key");
+        for byte in 1..=6 {
+            let set: Instruction = SetManyInstruction {
+                targets: vec![SetTargetSpec {
+                    key: key.clone(),
+                    value: StoreValue::new(Some(vec![byte])),
+                }],
+            }
+            .into();
+            vkv.execute(&set).unwrap();
+        }
+        let set_retention: Instruction = SetRetentionInstruction {
+            scope: None,
+            policy: Some(RetentionPolicy::KeepHeights(3)),
+        }
+        .into();
+        vkv.execute(&set_retention).unwrap();
+
+        // Compaction stopped after dropping the versions, before the journal and the marker
+        let version_kvkeys: Vec<KVKey> = (1..=3)
+            .map(|height| get_version_kvkey(&key, &ChainHeight::new(height)))
+            .collect();
+        vkv.kv_engine.atomic_batch_delete(&version_kvkeys).unwrap();
+        assert!(vkv.load_prune_markers().unwrap().is_empty());
+
+        let answer = vkv.compact().unwrap();
+        assert_eq!(answer.markers.len(), 1);
+        assert_eq!(answer.markers[0].height, ChainHeight::new(4));
+        assert_eq!(answer.pruned_records, 3);
+        let heights: Vec<u64> = vkv
+            .get_journal(&key)
+            .unwrap()
+            .update_heights
+            .iter()
+            .map(|height| height.as_u64())
+            .collect();
+        assert_eq!(heights, vec![4, 5, 6]);
+        let get: Instruction = GetOneInstruction {
+            height: Some(ChainHeight::new(3)),
+            key: key.clone(),
+        }
+        .into();
+        match vkv.execute(&get) {
+            Err(ImmuxError::VKV(VkvError::HeightPruned(_, pruned))) => {
+                assert_eq!(pruned, ChainHeight::new(4))
+            }
+            result => panic!("Unexpected result {:?}", result),
+        }
+        assert_eq!(vkv.verify_chain().unwrap().broken_height, None);
+    }
+}
@@ -1,16 +1,27 @@
 #[cfg(test)]
 mod vkv_tests {
+    use bincode::{deserialize, serialize};
     use immuxdb_dev_utils::reset_db_dir;
 
-    use crate::declarations::basics::{StoreKey, StoreValue};
+    use crate::config::KVKeySigil;
+
+    use crate::declarations::basics::{GroupingLabel, StoreKey, StoreValue, UnitId};
     use crate::declarations::errors::ImmuxError;
     use crate::storage::instructions::{
-        Answer, DataAnswer, DataReadAnswer, GetJournalInstruction, GetOneInstruction, Instruction,
-        SetManyInstruction, SetTargetSpec, StoreNamespace,
+        Answer, ChainTag, CompactInstruction, CompactOkAnswer, DBSystemAnswer, DBSystemInstruction,
+        DataAnswer, DataReadAnswer, DataWriteAnswer, DiffInstruction, ForkNamespaceInstruction,
+        GetChangesInstruction, GetHeightAtTimeInstruction, GetHeightInstruction,
+        GetJournalInstruction, GetManyInstruction, GetManyTargetSpec, GetOneInstruction,
+        GetProofInstruction, Instruction, MergeConflict, MergeNamespaceInstruction,
+        MergeResolution, MergeStrategy, ReadRetentionInstruction, ReadTagsInstruction,
+        RemoveTagInstruction, ReplayInstruction, ReplayMismatch, RetentionPolicy,
+        RevertAllInstruction, RevertInstruction, RevertKeysInstruction, RevertManyInstruction,
+        RevertTargetSpec, SetManyInstruction, SetRecordMetaInstruction, SetRetentionInstruction,
+        SetTagInstruction, SetTargetSpec, StoreNamespace, SwitchNamespaceInstruction,
     };
-    use crate::storage::kv::KeyValueEngine;
-    use crate::storage::vkv::ChainHeight;
+    use crate::storage::kv::{KVKey, KVValue, KeyValueEngine};
     use crate::storage::vkv::VkvError;
+    use crate::storage::vkv::{ChainHeight, InstructionRecord, MergeOrigin, RecordMeta};
     use crate::storage::vkv::{ImmuxDBVersionedKeyValueStore, VersionedKeyValueStore};
     use crate::utils::u32_to_u8_array;
 
@@ -149,4 +160,1438 @@ mod vkv_tests {
             _ => panic!("Unexpected result"),
         }
     }
+
+    #[test]
+    fn test_get_prefix_at_height() {
+        let mut vkv = make_vkv("test_get_prefix_at_height");
+        let grouping = GroupingLabel::from("grouping");
+        let key_a = StoreKey::build(&grouping, UnitId::new(1));
+        let key_b = StoreKey::build(&grouping, UnitId::new(2));
+
+        let updates = vec![
+            (&key_a, Some(1)), // height 1
+            (&key_b, Some(2)), // height 2
+            (&key_a, Some(3)), // height 3
+            (&key_b, None),    // height 4, removal
+        ];
+        for (key, value) in updates {
+            let set: Instruction = SetManyInstruction {
+                targets: vec![SetTargetSpec {
+                    key: key.to_owned(),
+                    value: StoreValue::new(value.map(|byte| vec![byte])),
+                }],
+            }
+            .into();
+            vkv.execute(&set).unwrap();
+        }
+
+        let expectations = vec![
+            (Some(1), vec![(&key_a, 1)]),
+            (Some(2), vec![(&key_a, 1), (&key_b, 2)]),
+            (Some(3), vec![(&key_a, 3), (&key_b, 2)]),
+            (Some(4), vec![(&key_a, 3)]),
+            (None, vec![(&key_a, 3)]),
+        ];
+        for (height, expected) in expectations {
+            let get: Instruction = GetManyInstruction {
+                height: height.map(ChainHeight::new),
+                targets: GetManyTargetSpec::KeyPrefix(grouping.marshal().into()),
+            }
+            .into();
+            match vkv.execute(&get).unwrap() {
+                Answer::DataAccess(DataAnswer::Read(DataReadAnswer::GetManyOk(answer))) => {
+                    let mut actual: Vec<(StoreKey, Option<Box<[u8]>>)> = answer
+                        .data
+                        .into_iter()
+                        .map(|(key, value)| (StoreKey::new(key.as_slice()), value.inner().clone()))
+                        .collect();
+                    actual.sort_by(|a, b| a.0.cmp(&b.0));
+                    let expected: Vec<(StoreKey, Option<Box<[u8]>>)> = expected
+                        .into_iter()
+                        .map(|(key, byte)| (key.to_owned(), Some(vec![byte].into_boxed_slice())))
+                        .collect();
+                    assert_eq!(actual, expected, "at height {:?}", height);
+                }
+                answer => panic!("Unexpected answer {:?}", answer),
+            }
+        }
+    }
+
+    #[test]
+    fn test_get_proof() {
+        let mut vkv = make_vkv("test_get_proof");
+        let key_a = StoreKey::from("a");
+        let key_b = StoreKey::from("b");
+        let updates = vec![
+            vec![(&key_a, Some(1)), (&key_b, Some(1))], // height 1
+            vec![(&key_a, Some(2))],                    // height 2
+            vec![(&key_b, None)],                       // height 3, removal
+        ];
+        for targets in updates {
+            let set: Instruction = SetManyInstruction {
+                targets: targets
+                    .into_iter()
+                    .map(|(key, value)| SetTargetSpec {
+                        key: key.to_owned(),
+                        value: StoreValue::new(value.map(|byte| vec![byte])),
+                    })
+                    .collect(),
+            }
+            .into();
+            vkv.execute(&set).unwrap();
+        }
+        let revert_all: Instruction = RevertAllInstruction {
+            target_height: ChainHeight::new(1),
+        }
+        .into();
+        vkv.execute(&revert_all).unwrap();
+
+        let prove = |vkv: &mut ImmuxDBVersionedKeyValueStore, key: &StoreKey, height: u64| {
+            let get_proof: Instruction = GetProofInstruction {
+                height: Some(ChainHeight::new(height)),
+                key: key.to_owned(),
+            }
+            .into();
+            match vkv.execute(&get_proof) {
+                Ok(Answer::DataAccess(DataAnswer::Read(DataReadAnswer::GetProofOk(answer)))) => {
+                    assert!(answer.proof.verify(key, &answer.value, &answer.root));
+                    Ok(answer)
+                }
+                Ok(answer) => panic!("Unexpected answer {:?}", answer),
+                Err(error) => Err(error),
+            }
+        };
+        let at_1 = prove(&mut vkv, &key_a, 1).unwrap();
+        let at_2 = prove(&mut vkv, &key_a, 2).unwrap();
+        assert_eq!(at_1.value, StoreValue::new(Some(vec![1])));
+        assert_eq!(at_2.value, StoreValue::new(Some(vec![2])));
+        assert_ne!(at_1.root, at_2.root);
+        match prove(&mut vkv, &key_b, 3) {
+            Err(ImmuxError::VKV(VkvError::ProofTargetMissing(key))) => assert_eq!(key, key_b),
+            result => panic!("Unexpected result {:?}", result),
+        }
+
+        // Reverting to height 1 brings back its state, and so its root
+        let at_4 = prove(&mut vkv, &key_b, 4).unwrap();
+        assert_eq!(at_4.root, at_1.root);
+        assert!(!at_4
+            .proof
+            .verify(&key_b, &StoreValue::new(Some(vec![2])), &at_4.root));
+    }
+
+    #[test]
+    fn test_deep_revert_chain() {
+        let mut vkv = make_vkv("test_deep_revert_chain");
+        let key = StoreKey::from("key");
+        for i in 0..2 {
+            let set: Instruction = SetManyInstruction {
+                targets: vec![SetTargetSpec {
+                    key: key.clone(),
+                    value: StoreValue::new(Some(vec![i])),
+                }],
+            }
+            .into();
+            vkv.execute(&set).unwrap();
+        }
+
+        // Each revert targets another revert, nesting far deeper than any recursion limit
+        let last_height = 1000;
+        for height in 3..=last_height {
+            let revert: Instruction = RevertManyInstruction {
+                targets: vec![RevertTargetSpec {
+                    key: key.clone(),
+                    height: ChainHeight::new(height - 2),
+                }],
+            }
+            .into();
+            vkv.execute(&revert).unwrap();
+        }
+
+        for height in 1..=last_height {
+            let get: Instruction = GetOneInstruction {
+                height: Some(ChainHeight::new(height)),
+                key: key.clone(),
+            }
+            .into();
+            match vkv.execute(&get).unwrap() {
+                Answer::DataAccess(DataAnswer::Read(DataReadAnswer::GetOneOk(answer))) => {
+                    let expected = ((height + 1) % 2) as u8;
+                    assert_eq!(answer.value, StoreValue::new(Some(vec![expected])))
+                }
+                answer => panic!("Unexpected answer {:?}", answer),
+            }
+        }
+    }
+
+    #[test]
+    fn test_verify_chain() {
+        let mut vkv = make_vkv("test_verify_chain");
+        let key = StoreKey::from("key");
+        for i in 0..10 {
+            let set: Instruction = SetManyInstruction {
+                targets: vec![SetTargetSpec {
+                    key: key.clone(),
+                    value: StoreValue::new(Some(vec![i])),
+                }],
+            }
+            .into();
+            vkv.execute(&set).unwrap();
+        }
+        let verification = vkv.verify_chain().unwrap();
+        assert_eq!(verification.height, ChainHeight::new(10));
+        assert_eq!(verification.broken_height, None);
+
+        // Rewrite the value set at height 4 behind the store's back
+        let mut record_key_bytes = vec![KVKeySigil::HeightToInstructionRecord as u8];
+        record_key_bytes.extend(ChainHeight::new(4).marshal());
+        let record_kvkey = KVKey::from(record_key_bytes);
+        let stored = vkv.kv_engine.get(&record_kvkey).unwrap().unwrap();
+        let mut record: InstructionRecord = deserialize(stored.as_bytes()).unwrap();
+        record.instruction = SetManyInstruction {
+            targets: vec![SetTargetSpec {
+                key: key.clone(),
+                value: StoreValue::new(Some(vec![0xff])),
+            }],
+        }
+        .into();
+        let tampered = KVValue::new(&serialize(&record).unwrap());
+        vkv.kv_engine.set(&record_kvkey, &tampered).unwrap();
+
+        let verification = vkv.verify_chain().unwrap();
+        assert_eq!(verification.broken_height, Some(ChainHeight::new(4)));
+    }
+
+    fn get_value(
+        vkv: &mut ImmuxDBVersionedKeyValueStore,
+        key: &StoreKey,
+        height: Option<ChainHeight>,
+    ) -> StoreValue {
+        let get: Instruction = GetOneInstruction {
+            height,
+            key: key.clone(),
+        }
+        .into();
+        match vkv.execute(&get).unwrap() {
+            Answer::DataAccess(DataAnswer::Read(DataReadAnswer::GetOneOk(answer))) => answer.value,
+            answer => panic!("Unexpected answer {:?}", answer),
+        }
+    }
+
+    fn test_fork_namespace(vkv: &mut ImmuxDBVersionedKeyValueStore, fork_name: &str) {
+        let source = vkv.kv_engine.read_namespace();
+        let key = StoreKey::from("key");
+        let set = |i: u8| -> Instruction {
+            SetManyInstruction {
+                targets: vec![SetTargetSpec {
+                    key: key.clone(),
+                    value: StoreValue::new(Some(vec![i])),
+                }],
+            }
+            .into()
+        };
+        for i in 1..=4 {
+            vkv.execute(&set(i)).unwrap();
+        }
+        let revert: Instruction = RevertManyInstruction {
+            targets: vec![RevertTargetSpec {
+                key: key.clone(),
+                height: ChainHeight::new(2),
+            }],
+        }
+        .into();
+        vkv.execute(&revert).unwrap();
+        for i in 6..=8 {
+            vkv.execute(&set(i)).unwrap();
+        }
+
+        let fork_height = ChainHeight::new(6);
+        let fork_namespace = StoreNamespace::new(fork_name.as_bytes());
+        let fork: Instruction = ForkNamespaceInstruction {
+            source: source.clone().into(),
+            at_height: fork_height,
+            new_namespace: fork_namespace.clone(),
+        }
+        .into();
+        let fork_head = match vkv.execute(&fork).unwrap() {
+            Answer::DBSystem(DBSystemAnswer::ForkNamespaceOk(answer)) => {
+                assert_eq!(answer.height, fork_height);
+                answer.head
+            }
+            answer => panic!("Unexpected answer {:?}", answer),
+        };
+        assert_eq!(vkv.kv_engine.read_namespace(), source);
+        assert_eq!(vkv.get_current_height(), ChainHeight::new(8));
+
+        let mut source_records = Vec::new();
+        for height in 1..=6 {
+            let mut record_key_bytes = vec![KVKeySigil::HeightToInstructionRecord as u8];
+            record_key_bytes.extend(ChainHeight::new(height).marshal());
+            source_records.push(KVKey::from(record_key_bytes));
+        }
+        let source_values: Vec<_> = source_records
+            .iter()
+            .map(|kvkey| vkv.kv_engine.get(kvkey).unwrap())
+            .collect();
+
+        vkv.kv_engine
+            .switch_namespace(&fork_namespace.clone().into())
+            .unwrap();
+        assert_eq!(vkv.get_current_height(), fork_height);
+        for (kvkey, source_value) in source_records.iter().zip(source_values.iter()) {
+            assert_eq!(&vkv.kv_engine.get(kvkey).unwrap(), source_value);
+        }
+        let verification = vkv.verify_chain().unwrap();
+        assert_eq!(verification.broken_height, None);
+        assert_eq!(verification.head, fork_head);
+        assert_eq!(get_value(vkv, &key, None), StoreValue::new(Some(vec![6])));
+        assert_eq!(
+            get_value(vkv, &key, Some(ChainHeight::new(5))),
+            StoreValue::new(Some(vec![2]))
+        );
+
+        // The chains move on independently after the fork
+        vkv.execute(&set(0xff)).unwrap();
+        vkv.kv_engine.switch_namespace(&source).unwrap();
+        assert_eq!(get_value(vkv, &key, None), StoreValue::new(Some(vec![8])));
+        assert_eq!(
+            get_value(vkv, &key, Some(ChainHeight::new(7))),
+            StoreValue::new(Some(vec![7]))
+        );
+
+        // Forking onto a chain with history is refused
+        match vkv.execute(&fork) {
+            Err(ImmuxError::VKV(VkvError::ForkTargetNotEmpty)) => {}
+            result => panic!("Unexpected result {:?}", result),
+        }
+        let future_fork: Instruction = ForkNamespaceInstruction {
+            source: source.clone().into(),
+            at_height: ChainHeight::new(9),
+            new_namespace: StoreNamespace::new(b"unused"),
+        }
+        .into();
+        match vkv.execute(&future_fork) {
+            Err(ImmuxError::VKV(VkvError::TryingToForkFromFuture)) => {}
+            result => panic!("Unexpected result {:?}", result),
+        }
+        assert_eq!(vkv.kv_engine.read_namespace(), source);
+    }
+
+    #[test]
+    fn test_fork_namespace_rocks() {
+        let mut vkv = make_vkv("test_fork_namespace_rocks");
+        reset_db_dir("/tmp/vkv_test/test_fork_namespace_rocks_fork").unwrap();
+        test_fork_namespace(&mut vkv, "test_fork_namespace_rocks_fork");
+    }
+
+    #[test]
+    fn test_fork_namespace_hashmap() {
+        let ns = StoreNamespace::new(b"test_fork_namespace_hashmap");
+        let mut vkv =
+            ImmuxDBVersionedKeyValueStore::new(&KeyValueEngine::HashMap, "", &ns).unwrap();
+        test_fork_namespace(&mut vkv, "fork");
+    }
+
+    #[test]
+    fn test_merge_namespace() {
+        let main = StoreNamespace::new(b"main");
+        let feature = StoreNamespace::new(b"feature");
+        let mut vkv =
+            ImmuxDBVersionedKeyValueStore::new(&KeyValueEngine::HashMap, "", &main).unwrap();
+        let [a, b, c, d] = [
+            StoreKey::from("a"),
+            StoreKey::from("b"),
+            StoreKey::from("c"),
+            StoreKey::from("d"),
+        ];
+        let set = |key: &StoreKey, byte: u8| -> Instruction {
+            SetManyInstruction {
+                targets: vec![SetTargetSpec {
+                    key: key.clone(),
+                    value: StoreValue::new(Some(vec![byte])),
+                }],
+            }
+            .into()
+        };
+        let value = |byte: u8| StoreValue::new(Some(vec![byte]));
+        let merge = |resolutions: Vec<MergeResolution>, default_strategy| -> Instruction {
+            MergeNamespaceInstruction {
+                target: main.clone(),
+                source: feature.clone(),
+                resolutions,
+                default_strategy,
+                dry_run: false,
+                keys: None,
+                index_targets: vec![],
+            }
+            .into()
+        };
+        let load_record = |vkv: &ImmuxDBVersionedKeyValueStore, height: u64| {
+            let mut record_key_bytes = vec![KVKeySigil::HeightToInstructionRecord as u8];
+            record_key_bytes.extend(ChainHeight::new(height).marshal());
+            let stored = vkv.kv_engine.get(&KVKey::from(record_key_bytes)).unwrap();
+            deserialize::<InstructionRecord>(stored.unwrap().as_bytes()).unwrap()
+        };
+
+        for key in &[&a, &b, &c] {
+            vkv.execute(&set(key, 1)).unwrap();
+        }
+        let fork: Instruction = ForkNamespaceInstruction {
+            source: main.clone(),
+            at_height: ChainHeight::new(3),
+            new_namespace: feature.clone(),
+        }
+        .into();
+        vkv.execute(&fork).unwrap();
+        vkv.execute(&set(&b, 2)).unwrap();
+        vkv.execute(&set(&c, 5)).unwrap();
+        vkv.kv_engine
+            .switch_namespace(&feature.clone().into())
+            .unwrap();
+        vkv.execute(&set(&a, 7)).unwrap();
+        vkv.execute(&set(&c, 9)).unwrap();
+        vkv.execute(&set(&d, 3)).unwrap();
+        vkv.kv_engine
+            .switch_namespace(&main.clone().into())
+            .unwrap();
+
+        // c changed on both sides, so nothing is merged unless it is resolved
+        match vkv.execute(&merge(vec![], MergeStrategy::Fail)) {
+            Err(ImmuxError::VKV(VkvError::MergeConflict(keys))) => {
+                assert_eq!(keys, vec![c.clone()])
+            }
+            result => panic!("Unexpected result {:?}", result),
+        }
+        assert_eq!(vkv.get_current_height(), ChainHeight::new(5));
+
+        let resolutions = vec![MergeResolution {
+            key: c.clone(),
+            strategy: MergeStrategy::Theirs,
+        }];
+        match vkv
+            .execute(&merge(resolutions, MergeStrategy::Fail))
+            .unwrap()
+        {
+            Answer::DBSystem(DBSystemAnswer::MergeNamespaceOk(answer)) => {
+                assert_eq!(answer.base_height, ChainHeight::new(3));
+                assert_eq!(answer.height, ChainHeight::new(8));
+                assert_eq!(
+                    answer.merged_heights,
+                    vec![
+                        ChainHeight::new(4),
+                        ChainHeight::new(5),
+                        ChainHeight::new(6)
+                    ]
+                );
+                assert_eq!(
+                    answer.conflicts,
+                    vec![MergeConflict {
+                        key: c.clone(),
+                        strategy: MergeStrategy::Theirs,
+                    }]
+                );
+            }
+            answer => panic!("Unexpected answer {:?}", answer),
+        }
+        assert_eq!(vkv.kv_engine.read_namespace(), main.clone().into());
+        assert_eq!(get_value(&mut vkv, &a, None), value(7));
+        assert_eq!(get_value(&mut vkv, &b, None), value(2));
+        assert_eq!(get_value(&mut vkv, &c, None), value(9));
+        assert_eq!(get_value(&mut vkv, &d, None), value(3));
+        assert_eq!(
+            load_record(&vkv, 8).merged_from,
+            Some(MergeOrigin {
+                namespace: feature.clone(),
+                height: ChainHeight::new(6),
+            })
+        );
+        assert_eq!(vkv.verify_chain().unwrap().broken_height, None);
+
+        // Merging again only brings over what the source did since the last merge
+        vkv.execute(&set(&c, 10)).unwrap();
+        vkv.kv_engine
+            .switch_namespace(&feature.clone().into())
+            .unwrap();
+        vkv.execute(&set(&a, 8)).unwrap();
+        vkv.execute(&set(&c, 11)).unwrap();
+        vkv.kv_engine
+            .switch_namespace(&main.clone().into())
+            .unwrap();
+        match vkv.execute(&merge(vec![], MergeStrategy::Ours)).unwrap() {
+            Answer::DBSystem(DBSystemAnswer::MergeNamespaceOk(answer)) => {
+                assert_eq!(
+                    answer.merged_heights,
+                    vec![ChainHeight::new(7), ChainHeight::new(8)]
+                );
+                assert_eq!(
+                    answer.conflicts,
+                    vec![MergeConflict {
+                        key: c.clone(),
+                        strategy: MergeStrategy::Ours,
+                    }]
+                );
+            }
+            answer => panic!("Unexpected answer {:?}", answer),
+        }
+        assert_eq!(get_value(&mut vkv, &a, None), value(8));
+        assert_eq!(get_value(&mut vkv, &c, None), value(10));
+    }
+
+    #[test]
+    fn test_diff() {
+        let mut vkv = ImmuxDBVersionedKeyValueStore::new(
+            &KeyValueEngine::HashMap,
+            "",
+            &StoreNamespace::new(b"test_diff"),
+        )
+        .unwrap();
+        let [a, b, c] = [
+            StoreKey::from("a"),
+            StoreKey::from("b"),
+            StoreKey::from("c"),
+        ];
+        let value = |byte: u8| StoreValue::new(Some(vec![byte]));
+        for (key, byte) in &[(&a, 1), (&b, 1), (&a, 2), (&c, 1), (&b, 2), (&b, 1)] {
+            let set: Instruction = SetManyInstruction {
+                targets: vec![SetTargetSpec {
+                    key: (*key).clone(),
+                    value: value(*byte),
+                }],
+            }
+            .into();
+            vkv.execute(&set).unwrap();
+        }
+        let diff = |from_height: u64, to_height: Option<u64>, scope: Option<&StoreKey>| {
+            let instruction: Instruction = DiffInstruction {
+                from_height: ChainHeight::new(from_height),
+                to_height: to_height.map(ChainHeight::new),
+                scope: scope.cloned(),
+            }
+            .into();
+            instruction
+        };
+
+        // b was changed and changed back, so only a and c differ
+        match vkv.execute(&diff(2, None, None)).unwrap() {
+            Answer::DataAccess(DataAnswer::Read(DataReadAnswer::DiffOk(answer))) => {
+                assert_eq!(answer.to_height, ChainHeight::new(6));
+                let changes: Vec<(StoreKey, StoreValue, StoreValue)> = answer
+                    .changes
+                    .into_iter()
+                    .map(|change| (change.key, change.before, change.after))
+                    .collect();
+                assert_eq!(
+                    changes,
+                    vec![
+                        (a.clone(), value(1), value(2)),
+                        (c.clone(), StoreValue::new(None), value(1)),
+                    ]
+                );
+            }
+            answer => panic!("Unexpected answer {:?}", answer),
+        }
+
+        match vkv.execute(&diff(0, Some(5), Some(&b))).unwrap() {
+            Answer::DataAccess(DataAnswer::Read(DataReadAnswer::DiffOk(answer))) => {
+                assert_eq!(answer.changes.len(), 1);
+                assert_eq!(answer.changes[0].key, b);
+                assert_eq!(answer.changes[0].before, StoreValue::new(None));
+                assert_eq!(answer.changes[0].after, value(2));
+            }
+            answer => panic!("Unexpected answer {:?}", answer),
+        }
+
+        for (from_height, to_height) in &[(4, Some(3)), (1, Some(7))] {
+            match vkv.execute(&diff(*from_height, *to_height, None)) {
+                Err(ImmuxError::VKV(VkvError::InvalidDiffRange(_, _))) => {}
+                result => panic!("Unexpected result {:?}", result),
+            }
+        }
+    }
+
+    #[test]
+    fn test_get_changes() {
+        let mut vkv = ImmuxDBVersionedKeyValueStore::new(
+            &KeyValueEngine::HashMap,
+            "",
+            &StoreNamespace::new(b"test_get_changes"),
+        )
+        .unwrap();
+        let [a, b] = [StoreKey::from("a"), StoreKey::from("b")];
+        let value = |byte: u8| StoreValue::new(Some(vec![byte]));
+        let set = |targets: Vec<(&StoreKey, u8)>| -> Instruction {
+            SetManyInstruction {
+                targets: targets
+                    .into_iter()
+                    .map(|(key, byte)| SetTargetSpec {
+                        key: key.clone(),
+                        value: value(byte),
+                    })
+                    .collect(),
+            }
+            .into()
+        };
+        vkv.execute(&set(vec![(&a, 1), (&b, 1)])).unwrap();
+        vkv.execute(&set(vec![(&a, 2)])).unwrap();
+        let revert: Instruction = RevertManyInstruction {
+            targets: vec![RevertTargetSpec {
+                key: a.clone(),
+                height: ChainHeight::new(1),
+            }],
+        }
+        .into();
+        vkv.execute(&revert).unwrap();
+
+        let get_changes = |after_height: u64, limit: usize| -> Instruction {
+            GetChangesInstruction {
+                after_height: ChainHeight::new(after_height),
+                limit,
+            }
+            .into()
+        };
+        match vkv.execute(&get_changes(0, 2)).unwrap() {
+            Answer::DataAccess(DataAnswer::Read(DataReadAnswer::GetChangesOk(answer))) => {
+                assert_eq!(answer.height, ChainHeight::new(2));
+                assert_eq!(answer.changes.len(), 2);
+                assert_eq!(answer.changes[0].height, ChainHeight::new(1));
+                assert_eq!(answer.changes[0].targets.len(), 2);
+                assert_eq!(answer.changes[1].targets[0].value, value(2));
+            }
+            answer => panic!("Unexpected answer {:?}", answer),
+        }
+
+        // Reverts are reported with the values they restored
+        match vkv.execute(&get_changes(2, 10)).unwrap() {
+            Answer::DataAccess(DataAnswer::Read(DataReadAnswer::GetChangesOk(answer))) => {
+                assert_eq!(answer.height, ChainHeight::new(3));
+                assert_eq!(answer.changes.len(), 1);
+                assert_eq!(answer.changes[0].targets[0].key, a);
+                assert_eq!(answer.changes[0].targets[0].value, value(1));
+            }
+            answer => panic!("Unexpected answer {:?}", answer),
+        }
+
+        match vkv.execute(&get_changes(3, 10)).unwrap() {
+            Answer::DataAccess(DataAnswer::Read(DataReadAnswer::GetChangesOk(answer))) => {
+                assert_eq!(answer.height, ChainHeight::new(3));
+                assert!(answer.changes.is_empty());
+            }
+            answer => panic!("Unexpected answer {:?}", answer),
+        }
+    }
+
+    #[test]
+    fn test_get_height_at_time() {
+        let mut vkv = ImmuxDBVersionedKeyValueStore::new(
+            &KeyValueEngine::HashMap,
+            "",
+            &StoreNamespace::new(b"test_get_height_at_time"),
+        )
+        .unwrap();
+        for byte in 1..=3 {
+            let set: Instruction = SetManyInstruction {
+                targets: vec![SetTargetSpec {
+                    key: StoreKey::from("key"),
+                    value: StoreValue::new(Some(vec![byte])),
+                }],
+            }
+            .into();
+            vkv.execute(&set).unwrap();
+        }
+        let get_changes: Instruction = GetChangesInstruction {
+            after_height: ChainHeight::new(0),
+            limit: 10,
+        }
+        .into();
+        let times: Vec<u128> = match vkv.execute(&get_changes).unwrap() {
+            Answer::DataAccess(DataAnswer::Read(DataReadAnswer::GetChangesOk(answer))) => answer
+                .changes
+                .iter()
+                .map(|change| change.sys_time)
+                .collect(),
+            answer => panic!("Unexpected answer {:?}", answer),
+        };
+        let mut height_at = |time: u128| -> u64 {
+            let get_height: Instruction = GetHeightAtTimeInstruction { time }.into();
+            match vkv.execute(&get_height).unwrap() {
+                Answer::DataAccess(DataAnswer::Read(DataReadAnswer::GetHeightAtTimeOk(answer))) => {
+                    answer.height.as_u64()
+                }
+                answer => panic!("Unexpected answer {:?}", answer),
+            }
+        };
+        assert_eq!(height_at(0), 0);
+        assert_eq!(height_at(times[0] - 1), 0);
+        // Records written within the same microsecond resolve to the last of them
+        for (index, time) in times.iter().enumerate() {
+            assert!(height_at(*time) >= index as u64 + 1);
+        }
+        assert_eq!(height_at(times[2]), 3);
+        assert_eq!(height_at(u128::max_value()), 3);
+
+        let get_height: Instruction = GetHeightInstruction {}.into();
+        match vkv.execute(&get_height).unwrap() {
+            Answer::DataAccess(DataAnswer::Read(DataReadAnswer::GetHeightOk(answer))) => {
+                assert_eq!(answer.height.as_u64(), 3)
+            }
+            answer => panic!("Unexpected answer {:?}", answer),
+        }
+    }
+
+    #[test]
+    fn test_revert_record() {
+        let mut vkv = ImmuxDBVersionedKeyValueStore::new(
+            &KeyValueEngine::HashMap,
+            "",
+            &StoreNamespace::new(b"test_revert_record"),
+        )
+        .unwrap();
+        let [a, b, c] = [
+            StoreKey::from("a"),
+            StoreKey::from("b"),
+            StoreKey::from("c"),
+        ];
+        let value = |byte: u8| StoreValue::new(Some(vec![byte]));
+        let set = |targets: Vec<(&StoreKey, u8)>| -> Instruction {
+            SetManyInstruction {
+                targets: targets
+                    .into_iter()
+                    .map(|(key, byte)| SetTargetSpec {
+                        key: key.clone(),
+                        value: value(byte),
+                    })
+                    .collect(),
+            }
+            .into()
+        };
+        vkv.execute(&set(vec![(&a, 1)])).unwrap();
+        vkv.execute(&set(vec![(&a, 2), (&b, 2), (&c, 2)])).unwrap();
+        vkv.execute(&set(vec![(&b, 3)])).unwrap();
+
+        let revert = |height: u64| -> Instruction {
+            RevertInstruction {
+                height: ChainHeight::new(height),
+            }
+            .into()
+        };
+        match vkv.execute(&revert(2)).unwrap() {
+            Answer::DataAccess(DataAnswer::Write(DataWriteAnswer::RevertRecordOk(answer))) => {
+                assert_eq!(answer.reverted_keys, vec![a.clone(), c.clone()]);
+                assert_eq!(answer.conflicts, vec![b.clone()]);
+            }
+            answer => panic!("Unexpected answer {:?}", answer),
+        }
+        assert_eq!(vkv.get_current_height(), ChainHeight::new(4));
+        assert_eq!(get_value(&mut vkv, &a, None), value(1));
+        assert_eq!(get_value(&mut vkv, &b, None), value(3));
+        assert_eq!(get_value(&mut vkv, &c, None), StoreValue::new(None));
+        assert_eq!(get_value(&mut vkv, &a, Some(ChainHeight::new(3))), value(2));
+        assert_eq!(vkv.verify_chain().unwrap().broken_height, None);
+
+        // Every key the record wrote has been written again since
+        match vkv.execute(&revert(2)).unwrap() {
+            Answer::DataAccess(DataAnswer::Write(DataWriteAnswer::RevertRecordOk(answer))) => {
+                assert!(answer.reverted_keys.is_empty());
+                assert_eq!(answer.conflicts.len(), 3);
+            }
+            answer => panic!("Unexpected answer {:?}", answer),
+        }
+        assert_eq!(vkv.get_current_height(), ChainHeight::new(4));
+
+        for height in &[0, 5] {
+            match vkv.execute(&revert(*height)) {
+                Err(ImmuxError::VKV(VkvError::InvalidRevertHeight(_))) => {}
+                result => panic!("Unexpected result {:?}", result),
+            }
+        }
+    }
+
+    #[test]
+    fn test_revert_keys() {
+        let mut vkv = ImmuxDBVersionedKeyValueStore::new(
+            &KeyValueEngine::HashMap,
+            "",
+            &StoreNamespace::new(b"test_revert_keys"),
+        )
+        .unwrap();
+        let [a, b, index] = [
+            StoreKey::from("a"),
+            StoreKey::from("b"),
+            StoreKey::from("index"),
+        ];
+        let value = |byte: u8| StoreValue::new(Some(vec![byte]));
+        let set = |targets: Vec<(&StoreKey, u8)>| -> Instruction {
+            SetManyInstruction {
+                targets: targets
+                    .into_iter()
+                    .map(|(key, byte)| SetTargetSpec {
+                        key: key.clone(),
+                        value: value(byte),
+                    })
+                    .collect(),
+            }
+            .into()
+        };
+        vkv.execute(&set(vec![(&a, 1), (&b, 1), (&index, 1)]))
+            .unwrap();
+        vkv.execute(&set(vec![(&a, 2), (&b, 2), (&index, 2)]))
+            .unwrap();
+        vkv.execute(&set(vec![(&b, 3)])).unwrap();
+
+        let revert_keys = |keys: Vec<&StoreKey>| -> Instruction {
+            RevertKeysInstruction {
+                height: ChainHeight::new(2),
+                keys: keys.into_iter().cloned().collect(),
+                index_targets: vec![SetTargetSpec {
+                    key: index.clone(),
+                    value: value(9),
+                }],
+            }
+            .into()
+        };
+
+        // A listed key written again since fails the revert, index targets included
+        match vkv.execute(&revert_keys(vec![&a, &b])) {
+            Err(ImmuxError::VKV(VkvError::RevertConflict(key))) => assert_eq!(key, b),
+            result => panic!("Unexpected result {:?}", result),
+        }
+        match vkv.execute(&revert_keys(vec![&StoreKey::from("c")])) {
+            Err(ImmuxError::VKV(VkvError::KeyNotInRecord(height, _))) => {
+                assert_eq!(height, ChainHeight::new(2))
+            }
+            result => panic!("Unexpected result {:?}", result),
+        }
+        assert_eq!(vkv.get_current_height(), ChainHeight::new(3));
+        assert_eq!(get_value(&mut vkv, &a, None), value(2));
+        assert_eq!(get_value(&mut vkv, &index, None), value(2));
+
+        match vkv.execute(&revert_keys(vec![&a])).unwrap() {
+            Answer::DataAccess(DataAnswer::Write(DataWriteAnswer::RevertRecordOk(answer))) => {
+                assert_eq!(answer.reverted_keys, vec![a.clone()]);
+                assert!(answer.conflicts.is_empty());
+            }
+            answer => panic!("Unexpected answer {:?}", answer),
+        }
+        assert_eq!(vkv.get_current_height(), ChainHeight::new(4));
+        assert_eq!(get_value(&mut vkv, &a, None), value(1));
+        assert_eq!(get_value(&mut vkv, &b, None), value(3));
+        assert_eq!(get_value(&mut vkv, &index, None), value(9));
+        assert_eq!(vkv.verify_chain().unwrap().broken_height, None);
+    }
+
+    #[test]
+    fn test_tags() {
+        let mut vkv = ImmuxDBVersionedKeyValueStore::new(
+            &KeyValueEngine::HashMap,
+            "",
+            &StoreNamespace::new(b"test_tags"),
+        )
+        .unwrap();
+        for byte in 1..=3 {
+            let set: Instruction = SetManyInstruction {
+                targets: vec![SetTargetSpec {
+                    key: StoreKey::from("key"),
+                    value: StoreValue::new(Some(vec![byte])),
+                }],
+            }
+            .into();
+            vkv.execute(&set).unwrap();
+        }
+        let set_tag = |name: &str, height: Option<u64>| -> Instruction {
+            SetTagInstruction {
+                name: String::from(name),
+                height: height.map(ChainHeight::new),
+            }
+            .into()
+        };
+        let read_tags = |vkv: &mut ImmuxDBVersionedKeyValueStore| -> Vec<ChainTag> {
+            match vkv.execute(&ReadTagsInstruction {}.into()).unwrap() {
+                Answer::DBSystem(DBSystemAnswer::ReadTagsOk(answer)) => answer.tags,
+                answer => panic!("Unexpected answer {:?}", answer),
+            }
+        };
+        vkv.execute(&set_tag("release", None)).unwrap();
+        vkv.execute(&set_tag("before-migration", Some(1))).unwrap();
+        assert_eq!(vkv.get_current_height(), ChainHeight::new(3));
+        assert_eq!(
+            read_tags(&mut vkv),
+            vec![
+                ChainTag {
+                    name: String::from("before-migration"),
+                    height: ChainHeight::new(1),
+                },
+                ChainTag {
+                    name: String::from("release"),
+                    height: ChainHeight::new(3),
+                },
+            ]
+        );
+
+        match vkv.execute(&set_tag("release", Some(2))) {
+            Err(ImmuxError::VKV(VkvError::TagExists(_))) => {}
+            result => panic!("Unexpected result {:?}", result),
+        }
+        match vkv.execute(&set_tag("future", Some(4))) {
+            Err(ImmuxError::VKV(VkvError::TryingToTagFuture(_))) => {}
+            result => panic!("Unexpected result {:?}", result),
+        }
+
+        let remove_tag: Instruction = RemoveTagInstruction {
+            name: String::from("release"),
+        }
+        .into();
+        match vkv.execute(&remove_tag).unwrap() {
+            Answer::DBSystem(DBSystemAnswer::RemoveTagOk(answer)) => {
+                assert_eq!(answer.tag.height, ChainHeight::new(3));
+            }
+            answer => panic!("Unexpected answer {:?}", answer),
+        }
+        assert_eq!(read_tags(&mut vkv).len(), 1);
+        match vkv.execute(&remove_tag) {
+            Err(ImmuxError::VKV(VkvError::MissingTag(_))) => {}
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+
+    #[test]
+    fn test_record_meta() {
+        let mut vkv = make_vkv("test_record_meta");
+        let set: Instruction = SetManyInstruction {
+            targets: vec![SetTargetSpec {
+                key: StoreKey::from("key"),
+                value: StoreValue::new(Some(vec![1])),
+            }],
+        }
+        .into();
+        let set_meta = |author: Option<&str>| -> Instruction {
+            SetRecordMetaInstruction {
+                meta: RecordMeta {
+                    author: author.map(String::from),
+                    message: author.map(|_| String::from("message")),
+                    client_id: None,
+                },
+            }
+            .into()
+        };
+        vkv.execute(&set_meta(Some("alice"))).unwrap();
+        vkv.execute(&set).unwrap();
+        vkv.execute(&set_meta(None)).unwrap();
+        vkv.execute(&set).unwrap();
+
+        // Forks replay records with their own metadata, then put back the metadata set before
+        vkv.execute(&set_meta(Some("bob"))).unwrap();
+        reset_db_dir("/tmp/vkv_test/test_record_meta_fork").unwrap();
+        let fork: Instruction = ForkNamespaceInstruction {
+            source: StoreNamespace::new(b"test_record_meta"),
+            at_height: ChainHeight::new(2),
+            new_namespace: StoreNamespace::new(b"test_record_meta_fork"),
+        }
+        .into();
+        vkv.execute(&fork).unwrap();
+        vkv.execute(&set).unwrap();
+
+        let get_changes: Instruction = GetChangesInstruction {
+            after_height: ChainHeight::new(0),
+            limit: 10,
+        }
+        .into();
+        match vkv.execute(&get_changes).unwrap() {
+            Answer::DataAccess(DataAnswer::Read(DataReadAnswer::GetChangesOk(answer))) => {
+                let authors: Vec<Option<String>> = answer
+                    .changes
+                    .into_iter()
+                    .map(|change| change.meta.author)
+                    .collect();
+                assert_eq!(
+                    authors,
+                    vec![Some(String::from("alice")), None, Some(String::from("bob"))]
+                );
+            }
+            answer => panic!("Unexpected answer {:?}", answer),
+        }
+        assert_eq!(vkv.verify_chain().unwrap().broken_height, None);
+    }
+
+    fn set_value(vkv: &mut ImmuxDBVersionedKeyValueStore, keys: &[&StoreKey], byte: u8) {
+        let set: Instruction = SetManyInstruction {
+            targets: keys
+                .iter()
+                .map(|key| SetTargetSpec {
+                    key: (*key).to_owned(),
+                    value: StoreValue::new(Some(vec![byte])),
+                })
+                .collect(),
+        }
+        .into();
+        vkv.execute(&set).unwrap();
+    }
+
+    fn get_value_at(
+        vkv: &mut ImmuxDBVersionedKeyValueStore,
+        key: &StoreKey,
+        height: u64,
+    ) -> Result<Option<Vec<u8>>, ImmuxError> {
+        let get_one: Instruction = GetOneInstruction {
+            height: Some(ChainHeight::new(height)),
+            key: key.to_owned(),
+        }
+        .into();
+        match vkv.execute(&get_one)? {
+            Answer::DataAccess(DataAnswer::Read(DataReadAnswer::GetOneOk(answer))) => {
+                Ok(answer.value.inner().to_owned())
+            }
+            answer => panic!("Unexpected answer {:?}", answer),
+        }
+    }
+
+    fn compact(vkv: &mut ImmuxDBVersionedKeyValueStore) -> CompactOkAnswer {
+        match vkv.execute(&CompactInstruction {}.into()).unwrap() {
+            Answer::DBSystem(DBSystemAnswer::CompactOk(answer)) => answer,
+            answer => panic!("Unexpected answer {:?}", answer),
+        }
+    }
+
+    fn assert_pruned<T: std::fmt::Debug>(result: Result<T, ImmuxError>, height: u64, below: u64) {
+        match result {
+            Err(ImmuxError::VKV(VkvError::HeightPruned(requested, pruned))) => {
+                assert_eq!(requested, ChainHeight::new(height));
+                assert_eq!(pruned, ChainHeight::new(below));
+            }
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+
+    #[test]
+    fn test_chain_compaction() {
+        let mut vkv = make_vkv("test_chain_compaction");
+        let key_a = StoreKey::from("a");
+        let key_b = StoreKey::from("b");
+        for byte in 1..=6 {
+            if byte == 2 {
+                set_value(&mut vkv, &[&key_a, &key_b], byte);
+            } else {
+                set_value(&mut vkv, &[&key_a], byte);
+            }
+        }
+        let set_retention = |policy: Option<RetentionPolicy>| -> Instruction {
+            SetRetentionInstruction {
+                scope: None,
+                policy,
+            }
+            .into()
+        };
+        match vkv.execute(&set_retention(Some(RetentionPolicy::KeepHeights(0)))) {
+            Err(ImmuxError::VKV(VkvError::InvalidRetention(_))) => {}
+            result => panic!("Unexpected result {:?}", result),
+        }
+        vkv.execute(&set_retention(Some(RetentionPolicy::KeepHeights(3))))
+            .unwrap();
+
+        let answer = compact(&mut vkv);
+        assert_eq!(answer.markers.len(), 1);
+        assert_eq!(answer.markers[0].scope, None);
+        assert_eq!(answer.markers[0].height, ChainHeight::new(4));
+        assert_eq!(answer.pruned_records, 3);
+        assert_eq!(answer.pruned_versions, 3);
+
+        // States from the marker up are intact, and so is what can still be answered below it
+        assert_eq!(get_value_at(&mut vkv, &key_a, 4).unwrap(), Some(vec![4]));
+        assert_eq!(get_value_at(&mut vkv, &key_a, 6).unwrap(), Some(vec![6]));
+        assert_eq!(get_value_at(&mut vkv, &key_b, 3).unwrap(), Some(vec![2]));
+        assert_pruned(get_value_at(&mut vkv, &key_a, 3), 3, 4);
+        assert_pruned(get_value_at(&mut vkv, &key_b, 1), 1, 4);
+
+        let get_changes: Instruction = GetChangesInstruction {
+            after_height: ChainHeight::new(0),
+            limit: 10,
+        }
+        .into();
+        assert_pruned(vkv.execute(&get_changes), 1, 4);
+        let diff: Instruction = DiffInstruction {
+            from_height: ChainHeight::new(2),
+            to_height: None,
+            scope: None,
+        }
+        .into();
+        assert_pruned(vkv.execute(&diff), 2, 4);
+        let revert_all: Instruction = RevertAllInstruction {
+            target_height: ChainHeight::new(2),
+        }
+        .into();
+        assert_pruned(vkv.execute(&revert_all), 2, 4);
+        let revert: Instruction = RevertInstruction {
+            height: ChainHeight::new(3),
+        }
+        .into();
+        assert_pruned(vkv.execute(&revert), 3, 4);
+
+        // The marker is recorded at a new height, under the hash chain
+        assert_eq!(vkv.get_current_height(), ChainHeight::new(7));
+        let get_changes: Instruction = GetChangesInstruction {
+            after_height: ChainHeight::new(6),
+            limit: 1,
+        }
+        .into();
+        match vkv.execute(&get_changes).unwrap() {
+            Answer::DataAccess(DataAnswer::Read(DataReadAnswer::GetChangesOk(answer))) => {
+                assert_eq!(answer.changes.len(), 1);
+                assert!(answer.changes[0].targets.is_empty());
+            }
+            answer => panic!("Unexpected answer {:?}", answer),
+        }
+        let verification = vkv.verify_chain().unwrap();
+        assert_eq!(verification.broken_height, None);
+
+        // Compacting again only drops what the grown chain no longer keeps
+        set_value(&mut vkv, &[&key_a], 8);
+        let answer = compact(&mut vkv);
+        assert_eq!(answer.markers[0].height, ChainHeight::new(6));
+        assert_eq!(answer.pruned_records, 2);
+        assert_eq!(answer.pruned_versions, 2);
+        assert_eq!(vkv.get_current_height(), ChainHeight::new(9));
+        assert_eq!(vkv.verify_chain().unwrap().broken_height, None);
+
+        // Without a rule, compaction keeps the markers where they are
+        vkv.execute(&set_retention(None)).unwrap();
+        set_value(&mut vkv, &[&key_a], 10);
+        let answer = compact(&mut vkv);
+        assert_eq!(answer.markers[0].height, ChainHeight::new(6));
+        assert_eq!(answer.pruned_records, 0);
+        assert_eq!(vkv.get_current_height(), ChainHeight::new(10));
+    }
+
+    #[test]
+    fn test_open_compacted_chain() {
+        let ns_str = "test_open_compacted_chain";
+        let mut vkv = make_vkv(ns_str);
+        let key = StoreKey::from("key");
+        for byte in 1..=5 {
+            set_value(&mut vkv, &[&key], byte);
+        }
+        let set_retention: Instruction = SetRetentionInstruction {
+            scope: None,
+            policy: Some(RetentionPolicy::KeepHeights(2)),
+        }
+        .into();
+        vkv.execute(&set_retention).unwrap();
+        compact(&mut vkv);
+        let compacted = vkv.verify_chain().unwrap();
+        assert_eq!(compacted.broken_height, None);
+
+        // Switching to the chain and opening it again are reads, which leave its records alone
+        let switch_namespace = |namespace: &str| -> Instruction {
+            Instruction::DBSystem(DBSystemInstruction::SwitchNamespace(
+                SwitchNamespaceInstruction {
+                    new_namespace: StoreNamespace::new(namespace.as_bytes()),
+                },
+            ))
+        };
+        vkv.execute(&switch_namespace("test_open_compacted_chain_other"))
+            .unwrap();
+        vkv.execute(&switch_namespace(ns_str)).unwrap();
+        let switched = vkv.verify_chain().unwrap();
+        assert_eq!(switched.height, compacted.height);
+        assert_eq!(switched.head, compacted.head);
+        drop(vkv);
+
+        let ns = StoreNamespace::new(ns_str.as_bytes());
+        let vkv = ImmuxDBVersionedKeyValueStore::new(&KeyValueEngine::Rocks, "/tmp/vkv_test/", &ns)
+            .unwrap();
+        let reopened = vkv.verify_chain().unwrap();
+        assert_eq!(reopened.height, compacted.height);
+        assert_eq!(reopened.head, compacted.head);
+        assert_eq!(reopened.broken_height, None);
+    }
+
+    #[test]
+    fn test_grouping_compaction() {
+        let mut vkv = make_vkv("test_grouping_compaction");
+        let pruned_grouping = GroupingLabel::from("logs");
+        let pruned_key = StoreKey::build(&pruned_grouping, UnitId::new(1));
+        let kept_key = StoreKey::build(&GroupingLabel::from("accounts"), UnitId::new(1));
+        for byte in 1..=4 {
+            set_value(&mut vkv, &[&pruned_key, &kept_key], byte);
+        }
+        let set_retention: Instruction = SetRetentionInstruction {
+            scope: Some(StoreKey::new(&pruned_grouping.marshal())),
+            policy: Some(RetentionPolicy::KeepHeights(1)),
+        }
+        .into();
+        vkv.execute(&set_retention).unwrap();
+
+        let answer = compact(&mut vkv);
+        assert_eq!(answer.markers.len(), 1);
+        assert_eq!(answer.markers[0].height, ChainHeight::new(4));
+        assert_eq!(answer.pruned_records, 0);
+        assert_eq!(answer.pruned_versions, 3);
+
+        assert_eq!(
+            get_value_at(&mut vkv, &pruned_key, 4).unwrap(),
+            Some(vec![4])
+        );
+        assert_pruned(get_value_at(&mut vkv, &pruned_key, 2), 2, 4);
+        assert_eq!(get_value_at(&mut vkv, &kept_key, 2).unwrap(), Some(vec![2]));
+
+        // Records are kept, but the versions of the pruned grouping they wrote are not
+        let get_changes: Instruction = GetChangesInstruction {
+            after_height: ChainHeight::new(0),
+            limit: 10,
+        }
+        .into();
+        assert_pruned(vkv.execute(&get_changes), 1, 4);
+        assert_eq!(vkv.verify_chain().unwrap().broken_height, None);
+
+        match vkv.execute(&ReadRetentionInstruction {}.into()).unwrap() {
+            Answer::DBSystem(DBSystemAnswer::ReadRetentionOk(answer)) => {
+                assert_eq!(answer.rules.len(), 1);
+                assert_eq!(answer.rules[0].policy, RetentionPolicy::KeepHeights(1));
+                assert_eq!(answer.markers.len(), 1);
+                assert_eq!(
+                    answer.markers[0].scope,
+                    Some(StoreKey::new(&pruned_grouping.marshal()))
+                );
+            }
+            answer => panic!("Unexpected answer {:?}", answer),
+        }
+    }
+
+    #[test]
+    fn test_paged_journal() {
+        let mut vkv = make_vkv("test_paged_journal");
+        let key = StoreKey::from("hot");
+        let other_key = StoreKey::from("cold");
+        let value_at = |height: u64| StoreValue::new(Some((height as u16).to_be_bytes().to_vec()));
+        let end = 300;
+        for height in 1..=end {
+            let mut targets = vec![SetTargetSpec {
+                key: key.clone(),
+                value: value_at(height),
+            }];
+            if height == 1 {
+                targets.push(SetTargetSpec {
+                    key: other_key.clone(),
+                    value: value_at(height),
+                });
+            }
+            vkv.execute(&SetManyInstruction { targets }.into()).unwrap();
+        }
+
+        // Heights on both sides of page boundaries resolve to the right versions
+        for height in &[1, 2, 127, 128, 129, 255, 256, 257, 299, 300] {
+            let value = get_value_at(&mut vkv, &key, *height).unwrap();
+            assert_eq!(value, value_at(*height).inner().to_owned());
+        }
+        assert_eq!(
+            get_value_at(&mut vkv, &other_key, 200).unwrap(),
+            value_at(1).inner().to_owned()
+        );
+
+        let get_journal: Instruction = GetJournalInstruction { key: key.clone() }.into();
+        match vkv.execute(&get_journal).unwrap() {
+            Answer::DataAccess(DataAnswer::Read(DataReadAnswer::GetJournalOk(answer))) => {
+                assert_eq!(answer.journal.value, value_at(end));
+                let heights: Vec<u64> = answer
+                    .journal
+                    .update_heights
+                    .iter()
+                    .map(|height| height.as_u64())
+                    .collect();
+                assert_eq!(heights, (1..=end).collect::<Vec<u64>>());
+            }
+            answer => panic!("Unexpected answer {:?}", answer),
+        }
+
+        // Reverting to a height in a sealed page appends to the recent heights
+        let revert: Instruction = RevertManyInstruction {
+            targets: vec![RevertTargetSpec {
+                key: key.clone(),
+                height: ChainHeight::new(5),
+            }],
+        }
+        .into();
+        vkv.execute(&revert).unwrap();
+        assert_eq!(
+            get_value_at(&mut vkv, &key, end + 1).unwrap(),
+            value_at(5).inner().to_owned()
+        );
+        assert_eq!(
+            get_value_at(&mut vkv, &key, end).unwrap(),
+            value_at(end).inner().to_owned()
+        );
+
+        // Compaction repages what is left of the journal
+        let set_retention: Instruction = SetRetentionInstruction {
+            scope: None,
+            policy: Some(RetentionPolicy::KeepHeights(150)),
+        }
+        .into();
+        vkv.execute(&set_retention).unwrap();
+        let answer = compact(&mut vkv);
+        assert_eq!(answer.markers[0].height, ChainHeight::new(152));
+        assert_eq!(answer.pruned_versions, 151);
+        assert_eq!(
+            get_value_at(&mut vkv, &key, 152).unwrap(),
+            value_at(152).inner().to_owned()
+        );
+        assert_eq!(
+            get_value_at(&mut vkv, &key, 290).unwrap(),
+            value_at(290).inner().to_owned()
+        );
+        assert_eq!(
+            get_value_at(&mut vkv, &other_key, 200).unwrap(),
+            value_at(1).inner().to_owned()
+        );
+        assert_pruned(get_value_at(&mut vkv, &key, 100), 100, 152);
+        match vkv.execute(&get_journal).unwrap() {
+            Answer::DataAccess(DataAnswer::Read(DataReadAnswer::GetJournalOk(answer))) => {
+                let heights: Vec<u64> = answer
+                    .journal
+                    .update_heights
+                    .iter()
+                    .map(|height| height.as_u64())
+                    .collect();
+                assert_eq!(heights, (152..=end + 1).collect::<Vec<u64>>());
+            }
+            answer => panic!("Unexpected answer {:?}", answer),
+        }
+    }
+
+    #[test]
+    fn test_revert_all_with_missing_affected_keys() {
+        let mut vkv = make_vkv("test_revert_all_with_missing_affected_keys");
+        let key_a = StoreKey::from("a");
+        let key_b = StoreKey::from("b");
+        set_value(&mut vkv, &[&key_a, &key_b], 1);
+        set_value(&mut vkv, &[&key_a], 2);
+        set_value(&mut vkv, &[&key_b], 3);
+
+        let revert_all: Instruction = RevertAllInstruction {
+            target_height: ChainHeight::new(1),
+        }
+        .into();
+        let mut affected_keys_kvkey = vec![KVKeySigil::HeightToAffectedKeys as u8];
+        affected_keys_kvkey.extend(ChainHeight::new(3).marshal());
+        let affected_keys_kvkey = KVKey::from(affected_keys_kvkey);
+        let stored = vkv.kv_engine.get(&affected_keys_kvkey).unwrap().unwrap();
+        vkv.kv_engine
+            .set(&affected_keys_kvkey, &KVValue::new(&[]))
+            .unwrap();
+        match vkv.execute(&revert_all) {
+            Err(ImmuxError::VKV(VkvError::AffectedKeysParsing)) => {}
+            result => panic!("Unexpected result {:?}", result),
+        }
+        vkv.kv_engine
+            .atomic_batch_delete(&[affected_keys_kvkey.clone()])
+            .unwrap();
+        match vkv.execute(&revert_all) {
+            Err(ImmuxError::VKV(VkvError::MissingAffectedKeys(height))) => {
+                assert_eq!(height, ChainHeight::new(3))
+            }
+            result => panic!("Unexpected result {:?}", result),
+        }
+        assert_eq!(vkv.get_current_height(), ChainHeight::new(3));
+
+        vkv.kv_engine.set(&affected_keys_kvkey, &stored).unwrap();
+        match vkv.execute(&revert_all).unwrap() {
+            Answer::DataAccess(DataAnswer::Write(DataWriteAnswer::RevertAllOk(answer))) => {
+                assert_eq!(answer.reverted_keys, vec![key_a.clone(), key_b.clone()]);
+            }
+            answer => panic!("Unexpected answer {:?}", answer),
+        }
+        assert_eq!(get_value_at(&mut vkv, &key_a, 4).unwrap(), Some(vec![1]));
+        assert_eq!(get_value_at(&mut vkv, &key_b, 4).unwrap(), Some(vec![1]));
+    }
+
+    #[test]
+    fn test_replay() {
+        let mut vkv = make_vkv("test_replay");
+        let key_a = StoreKey::from("a");
+        let key_b = StoreKey::from("b");
+        set_value(&mut vkv, &[&key_a, &key_b], 1);
+        set_value(&mut vkv, &[&key_a], 2);
+        let revert: Instruction = RevertManyInstruction {
+            targets: vec![RevertTargetSpec {
+                key: key_a.clone(),
+                height: ChainHeight::new(1),
+            }],
+        }
+        .into();
+        vkv.execute(&revert).unwrap();
+        set_value(&mut vkv, &[&key_b], 3);
+        let verified = vkv.verify_chain().unwrap();
+
+        let replay: Instruction = ReplayInstruction {
+            engine: KeyValueEngine::HashMap,
+            data_root: String::new(),
+            namespace: None,
+        }
+        .into();
+        match vkv.execute(&replay).unwrap() {
+            Answer::DBSystem(DBSystemAnswer::ReplayOk(answer)) => {
+                assert_eq!(answer.height, verified.height);
+                assert_eq!(answer.head, verified.head);
+                assert_eq!(answer.mismatches, vec![]);
+            }
+            answer => panic!("Unexpected answer {:?}", answer),
+        }
+
+        // Swapping in another journal is caught though every record is intact
+        let mut journal_a = vec![KVKeySigil::UnitJournal as u8];
+        journal_a.extend_from_slice(key_a.as_slice());
+        let journal_a = KVKey::from(journal_a);
+        let mut journal_b = vec![KVKeySigil::UnitJournal as u8];
+        journal_b.extend_from_slice(key_b.as_slice());
+        let journal_b = KVKey::from(journal_b);
+        let original = vkv.kv_engine.get(&journal_a).unwrap().unwrap();
+        let tampered = vkv.kv_engine.get(&journal_b).unwrap().unwrap();
+        vkv.kv_engine.set(&journal_a, &tampered).unwrap();
+        assert_eq!(vkv.verify_chain().unwrap().broken_height, None);
+        match vkv.execute(&replay).unwrap() {
+            Answer::DBSystem(DBSystemAnswer::ReplayOk(answer)) => {
+                assert_eq!(
+                    answer.mismatches,
+                    vec![ReplayMismatch {
+                        kvkey: journal_a.as_bytes().to_vec(),
+                        original: Some(tampered.as_bytes().to_vec()),
+                        replayed: Some(original.as_bytes().to_vec()),
+                    }]
+                );
+            }
+            answer => panic!("Unexpected answer {:?}", answer),
+        }
+    }
+
+    #[test]
+    fn test_replay_into_rocks() {
+        let mut vkv = make_vkv("test_replay_into_rocks");
+        let key = StoreKey::from("a");
+        set_value(&mut vkv, &[&key], 1);
+        set_value(&mut vkv, &[&key], 2);
+        let replay: Instruction = ReplayInstruction {
+            engine: KeyValueEngine::Rocks,
+            data_root: String::from("/tmp/vkv_test/"),
+            namespace: Some(StoreNamespace::new(b"test_replay_into_rocks_replayed")),
+        }
+        .into();
+        reset_db_dir("/tmp/vkv_test/test_replay_into_rocks_replayed").unwrap();
+        match vkv.execute(&replay).unwrap() {
+            Answer::DBSystem(DBSystemAnswer::ReplayOk(answer)) => {
+                assert_eq!(answer.height, ChainHeight::new(2));
+                assert_eq!(answer.mismatches, vec![]);
+            }
+            answer => panic!("Unexpected answer {:?}", answer),
+        }
+
+        // The replayed chain is kept, so it cannot be replayed into again
+        match vkv.execute(&replay) {
+            Err(ImmuxError::VKV(VkvError::ReplayTargetNotEmpty)) => {}
+            result => panic!("Unexpected result {:?}", result),
+        }
+        let mut replayed = ImmuxDBVersionedKeyValueStore::new(
+            &KeyValueEngine::Rocks,
+            "/tmp/vkv_test/",
+            &StoreNamespace::new(b"test_replay_into_rocks_replayed"),
+        )
+        .unwrap();
+        assert_eq!(get_value_at(&mut replayed, &key, 1).unwrap(), Some(vec![1]));
+    }
 }
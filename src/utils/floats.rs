@@ -8,9 +8,20 @@ pub fn u8_array_to_f64(data: &[u8; 8]) -> f64 {
     return f64::from_bits(u8_array_to_u64(data));
 }
 
+/// Big-endian bytes that compare in the numeric order of the floats, with -0.0 taken as 0.0
+pub fn f64_to_sortable_u8_array(f: f64) -> [u8; 8] {
+    let bits = if f == 0.0 { 0 } else { f.to_bits() };
+    let sortable_bits = if bits >> 63 == 1 {
+        !bits
+    } else {
+        bits | (1 << 63)
+    };
+    return sortable_bits.to_be_bytes();
+}
+
 #[cfg(test)]
 mod float_utils_test {
-    use crate::utils::floats::{f64_to_u8_array, u8_array_to_f64};
+    use crate::utils::floats::{f64_to_sortable_u8_array, f64_to_u8_array, u8_array_to_f64};
 
     #[test]
     fn spot_check_f64_array_reversibility() {
@@ -41,4 +52,28 @@ mod float_utils_test {
             }
         }
     }
+
+    #[test]
+    fn test_sortable_f64_array_order() {
+        let sorted = [
+            std::f64::NEG_INFINITY,
+            -1e20,
+            -2.5,
+            -1.0,
+            -1e-10,
+            0.0,
+            1e-10,
+            1.0,
+            2.5,
+            1e20,
+            std::f64::INFINITY,
+        ];
+        for pair in sorted.windows(2) {
+            assert!(f64_to_sortable_u8_array(pair[0]) < f64_to_sortable_u8_array(pair[1]));
+        }
+        assert_eq!(
+            f64_to_sortable_u8_array(-0.0),
+            f64_to_sortable_u8_array(0.0)
+        );
+    }
 }